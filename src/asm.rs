@@ -3,7 +3,7 @@ use crate::{
     Identifier, StringLiteral,
 };
 use std::{
-    collections::BTreeMap,
+    collections::{BTreeMap, BTreeSet},
     fmt::{Debug, Display, Error, Formatter},
     fs::read_to_string,
     path::PathBuf,
@@ -15,6 +15,13 @@ pub enum AsmError {
     VariableNotDefined(Identifier),
     FunctionNotDefined(Identifier),
     NoEntryPoint,
+    MultipleEntryPoints(Vec<Identifier>),
+    /// The global data and the memory for the stack and heap
+    /// need more cells than the virtual machine can address
+    MemoryTooLarge(i32, i32),
+    /// An exported function takes or returns a value that
+    /// doesn't fit in a single cell
+    InvalidExport(Identifier),
 }
 
 impl Display for AsmError {
@@ -25,7 +32,46 @@ impl Display for AsmError {
             }
             Self::FunctionNotDefined(name) => write!(f, "function '{}' is not defined", name),
             Self::VariableNotDefined(name) => write!(f, "variable '{}' is not defined", name),
-            Self::NoEntryPoint => write!(f, "no entry point defined"),
+            Self::NoEntryPoint => write!(
+                f,
+                "no entry point defined. define a 'main' function, or mark a function with '#[entry]'"
+            ),
+            Self::MultipleEntryPoints(names) => write!(
+                f,
+                "multiple functions are marked with '#[entry]': '{}'",
+                names.join("', '")
+            ),
+            Self::MemoryTooLarge(global_scope_size, memory_size) => write!(
+                f,
+                "the program's {} cells of global data and {} cells of stack and heap memory are more than can be addressed. try using a smaller '#[memory(n)]'",
+                global_scope_size, memory_size
+            ),
+            Self::InvalidExport(name) => write!(
+                f,
+                "the exported function '{}' can only take and return values that fit in a single cell",
+                name
+            ),
+        }
+    }
+}
+
+/// Problems with the program that don't stop it from compiling,
+/// but that the user should know about.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum AsmWarning {
+    /// A function assembles to so many instructions that
+    /// the backend's compiler may choke on it.
+    LongFunction(Identifier, usize),
+}
+
+impl Display for AsmWarning {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match self {
+            Self::LongFunction(name, count) => write!(
+                f,
+                "function '{}' assembles to {} instructions, which may be too large for the backend to compile. try splitting it into smaller functions",
+                name, count
+            ),
         }
     }
 }
@@ -60,7 +106,7 @@ impl AsmType {
     pub fn deref(&self) -> Option<Self> {
         if self.ptr_level > 0 {
             let mut copy = *self;
-            copy.ptr_level += 1;
+            copy.ptr_level -= 1;
             Some(copy)
         } else {
             None
@@ -93,6 +139,7 @@ pub struct AsmProgram {
 }
 
 impl AsmProgram {
+    /// The entry point used when no function is marked with `#[entry]`
     const ENTRY_POINT: &'static str = "main";
 
     pub fn new(externs: Vec<PathBuf>, funcs: Vec<AsmFunction>, memory_size: i32) -> Self {
@@ -103,7 +150,120 @@ impl AsmProgram {
         }
     }
 
-    pub fn assemble(&self, target: &impl Target) -> Result<String, AsmError> {
+    /// The number of cells of memory for the stack and heap
+    pub fn memory_size(&self) -> i32 {
+        self.memory_size
+    }
+
+    /// Get the warnings about the program that can be found before assembling it
+    pub fn warnings(&self) -> Vec<AsmWarning> {
+        let mut result = vec![];
+        for func in &self.funcs {
+            let count = func.instruction_count();
+            if count > AsmFunction::MAX_INSTRUCTIONS {
+                result.push(AsmWarning::LongFunction(func.name.clone(), count))
+            }
+        }
+        result
+    }
+
+    /// Does any function outside of a library use the library? A function
+    /// uses it by calling one of its functions, or by calling one of the
+    /// foreign functions that the library's functions wrap.
+    pub fn uses_library(&self, library: &BTreeSet<Identifier>) -> bool {
+        let (library_funcs, other_funcs): (Vec<_>, Vec<_>) = self
+            .funcs
+            .iter()
+            .partition(|func| library.contains(&func.name));
+
+        other_funcs.iter().any(|func| {
+            func.body.iter().any(|stmt| {
+                stmt.any_expression(&|expr| match expr {
+                    AsmExpression::Call(name) => library.contains(name),
+                    AsmExpression::ForeignCall(name)
+                    | AsmExpression::ForeignCallWithContext(name) => {
+                        library_funcs.iter().any(|lib| lib.calls_foreign(name))
+                    }
+                    _ => false,
+                })
+            })
+        })
+    }
+
+    /// Remove the functions with these names from the program
+    pub fn remove_functions(&mut self, names: &BTreeSet<Identifier>) {
+        self.funcs.retain(|func| !names.contains(&func.name))
+    }
+
+    /// Get the name of the function that the program starts in.
+    fn entry_point(&self) -> Result<Identifier, AsmError> {
+        let entries = self
+            .funcs
+            .iter()
+            .filter(|func| func.is_entry)
+            .map(|func| func.name.clone())
+            .collect::<Vec<_>>();
+
+        match entries.len() {
+            0 => Ok(Identifier::from(Self::ENTRY_POINT)),
+            1 => Ok(entries[0].clone()),
+            _ => Err(AsmError::MultipleEntryPoints(entries)),
+        }
+    }
+
+    /// Find the functions that are defined exactly the same as an earlier
+    /// function, apart from their names, such as the member getters of
+    /// structures with the same size. Each is mapped to the index of the
+    /// first function defined like it, which its calls are redirected to.
+    /// The entry point, closures, and the functions closures are defined
+    /// in are never merged, because closures find their parent's stack
+    /// frame by its name.
+    fn duplicates(&self) -> BTreeMap<Identifier, usize> {
+        let entry_name = self.entry_point().ok();
+        let parents = self
+            .funcs
+            .iter()
+            .filter_map(|func| func.parent.clone())
+            .collect::<BTreeSet<_>>();
+        let mergeable = |func: &AsmFunction| {
+            Some(&func.name) != entry_name.as_ref()
+                && func.parent.is_none()
+                && !parents.contains(&func.name)
+        };
+
+        let mut result = BTreeMap::new();
+        for (i, func) in self.funcs.iter().enumerate() {
+            if !mergeable(func) {
+                continue;
+            }
+            if let Some(original) = self.funcs[..i].iter().position(|other| {
+                mergeable(other) && !result.contains_key(&other.name) && func.same_definition(other)
+            }) {
+                result.insert(func.name.clone(), original);
+            }
+        }
+        result
+    }
+
+    /// List the name of each function with the symbol it is assembled as,
+    /// one function per line. The symbols are numbered in the same order
+    /// as the function IDs given out by `assemble`, and a function that is
+    /// merged with another is listed with the other's symbol.
+    pub fn symbol_map(&self, target: &(impl Target + ?Sized)) -> String {
+        let duplicates = self.duplicates();
+        let mut result = String::new();
+        for (id, func) in self.funcs.iter().enumerate() {
+            let id = duplicates.get(&func.name).copied().unwrap_or(id);
+            result += &format!(
+                "{} {}\n",
+                AsmFunction::get_assembled_name(id as i32, target),
+                func.name
+            );
+        }
+        result
+    }
+
+    pub fn assemble(&self, target: &(impl Target + ?Sized)) -> Result<String, AsmError> {
         // Set up the output code
         let mut result = String::new();
 
@@ -127,36 +287,111 @@ impl AsmProgram {
         let mut func_ids = BTreeMap::new();
         // The number of cells to preemptively allocate on the stack before the program starts
         let mut global_scope_size = 0;
+        // Functions defined exactly like an earlier function are left out,
+        // and are called by the earlier function's ID instead
+        let duplicates = self.duplicates();
         for (id, func) in self.funcs.iter().enumerate() {
-            // Store the function's ID
-            func_ids.insert(func.name.clone(), id as i32);
+            // Store the function's ID, and whether it returns natively
+            let native = func.returns_natively(target);
+            if let Some(original) = duplicates.get(&func.name) {
+                func_ids.insert(func.name.clone(), (*original as i32, native));
+                continue;
+            }
+            func_ids.insert(func.name.clone(), (id as i32, native));
             // Add the function header to the output code
-            result += &target.fn_header(AsmFunction::get_assembled_name(id as i32));
+            let name = AsmFunction::get_assembled_name(id as i32, target);
+            result += &if native {
+                target.fn_header_returning(name, func.return_type.get_size())
+            } else {
+                target.fn_header(name)
+            };
         }
 
-        // It is very important that the entry point is assembled last.
+        // It is very important that the entry point is written last.
         // This is because of the way things are allocated on the stack.
+        // Closures are assembled after the functions they are defined in,
+        // so the entry point's code is kept until every function is assembled.
+        let entry_name = self.entry_point()?;
+        // A shared library has no entry point, so every function is assembled
+        // like any other, and the exported functions are wrapped afterwards.
+        let shared = target.shared_library();
+        // The entry point's body can only be written in place of
+        // its call if no other code calls it.
+        let inline_entry = !shared
+            && target.inline_entry_point()
+            && !self.funcs.iter().any(|func| func.calls(&entry_name));
         let mut entry_point = None;
+        let mut frames = BTreeMap::new();
+        result += &target.begin_fn_definitions();
         for func in &self.funcs {
-            // Compile the function
-            if !func.is_entry_point() {
-                result += &func.assemble(&func_ids, &mut global_scope_size, target)?;
+            if duplicates.contains_key(&func.name) {
+                continue;
+            } else if shared || func.name != entry_name {
+                // Compile the function
+                result += &func.assemble(&func_ids, &mut global_scope_size, &mut frames, target)?;
             } else {
                 // Store the entry point for use later
                 // This has the side effect of ignoring multiple definitions
-                // of the `main` function, and just using the last one defined.
-                entry_point = Some(func);
+                // of the entry point, and just using the last one defined.
+                let code = if inline_entry {
+                    func.source_location(target)
+                        + &func.assemble_body(
+                            &func_ids,
+                            &mut global_scope_size,
+                            &mut frames,
+                            target,
+                        )?
+                } else {
+                    func.assemble(&func_ids, &mut global_scope_size, &mut frames, target)?
+                };
+                entry_point = Some((code, func.return_type.get_size()));
             }
         }
 
-        if let Some(func) = entry_point {
-            if let Some(main_id) = func_ids.get(Self::ENTRY_POINT) {
-                // Assemble the entry point code
-                result += &func.assemble(&func_ids, &mut global_scope_size, target)?;
+        // The targets allocate the global data and the stack and heap together.
+        // The global scope size can only be negative if it overflowed.
+        if global_scope_size < 0 || global_scope_size.checked_add(self.memory_size).is_none() {
+            return Err(AsmError::MemoryTooLarge(
+                global_scope_size,
+                self.memory_size,
+            ));
+        }
 
-                // Call the entry point
-                result += &target.begin_entry_point(global_scope_size, self.memory_size);
-                result += &target.call_fn(AsmFunction::get_assembled_name(*main_id));
+        if shared {
+            // Each exported function is called from other languages
+            // through a wrapper that runs it on a shared machine
+            result += &target.begin_exports(global_scope_size, self.memory_size);
+            for func in &self.funcs {
+                if func.is_exported {
+                    result += &func.assemble_export(&func_ids, target)?;
+                }
+            }
+            return Ok(result);
+        }
+
+        if let Some((code, return_size)) = entry_point {
+            if let Some((main_id, _)) = func_ids.get(&entry_name) {
+                let main_name = AsmFunction::get_assembled_name(*main_id, target);
+                if inline_entry {
+                    result += &target.end_fn_definitions(main_name);
+                    // Run the entry point's body, with its own stack frame,
+                    // directly in the program's entry point
+                    result += &target.begin_entry_point(global_scope_size, self.memory_size);
+                    result += &code;
+                } else {
+                    // Write the entry point code
+                    result += &code;
+                    result += &target.end_fn_definitions(main_name.clone());
+
+                    // Call the entry point
+                    result += &target.begin_entry_point(global_scope_size, self.memory_size);
+                    result += &target.call_entry_point(main_name);
+                }
+                // The entry point's return value is left on the stack
+                // above the global scope, and nothing else should be.
+                if target.debug_runtime() {
+                    result += &target.check_stack(global_scope_size + return_size);
+                }
                 result += &target.end_entry_point();
 
                 Ok(result)
@@ -175,42 +410,209 @@ pub struct AsmFunction {
     args: Vec<(Identifier, AsmType)>,
     return_type: AsmType,
     body: Vec<AsmStatement>,
+    /// Is the function marked with `#[entry]`?
+    is_entry: bool,
+    /// If the function is a closure, the function it is defined in
+    parent: Option<Identifier>,
+    /// The file and line the function is defined at, if it's written in Oak
+    location: Option<(String, usize)>,
+    /// Is the function marked with `#[export]`?
+    is_exported: bool,
 }
 
 impl AsmFunction {
+    /// The number of instructions a function body can have before the
+    /// compiler warns that it may be too large for the backend to compile.
+    const MAX_INSTRUCTIONS: usize = 10000;
+
+    /// The name of a closure's last argument: the base pointer of the
+    /// stack frame its captured variables are stored in. `%` can't be
+    /// written in an Oak identifier, so it can't clash with a variable.
+    pub const ENVIRONMENT: &'static str = "%env";
+
     pub fn new(
         name: Identifier,
         args: Vec<(Identifier, AsmType)>,
         return_type: AsmType,
         body: Vec<AsmStatement>,
+        is_entry: bool,
+        parent: Option<Identifier>,
     ) -> Self {
         Self {
             name,
             args,
             return_type,
             body,
+            is_entry,
+            parent,
+            location: None,
+            is_exported: false,
+        }
+    }
+
+    /// Mark the function as exported from a shared library
+    pub fn exported(mut self, is_exported: bool) -> Self {
+        self.is_exported = is_exported;
+        self
+    }
+
+    /// Set the file and line the function is defined at
+    pub fn with_location(mut self, location: Option<(String, usize)>) -> Self {
+        self.location = location;
+        self
+    }
+
+    /// Mark where the function is defined in the Oak source, if it's known
+    fn source_location(&self, target: &(impl Target + ?Sized)) -> String {
+        match &self.location {
+            Some((filename, line)) => target.source_location(filename, *line, &self.name),
+            None => String::new(),
         }
     }
 
-    fn is_entry_point(&self) -> bool {
-        self.name == AsmProgram::ENTRY_POINT
+    /// The name a closure stores a captured variable under in its scope
+    pub fn captured_name(var_name: &Identifier) -> Identifier {
+        format!("{}.{}", Self::ENVIRONMENT, var_name)
+    }
+
+    /// Is this function defined exactly the same as another, apart from
+    /// its name? Their arguments must have the same names and types too.
+    fn same_definition(&self, other: &Self) -> bool {
+        self.args == other.args
+            && self.return_type == other.return_type
+            && self.body == other.body
+            && self.is_entry == other.is_entry
+            && self.parent == other.parent
+    }
+
+    /// The number of VM instructions in the function's body
+    fn instruction_count(&self) -> usize {
+        self.body.iter().map(AsmStatement::instruction_count).sum()
+    }
+
+    /// Estimate how much work the function's body does, independent of
+    /// the target. Each VM instruction is weighted by the cells it moves,
+    /// and calls and heap searches are weighted as several instructions.
+    /// Optimizations compare this to decide whether they are worth it.
+    pub fn estimated_cost(&self) -> usize {
+        let mut sizes = self
+            .args
+            .iter()
+            .map(|(name, data_type)| (name.clone(), data_type.get_size()))
+            .collect::<BTreeMap<_, _>>();
+        self.body.iter().map(|stmt| stmt.cost(&mut sizes)).sum()
     }
 
     /// Use the function's ID to get the output code's name of the function.
     /// An ID is used to prevent invalid output code function names, or names
     /// that clash with standard library names such as "printf" or "malloc".
-    fn get_assembled_name(id: i32) -> String {
-        format!("fn{}", id)
+    fn get_assembled_name(id: i32, target: &(impl Target + ?Sized)) -> String {
+        target.identifier(format!("fn{}", id))
+    }
+
+    /// Does this function call the function with this name?
+    fn calls(&self, name: &Identifier) -> bool {
+        self.body.iter().any(|stmt| stmt.calls(name))
+    }
+
+    /// Does this function call the foreign function with this name?
+    fn calls_foreign(&self, name: &Identifier) -> bool {
+        self.body.iter().any(|stmt| {
+            stmt.any_expression(&|expr| match expr {
+                AsmExpression::ForeignCall(foreign_name)
+                | AsmExpression::ForeignCallWithContext(foreign_name) => foreign_name == name,
+                _ => false,
+            })
+        })
+    }
+
+    /// Does this function push its own base pointer, such as to pass
+    /// its stack frame to the closures defined in it?
+    fn uses_base_ptr(&self) -> bool {
+        self.body
+            .iter()
+            .any(|stmt| stmt.any_expression(&|expr| *expr == AsmExpression::BasePtr))
+    }
+
+    /// Can this function return its value natively, instead of on the
+    /// virtual machine's stack? Only leaf functions that return a single
+    /// cell use the native calling convention.
+    fn returns_natively(&self, target: &(impl Target + ?Sized)) -> bool {
+        let is_leaf = !self.body.iter().any(|stmt| {
+            stmt.any_expression(&|expr| match expr {
+                AsmExpression::Call(_) => true,
+                _ => false,
+            })
+        });
+        target.native_return() && !self.is_entry && self.return_type.get_size() == 1 && is_leaf
     }
 
     fn assemble(
         &self,
-        func_ids: &BTreeMap<String, i32>,
+        func_ids: &BTreeMap<String, (i32, bool)>,
+        global_scope_size: &mut i32,
+        frames: &mut BTreeMap<Identifier, BTreeMap<String, (i32, AsmType)>>,
+        target: &(impl Target + ?Sized),
+    ) -> Result<String, AsmError> {
+        let body = self.assemble_body(func_ids, global_scope_size, frames, target)?;
+
+        // Write the function as output code
+        if let Some((id, native)) = func_ids.get(&self.name) {
+            let name = Self::get_assembled_name(*id, target);
+            Ok(self.source_location(target)
+                + &if *native {
+                    target.fn_definition_returning(name, body, self.return_type.get_size())
+                } else {
+                    target.fn_definition(name, body)
+                })
+        } else {
+            Err(AsmError::FunctionNotDefined(self.name.clone()))
+        }
+    }
+
+    /// Assemble a wrapper that calls this exported function with native
+    /// numbers as its arguments, and returns its result as a native number.
+    /// Only values that fit in a single cell can be passed this way.
+    fn assemble_export(
+        &self,
+        func_ids: &BTreeMap<String, (i32, bool)>,
+        target: &(impl Target + ?Sized),
+    ) -> Result<String, AsmError> {
+        let return_size = self.return_type.get_size();
+        if return_size > 1 || self.args.iter().any(|(_, t)| t.get_size() != 1) {
+            return Err(AsmError::InvalidExport(self.name.clone()));
+        }
+
+        if let Some((id, native)) = func_ids.get(&self.name) {
+            let name = Self::get_assembled_name(*id, target);
+            let call = if *native {
+                target.call_fn_returning(name)
+            } else {
+                target.call_fn(name)
+            };
+            Ok(target.export_fn(&self.name, self.args.len(), call, return_size == 1))
+        } else {
+            Err(AsmError::FunctionNotDefined(self.name.clone()))
+        }
+    }
+
+    /// Assemble the function's body, from establishing its stack frame
+    /// to ending it, without wrapping it in a function definition.
+    fn assemble_body(
+        &self,
+        func_ids: &BTreeMap<String, (i32, bool)>,
         global_scope_size: &mut i32,
-        target: &impl Target,
+        // The addresses and types of the variables of each assembled function,
+        // for the closures defined in them
+        frames: &mut BTreeMap<Identifier, BTreeMap<String, (i32, AsmType)>>,
+        target: &(impl Target + ?Sized),
     ) -> Result<String, AsmError> {
         let mut result = String::new();
         let mut arg_size = 0;
+        let fn_name = match func_ids.get(&self.name) {
+            Some((id, _)) => Self::get_assembled_name(*id, target),
+            None => return Err(AsmError::FunctionNotDefined(self.name.clone())),
+        };
 
         // The local scope size starts at one. This is VERY important.
         // The reason the local scope size starts at one is to make room for
@@ -220,12 +622,24 @@ impl AsmFunction {
 
         // Store the variables's addresses and types in the scope
         let mut vars = BTreeMap::new();
+        // A closure finds its captured variables in its parent's stack frame
+        if let Some(parent) = &self.parent {
+            match frames.get(parent) {
+                Some(frame) => {
+                    for (var_name, var) in frame {
+                        vars.insert(Self::captured_name(var_name), *var);
+                    }
+                }
+                None => return Err(AsmError::FunctionNotDefined(parent.clone())),
+            }
+        }
         for (arg_name, arg_type) in &self.args {
             // Add together the total size of all the arguments supplied to the function
             arg_size += arg_type.get_size();
 
             // Define each argument of the function
             result += &AsmStatement::Define(arg_name.clone(), *arg_type).assemble(
+                &fn_name,
                 func_ids,
                 &mut vars,
                 global_scope_size,
@@ -233,6 +647,7 @@ impl AsmFunction {
                 target,
             )?;
             result += &AsmStatement::Assign(*arg_type).assemble(
+                &fn_name,
                 func_ids,
                 &mut vars,
                 global_scope_size,
@@ -244,6 +659,7 @@ impl AsmFunction {
         for stmt in &self.body {
             // Assemble each statement in the function body
             result += &stmt.assemble(
+                &fn_name,
                 func_ids,
                 &mut vars,
                 global_scope_size,
@@ -252,34 +668,125 @@ impl AsmFunction {
             )?;
         }
 
-        let start = target.establish_stack_frame(arg_size, local_scope_size);
-        result += &target.end_stack_frame(self.return_type.get_size(), local_scope_size);
+        let native = match func_ids.get(&self.name) {
+            Some((_, native)) => *native,
+            None => false,
+        };
+        frames.insert(self.name.clone(), vars);
 
-        // Write the function as output code
-        if let Some(id) = func_ids.get(&self.name) {
-            Ok(target.fn_definition(Self::get_assembled_name(*id), start + &result))
-        } else {
-            Err(AsmError::FunctionNotDefined(self.name.clone()))
+        // A function without arguments or local variables has nothing to keep
+        // in a stack frame, so it's left out, unless the function reads its
+        // base pointer. The values it returns are left on the stack where
+        // they were pushed, or popped off of it to be returned natively.
+        if arg_size == 0 && local_scope_size == 1 && !self.uses_base_ptr() {
+            if native {
+                result += &target.return_native();
+            }
+            return Ok(result);
         }
+
+        let start = target.establish_stack_frame(arg_size, local_scope_size);
+        result += &if native {
+            target.end_stack_frame_returning(local_scope_size)
+        } else {
+            target.end_stack_frame(self.return_type.get_size(), local_scope_size)
+        };
+        Ok(start + &result)
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum AsmStatement {
-    For(Vec<Self>, Vec<Self>, Vec<Self>, Vec<Self>),
+    /// A loop, with a label that is unique in its function if
+    /// `break` or `continue` can name it.
+    For(
+        Option<Identifier>,
+        Vec<Self>,
+        Vec<Self>,
+        Vec<Self>,
+        Vec<Self>,
+    ),
+    /// An if statement, with the statements that push its condition,
+    /// its body, and its else body, which is empty if it has none.
+    If(Vec<Self>, Vec<Self>, Vec<Self>),
+    /// Leave the loop with a label
+    Break(Identifier),
+    /// Skip to the next iteration of the loop with a label
+    Continue(Identifier),
     Define(Identifier, AsmType),
     Assign(AsmType),
     Expression(Vec<AsmExpression>),
 }
 
 impl AsmStatement {
+    /// Does any expression in this statement satisfy a predicate?
+    fn any_expression(&self, predicate: &impl Fn(&AsmExpression) -> bool) -> bool {
+        match self {
+            Self::For(_, pre, cond, post, body) => [pre, cond, post, body]
+                .iter()
+                .any(|stmts| stmts.iter().any(|stmt| stmt.any_expression(predicate))),
+            Self::If(cond, then_body, else_body) => [cond, then_body, else_body]
+                .iter()
+                .any(|stmts| stmts.iter().any(|stmt| stmt.any_expression(predicate))),
+            Self::Define(_, _) | Self::Assign(_) | Self::Break(_) | Self::Continue(_) => false,
+            Self::Expression(exprs) => exprs.iter().any(predicate),
+        }
+    }
+
+    /// Does this statement call the function with this name?
+    fn calls(&self, name: &Identifier) -> bool {
+        self.any_expression(&|expr| *expr == AsmExpression::Call(name.clone()))
+    }
+
+    /// The number of VM instructions this statement assembles to
+    fn instruction_count(&self) -> usize {
+        let count = |stmts: &Vec<Self>| stmts.iter().map(Self::instruction_count).sum::<usize>();
+        match self {
+            // A for loop also has instructions to begin and end the loop
+            Self::For(_, pre, cond, post, body) => {
+                count(pre) + count(cond) + count(post) + count(body) + 2
+            }
+            // So does an if statement
+            Self::If(cond, then_body, else_body) => {
+                count(cond) + count(then_body) + count(else_body) + 2
+            }
+            Self::Define(_, _) | Self::Assign(_) | Self::Break(_) | Self::Continue(_) => 1,
+            Self::Expression(exprs) => exprs.len(),
+        }
+    }
+
+    /// The estimated cost of this statement. The size of each variable
+    /// defined in the function so far is needed to weigh loading it.
+    fn cost(&self, sizes: &mut BTreeMap<Identifier, i32>) -> usize {
+        let mut cost = |stmts: &Vec<Self>| stmts.iter().map(|stmt| stmt.cost(sizes)).sum::<usize>();
+        match self {
+            // Beginning and ending a loop or an if statement are instructions too
+            Self::For(_, pre, cond, post, body) => {
+                cost(pre) + cost(cond) + cost(post) + cost(body) + 2
+            }
+            Self::If(cond, then_body, else_body) => {
+                cost(cond) + cost(then_body) + cost(else_body) + 2
+            }
+            // Defining a variable pushes its address
+            Self::Define(name, data_type) => {
+                sizes.insert(name.clone(), data_type.get_size());
+                3
+            }
+            Self::Assign(data_type) => data_type.get_size() as usize,
+            Self::Break(_) | Self::Continue(_) => 1,
+            Self::Expression(exprs) => exprs.iter().map(|expr| expr.cost(sizes)).sum(),
+        }
+    }
+
     fn assemble(
         &self,
-        func_ids: &BTreeMap<String, i32>,
+        // The output code's name of the function the statement is in
+        fn_name: &str,
+        func_ids: &BTreeMap<String, (i32, bool)>,
         vars: &mut BTreeMap<String, (i32, AsmType)>,
         global_scope_size: &mut i32,
         local_scope_size: &mut i32,
-        target: &impl Target,
+        target: &(impl Target + ?Sized),
     ) -> Result<String, AsmError> {
         Ok(match self {
             // Define a variable on the stack
@@ -289,17 +796,24 @@ impl AsmStatement {
                 vars.insert(name.clone(), (address, *data_type));
                 // Increment the size of the program's variables
                 *local_scope_size += data_type.get_size();
-                // Push the address of the new variable onto the stack
+                // Push the address of the new variable onto the stack. Addresses
+                // on the stack are relative to the base pointer, so the function
+                // can run on any machine, at any height of the machine's stack.
                 target.push(address as f64) + &target.load_base_ptr() + &target.add()
             }
             // Pop an address off of the stack, pop an item of size `data_type`
             // off of the stack, and store the item at the address
             Self::Assign(data_type) => target.store(data_type.get_size()),
-            Self::For(pre, cond, post, body) => {
+            Self::For(label, pre, cond, post, body) => {
+                // Labels are only unique in their function, so they're
+                // qualified with its name to be unique in the program.
+                let label = label.as_ref().map(|label| format!("{}_{}", fn_name, label));
+                let label = label.as_deref();
                 let mut result = String::new();
                 // Run the code that preps the for loop
                 for stmt in pre {
                     result += &stmt.assemble(
+                        fn_name,
                         func_ids,
                         vars,
                         global_scope_size,
@@ -308,8 +822,9 @@ impl AsmStatement {
                     )?;
                 }
                 // Check the condition of the for loop
-                for expr in cond {
-                    result += &expr.assemble(
+                for stmt in cond {
+                    result += &stmt.assemble(
+                        fn_name,
                         func_ids,
                         vars,
                         global_scope_size,
@@ -318,10 +833,11 @@ impl AsmStatement {
                     )?;
                 }
                 // Begin the loop body
-                result += &target.begin_while();
+                result += &target.begin_while(label);
                 // Run the body of the loop
                 for stmt in body {
                     result += &stmt.assemble(
+                        fn_name,
                         func_ids,
                         vars,
                         global_scope_size,
@@ -329,9 +845,12 @@ impl AsmStatement {
                         target,
                     )?;
                 }
+                // A `continue` jumps to the end of the body
+                result += &target.end_while_body(label);
                 // Run the code that procedes the body of the loop
                 for stmt in post {
                     result += &stmt.assemble(
+                        fn_name,
                         func_ids,
                         vars,
                         global_scope_size,
@@ -340,8 +859,9 @@ impl AsmStatement {
                     )?;
                 }
                 // Check the condition again
-                for expr in cond {
-                    result += &expr.assemble(
+                for stmt in cond {
+                    result += &stmt.assemble(
+                        fn_name,
                         func_ids,
                         vars,
                         global_scope_size,
@@ -350,8 +870,49 @@ impl AsmStatement {
                     )?;
                 }
                 // End the loop body
-                result + &target.end_while()
+                result + &target.end_while(label)
             }
+            Self::If(cond, then_body, else_body) => {
+                let mut result = String::new();
+                // Push the condition, which the if statement pops
+                for stmt in cond {
+                    result += &stmt.assemble(
+                        fn_name,
+                        func_ids,
+                        vars,
+                        global_scope_size,
+                        local_scope_size,
+                        target,
+                    )?;
+                }
+                result += &target.begin_if();
+                for stmt in then_body {
+                    result += &stmt.assemble(
+                        fn_name,
+                        func_ids,
+                        vars,
+                        global_scope_size,
+                        local_scope_size,
+                        target,
+                    )?;
+                }
+                if !else_body.is_empty() {
+                    result += &target.begin_else();
+                    for stmt in else_body {
+                        result += &stmt.assemble(
+                            fn_name,
+                            func_ids,
+                            vars,
+                            global_scope_size,
+                            local_scope_size,
+                            target,
+                        )?;
+                    }
+                }
+                result + &target.end_if()
+            }
+            Self::Break(label) => target.break_while(&format!("{}_{}", fn_name, label)),
+            Self::Continue(label) => target.continue_while(&format!("{}_{}", fn_name, label)),
 
             Self::Expression(exprs) => {
                 let mut result = String::new();
@@ -370,7 +931,7 @@ impl AsmStatement {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, PartialOrd)]
 pub enum AsmExpression {
     String(StringLiteral),
     Character(char),
@@ -378,56 +939,132 @@ pub enum AsmExpression {
     Void,
 
     ForeignCall(Identifier),
+    /// Call a foreign function, and pass it the virtual machine's context pointer
+    ForeignCallWithContext(Identifier),
+    ForeignLoad(Identifier),
+    ForeignStore(Identifier),
 
     Variable(Identifier),
     Call(Identifier),
     Refer(Identifier),
+    Captured(Identifier),
+    BasePtr,
     Deref(i32),
+    /// Pop an index and an address, and push the byte at
+    /// that index of the memory starting at the address
+    LoadByte,
+    /// Pop a byte, an index, and an address, and store the
+    /// byte at that index of the memory starting at the address
+    StoreByte,
+    /// Push a random number in [0, 1)
+    Random,
+    /// Pop a seed, and start a new sequence of random numbers with it
+    SeedRandom,
+    /// Push the number of seconds since the program started
+    Clock,
 
     Alloc,
     Free,
+    Flush,
+    Memset(i32),
+    Array(i32),
 
     Divide,
+    Modulo,
     Multiply,
     Subtract,
     Add,
     Sign,
+    /// Bitwise operations on two numbers, as 32 bit signed integers
+    BitAnd,
+    BitOr,
+    BitXor,
+    ShiftLeft,
+    ShiftRight,
+    /// Truncate a number to an integer with this many bits,
+    /// and whether or not the integer is signed
+    Truncate(i32, bool),
 }
 
 impl AsmExpression {
+    /// The weight of calling a function, which establishes
+    /// and ends a stack frame around the call
+    const CALL_COST: usize = 10;
+    /// The weight of an operation that searches the heap for free cells
+    const HEAP_SEARCH_COST: usize = 20;
+
+    /// The estimated cost of this expression, using
+    /// the sizes of the variables in scope
+    fn cost(&self, sizes: &BTreeMap<Identifier, i32>) -> usize {
+        match self {
+            // A string's cells, and its zero terminator, are stored at once
+            Self::String(s) => s.len() + 2,
+            Self::Character(_) | Self::Float(_) | Self::BasePtr => 1,
+            Self::Void => 0,
+
+            Self::ForeignCall(_) | Self::ForeignCallWithContext(_) | Self::Call(_) => {
+                Self::CALL_COST
+            }
+            Self::ForeignLoad(_) | Self::ForeignStore(_) => 1,
+
+            // Push the variable's address, and load its cells
+            Self::Variable(name) => 3 + sizes.get(name).copied().unwrap_or(1) as usize,
+            Self::Refer(_) => 3,
+            // The environment is loaded to find the captured variable's address
+            Self::Captured(_) => 6,
+            Self::Deref(size) => *size as usize,
+            Self::LoadByte | Self::StoreByte => 1,
+            Self::Random | Self::SeedRandom | Self::Clock => 1,
+
+            Self::Alloc => Self::HEAP_SEARCH_COST,
+            Self::Free | Self::Flush => 1,
+            Self::Memset(size) => *size as usize + 1,
+            // Store the array's cells, and push its address
+            Self::Array(size) => *size as usize + 6,
+
+            Self::Divide
+            | Self::Modulo
+            | Self::BitAnd
+            | Self::BitOr
+            | Self::BitXor
+            | Self::ShiftLeft
+            | Self::ShiftRight
+            | Self::Multiply
+            | Self::Subtract
+            | Self::Add
+            | Self::Sign => 1,
+            Self::Truncate(_, _) => 1,
+        }
+    }
+
     fn assemble(
         &self,
-        func_ids: &BTreeMap<String, i32>,
+        func_ids: &BTreeMap<String, (i32, bool)>,
         vars: &mut BTreeMap<String, (i32, AsmType)>,
         global_scope_size: &mut i32,
         local_scope_size: &mut i32,
-        target: &impl Target,
+        target: &(impl Target + ?Sized),
     ) -> Result<String, AsmError> {
         Ok(match self {
             Self::String(s) => {
                 // The address of the string is at the current first
-                // empty spot on the stack.
+                // empty spot on the stack. This is the only absolute
+                // address in the program, but the global scope is at
+                // the bottom of every machine's memory, so the string
+                // is at the same address in each of them.
                 let address = *global_scope_size;
-                // The size of the string is the length of the characters,
-                // plus 1 for the zero terminated character.
-                let size = s.len() as i32 + 1;
-
-                // Push each character of the string onto the stack
-                let mut result = String::new();
-                for ch in s.chars() {
-                    result += &target.push(ch as u8 as f64);
-                }
-                // Push the zero terminated character
-                result += &target.push(0.0);
+                // The UTF-8 bytes of the string, followed by the zero
+                // terminated character. A character that is encoded as
+                // multiple bytes takes up multiple cells.
+                let mut cells = s.bytes().map(|byte| byte as f64).collect::<Vec<_>>();
+                cells.push(0.0);
 
                 // Store the characters at the address of the string,
                 // and push the address onto the stack.
-                result += &(target.push(address as f64)
-                    + &target.store(size)
-                    + &target.push(address as f64));
+                let result = target.emit_initialized_data(address, &cells);
 
                 // Increment the amount of data stored on the stack
-                *global_scope_size += size;
+                *global_scope_size += cells.len() as i32;
                 result
             }
             // Push a character onto the stack
@@ -454,8 +1091,13 @@ impl AsmExpression {
 
             // Call a function
             Self::Call(fn_name) => {
-                if let Some(fn_id) = func_ids.get(fn_name) {
-                    target.call_fn(AsmFunction::get_assembled_name(*fn_id))
+                if let Some((fn_id, native)) = func_ids.get(fn_name) {
+                    let name = AsmFunction::get_assembled_name(*fn_id, target);
+                    if *native {
+                        target.call_fn_returning(name)
+                    } else {
+                        target.call_fn(name)
+                    }
                 } else {
                     return Err(AsmError::FunctionNotDefined(fn_name.clone()));
                 }
@@ -464,10 +1106,35 @@ impl AsmExpression {
             // Call a foreign function
             Self::ForeignCall(fn_name) => target.call_foreign_fn(fn_name.clone()),
 
+            // Call a foreign function that is passed the context pointer
+            Self::ForeignCallWithContext(fn_name) => {
+                target.call_foreign_fn_with_context(fn_name.clone())
+            }
+
+            // Push the value of a foreign global variable
+            Self::ForeignLoad(var_name) => target.load_foreign_var(var_name.clone()),
+
+            // Pop a value into a foreign global variable
+            Self::ForeignStore(var_name) => target.store_foreign_var(var_name.clone()),
+
+            // Wrap a number around to a sized integer
+            Self::Truncate(bits, is_signed) => target.truncate(*bits, *is_signed),
             // Allocate data on the heap
             Self::Alloc => target.allocate(),
             // Free data on the heap
             Self::Free => target.free(),
+            // Write the buffered output to stdout
+            Self::Flush => target.flush(),
+            // Fill memory with copies of a value
+            Self::Memset(size) => target.memset(*size),
+            // Read and write the bytes of the cells in memory
+            Self::LoadByte => target.load_byte(),
+            Self::StoreByte => target.store_byte(),
+            // Generate random numbers
+            Self::Random => target.random(),
+            Self::SeedRandom => target.seed_random(),
+            // Measure time
+            Self::Clock => target.clock(),
             // Get the address of a variable on the stack
             Self::Refer(name) => {
                 if let Some((addr, _)) = vars.get(name) {
@@ -476,8 +1143,42 @@ impl AsmExpression {
                     return Err(AsmError::VariableNotDefined(name.clone()));
                 }
             }
+            // Get the address of a captured variable in the stack frame of
+            // the closure's parent. The frame's base pointer is the closure's
+            // environment argument.
+            Self::Captured(name) => {
+                match (
+                    vars.get(&AsmFunction::captured_name(name)),
+                    vars.get(AsmFunction::ENVIRONMENT),
+                ) {
+                    (Some((addr, _)), Some((env_addr, env_type))) => {
+                        target.push(*addr as f64)
+                            + &target.push(*env_addr as f64)
+                            + &target.load_base_ptr()
+                            + &target.add()
+                            + &target.load(env_type.get_size())
+                            + &target.add()
+                    }
+                    _ => return Err(AsmError::VariableNotDefined(name.clone())),
+                }
+            }
+            // Push the base pointer of the current stack frame
+            Self::BasePtr => target.load_base_ptr(),
             // Dereference an address
             Self::Deref(size) => target.load(*size),
+            // Pop `size` cells off of the stack into a new array
+            // in the local scope, and push the array's address
+            Self::Array(size) => {
+                let address = *local_scope_size;
+                *local_scope_size += size;
+                target.push(address as f64)
+                    + &target.load_base_ptr()
+                    + &target.add()
+                    + &target.store(*size)
+                    + &target.push(address as f64)
+                    + &target.load_base_ptr()
+                    + &target.add()
+            }
 
             // Get the absolute value of a number on the stack
             Self::Sign => target.sign(),
@@ -489,6 +1190,34 @@ impl AsmExpression {
             Self::Multiply => target.multiply(),
             // Divide two numbers on the stack
             Self::Divide => target.divide(),
+            // Take the remainder of dividing two numbers on the stack
+            Self::Modulo => target.modulus(),
+            // Bitwise operations on two numbers on the stack
+            Self::BitAnd => target.bitand(),
+            Self::BitOr => target.bitor(),
+            Self::BitXor => target.bitxor(),
+            Self::ShiftLeft => target.shl(),
+            Self::ShiftRight => target.shr(),
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::AsmType;
+
+    #[test]
+    fn deref_undoes_refer() {
+        assert_eq!(AsmType::float().refer().deref(), Some(AsmType::float()));
+        assert_eq!(
+            AsmType::new(3).refer().refer().deref(),
+            Some(AsmType::new(3).refer())
+        );
+    }
+
+    #[test]
+    fn deref_of_non_pointer() {
+        assert_eq!(AsmType::float().deref(), None);
+        assert_eq!(AsmType::void().deref(), None);
+    }
+}
@@ -1,5 +1,8 @@
 use clap::{clap_app, crate_authors, crate_version, AppSettings::ArgRequiredElseHelp};
-use oakc::{compile, generate_docs, Go, C, TS};
+use oakc::{
+    compile, describe_features, generate_docs, CompileOptions, Go, PanicStrategy, Ruby, Rust,
+    Target, Wat, Zig, C, TS,
+};
 use std::{
     fs::{read_to_string, write},
     io::Result,
@@ -16,10 +19,31 @@ fn main() {
             (@arg cc: -c --cc "Compile with C backend")
             (@arg go: -g --go "Compile with Golang backend")
             (@arg ts: -t --ts "Compile with TypeScript backend")
+            (@arg rs: -r --rs "Compile with Rust backend")
+            (@arg zig: -z --zig "Compile with Zig backend")
+            (@arg rb: -b --rb visible_alias[ruby] "Compile with Ruby backend")
+            (@arg wat: -w --wat "Compile with WebAssembly text backend")
         )
+        (@arg pretty: --pretty "Indent the generated code")
+        (@arg emit_c: --("emit-c") "Write the generated C code to out.c without compiling it")
+        (@arg debug: --debug "Panic on invalid frees, and on an unbalanced stack at exit, at runtime")
+        (@arg trace: --trace "Log every operation of the virtual machine to stderr at runtime")
+        (@arg heap_stats: --("heap-stats") "Print the most cells allocated on the heap at once to stderr when the program exits")
+        (@arg inline_main: --("inline-main") "Write the body of the entry point directly in the program's entry point, instead of calling it")
+        (@arg panic: --panic +takes_value "What the C and Rust runtimes do when they panic: 'abort' exits, 'return-error' returns the error code from the entry point")
+        (@arg memory: --memory +takes_value "The number of cells of memory for the stack and heap, unless the program sets it with #[memory(n)]")
+        (@arg no_core: --("no-core") "Compile without the core library, leaving only the virtual machine's primitives")
+        (@arg explicit_stack: --("explicit-stack") "Run function calls on an explicit call stack with the C backend")
+        (@arg shared: --shared "Build a shared library of the functions marked with #[export] with the C backend, instead of an executable")
+        (@arg werror: --werror "Treat every warning as an error, and fail the compilation if there are any")
+        (@arg map: --map "Write each function's symbol in the output code to a .map file next to the executable")
+        (@arg stats: --stats "Print the size of the program, and the time spent in each phase of the compiler, to stderr")
+        (@arg features: --features "Print the available targets and what they support, one per line")
+        (@arg include: -I --include +takes_value +multiple number_of_values(1) "A directory to search for included and foreign files, before the input file's directory")
         (@subcommand c =>
             (about: "Compile an Oak file")
             (@arg FILE: +required "The input file to use")
+            (@arg c_compiler: --("c-compiler") +takes_value "The C compiler to use, instead of $CC or gcc")
         )
         (@subcommand doc =>
             (about: "Generate documentation for an Oak file")
@@ -30,8 +54,17 @@ fn main() {
     .setting(ArgRequiredElseHelp)
     .get_matches();
 
+    // The directories given with `-I`, searched in the order they're given
+    let search_path: Vec<PathBuf> = matches
+        .values_of("include")
+        .map(|dirs| dirs.map(PathBuf::from).collect())
+        .unwrap_or_default();
+
+    // If the supported targets and features are requested
+    if matches.is_present("features") {
+        print!("{}", describe_features());
     // If the compile subcommand is being used
-    if let Some(sub_matches) = matches.subcommand_matches("c") {
+    } else if let Some(sub_matches) = matches.subcommand_matches("c") {
         // Get the input file
         if let Some(input_file) = sub_matches.value_of("FILE") {
             // Get the contents of the input file
@@ -43,19 +76,119 @@ fn main() {
                     PathBuf::from("./")
                 };
 
-                // Compile using the target backend
-                let compile_result = if matches.is_present("cc") {
-                    compile(&cwd, &input_file, contents, C)
-                } else if matches.is_present("go") {
-                    compile(&cwd, &input_file, contents, Go)
-                } else if matches.is_present("ts") {
-                    compile(&cwd, &input_file, contents, TS)
+                let mut c = if matches.is_present("emit_c") {
+                    C::emit_only()
                 } else {
-                    compile(&cwd, &input_file, contents, C)
+                    C::default()
                 };
+                let (mut go, mut ts, mut rs, mut zig, mut rb, mut wat) = (
+                    Go::default(),
+                    TS::default(),
+                    Rust::default(),
+                    Zig::default(),
+                    Ruby::default(),
+                    Wat::default(),
+                );
+                if matches.is_present("debug") {
+                    c = c.debug();
+                    go = go.debug();
+                    ts = ts.debug();
+                    rs = rs.debug();
+                    zig = zig.debug();
+                    rb = rb.debug();
+                    wat = wat.debug();
+                }
+                if matches.is_present("trace") {
+                    c = c.traced();
+                    go = go.traced();
+                    ts = ts.traced();
+                    rs = rs.traced();
+                    zig = zig.traced();
+                    rb = rb.traced();
+                }
+                if matches.is_present("heap_stats") {
+                    c = c.report_heap_stats();
+                    go = go.report_heap_stats();
+                    ts = ts.report_heap_stats();
+                    rs = rs.report_heap_stats();
+                    zig = zig.report_heap_stats();
+                    rb = rb.report_heap_stats();
+                }
+                if matches.is_present("inline_main") {
+                    c = c.inline_entry();
+                    go = go.inline_entry();
+                    ts = ts.inline_entry();
+                    rs = rs.inline_entry();
+                    zig = zig.inline_entry();
+                    rb = rb.inline_entry();
+                    wat = wat.inline_entry();
+                }
+                if matches.is_present("explicit_stack") {
+                    c = c.explicit_stack();
+                }
+                if matches.is_present("shared") {
+                    if matches.is_present("explicit_stack") {
+                        eprintln!("error: a shared library can't run on an explicit call stack");
+                        return;
+                    }
+                    c = c.shared();
+                }
+                match matches.value_of("panic") {
+                    Some("abort") | None => {}
+                    Some("return-error") => {
+                        c = c.panic_strategy(PanicStrategy::ReturnError);
+                        rs = rs.panic_strategy(PanicStrategy::ReturnError);
+                    }
+                    Some(_) => {
+                        eprintln!("error: panic strategy must be 'abort' or 'return-error'");
+                        return;
+                    }
+                }
+                if let Some(compiler) = sub_matches.value_of("c_compiler") {
+                    c = c.c_compiler(String::from(compiler));
+                }
+                let default_memory = match matches.value_of("memory").map(str::parse::<i32>) {
+                    Some(Ok(size)) => Some(size),
+                    Some(Err(_)) => {
+                        eprintln!("error: memory size must be a whole number of cells");
+                        return;
+                    }
+                    None => None,
+                };
+                let options = CompileOptions {
+                    pretty: matches.is_present("pretty"),
+                    default_memory,
+                    no_core: matches.is_present("no_core"),
+                    map: matches.is_present("map"),
+                    warnings_are_errors: matches.is_present("werror"),
+                    stats: matches.is_present("stats"),
+                };
+                // Compile using the target backend
+                let target: Box<dyn Target> =
+                    if matches.is_present("emit_c") || matches.is_present("cc") {
+                        Box::new(c)
+                    } else if matches.is_present("go") {
+                        Box::new(go)
+                    } else if matches.is_present("ts") {
+                        Box::new(ts)
+                    } else if matches.is_present("rs") {
+                        Box::new(rs)
+                    } else if matches.is_present("zig") {
+                        Box::new(zig)
+                    } else if matches.is_present("rb") {
+                        Box::new(rb)
+                    } else if matches.is_present("wat") {
+                        Box::new(wat)
+                    } else {
+                        Box::new(c)
+                    };
+                let compile_result =
+                    compile(&cwd, &search_path, input_file, contents, &*target, &options);
 
                 match compile_result {
-                    Result::Ok(_) => println!("compilation successful"),
+                    Result::Ok(artifact) => {
+                        println!("compilation successful: wrote {}", artifact.display())
+                    }
                     Result::Err(error) => {
                         if let Some(inner_error) = error.get_ref() {
                             eprintln!("error: {}", inner_error);
@@ -83,11 +216,19 @@ fn main() {
 
                 // Document the input file using the target backend
                 let docs = if matches.is_present("cc") {
-                    generate_docs(&cwd, input_file, contents, C)
+                    generate_docs(&cwd, &search_path, input_file, contents, C::default())
                 } else if matches.is_present("go") {
-                    generate_docs(&cwd, input_file, contents, Go)
+                    generate_docs(&cwd, &search_path, input_file, contents, Go::default())
+                } else if matches.is_present("rs") {
+                    generate_docs(&cwd, &search_path, input_file, contents, Rust::default())
+                } else if matches.is_present("zig") {
+                    generate_docs(&cwd, &search_path, input_file, contents, Zig::default())
+                } else if matches.is_present("rb") {
+                    generate_docs(&cwd, &search_path, input_file, contents, Ruby::default())
+                } else if matches.is_present("wat") {
+                    generate_docs(&cwd, &search_path, input_file, contents, Wat::default())
                 } else {
-                    generate_docs(&cwd, input_file, contents, C)
+                    generate_docs(&cwd, &search_path, input_file, contents, C::default())
                 };
 
                 // If the output file exists, write the output to it
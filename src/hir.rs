@@ -1,5 +1,5 @@
 use std::{
-    collections::BTreeMap,
+    collections::{BTreeMap, BTreeSet},
     fmt::{Display, Error, Formatter},
     fs::read_to_string,
     path::PathBuf,
@@ -7,8 +7,10 @@ use std::{
 };
 
 use crate::{
+    asm::AsmExpression,
     mir::{
-        MirDeclaration, MirExpression, MirFunction, MirProgram, MirStatement, MirStructure, MirType,
+        IntWidth, MirDeclaration, MirExpression, MirFunction, MirProgram, MirStatement,
+        MirStructure, MirType,
     },
     parse, Identifier, StringLiteral,
 };
@@ -32,6 +34,17 @@ impl HirProgram {
         self.0.extend(decls.clone())
     }
 
+    /// Get the names of the functions declared by the program
+    pub fn function_names(&self) -> BTreeSet<Identifier> {
+        self.get_declarations()
+            .iter()
+            .filter_map(|decl| match decl {
+                HirDeclaration::Function(func) => Some(func.name.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
     fn get_memory_size(&self) -> i32 {
         let Self(_, memory_size) = self;
         *memory_size
@@ -48,6 +61,15 @@ impl HirProgram {
         false
     }
 
+    pub fn use_core(&self) -> bool {
+        for decl in self.get_declarations() {
+            if let HirDeclaration::NoCore = decl {
+                return false;
+            }
+        }
+        true
+    }
+
     pub fn generate_docs(
         &self,
         filename: String,
@@ -134,15 +156,25 @@ impl HirProgram {
                 }
                 HirDeclaration::Extern(filename) => {
                     let file_path = cwd.join(filename.clone());
+                    // Check for the file now, instead of after the whole
+                    // program is type checked and assembled
+                    if !file_path.is_file() {
+                        return Err(HirError::ExternFileNotFound(
+                            file_path.to_string_lossy().to_string(),
+                        ));
+                    }
                     mir_decls.push(MirDeclaration::Extern(file_path))
                 }
                 HirDeclaration::Error(err) => return Err(HirError::UserError(err.clone())),
 
+                // Every declaration is passed to the constant, so it can
+                // use the size of a structure declared after the flag
                 HirDeclaration::Memory(size) => {
-                    if *size >= Self::MINIMUM_MEMORY_SIZE {
-                        memory_size = *size;
+                    let size = size.to_value(self.get_declarations(), constants)? as i32;
+                    if size >= Self::MINIMUM_MEMORY_SIZE {
+                        memory_size = size;
                     } else {
-                        return Err(HirError::MemorySizeTooSmall(*size));
+                        return Err(HirError::MemorySizeTooSmall(size));
                     }
                 }
                 _ => {}
@@ -178,6 +210,20 @@ pub enum HirError {
     /// This occurs when a literal expression is cast as a pointer.
     /// This isn't ACTUALLY bad, but it's intended to promote type correctness.
     CastLiteralAsPointer(HirType),
+    /// A constant whose definition depends on itself,
+    /// such as `const A = B; const B = A;`. This holds
+    /// the names of the constants in the cycle.
+    RecursiveConstant(Vec<Identifier>),
+    /// A foreign file used with the `extern` flag does not exist.
+    /// This holds the path that was searched for the file.
+    ExternFileNotFound(String),
+    /// The program has no declarations at all, such as an empty file
+    /// or a file with only comments.
+    EmptyProgram,
+    /// A foreign function or variable's name can't be written in the
+    /// output code as-is. This holds the name, and the name with each
+    /// character that can't be used in an identifier replaced.
+    InvalidForeignName(Identifier, Identifier),
 }
 
 impl Display for HirError {
@@ -197,10 +243,47 @@ impl Display for HirError {
             Self::FailedAssertion(assertion) => write!(f, "failed assertion '{}'", assertion),
             Self::TypeNotDefined(type_name) => write!(f, "type not defined '{}'", type_name),
             Self::CastLiteralAsPointer(t) => write!(f, "cannot cast literal to type '{}'", t),
+            Self::RecursiveConstant(cycle) => write!(
+                f,
+                "constant '{}' is defined in terms of itself: {}",
+                cycle[0],
+                cycle.join(" -> ")
+            ),
+            Self::ExternFileNotFound(path) => {
+                write!(f, "could not find foreign file '{}'", path)
+            }
+            Self::EmptyProgram => write!(
+                f,
+                "the program is empty. define a 'main' function, or mark a function with '#[entry]'"
+            ),
+            Self::InvalidForeignName(name, escaped) => write!(
+                f,
+                "foreign name '{}' is not a valid identifier. try naming it '{}'",
+                name, escaped
+            ),
         }
     }
 }
 
+/// Make sure a foreign function or variable's name can be written in the
+/// output code as-is, by every target.
+fn check_foreign_name(name: &Identifier) -> Result<Identifier, HirError> {
+    let mut escaped = String::new();
+    for (i, ch) in name.chars().enumerate() {
+        if ch == '_' || ch.is_ascii_alphabetic() || (i > 0 && ch.is_ascii_digit()) {
+            escaped.push(ch)
+        } else {
+            escaped.push('_')
+        }
+    }
+
+    if &escaped == name {
+        Ok(escaped)
+    } else {
+        Err(HirError::InvalidForeignName(name.clone(), escaped))
+    }
+}
+
 /// This enum represents a type name in an expression.
 /// Take for example the declaration `fn test(x: num) -> &void`.
 /// `num` and `&void` are both `HirType` instances.
@@ -208,6 +291,9 @@ impl Display for HirError {
 pub enum HirType {
     /// A pointer to another type
     Pointer(Box<Self>),
+    /// A pointer to another type that may be null.
+    /// It must be checked before it is dereferenced.
+    Nullable(Box<Self>),
     /// The unit type, or the type that represents no
     /// return value.
     Void,
@@ -217,6 +303,8 @@ pub enum HirType {
     Boolean,
     /// The character type
     Character,
+    /// A sized integer type, used to bind foreign functions
+    Integer(IntWidth),
     /// A user defined type
     Structure(Identifier),
 }
@@ -236,7 +324,12 @@ impl HirType {
             Self::Void => 0,
             // A pointer, a number, a boolean, and a character
             // all have a size of 1 on the stack
-            Self::Pointer(_) | Self::Float | Self::Boolean | Self::Character => 1,
+            Self::Pointer(_)
+            | Self::Nullable(_)
+            | Self::Float
+            | Self::Boolean
+            | Self::Character
+            | Self::Integer(_) => 1,
             Self::Structure(name) => {
                 for decl in decls {
                     if let HirDeclaration::Structure(structure) = decl {
@@ -251,10 +344,35 @@ impl HirType {
         })
     }
 
+    /// Can this type be moved without calling its copy and drop methods?
+    /// Only structures can be non-movable.
+    pub fn is_movable(&self, decls: &Vec<HirDeclaration>) -> Result<bool, HirError> {
+        if let Self::Structure(name) = self {
+            for decl in decls {
+                if let HirDeclaration::Structure(structure) = decl {
+                    if name == structure.get_name() {
+                        return Ok(structure.is_movable);
+                    }
+                }
+            }
+            Err(HirError::TypeNotDefined(name.clone()))
+        } else {
+            Ok(true)
+        }
+    }
+
     /// Is this type a pointer type?
     pub fn is_pointer(&self) -> bool {
         match self {
-            Self::Pointer(_) => true,
+            Self::Pointer(_) | Self::Nullable(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Is this type a pointer that may be null?
+    pub fn is_nullable(&self) -> bool {
+        match self {
+            Self::Nullable(_) => true,
             _ => false,
         }
     }
@@ -263,10 +381,12 @@ impl HirType {
     pub fn to_mir_type(&self) -> MirType {
         match self {
             Self::Pointer(inner) => inner.to_mir_type().refer(),
+            Self::Nullable(inner) => inner.to_mir_type().refer().nullable(),
             Self::Void => MirType::void(),
             Self::Float => MirType::float(),
             Self::Boolean => MirType::boolean(),
             Self::Character => MirType::character(),
+            Self::Integer(width) => MirType::integer(*width),
             Self::Structure(name) => MirType::structure(name.clone()),
         }
     }
@@ -276,10 +396,12 @@ impl Display for HirType {
     fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
         match self {
             Self::Pointer(t) => write!(f, "&{}", t),
+            Self::Nullable(t) => write!(f, "?{}", t),
             Self::Void => write!(f, "{}", MirType::VOID),
             Self::Float => write!(f, "{}", MirType::FLOAT),
             Self::Boolean => write!(f, "{}", MirType::BOOLEAN),
             Self::Character => write!(f, "{}", MirType::CHAR),
+            Self::Integer(width) => write!(f, "{}", width.name()),
             Self::Structure(name) => write!(f, "{}", name),
         }
     }
@@ -309,12 +431,16 @@ pub enum HirDeclaration {
     Error(String),
     /// Include a foreign file using the `extern` flag.
     Extern(String),
-    /// Set the memory used for the stack and heap.
-    Memory(i32),
+    /// Set the memory used for the stack and heap. The size is a constant,
+    /// so it can be measured in the sizes of structures.
+    Memory(HirConstant),
     /// Mark that the standard library is required for the program
     RequireStd,
     /// Mark that the standard library is not allowed for the program
     NoStd,
+    /// Mark that the core library should not be included in the program,
+    /// leaving only the virtual machine's primitives
+    NoCore,
     /// Do nothing
     Pass,
 }
@@ -328,8 +454,13 @@ pub struct HirStructure {
     name: Identifier,
     /// The size of the structure on the stack
     size: HirConstant,
+    /// The names of the structure's members
+    members: Vec<Identifier>,
     /// The list of methods for the structure.
     methods: Vec<HirFunction>,
+    /// The methods that can only be called from
+    /// within the structure's own methods.
+    private_methods: Vec<Identifier>,
     /// This represents whether or not the type is
     /// movable: if the type requires the copy and
     /// drop methods to be called. This allows
@@ -343,14 +474,18 @@ impl HirStructure {
         doc: Option<String>,
         name: Identifier,
         size: HirConstant,
+        members: Vec<Identifier>,
         methods: Vec<HirFunction>,
+        private_methods: Vec<Identifier>,
         is_movable: bool,
     ) -> Self {
         Self {
             doc,
             name,
             size,
+            members,
             methods,
+            private_methods,
             is_movable,
         }
     }
@@ -383,9 +518,12 @@ impl HirStructure {
             result += &(doc.trim().to_string() + "\n");
         }
         // Add documentation for each member function
-        // as a method
+        // as a method. Private methods are an implementation
+        // detail, so they are left out.
         for method in &self.methods {
-            result += &method.generate_docs(true)
+            if !self.private_methods.contains(&method.name) {
+                result += &method.generate_docs(true)
+            }
         }
         result
     }
@@ -404,11 +542,13 @@ impl HirStructure {
         }
 
         // Create an MIR structure with this structure's
-        // name, size, methods, and movability.
+        // name, size, members, methods, and movability.
         Ok(MirStructure::new(
             self.name.clone(),
             self.size.to_value(decls, constants)? as i32,
+            self.members.clone(),
             mir_methods,
+            self.private_methods.clone(),
             self.is_movable,
         ))
     }
@@ -427,6 +567,12 @@ pub struct HirFunction {
     return_type: HirType,
     /// The body of the function
     body: Vec<HirStatement>,
+    /// Is the function marked with `#[entry]`?
+    is_entry: bool,
+    /// The file and line the function is defined at, if it's written in Oak
+    location: Option<(String, usize)>,
+    /// Is the function marked with `#[export]`?
+    is_exported: bool,
 }
 
 impl HirFunction {
@@ -436,6 +582,7 @@ impl HirFunction {
         args: Vec<(Identifier, HirType)>,
         return_type: HirType,
         body: Vec<HirStatement>,
+        is_entry: bool,
     ) -> Self {
         Self {
             doc,
@@ -443,9 +590,24 @@ impl HirFunction {
             args,
             return_type,
             body,
+            is_entry,
+            location: None,
+            is_exported: false,
         }
     }
 
+    /// Mark the function as exported from a shared library
+    pub fn exported(mut self, is_exported: bool) -> Self {
+        self.is_exported = is_exported;
+        self
+    }
+
+    /// Set the file and line the function is defined at
+    pub fn with_location(mut self, location: Option<(String, usize)>) -> Self {
+        self.location = location;
+        self
+    }
+
     /// Generate the documentation for the function.
     fn generate_docs(&self, is_method: bool) -> String {
         let mut result = if is_method {
@@ -502,7 +664,7 @@ impl HirFunction {
         // convert it to an MIR statement.
         let mut mir_body = Vec::new();
         for stmt in self.body.clone() {
-            mir_body.push(stmt.to_mir_stmt(decls, constants)?);
+            mir_body.extend(stmt.to_mir_stmts(decls, constants)?);
         }
 
         Ok(MirFunction::new(
@@ -510,7 +672,10 @@ impl HirFunction {
             mir_args,
             self.return_type.to_mir_type(),
             mir_body,
-        ))
+            self.is_entry,
+        )
+        .exported(self.is_exported)
+        .with_location(self.location.clone()))
     }
 }
 
@@ -533,6 +698,8 @@ pub enum HirConstant {
     Multiply(Box<Self>, Box<Self>),
     /// Divide two constants
     Divide(Box<Self>, Box<Self>),
+    /// The remainder of dividing two constants
+    Modulo(Box<Self>, Box<Self>),
 
     /// Boolean And two constants
     And(Box<Self>, Box<Self>),
@@ -553,8 +720,17 @@ pub enum HirConstant {
     Constant(Identifier),
     /// Determines whether a constant is defined
     IsDefined(String),
+    /// The value of a named constant if it is defined,
+    /// and the value of a default constant otherwise
+    DefinedOr(Identifier, Box<Self>),
     /// The size of a constant
     SizeOf(HirType),
+    /// A constant rounded up to a multiple of an alignment.
+    /// This is used to pad the members of aligned structures.
+    AlignTo(Box<Self>, i32),
+    /// Determines whether a type can be moved without
+    /// calling its copy and drop methods
+    IsMovable(HirType),
     /// A constant expression that is contingent on another constant expression
     Conditional(Box<Self>, Box<Self>, Box<Self>),
 }
@@ -573,6 +749,7 @@ impl Display for HirConstant {
             Self::Subtract(l, r) => write!(f, "{}-{}", l, r),
             Self::Multiply(l, r) => write!(f, "{}*{}", l, r),
             Self::Divide(l, r) => write!(f, "{}/{}", l, r),
+            Self::Modulo(l, r) => write!(f, "{}%{}", l, r),
             Self::And(l, r) => write!(f, "{}&&{}", l, r),
             Self::Or(l, r) => write!(f, "{}||{}", l, r),
             Self::Greater(l, r) => write!(f, "{}>{}", l, r),
@@ -583,7 +760,10 @@ impl Display for HirConstant {
             Self::NotEqual(l, r) => write!(f, "{}!={}", l, r),
             Self::Constant(name) => write!(f, "{}", name),
             Self::SizeOf(name) => write!(f, "sizeof(\"{}\")", name),
+            Self::AlignTo(constant, alignment) => write!(f, "align({}, {})", constant, alignment),
             Self::IsDefined(name) => write!(f, "is_defined(\"{}\")", name),
+            Self::DefinedOr(name, default) => write!(f, "defined_or({}, {})", name, default),
+            Self::IsMovable(t) => write!(f, "is_movable({})", t),
             Self::Not(expr) => write!(f, "!{}", expr),
         }
     }
@@ -605,7 +785,8 @@ impl HirConstant {
             | Self::Add(a, _)
             | Self::Subtract(a, _)
             | Self::Multiply(a, _)
-            | Self::Divide(a, _) => a.get_type(constants)?,
+            | Self::Divide(a, _)
+            | Self::Modulo(a, _) => a.get_type(constants)?,
 
             Self::True
             | Self::False
@@ -618,7 +799,8 @@ impl HirConstant {
             | Self::LessEqual(_, _)
             | Self::Equal(_, _)
             | Self::NotEqual(_, _)
-            | Self::IsDefined(_) => HirType::Boolean,
+            | Self::IsDefined(_)
+            | Self::IsMovable(_) => HirType::Boolean,
 
             Self::Constant(name) => {
                 if let Some(value) = constants.get(name) {
@@ -628,9 +810,17 @@ impl HirConstant {
                 }
             }
 
+            Self::DefinedOr(name, default) => {
+                if let Some(value) = constants.get(name) {
+                    value.get_type(constants)?
+                } else {
+                    default.get_type(constants)?
+                }
+            }
+
             Self::Character(_) => HirType::Character,
 
-            Self::Float(_) | Self::SizeOf(_) => HirType::Float,
+            Self::Float(_) | Self::SizeOf(_) | Self::AlignTo(_, _) => HirType::Float,
         })
     }
 
@@ -639,17 +829,29 @@ impl HirConstant {
         &self,
         decls: &Vec<HirDeclaration>,
         constants: &BTreeMap<Identifier, Self>,
+    ) -> Result<f64, HirError> {
+        self.evaluate(decls, constants, &mut vec![])
+    }
+
+    /// Find a constants floating point value, keeping track of
+    /// the named constants currently being evaluated so that
+    /// a constant defined in terms of itself is caught.
+    fn evaluate(
+        &self,
+        decls: &Vec<HirDeclaration>,
+        constants: &BTreeMap<Identifier, Self>,
+        visiting: &mut Vec<Identifier>,
     ) -> Result<f64, HirError> {
         Ok(match self {
             Self::Conditional(cond, then, otherwise) => {
-                if cond.to_value(decls, constants)? != 0.0 {
+                if cond.evaluate(decls, constants, visiting)? != 0.0 {
                     // If the constant condition is true, then use
                     // the first constant branch
-                    then.to_value(decls, constants)?
+                    then.evaluate(decls, constants, visiting)?
                 } else {
                     // If the constant condition is false, then use
                     // the second constant branch
-                    otherwise.to_value(decls, constants)?
+                    otherwise.evaluate(decls, constants, visiting)?
                 }
             }
 
@@ -660,14 +862,18 @@ impl HirConstant {
             Self::Character(ch) => *ch as u8 as f64,
 
             Self::And(l, r) => {
-                if l.to_value(decls, constants)? != 0.0 && r.to_value(decls, constants)? != 0.0 {
+                if l.evaluate(decls, constants, visiting)? != 0.0
+                    && r.evaluate(decls, constants, visiting)? != 0.0
+                {
                     1.0
                 } else {
                     0.0
                 }
             }
             Self::Or(l, r) => {
-                if l.to_value(decls, constants)? != 0.0 || r.to_value(decls, constants)? != 0.0 {
+                if l.evaluate(decls, constants, visiting)? != 0.0
+                    || r.evaluate(decls, constants, visiting)? != 0.0
+                {
                     1.0
                 } else {
                     0.0
@@ -675,56 +881,90 @@ impl HirConstant {
             }
 
             Self::Equal(l, r) => {
-                if l.to_value(decls, constants)? == r.to_value(decls, constants)? {
+                if l.evaluate(decls, constants, visiting)?
+                    == r.evaluate(decls, constants, visiting)?
+                {
                     1.0
                 } else {
                     0.0
                 }
             }
             Self::NotEqual(l, r) => {
-                if l.to_value(decls, constants)? != r.to_value(decls, constants)? {
+                if l.evaluate(decls, constants, visiting)?
+                    != r.evaluate(decls, constants, visiting)?
+                {
                     1.0
                 } else {
                     0.0
                 }
             }
             Self::Greater(l, r) => {
-                if l.to_value(decls, constants)? > r.to_value(decls, constants)? {
+                if l.evaluate(decls, constants, visiting)?
+                    > r.evaluate(decls, constants, visiting)?
+                {
                     1.0
                 } else {
                     0.0
                 }
             }
             Self::Less(l, r) => {
-                if l.to_value(decls, constants)? < r.to_value(decls, constants)? {
+                if l.evaluate(decls, constants, visiting)?
+                    < r.evaluate(decls, constants, visiting)?
+                {
                     1.0
                 } else {
                     0.0
                 }
             }
             Self::GreaterEqual(l, r) => {
-                if l.to_value(decls, constants)? >= r.to_value(decls, constants)? {
+                if l.evaluate(decls, constants, visiting)?
+                    >= r.evaluate(decls, constants, visiting)?
+                {
                     1.0
                 } else {
                     0.0
                 }
             }
             Self::LessEqual(l, r) => {
-                if l.to_value(decls, constants)? <= r.to_value(decls, constants)? {
+                if l.evaluate(decls, constants, visiting)?
+                    <= r.evaluate(decls, constants, visiting)?
+                {
                     1.0
                 } else {
                     0.0
                 }
             }
 
-            Self::Add(l, r) => l.to_value(decls, constants)? + r.to_value(decls, constants)?,
-            Self::Subtract(l, r) => l.to_value(decls, constants)? - r.to_value(decls, constants)?,
-            Self::Multiply(l, r) => l.to_value(decls, constants)? * r.to_value(decls, constants)?,
-            Self::Divide(l, r) => l.to_value(decls, constants)? / r.to_value(decls, constants)?,
+            Self::Add(l, r) => {
+                l.evaluate(decls, constants, visiting)? + r.evaluate(decls, constants, visiting)?
+            }
+            Self::Subtract(l, r) => {
+                l.evaluate(decls, constants, visiting)? - r.evaluate(decls, constants, visiting)?
+            }
+            Self::Multiply(l, r) => {
+                l.evaluate(decls, constants, visiting)? * r.evaluate(decls, constants, visiting)?
+            }
+            Self::Divide(l, r) => {
+                l.evaluate(decls, constants, visiting)? / r.evaluate(decls, constants, visiting)?
+            }
+            Self::Modulo(l, r) => {
+                l.evaluate(decls, constants, visiting)? % r.evaluate(decls, constants, visiting)?
+            }
 
             Self::Constant(name) => {
+                // If this constant is already being evaluated,
+                // then its definition depends on itself.
+                if let Some(i) = visiting.iter().position(|n| n == name) {
+                    let mut cycle = visiting[i..].to_vec();
+                    cycle.push(name.clone());
+                    return Err(HirError::RecursiveConstant(cycle));
+                }
+
                 if let Some(value) = constants.get(name) {
-                    value.to_value(decls, constants)?
+                    visiting.push(name.clone());
+                    let result = value.evaluate(decls, constants, visiting)?;
+                    visiting.pop();
+                    result
                 } else {
                     return Err(HirError::ConstantNotDefined(name.clone()));
                 }
@@ -732,6 +972,19 @@ impl HirConstant {
 
             Self::SizeOf(t) => t.get_size(decls, constants)? as f64,
 
+            Self::AlignTo(constant, alignment) => {
+                let alignment = *alignment as f64;
+                (constant.evaluate(decls, constants, visiting)? / alignment).ceil() * alignment
+            }
+
+            Self::IsMovable(t) => {
+                if t.is_movable(decls)? {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+
             Self::IsDefined(name) => {
                 if let Some(value) = constants.get(name) {
                     1.0
@@ -740,8 +993,18 @@ impl HirConstant {
                 }
             }
 
+            Self::DefinedOr(name, default) => {
+                // The name is only evaluated if it is defined,
+                // so an undefined name is not an error
+                if constants.contains_key(name) {
+                    Self::Constant(name.clone()).evaluate(decls, constants, visiting)?
+                } else {
+                    default.evaluate(decls, constants, visiting)?
+                }
+            }
+
             Self::Not(constant) => {
-                if constant.to_value(decls, constants)? != 0.0 {
+                if constant.evaluate(decls, constants, visiting)? != 0.0 {
                     0.0
                 } else {
                     1.0
@@ -765,10 +1028,21 @@ pub enum HirStatement {
     /// An assignment to a dereferenced address
     AssignAddress(HirExpression, HirExpression),
 
-    /// An HIR for loop
-    For(Box<Self>, HirExpression, Box<Self>, Vec<Self>),
-    /// An HIR while loop
-    While(HirExpression, Vec<Self>),
+    /// An HIR for loop, with an optional label
+    For(
+        Option<Identifier>,
+        Box<Self>,
+        HirExpression,
+        Box<Self>,
+        Vec<Self>,
+    ),
+    /// An HIR while loop, with an optional label
+    While(Option<Identifier>, HirExpression, Vec<Self>),
+    /// Leave the innermost loop, or the loop with the label
+    Break(Option<Identifier>),
+    /// Skip to the next iteration of the innermost
+    /// loop, or of the loop with the label
+    Continue(Option<Identifier>),
     /// An HIR if statement
     If(HirExpression, Vec<Self>),
     /// An HIR if statement with an else clause
@@ -778,12 +1052,70 @@ pub enum HirStatement {
     Free(HirExpression, HirExpression),
     /// Return one or more values at the end of a function
     Return(Vec<HirExpression>),
+    /// A function defined inside of another function
+    Function(HirFunction),
+    /// Check that the structure a destructuring `let` reads
+    /// has the members it names. Unless the flag is set, with
+    /// `..`, the structure must have no other members.
+    CheckMembers(HirExpression, Vec<Identifier>, bool),
 
     /// Any expression
     Expression(HirExpression),
 }
 
 impl HirStatement {
+    /// Lower an HIR statement into equivalent MIR statements.
+    /// Branches and loops whose conditions are known at compile time
+    /// are replaced with the code that would run, if any.
+    fn to_mir_stmts(
+        &self,
+        decls: &Vec<HirDeclaration>,
+        constants: &BTreeMap<Identifier, HirConstant>,
+    ) -> Result<Vec<MirStatement>, HirError> {
+        let lower = |body: &Vec<Self>| -> Result<Vec<MirStatement>, HirError> {
+            let mut result = Vec::new();
+            for stmt in body {
+                result.extend(stmt.to_mir_stmts(decls, constants)?);
+            }
+            Ok(result)
+        };
+
+        // The branch that runs is still an if statement, so that it keeps
+        // its own scope: its variables don't shadow the enclosing block's
+        // after it ends, and they're dropped at its end.
+        let scoped = |body: &Vec<Self>| -> Result<Vec<MirStatement>, HirError> {
+            Ok(vec![MirStatement::If(MirExpression::True, lower(body)?)])
+        };
+
+        Ok(match self {
+            Self::If(cond, body) => match cond.to_constant_condition(decls, constants)? {
+                Some(true) => scoped(body)?,
+                Some(false) => vec![],
+                None => vec![self.to_mir_stmt(decls, constants)?],
+            },
+            Self::IfElse(cond, then_body, else_body) => {
+                match cond.to_constant_condition(decls, constants)? {
+                    Some(true) => scoped(then_body)?,
+                    Some(false) => scoped(else_body)?,
+                    None => vec![self.to_mir_stmt(decls, constants)?],
+                }
+            }
+            // A loop that never runs does nothing, except
+            // for the code that prepares a for loop.
+            Self::While(_, cond, _)
+                if cond.to_constant_condition(decls, constants)? == Some(false) =>
+            {
+                vec![]
+            }
+            Self::For(_, pre, cond, _, _)
+                if cond.to_constant_condition(decls, constants)? == Some(false) =>
+            {
+                pre.to_mir_stmts(decls, constants)?
+            }
+            _ => vec![self.to_mir_stmt(decls, constants)?],
+        })
+    }
+
     /// Lower an HIR statement into an equivalent MIR statement
     fn to_mir_stmt(
         &self,
@@ -808,12 +1140,13 @@ impl HirStatement {
                 expr.to_mir_expr(decls, constants)?,
             ),
 
-            Self::For(pre, cond, post, body) => {
+            Self::For(label, pre, cond, post, body) => {
                 let mut mir_body = Vec::new();
                 for stmt in body {
-                    mir_body.push(stmt.to_mir_stmt(decls, constants)?);
+                    mir_body.extend(stmt.to_mir_stmts(decls, constants)?);
                 }
                 MirStatement::For(
+                    label.clone(),
                     Box::new(pre.to_mir_stmt(decls, constants)?),
                     cond.to_mir_expr(decls, constants)?,
                     Box::new(post.to_mir_stmt(decls, constants)?),
@@ -821,18 +1154,21 @@ impl HirStatement {
                 )
             }
 
-            Self::While(cond, body) => {
+            Self::While(label, cond, body) => {
                 let mut mir_body = Vec::new();
                 for stmt in body {
-                    mir_body.push(stmt.to_mir_stmt(decls, constants)?);
+                    mir_body.extend(stmt.to_mir_stmts(decls, constants)?);
                 }
-                MirStatement::While(cond.to_mir_expr(decls, constants)?, mir_body)
+                MirStatement::While(label.clone(), cond.to_mir_expr(decls, constants)?, mir_body)
             }
+            // The variables to drop are found when the loop is resolved in MIR
+            Self::Break(label) => MirStatement::Break(label.clone(), vec![]),
+            Self::Continue(label) => MirStatement::Continue(label.clone(), vec![]),
 
             Self::If(cond, body) => {
                 let mut mir_body = Vec::new();
                 for stmt in body {
-                    mir_body.push(stmt.to_mir_stmt(decls, constants)?);
+                    mir_body.extend(stmt.to_mir_stmts(decls, constants)?);
                 }
                 MirStatement::If(cond.to_mir_expr(decls, constants)?, mir_body)
             }
@@ -841,12 +1177,12 @@ impl HirStatement {
                 // Convert the `then` case to MIR
                 let mut mir_then_body = Vec::new();
                 for stmt in then_body {
-                    mir_then_body.push(stmt.to_mir_stmt(decls, constants)?);
+                    mir_then_body.extend(stmt.to_mir_stmts(decls, constants)?);
                 }
                 // Convert the `else` case to MIR
                 let mut mir_else_body = Vec::new();
                 for stmt in else_body {
-                    mir_else_body.push(stmt.to_mir_stmt(decls, constants)?);
+                    mir_else_body.extend(stmt.to_mir_stmts(decls, constants)?);
                 }
                 MirStatement::IfElse(
                     cond.to_mir_expr(decls, constants)?,
@@ -867,6 +1203,12 @@ impl HirStatement {
                 addr.to_mir_expr(decls, constants)?,
                 size.to_mir_expr(decls, constants)?,
             ),
+            Self::Function(func) => MirStatement::Function(func.to_mir_fn(decls, constants)?),
+            Self::CheckMembers(expr, members, partial) => MirStatement::CheckMembers(
+                expr.to_mir_expr(decls, constants)?,
+                members.clone(),
+                *partial,
+            ),
 
             Self::Expression(expr) => MirStatement::Expression(expr.to_mir_expr(decls, constants)?),
         })
@@ -890,6 +1232,19 @@ pub enum HirExpression {
     Multiply(Box<Self>, Box<Self>),
     /// The division of two expressions
     Divide(Box<Self>, Box<Self>),
+    /// The remainder of the division of two expressions
+    Modulo(Box<Self>, Box<Self>),
+
+    /// The bitwise and of two expressions
+    BitAnd(Box<Self>, Box<Self>),
+    /// The bitwise or of two expressions
+    BitOr(Box<Self>, Box<Self>),
+    /// The bitwise exclusive or of two expressions
+    BitXor(Box<Self>, Box<Self>),
+    /// An expression shifted left by a number of bits
+    ShiftLeft(Box<Self>, Box<Self>),
+    /// An expression shifted right by a number of bits
+    ShiftRight(Box<Self>, Box<Self>),
 
     /// Boolean not of an expression
     Not(Box<Self>),
@@ -927,6 +1282,9 @@ pub enum HirExpression {
     Character(char),
     /// A stack allocated character array literal
     String(StringLiteral),
+    /// A mutable character array literal, such as `['a', 'b', 0]`,
+    /// stored in the local scope of the function that evaluates it
+    CharArray(Vec<Self>),
     /// A variable expression
     Variable(Identifier),
 
@@ -938,11 +1296,24 @@ pub enum HirExpression {
     /// The address of N number of free
     /// memory cells on the stack.
     Alloc(Box<Self>),
+    /// Fill `count` elements at a pointer with a value
+    Memset(Box<Self>, Box<Self>, Box<Self>),
+    /// Fill `count` elements at a pointer with zeros
+    Zero(Box<Self>, Box<Self>),
+    /// Write all of the buffered output to stdout
+    Flush,
+    /// Check that a pointer variable is not null
+    NotNull(Identifier),
 
     /// A function call
     Call(Identifier, Vec<Self>),
-    /// A foreign function call
-    ForeignCall(Identifier, Vec<Self>),
+    /// A foreign function call, which is passed the virtual
+    /// machine's context pointer if the flag is set
+    ForeignCall(Identifier, Vec<Self>, bool),
+    /// Read a foreign global variable
+    ForeignLoad(Identifier),
+    /// Write a value to a foreign global variable
+    ForeignStore(Identifier, Box<Self>),
     /// A method call on an object
     Method(Box<Self>, Identifier, Vec<Self>),
     /// An index of a pointer value
@@ -950,9 +1321,59 @@ pub enum HirExpression {
 
     /// A conditional expression
     Conditional(Box<Self>, Box<Self>, Box<Self>),
+
+    /// A block of raw VM instructions. The type is whatever
+    /// the user claims the block leaves on the stack.
+    RawAsm(HirType, Vec<AsmExpression>),
 }
 
 impl HirExpression {
+    /// If this expression is a boolean that is known at compile
+    /// time, such as `true` or a boolean constant, get its value.
+    fn to_constant_condition(
+        &self,
+        decls: &Vec<HirDeclaration>,
+        constants: &BTreeMap<Identifier, HirConstant>,
+    ) -> Result<Option<bool>, HirError> {
+        Ok(match self {
+            Self::True => Some(true),
+            Self::False => Some(false),
+            Self::Not(expr) => expr.to_constant_condition(decls, constants)?.map(|b| !b),
+            Self::And(l, r) => match (
+                l.to_constant_condition(decls, constants)?,
+                r.to_constant_condition(decls, constants)?,
+            ) {
+                (Some(l), Some(r)) => Some(l && r),
+                _ => None,
+            },
+            Self::Or(l, r) => match (
+                l.to_constant_condition(decls, constants)?,
+                r.to_constant_condition(decls, constants)?,
+            ) {
+                (Some(l), Some(r)) => Some(l || r),
+                _ => None,
+            },
+            Self::Constant(constant) => {
+                // Evaluate the constant first, so that recursive
+                // constants are reported before finding the type
+                let value = constant.to_value(decls, constants)?;
+                match constant.get_type(constants)? {
+                    HirType::Boolean => Some(value != 0.0),
+                    // Using other constants as conditions is a type error,
+                    // which is reported when the MIR is type checked.
+                    _ => None,
+                }
+            }
+            Self::Variable(name) => match constants.get(name) {
+                Some(constant) => {
+                    Self::Constant(constant.clone()).to_constant_condition(decls, constants)?
+                }
+                None => None,
+            },
+            _ => None,
+        })
+    }
+
     fn is_literal(&self) -> bool {
         match self {
             Self::Void
@@ -1056,6 +1477,36 @@ impl HirExpression {
                 Box::new(r.to_mir_expr(decls, constants)?),
             ),
 
+            Self::Modulo(l, r) => MirExpression::Modulo(
+                Box::new(l.to_mir_expr(decls, constants)?),
+                Box::new(r.to_mir_expr(decls, constants)?),
+            ),
+
+            Self::BitAnd(l, r) => MirExpression::BitAnd(
+                Box::new(l.to_mir_expr(decls, constants)?),
+                Box::new(r.to_mir_expr(decls, constants)?),
+            ),
+
+            Self::BitOr(l, r) => MirExpression::BitOr(
+                Box::new(l.to_mir_expr(decls, constants)?),
+                Box::new(r.to_mir_expr(decls, constants)?),
+            ),
+
+            Self::BitXor(l, r) => MirExpression::BitXor(
+                Box::new(l.to_mir_expr(decls, constants)?),
+                Box::new(r.to_mir_expr(decls, constants)?),
+            ),
+
+            Self::ShiftLeft(l, r) => MirExpression::ShiftLeft(
+                Box::new(l.to_mir_expr(decls, constants)?),
+                Box::new(r.to_mir_expr(decls, constants)?),
+            ),
+
+            Self::ShiftRight(l, r) => MirExpression::ShiftRight(
+                Box::new(l.to_mir_expr(decls, constants)?),
+                Box::new(r.to_mir_expr(decls, constants)?),
+            ),
+
             Self::Refer(var_name) => MirExpression::Refer(var_name.clone()),
             Self::Deref(value) => {
                 MirExpression::Deref(Box::new(value.to_mir_expr(decls, constants)?))
@@ -1064,6 +1515,13 @@ impl HirExpression {
             Self::Void => MirExpression::Void,
             Self::Character(ch) => MirExpression::Character(*ch),
             Self::String(string) => MirExpression::String(string.clone()),
+            Self::CharArray(exprs) => MirExpression::CharArray({
+                let mut result = Vec::new();
+                for expr in exprs {
+                    result.push(expr.to_mir_expr(decls, constants)?);
+                }
+                result
+            }),
 
             /// If a variable is actually a constant,
             /// replace it with its constant value
@@ -1078,8 +1536,20 @@ impl HirExpression {
             Self::Alloc(value) => {
                 MirExpression::Alloc(Box::new(value.to_mir_expr(decls, constants)?))
             }
+            Self::Memset(ptr, value, count) => MirExpression::Memset(
+                Box::new(ptr.to_mir_expr(decls, constants)?),
+                Box::new(value.to_mir_expr(decls, constants)?),
+                Box::new(count.to_mir_expr(decls, constants)?),
+            ),
+            Self::Zero(ptr, count) => MirExpression::Zero(
+                Box::new(ptr.to_mir_expr(decls, constants)?),
+                Box::new(count.to_mir_expr(decls, constants)?),
+            ),
+            Self::Flush => MirExpression::Flush,
+            Self::NotNull(name) => MirExpression::NotNull(name.clone()),
 
-            Self::TypeCast(expr, t) if expr.is_literal() && t.is_pointer() => {
+            // Literals can be cast to nullable pointers to make null pointers, `0 as ?T`
+            Self::TypeCast(expr, t) if expr.is_literal() && t.is_pointer() && !t.is_nullable() => {
                 return Err(HirError::CastLiteralAsPointer(t.clone()))
             }
 
@@ -1096,13 +1566,23 @@ impl HirExpression {
                 result
             }),
 
-            Self::ForeignCall(name, arguments) => MirExpression::ForeignCall(name.clone(), {
-                let mut result = Vec::new();
-                for arg in arguments {
-                    result.push(arg.to_mir_expr(decls, constants)?);
-                }
-                result
-            }),
+            Self::ForeignCall(name, arguments, takes_context) => MirExpression::ForeignCall(
+                check_foreign_name(name)?,
+                {
+                    let mut result = Vec::new();
+                    for arg in arguments {
+                        result.push(arg.to_mir_expr(decls, constants)?);
+                    }
+                    result
+                },
+                *takes_context,
+            ),
+
+            Self::ForeignLoad(name) => MirExpression::ForeignLoad(check_foreign_name(name)?),
+            Self::ForeignStore(name, value) => MirExpression::ForeignStore(
+                check_foreign_name(name)?,
+                Box::new(value.to_mir_expr(decls, constants)?),
+            ),
 
             Self::Method(instance, name, arguments) => MirExpression::Method(
                 Box::new(instance.to_mir_expr(decls, constants)?),
@@ -1126,6 +1606,8 @@ impl HirExpression {
                 Box::new(then.to_mir_expr(decls, constants)?),
                 Box::new(otherwise.to_mir_expr(decls, constants)?),
             ),
+
+            Self::RawAsm(t, body) => MirExpression::RawAsm(t.to_mir_type(), body.clone()),
         })
     }
 }
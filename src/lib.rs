@@ -1,11 +1,16 @@
 #![allow(warnings, clippy, unknown_lints)]
 use std::{
-    collections::BTreeMap,
-    env::consts::{FAMILY, OS},
-    fmt::Display,
+    collections::{BTreeMap, BTreeSet},
+    env::{
+        self,
+        consts::{FAMILY, OS},
+    },
+    fmt::{Display, Formatter, Result as FmtResult},
+    fs::write,
     io::Result,
     path::PathBuf,
     process::exit,
+    time::{Duration, Instant},
 };
 pub type Identifier = String;
 pub type StringLiteral = String;
@@ -14,11 +19,13 @@ pub mod asm;
 pub mod hir;
 pub mod mir;
 pub mod tir;
-use hir::{HirConstant, HirProgram};
-use tir::TirProgram;
+use asm::{AsmError, AsmProgram};
+use hir::{HirConstant, HirError, HirProgram};
+use mir::MirError;
+use tir::{TirError, TirProgram};
 
 mod target;
-pub use target::{Go, Target, C, TS};
+pub use target::{BuildInfo, Go, PanicStrategy, Ruby, Rust, Target, Wat, Zig, C, TS};
 
 use asciicolor::Colorize;
 use comment::cpp::strip;
@@ -27,7 +34,25 @@ use time::OffsetDateTime;
 use lalrpop_util::{lalrpop_mod, ParseError};
 lalrpop_mod!(pub parser);
 
-pub fn get_predefined_constants(target: &impl Target) -> BTreeMap<String, HirConstant> {
+/// The latest time that can be given with `SOURCE_DATE_EPOCH`,
+/// which is the last second of the year 9999.
+const MAX_SOURCE_DATE_EPOCH: i64 = 253_402_300_799;
+
+/// The time to use for the `DATE_*` constants and the manifest.
+/// If `SOURCE_DATE_EPOCH` is set to a number of seconds since the
+/// Unix epoch, that time is used in UTC so that builds are reproducible.
+/// Otherwise, this is the local time, or UTC if the local UTC offset
+/// can't be determined on this system. This never fails.
+fn compile_time() -> OffsetDateTime {
+    match env::var("SOURCE_DATE_EPOCH").map(|epoch| epoch.trim().parse::<i64>()) {
+        Ok(Ok(timestamp)) if (0..=MAX_SOURCE_DATE_EPOCH).contains(&timestamp) => {
+            OffsetDateTime::from_unix_timestamp(timestamp)
+        }
+        _ => OffsetDateTime::try_now_local().unwrap_or_else(|_| OffsetDateTime::now_utc()),
+    }
+}
+
+pub fn get_predefined_constants(target: &(impl Target + ?Sized)) -> BTreeMap<String, HirConstant> {
     let mut constants = BTreeMap::new();
 
     constants.insert(
@@ -52,17 +77,37 @@ pub fn get_predefined_constants(target: &impl Target) -> BTreeMap<String, HirCon
         HirConstant::boolean(FAMILY != "unix"),
     );
 
+    // Take a single snapshot of the clock so that every `DATE_*` constant
+    // describes the same instant.
+    let now = compile_time();
     constants.insert(
         String::from("DATE_DAY"),
-        HirConstant::Float(OffsetDateTime::now_local().day() as f64),
+        HirConstant::Float(now.day() as f64),
     );
     constants.insert(
         String::from("DATE_MONTH"),
-        HirConstant::Float(OffsetDateTime::now_local().month() as f64),
+        HirConstant::Float(now.month() as f64),
     );
     constants.insert(
         String::from("DATE_YEAR"),
-        HirConstant::Float(OffsetDateTime::now_local().year() as f64),
+        HirConstant::Float(now.year() as f64),
+    );
+    constants.insert(
+        String::from("DATE_HOUR"),
+        HirConstant::Float(now.hour() as f64),
+    );
+    constants.insert(
+        String::from("DATE_MINUTE"),
+        HirConstant::Float(now.minute() as f64),
+    );
+    constants.insert(
+        String::from("DATE_SECOND"),
+        HirConstant::Float(now.second() as f64),
+    );
+    // The number of seconds since the Unix epoch at compile time
+    constants.insert(
+        String::from("BUILD_TIMESTAMP"),
+        HirConstant::Float(now.timestamp() as f64),
     );
 
     constants.insert(
@@ -73,6 +118,11 @@ pub fn get_predefined_constants(target: &impl Target) -> BTreeMap<String, HirCon
         String::from("IS_STANDARD"),
         HirConstant::boolean(target.is_standard()),
     );
+    // The alignment in memory cells of addresses returned by `alloc`
+    constants.insert(
+        String::from("ALIGNMENT"),
+        HirConstant::Float(target.alignment() as f64),
+    );
 
     constants
 }
@@ -81,6 +131,9 @@ pub fn generate_docs(
     // The working directory of the input file.
     // This is where included files will be gathered from.
     cwd: &PathBuf,
+    // The directories searched for included files
+    // before the working directory
+    search_path: &[PathBuf],
     // The name of the input file to generate docs for
     filename: &str,
     // The code to generate docs for
@@ -88,7 +141,7 @@ pub fn generate_docs(
     // The target to use for the documented code's TARGET const
     target: impl Target,
 ) -> String {
-    match parse(filename, input).compile(cwd, &mut get_predefined_constants(&target)) {
+    match parse(filename, input).compile(cwd, search_path, &mut get_predefined_constants(&target)) {
         Ok(output) => output,
         Err(e) => print_compile_error(e),
     }
@@ -99,100 +152,461 @@ pub fn generate_docs(
     )
 }
 
+/// A machine readable report of every available target and what
+/// it supports, with one record of `key=value` pairs per line.
+pub fn describe_features() -> String {
+    let targets: [&dyn Target; 7] = [
+        &C::default(),
+        &Go::default(),
+        &TS::default(),
+        &Rust::default(),
+        &Zig::default(),
+        &Ruby::default(),
+        &Wat::default(),
+    ];
+
+    let mut result = format!("version={}\n", env!("CARGO_PKG_VERSION"));
+    for target in targets.iter() {
+        result += &format!(
+            "target={} language={} standard={} alignment={}\n",
+            target.get_name(),
+            target.language(),
+            target.is_standard(),
+            target.alignment()
+        );
+    }
+    result
+}
+
 fn print_compile_error(e: impl Display) -> ! {
-    eprintln!("compilation error: {}", e.bright_red().underline());
+    report_compile_error(e);
     exit(1);
 }
 
+/// Print an error without exiting, so that the errors after it are printed too
+fn report_compile_error(e: impl Display) {
+    eprintln!("compilation error: {}", e.bright_red().underline());
+}
+
+fn print_compile_warning(e: impl Display) {
+    eprintln!("compilation warning: {}", e.bright_yellow().underline());
+}
+
+/// An error from any stage of the compiler. These are returned
+/// to tools that use the compiler as a library, instead of being
+/// printed before exiting.
+#[derive(Clone, Debug)]
+pub enum OakError {
+    /// The program could not be parsed. This holds the formatted
+    /// message of each syntax error, in the order they appear.
+    Parse(Vec<String>),
+    Tir(TirError),
+    Hir(HirError),
+    Mir(MirError),
+    Asm(AsmError),
+}
+
+impl Display for OakError {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            Self::Parse(messages) => write!(f, "{}", messages.join("\n")),
+            Self::Tir(e) => write!(f, "{}", e),
+            Self::Hir(e) => write!(f, "{}", e),
+            Self::Mir(e) => write!(f, "{}", e),
+            Self::Asm(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+/// Metrics about a compilation, which are
+/// printed to stderr with the `--stats` flag.
+#[derive(Clone, Debug, Default)]
+pub struct CompileStats {
+    /// The number of declarations, including those of the libraries
+    pub declarations: usize,
+    /// The number of functions, including methods
+    pub functions: usize,
+    pub structures: usize,
+    /// The number of MIR statements in every function
+    pub statements: usize,
+    /// The size of the generated code in bytes
+    pub output_size: usize,
+    /// The time spent parsing the program and the libraries
+    pub parse_time: Duration,
+    /// The time spent lowering TIR to HIR
+    pub hir_time: Duration,
+    /// The time spent lowering HIR to MIR
+    pub mir_time: Duration,
+    /// The time spent assembling MIR for the target
+    pub assemble_time: Duration,
+}
+
+impl Display for CompileStats {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        writeln!(f, "declarations: {}", self.declarations)?;
+        writeln!(f, "functions: {}", self.functions)?;
+        writeln!(f, "structures: {}", self.structures)?;
+        writeln!(f, "MIR statements: {}", self.statements)?;
+        writeln!(f, "output size: {} bytes", self.output_size)?;
+        writeln!(f, "parse: {:?}", self.parse_time)?;
+        writeln!(f, "TIR to HIR: {:?}", self.hir_time)?;
+        writeln!(f, "HIR to MIR: {:?}", self.mir_time)?;
+        write!(f, "assemble: {:?}", self.assemble_time)
+    }
+}
+
+/// The options for compiling a program to an artifact,
+/// which are given with flags on the command line.
+#[derive(Clone, Debug, Default)]
+pub struct CompileOptions {
+    /// Indent the generated code so that it's readable
+    pub pretty: bool,
+    /// The memory used for the stack and heap when the
+    /// program doesn't set it with `#[memory(n)]`
+    pub default_memory: Option<i32>,
+    /// Leave out the core library, even if the
+    /// program doesn't use the `#[no_core]` flag
+    pub no_core: bool,
+    /// Write a map of each function's symbol in the output
+    /// code alongside the artifact, with the `.map` extension
+    pub map: bool,
+    /// Report each warning as an error, and fail
+    /// the compilation if there are any
+    pub warnings_are_errors: bool,
+    /// Print the compilation's metrics to stderr
+    pub stats: bool,
+}
+
 pub fn compile(
     // The working directory of the input file.
     // This is where included files will be gathered from.
     cwd: &PathBuf,
+    // The directories searched for included files
+    // before the working directory
+    search_path: &[PathBuf],
     // The name of the input file being compiled.
     // This is used for the `current_file()` operator
     filename: &str,
     // The code to compile
     input: impl ToString,
     // The target to compile for
-    target: impl Target,
-) -> Result<()> {
-    let mut constants = get_predefined_constants(&target);
+    target: &dyn Target,
+    options: &CompileOptions,
+) -> Result<PathBuf> {
+    let mut constants = get_predefined_constants(target);
+    let mut compile_stats = CompileStats::default();
 
     // Get the TIR code for the user's Oak code
+    let start = Instant::now();
     let mut tir = parse(filename, input);
-    // Convert the TIR to HIR
-    let mut hir = match tir.compile(cwd, &mut constants) {
-        Ok(output) => output,
+    compile_stats.parse_time += start.elapsed();
+    if let Some(memory_size) = options.default_memory {
+        if memory_size < HirProgram::MINIMUM_MEMORY_SIZE {
+            print_compile_error(HirError::MemorySizeTooSmall(memory_size))
+        }
+        tir.set_memory_size(memory_size);
+    }
+
+    let mut warning_count = 0;
+    let mut warn = |warning| {
+        warning_count += 1;
+        if options.warnings_are_errors {
+            report_compile_error(warning)
+        } else {
+            print_compile_warning(warning)
+        }
+    };
+    let lowered = lower(
+        &tir,
+        cwd,
+        search_path,
+        &mut constants,
+        options.no_core,
+        &mut warn,
+        &mut compile_stats,
+    );
+    let (asm, use_std) = match lowered {
+        Ok(lowered) => lowered,
         Err(e) => print_compile_error(e),
     };
+    // Every warning is reported before failing
+    if options.warnings_are_errors && warning_count > 0 {
+        exit(1);
+    }
 
-    // Add the core library code to the users code
-    hir.extend_declarations(
-        match parse("core.ok", include_str!("core.ok"))
-            .compile(cwd, &mut constants)
-        {
-            Ok(output) => output,
-            Err(e) => print_compile_error(e),
+    let start = Instant::now();
+    let assembled = asm.assemble(target);
+    compile_stats.assemble_time += start.elapsed();
+    match assembled {
+        Ok(mut result) => {
+            // Only the generated code is formatted, the hand
+            // written runtime is left as it is.
+            if options.pretty {
+                result = target.pretty_print(result)
+            }
+            let source = generate_source(target, filename, &asm, &result, use_std);
+            compile_stats.output_size = source.len();
+            if options.stats {
+                eprintln!("{}", compile_stats);
+            }
+            let artifact = target.compile(source)?;
+
+            if options.map {
+                write(artifact.with_extension("map"), asm.symbol_map(target))?;
+            }
+            Ok(artifact)
         }
-        .get_declarations(),
-    );
+        Err(e) => print_compile_error(e),
+    }
+}
 
+/// Generate the source code of a program for each of the given targets,
+/// without compiling it. The program is only parsed once, and targets
+/// with the same predefined constants, such as `TARGET`, share a single
+/// lowering of the program to assembly. Warnings are not reported.
+pub fn compile_all(
+    // The working directory of the input file.
+    // This is where included files will be gathered from.
+    cwd: &PathBuf,
+    // The directories searched for included files
+    // before the working directory
+    search_path: &[PathBuf],
+    // The name of the input file being compiled.
+    // This is used for the `current_file()` operator
+    filename: &str,
+    // The code to compile
+    input: impl ToString,
+    // The targets to generate code for
+    targets: &[&dyn Target],
+) -> Vec<std::result::Result<String, OakError>> {
+    let tir = match parse_program(filename, input) {
+        Ok(tir) => tir,
+        Err(e) => return targets.iter().map(|_| Err(e.clone())).collect(),
+    };
+
+    // Each lowering of the program, with the constants it was lowered with
+    let mut lowerings = Vec::new();
+    let mut results = Vec::new();
+    for target in targets {
+        let mut constants = get_predefined_constants(*target);
+        let index = match lowerings
+            .iter()
+            .position(|(predefined, _)| *predefined == constants)
+        {
+            Some(index) => index,
+            None => {
+                let predefined = constants.clone();
+                let lowered = lower(
+                    &tir,
+                    cwd,
+                    search_path,
+                    &mut constants,
+                    false,
+                    &mut |_| {},
+                    &mut CompileStats::default(),
+                );
+                lowerings.push((predefined, lowered));
+                lowerings.len() - 1
+            }
+        };
+
+        results.push(match &lowerings[index].1 {
+            Ok((asm, use_std)) => match asm.assemble(*target) {
+                Ok(result) => Ok(generate_source(*target, filename, asm, &result, *use_std)),
+                Err(e) => Err(OakError::Asm(e)),
+            },
+            Err(e) => Err(e.clone()),
+        });
+    }
+    results
+}
+
+/// Lower a parsed program, along with the core and standard libraries,
+/// to assembly. This returns the assembled program, and whether or not
+/// the standard library is used by it. The time spent in each
+/// phase, and the size of the program, are recorded in `stats`.
+fn lower(
+    tir: &TirProgram,
+    cwd: &PathBuf,
+    search_path: &[PathBuf],
+    constants: &mut BTreeMap<String, HirConstant>,
+    no_core: bool,
+    // Called with each warning about the program
+    warn: &mut dyn FnMut(String),
+    stats: &mut CompileStats,
+) -> std::result::Result<(AsmProgram, bool), OakError> {
+    // Convert the TIR to HIR
+    let start = Instant::now();
+    let mut hir = tir
+        .compile(cwd, search_path, constants)
+        .map_err(OakError::Tir)?;
+    stats.hir_time += start.elapsed();
+    if hir.get_declarations().is_empty() {
+        return Err(OakError::Hir(HirError::EmptyProgram));
+    }
+
+    // Add the core library code to the users code, unless it's left out
+    if hir.use_core() && !no_core {
+        hir.extend_declarations(
+            lower_library("core.ok", include_str!("core.ok"), cwd, constants, stats)?
+                .get_declarations(),
+        );
+    }
+
+    // The functions of the standard library, so that it can
+    // be left out if the program never uses any of them
+    let mut std_functions = BTreeSet::new();
     // If the user specifies that they want to include the standard library
     if hir.use_std() {
         // Then add the standard library code to the users code
-        hir.extend_declarations(
-            match parse("std.ok", include_str!("std.ok"))
-                .compile(cwd, &mut constants)
-            {
-                Ok(output) => output,
-                Err(e) => print_compile_error(e),
-            }
-            .get_declarations(),
-        );
+        let std = lower_library("std.ok", include_str!("std.ok"), cwd, constants, stats)?;
+        std_functions = std.function_names();
+        hir.extend_declarations(std.get_declarations());
     }
+    stats.declarations = hir.get_declarations().len();
 
-    match hir.compile(cwd, &mut constants) {
-        Ok(mir) => match mir.assemble() {
-            Ok(asm) => match asm.assemble(&target) {
-                Ok(result) => target.compile(if hir.use_std() {
-                    target.core_prelude() + &target.std() + &result + &target.core_postlude()
-                } else {
-                    target.core_prelude() + &result + &target.core_postlude()
-                }),
-                Err(e) => print_compile_error(e),
-            },
-            Err(e) => print_compile_error(e),
-        },
-        Err(e) => print_compile_error(e),
+    let start = Instant::now();
+    let mir = hir.compile(cwd, constants).map_err(OakError::Hir)?;
+    stats.mir_time += start.elapsed();
+    stats.functions = mir.function_count();
+    stats.structures = mir.structure_count();
+    stats.statements = mir.statement_count();
+    for warning in mir.warnings() {
+        warn(warning.to_string())
+    }
+
+    let start = Instant::now();
+    let mut asm = mir.assemble().map_err(OakError::Mir)?;
+    stats.assemble_time += start.elapsed();
+    // Every function is assembled, so the standard library is only
+    // needed if some function outside of it uses one of its functions.
+    let use_std = hir.use_std() && asm.uses_library(&std_functions);
+    if !use_std {
+        asm.remove_functions(&std_functions);
+    }
+
+    for warning in asm.warnings() {
+        warn(warning.to_string())
+    }
+    Ok((asm, use_std))
+}
+
+/// Parse a library, and convert it to HIR
+fn lower_library(
+    filename: &str,
+    code: &str,
+    cwd: &PathBuf,
+    constants: &mut BTreeMap<String, HirConstant>,
+    stats: &mut CompileStats,
+) -> std::result::Result<HirProgram, OakError> {
+    let start = Instant::now();
+    let tir = parse(filename, code);
+    stats.parse_time += start.elapsed();
+
+    let start = Instant::now();
+    // The libraries don't include any other files
+    let hir = tir.compile(cwd, &[], constants).map_err(OakError::Tir);
+    stats.hir_time += start.elapsed();
+    hir
+}
+
+/// Join the generated code for a target with its manifest, runtime,
+/// and the standard library if it's used.
+fn generate_source(
+    target: &(impl Target + ?Sized),
+    filename: &str,
+    asm: &AsmProgram,
+    result: &str,
+    use_std: bool,
+) -> String {
+    let manifest = target.manifest(&BuildInfo {
+        version: env!("CARGO_PKG_VERSION"),
+        target: target.language(),
+        timestamp: compile_time().format("%Y-%m-%d %H:%M:%S %z"),
+        filename: filename.to_string(),
+        memory_size: asm.memory_size(),
+    });
+
+    // Join the pieces in a single allocation
+    if use_std {
+        [
+            &manifest[..],
+            &target.core_prelude(),
+            &target.std(),
+            result,
+            &target.core_postlude(),
+        ]
+        .concat()
+    } else {
+        [
+            &manifest[..],
+            &target.core_prelude(),
+            result,
+            &target.core_postlude(),
+        ]
+        .concat()
     }
 }
 
 pub fn parse(filename: &str, input: impl ToString) -> TirProgram {
+    match parse_program(filename, input) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            eprintln!("{}", e);
+            exit(1);
+        }
+    }
+}
+
+/// Parse a program, returning the formatted parse errors if it fails.
+fn parse_program(
+    filename: &str,
+    input: impl ToString,
+) -> std::result::Result<TirProgram, OakError> {
     // Strip the user's code of all comments
     let code = &strip(input.to_string()).unwrap();
 
-    // Parse the users code and return the resulting TIR
-    match parser::ProgramParser::new().parse(filename, &code, code) {
-        // if the parser succeeds, build will succeed
-        Ok(parsed) => parsed,
-        // if the parser succeeds, annotate code with comments
+    // Parse the users code and return the resulting TIR. The parser
+    // skips past the errors it can recover from, and records them.
+    let mut recovered = Vec::new();
+    let result = parser::ProgramParser::new().parse(filename, &code, &mut recovered, code);
+    let mut errors = recovered
+        .into_iter()
+        .map(|recovery| format_error(&code, recovery.error))
+        .collect::<Vec<_>>();
+    match result {
+        // if the parser succeeds without recovering from any errors, build will succeed
+        Ok(parsed) if errors.is_empty() => Ok(parsed),
+        Ok(_) => Err(OakError::Parse(errors)),
+        // if the parser fails, annotate code with comments
         Err(e) => {
-            eprintln!("{}", format_error(&code, e));
-            exit(1);
+            errors.push(format_error(&code, e));
+            Err(OakError::Parse(errors))
         }
     }
 }
 
+/// Get every syntax error in a program, formatted for the user, without
+/// compiling it. This is empty if the program parses. Editors use this to
+/// show all of the errors in a file at once, instead of only the first.
+pub fn syntax_errors(filename: &str, input: impl ToString) -> Vec<String> {
+    match parse_program(filename, input) {
+        Err(OakError::Parse(errors)) => errors,
+        _ => Vec::new(),
+    }
+}
+
 type Error<'a, T> = ParseError<usize, T, &'a str>;
 
 /// This formats an error properly given the line, the `unexpected` token as a string,
 /// the line number, and the column number of the unexpected token.
 fn make_error(line: &str, unexpected: &str, line_number: usize, column_number: usize) -> String {
     // The string used to underline the unexpected token
+    // An empty token is still marked with a single caret.
     let underline = format!(
         "{}^{}",
         " ".repeat(column_number),
-        "-".repeat(unexpected.len() - 1)
+        "-".repeat(unexpected.len().saturating_sub(1))
     );
 
     // Format string properly and return
@@ -210,10 +624,43 @@ fn make_error(line: &str, unexpected: &str, line_number: usize, column_number: u
     )
 }
 
+/// Process the escape sequences in the contents of a string or character
+/// literal. A backslash before a character that isn't escaped is kept.
+pub fn unescape(literal: &str) -> String {
+    let mut result = String::new();
+    let mut chars = literal.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            result.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => result.push('\n'),
+            Some('r') => result.push('\r'),
+            Some('t') => result.push('\t'),
+            Some('0') => result.push('\0'),
+            Some(escaped @ '\\') | Some(escaped @ '"') | Some(escaped @ '\'') => {
+                result.push(escaped)
+            }
+            Some(other) => {
+                result.push('\\');
+                result.push(other)
+            }
+            None => result.push('\\'),
+        }
+    }
+    result
+}
+
 // Gets the line number, the line, and the column number of the error
 pub fn get_line(script: &str, location: usize) -> (usize, String, usize) {
-    // Get the line number from the character location
-    let line_number = script[..location + 1].lines().count();
+    // Get the line number from the character location. The location is
+    // one past the end of the script when the error is at the end of the file,
+    // and an empty script still has a first line to point at.
+    let line_number = script[..(location + 1).min(script.len())]
+        .lines()
+        .count()
+        .max(1);
     // Get the line from the line number
     let line = match script.lines().nth(line_number - 1) {
         Some(line) => line,
@@ -248,7 +695,11 @@ pub fn get_line(script: &str, location: usize) -> (usize, String, usize) {
     let trimmed_line = line.trim_start();
     column -= (line.len() - trimmed_line.len()) as i32;
 
-    (line_number, String::from(trimmed_line), column as usize)
+    (
+        line_number,
+        String::from(trimmed_line),
+        column.max(0) as usize,
+    )
 }
 
 /// This is used to take an LALRPOP error and convert
@@ -257,12 +708,11 @@ fn format_error<T: core::fmt::Debug>(script: &str, err: Error<T>) -> String {
     match err {
         Error::InvalidToken { location } => {
             let (line_number, line, column) = get_line(script, location);
-            make_error(
-                &line,
-                &(script.as_bytes()[location] as char).to_string(),
-                line_number,
-                column,
-            )
+            let unexpected = match script[location..].chars().next() {
+                Some(ch) => ch.to_string(),
+                None => String::from("EOF"),
+            };
+            make_error(&line, &unexpected, line_number, column)
         }
         Error::UnrecognizedEOF { location, .. } => {
             let (line_number, line, _) = get_line(script, location);
@@ -290,7 +740,7 @@ fn format_error<T: core::fmt::Debug>(script: &str, err: Error<T>) -> String {
         Error::User { error } => format!(
             "  |\n? | {}\n  | {}\n  |\n  = unexpected compiling error",
             error,
-            format!("^{}", "-".repeat(error.len() - 1))
+            format!("^{}", "-".repeat(error.len().saturating_sub(1)))
         ),
     }
 }
@@ -1,5 +1,6 @@
 use std::{
-    collections::BTreeMap,
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet},
     fmt::{Display, Error, Formatter},
     path::PathBuf,
 };
@@ -20,6 +21,8 @@ pub enum MirError {
     PrimitiveTypeRedefined(Identifier),
     /// Defining a function multiple times
     FunctionRedefined(Identifier),
+    /// Defining a function inside of a function that is itself nested
+    NestedClosure(Identifier),
     /// Using a variable without defining it
     VariableNotDefined(Identifier),
     /// Defining a method multiple times for a type
@@ -32,6 +35,10 @@ pub enum MirError {
     StructureNotDefined(Identifier),
     /// Dereferencing a non-pointer value
     DereferenceNonPointer(MirType),
+    /// Dereferencing a nullable pointer without checking it with `p?` first
+    DerefMaybeNull(MirExpression),
+    /// Checking a non-pointer variable for null with `p?`
+    NullCheckNonPointer(Identifier),
     /// Indexing a void pointer
     /// This is inherently bad because void pointers have size
     /// zero. Indexing them is the same as dereferencing, but
@@ -56,8 +63,23 @@ pub enum MirError {
     NonBooleanCondition(MirExpression),
     /// Using a non-number for an `alloc` call
     NonNumberAllocate(MirExpression),
+    /// Using something other than a pointer to a sized type
+    /// as the address in a `memset` or `zero` call
+    MemsetNonPointer(MirExpression),
+    /// Using a non-number as the count in a `memset` or `zero` call
+    NonNumberMemsetCount(MirExpression),
+    /// The value in a `memset` call is not the type that the pointer points to
+    MemsetMismatchedType(MirExpression, MirExpression),
+    /// Filling memory with copies of a value that must be dropped
+    MemsetNonMovable(MirExpression),
     /// Indexing an array with a non-number value
     NonNumberIndex(MirExpression),
+    /// Using a value that is not a character or a number
+    /// as an element of a character array literal
+    NonCharacterArrayElement(MirExpression),
+    /// Storing a value that doesn't fit in a single cell
+    /// in a foreign global variable
+    LargeForeignStore(Identifier, MirExpression),
     /// Adding, subtracting, multiplying, or dividing two
     /// values where one or more of them is not a number.
     NonNumberBinaryOperation(MirExpression, MirExpression),
@@ -90,11 +112,30 @@ pub enum MirError {
     LoopReturns(String),
     /// A non-void function never returns
     NonVoidNoReturn(String),
+    /// A non-void function can reach its end without returning
+    /// after the if statement with this condition
+    FallsThrough(String, MirExpression),
     /// Prevent memory leaks by preventing the user from calling methods
     /// on objects that will not be dropped
     MethodOnUnboundCopyDrop(MirExpression),
     /// The branches of a conditional expression have different types
     MismatchedConditionalBranchTypes(MirExpression, MirExpression),
+    /// Accessing a private member from outside of the structure's methods
+    PrivateMemberAccess(Identifier, Identifier),
+    /// A destructuring `let` names members that the
+    /// type of the destructured value doesn't have
+    UnknownDestructuredMembers(MirType, Vec<Identifier>),
+    /// Using `break` or `continue` outside of a loop
+    NotInLoop(String),
+    /// Using `break` or `continue` with the label of no enclosing loop
+    LoopLabelNotDefined(Identifier),
+    /// Calling `drop` on a variable outside of the block that defines it
+    DropOutsideOfBlock(Identifier),
+    /// Calling `drop` on a variable that has already been dropped
+    VariableDroppedTwice(Identifier),
+    /// Returning the address of a variable on the stack from the function
+    /// that defines it, which is freed when the function returns
+    ReturnStackPointer(Identifier, Identifier),
 }
 
 /// Print an MIR error on the command line
@@ -134,7 +175,22 @@ impl Display for MirError {
             Self::MethodNotDefined(t, name) => {
                 write!(f, "method '{}' is not defined for type '{}'", name, t)
             }
+            Self::NestedClosure(name) => write!(
+                f,
+                "function '{}' is defined inside of a nested function. functions can only be nested one level deep",
+                name
+            ),
             Self::DereferenceNonPointer(t) => write!(f, "cannot dereference type '{}'", t),
+            Self::DerefMaybeNull(expr) => write!(
+                f,
+                "cannot dereference '{}', which may be null. check it with 'if {}? {{ ... }}' first",
+                expr, expr
+            ),
+            Self::NullCheckNonPointer(var_name) => write!(
+                f,
+                "cannot check non-pointer '{}' for null",
+                var_name
+            ),
             Self::IndexVoidPointer(expr) => write!(f, "cannot index void pointer '{}'", expr),
             Self::AutoDefineVoidPointer(var_name, expr) => write!(
                 f,
@@ -162,11 +218,41 @@ impl Display for MirError {
                 "cannot use '{}' as a size argument in 'alloc' function",
                 size_expr
             ),
+            Self::MemsetNonPointer(ptr_expr) => write!(
+                f,
+                "cannot use '{}' as an address in 'memset' or 'zero'. the address must be a pointer to a type with a size",
+                ptr_expr
+            ),
+            Self::NonNumberMemsetCount(count_expr) => write!(
+                f,
+                "cannot use non-number '{}' as a count in 'memset' or 'zero'",
+                count_expr
+            ),
+            Self::MemsetMismatchedType(ptr_expr, value_expr) => write!(
+                f,
+                "mismatched types in 'memset': cannot fill '{}' with '{}'",
+                ptr_expr, value_expr
+            ),
+            Self::MemsetNonMovable(value_expr) => write!(
+                f,
+                "cannot fill memory with copies of non-movable value '{}'",
+                value_expr
+            ),
             Self::NonNumberIndex(idx_expr) => write!(
                 f,
                 "cannot use non-number '{}' as an index for an array",
                 idx_expr
             ),
+            Self::NonCharacterArrayElement(expr) => write!(
+                f,
+                "cannot use non-character '{}' as an element of a character array",
+                expr
+            ),
+            Self::LargeForeignStore(name, expr) => write!(
+                f,
+                "cannot store '{}' in the foreign variable '{}', because it is larger than a single cell",
+                expr, name
+            ),
             Self::NonNumberBinaryOperation(lhs, rhs) => write!(
                 f,
                 "cannot use non-numbers '{}' and '{}' in binary operation",
@@ -214,6 +300,11 @@ impl Display for MirError {
                 "the non-void function '{}' never returns an expression",
                 fn_name
             ),
+            Self::FallsThrough(fn_name, cond) => write!(
+                f,
+                "the non-void function '{}' may reach its end without returning after the if statement with the condition '{}'",
+                fn_name, cond
+            ),
             Self::MethodOnUnboundCopyDrop(method_call) => write!(
                 f,
                 "the expression '{}' calls a method on an unbound object that implements 'copy' or 'drop'. try binding the object using a let expression",
@@ -224,6 +315,349 @@ impl Display for MirError {
                 "the conditional branches '{}' and '{}' have mismatched types",
                 then, otherwise
             ),
+            Self::PrivateMemberAccess(type_name, member) => write!(
+                f,
+                "the member '{}' of type '{}' is private, and can only be accessed from within its methods",
+                member, type_name
+            ),
+            Self::UnknownDestructuredMembers(t, members) => write!(
+                f,
+                "the type '{}' does not have the members '{{{}}}'. use '..' to ignore the remaining members",
+                t,
+                members.join(", ")
+            ),
+            Self::NotInLoop(keyword) => write!(f, "'{}' used outside of a loop", keyword),
+            Self::LoopLabelNotDefined(label) => {
+                write!(f, "no enclosing loop is labeled '{}'", label)
+            }
+            Self::DropOutsideOfBlock(var_name) => write!(
+                f,
+                "the variable '{}' can only be dropped in the block it is defined in",
+                var_name
+            ),
+            Self::VariableDroppedTwice(var_name) => {
+                write!(f, "the variable '{}' is dropped more than once", var_name)
+            }
+            Self::ReturnStackPointer(fn_name, var_name) => write!(
+                f,
+                "the function '{}' returns the address of its variable '{}', which is freed when it returns. allocate it with 'alloc' instead",
+                fn_name, var_name
+            ),
+        }
+    }
+}
+
+/// A value representing a possible mistake in the MIR code
+/// that does not stop the program from compiling
+#[derive(Clone, Debug, PartialEq, PartialOrd)]
+pub enum MirWarning {
+    /// A variable defined in a block has the same name as a
+    /// variable in an enclosing block of the same function
+    ShadowedVariable(Identifier, Identifier),
+    /// A function passes a non-movable variable by value to a parameter
+    /// that the callee keeps after the call, by returning it or storing it.
+    /// The caller still drops its own copy, so ownership is easy to mistake.
+    KeptNonMovableArgument(Identifier, MirExpression, Identifier, Identifier),
+    /// A function does arithmetic on a `char` and a `num` without casting
+    /// either of them. The result takes the type of the left hand side.
+    MixedCharArithmetic(Identifier, MirExpression),
+}
+
+/// Print an MIR warning on the command line
+impl Display for MirWarning {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match self {
+            Self::ShadowedVariable(fn_name, var_name) => write!(
+                f,
+                "the variable '{}' in the function '{}' shadows a variable in an enclosing block",
+                var_name, fn_name
+            ),
+            Self::KeptNonMovableArgument(fn_name, value, callee, param) => write!(
+                f,
+                "the function '{}' passes the non-movable value '{}' by value to the parameter '{}' of '{}', which keeps it after the call. pass a reference to it instead, or give it up with 'move({})'",
+                fn_name, value, param, callee, value
+            ),
+            Self::MixedCharArithmetic(fn_name, expr) => write!(
+                f,
+                "the function '{}' mixes 'char' and 'num' in the arithmetic '{}', which has the type of its left hand side. cast one of them with 'as' to choose the type",
+                fn_name, expr
+            ),
+        }
+    }
+}
+
+/// The variables visible at a point in a function. Oak variables are
+/// lexically scoped, but the assembly of a function gives each variable
+/// name a single place on the stack. So, every variable defined with a
+/// name that is already in use is given a new name that is unique in
+/// the function.
+#[derive(Clone)]
+struct Scope {
+    /// The name of the function, for warning messages
+    fn_name: Identifier,
+    /// The variables defined in each enclosing block, from the outermost
+    /// block to the innermost. Each name maps to its unique name.
+    blocks: Vec<BTreeMap<Identifier, Identifier>>,
+    /// Every unique name given to a variable in the function
+    used: BTreeSet<Identifier>,
+    /// The functions defined in each enclosing block. Each name
+    /// maps to the name of the closure it is assembled as.
+    closures: Vec<BTreeMap<Identifier, Identifier>>,
+    /// If the function is a closure, the scope it was defined in.
+    /// The variables visible there are captured by the closure.
+    parent: Option<Box<Scope>>,
+    /// The warnings found while resolving the variables
+    warnings: Vec<MirWarning>,
+    /// The enclosing loops, from the outermost to the innermost. Each
+    /// has its label in the source, its unique label, and the number of
+    /// blocks that enclosed it.
+    loops: Vec<(Option<Identifier>, Identifier, usize)>,
+    /// The number of loops in the function
+    loop_count: usize,
+    /// The unique names of the variables that have been dropped
+    /// manually, which are not dropped again at the end of their block
+    dropped: BTreeSet<Identifier>,
+}
+
+impl Scope {
+    fn new(fn_name: Identifier) -> Self {
+        Self {
+            fn_name,
+            blocks: vec![BTreeMap::new()],
+            used: BTreeSet::new(),
+            closures: vec![BTreeMap::new()],
+            parent: None,
+            warnings: vec![],
+            loops: vec![],
+            loop_count: 0,
+            dropped: BTreeSet::new(),
+        }
+    }
+
+    /// Enter a new block
+    fn push(&mut self) {
+        self.blocks.push(BTreeMap::new());
+        self.closures.push(BTreeMap::new())
+    }
+
+    /// Leave the current block. Its variables are no longer visible.
+    fn pop(&mut self) {
+        self.blocks.pop();
+        self.closures.pop();
+    }
+
+    /// Define a variable in the current block, and get its unique name
+    fn define(&mut self, name: &Identifier) -> Identifier {
+        // Redefining a variable in the same block reuses its place on the stack
+        // and gives it a new value to drop.
+        if let Some(unique) = self.blocks.last().and_then(|block| block.get(name)) {
+            let unique = unique.clone();
+            self.dropped.remove(&unique);
+            return unique;
+        }
+
+        if self.blocks.iter().any(|block| block.contains_key(name)) {
+            self.warnings.push(MirWarning::ShadowedVariable(
+                self.fn_name.clone(),
+                name.clone(),
+            ));
+        }
+
+        // `%` can't be written in an Oak identifier, so the
+        // new name can't clash with the user's variables.
+        let mut unique = name.clone();
+        if self.used.contains(&unique) {
+            unique = format!("{}%{}", name, self.used.len());
+        }
+        self.used.insert(unique.clone());
+        if let Some(block) = self.blocks.last_mut() {
+            block.insert(name.clone(), unique.clone());
+        }
+        unique
+    }
+
+    /// Get the name a variable is written with from its unique name
+    fn original_name(unique: &Identifier) -> Identifier {
+        match unique.find('%') {
+            Some(suffix) => unique[..suffix].to_string(),
+            None => unique.clone(),
+        }
+    }
+
+    /// Get the unique name of the innermost visible variable with a given name
+    fn get(&self, name: &Identifier) -> Option<&Identifier> {
+        self.blocks.iter().rev().find_map(|block| block.get(name))
+    }
+
+    /// Get the unique name of a variable used in an expression or statement
+    fn resolve(&self, name: &Identifier) -> Result<Identifier, MirError> {
+        match self.get(name) {
+            Some(unique) => Ok(unique.clone()),
+            None => Err(MirError::VariableNotDefined(name.clone())),
+        }
+    }
+
+    /// Mark a variable as dropped by a manual call to its `drop` method, and
+    /// get its unique name. The variable must be defined in the current block,
+    /// so that it is dropped on every path to the end of the block.
+    fn drop_variable(&mut self, name: &Identifier) -> Result<Identifier, MirError> {
+        match self
+            .blocks
+            .last()
+            .and_then(|block| block.get(name))
+            .cloned()
+        {
+            Some(unique) => {
+                if self.dropped.insert(unique.clone()) {
+                    Ok(unique)
+                } else {
+                    Err(MirError::VariableDroppedTwice(name.clone()))
+                }
+            }
+            None if self.get(name).is_some() || self.capture(name).is_ok() => {
+                Err(MirError::DropOutsideOfBlock(name.clone()))
+            }
+            None => Err(MirError::VariableNotDefined(name.clone())),
+        }
+    }
+
+    /// Enter the body of a loop, and get the loop's unique label
+    fn enter_loop(&mut self, label: &Option<Identifier>) -> Identifier {
+        self.loop_count += 1;
+        let unique = format!("loop{}", self.loop_count);
+        self.loops
+            .push((label.clone(), unique.clone(), self.blocks.len()));
+        unique
+    }
+
+    /// Leave the body of the innermost loop
+    fn leave_loop(&mut self) {
+        self.loops.pop();
+    }
+
+    /// Get the unique label of the loop a `break` or `continue` jumps out of,
+    /// and the unique names of the variables defined in the blocks it leaves.
+    fn find_loop(
+        &self,
+        keyword: &str,
+        label: &Option<Identifier>,
+    ) -> Result<(Identifier, Vec<Identifier>), MirError> {
+        let found = match label {
+            Some(label) => self
+                .loops
+                .iter()
+                .rev()
+                .find(|(name, _, _)| name.as_ref() == Some(label)),
+            None => self.loops.last(),
+        };
+
+        match (found, label) {
+            (Some((_, unique, depth)), _) => Ok((
+                unique.clone(),
+                // The innermost block's variables are dropped first
+                self.blocks[*depth..]
+                    .iter()
+                    .rev()
+                    .flat_map(|block| block.values().cloned())
+                    .filter(|unique| !self.dropped.contains(unique))
+                    .collect(),
+            )),
+            (None, Some(label)) => Err(MirError::LoopLabelNotDefined(label.clone())),
+            (None, None) => Err(MirError::NotInLoop(String::from(keyword))),
+        }
+    }
+
+    /// Get the unique name, in the enclosing function, of a variable
+    /// that a closure captures
+    fn capture(&self, name: &Identifier) -> Result<Identifier, MirError> {
+        match self.parent.as_ref().and_then(|parent| parent.get(name)) {
+            Some(unique) => Ok(unique.clone()),
+            None => Err(MirError::VariableNotDefined(name.clone())),
+        }
+    }
+
+    /// Define a function in the current block, and give every variable
+    /// used in its body its unique name. The function becomes a closure
+    /// that takes the environment of this function as its last argument.
+    fn define_closure(&mut self, func: &MirFunction) -> Result<MirFunction, MirError> {
+        if self.parent.is_some() {
+            return Err(MirError::NestedClosure(func.get_name()));
+        }
+
+        let mut closure = func.as_closure(&self.fn_name);
+        // The closure is defined before its body is resolved,
+        // so that it can call itself.
+        if let Some(block) = self.closures.last_mut() {
+            block.insert(func.get_name(), closure.get_name());
+        }
+
+        let mut scope = Self::new(closure.get_name());
+        scope.parent = Some(Box::new(self.clone()));
+        closure.body = closure.resolve_body(&mut scope)?;
+        self.warnings.extend(scope.warnings);
+        Ok(closure)
+    }
+
+    /// Get the name of the closure a function call refers to, and
+    /// the environment to call it with. A closure calls the other
+    /// closures of its function with the environment it was given.
+    fn get_closure(&self, name: &Identifier) -> Option<(Identifier, MirExpression)> {
+        match self.closures.iter().rev().find_map(|block| block.get(name)) {
+            Some(closure) => Some((closure.clone(), MirExpression::Closure)),
+            None => self.parent.as_ref()?.get_closure(name).map(|(closure, _)| {
+                (
+                    closure,
+                    MirExpression::Variable(Identifier::from(AsmFunction::ENVIRONMENT)),
+                )
+            }),
+        }
+    }
+}
+
+/// The width of a sized integer type. Every sized integer is a single
+/// cell in the virtual machine, like a `num`. The width is only used to
+/// truncate values when they're cast, or passed to and from foreign functions.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub enum IntWidth {
+    I8,
+    I16,
+    I32,
+    U8,
+}
+
+impl IntWidth {
+    /// Every sized integer width
+    pub const ALL: [Self; 4] = [Self::I8, Self::I16, Self::I32, Self::U8];
+
+    /// The name of the sized integer type in Oak code
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::I8 => "i8",
+            Self::I16 => "i16",
+            Self::I32 => "i32",
+            Self::U8 => "u8",
+        }
+    }
+
+    /// Get the width of the sized integer type with this name
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|width| width.name() == name)
+    }
+
+    /// The number of bits in the integer
+    pub fn bits(&self) -> i32 {
+        match self {
+            Self::I8 | Self::U8 => 8,
+            Self::I16 => 16,
+            Self::I32 => 32,
+        }
+    }
+
+    /// Can the integer be negative?
+    pub fn is_signed(&self) -> bool {
+        match self {
+            Self::I8 | Self::I16 | Self::I32 => true,
+            Self::U8 => false,
         }
     }
 }
@@ -235,6 +669,10 @@ pub struct MirType {
     /// How many references deep this type is,
     /// or how many `&` are in front of the type.
     ptr_level: i32,
+    /// The reference level of the pointer that may be null, or zero
+    /// if no pointer may be null. The type `&?num` has a nullable level
+    /// of one, and `?&num` has a nullable level of two.
+    nullable_level: i32,
 }
 
 impl MirType {
@@ -266,7 +704,11 @@ impl MirType {
 
     /// A user defined type
     pub fn structure(name: Identifier) -> Self {
-        Self { name, ptr_level: 0 }
+        Self {
+            name,
+            ptr_level: 0,
+            nullable_level: 0,
+        }
     }
 
     /// Oak's boolean type
@@ -289,6 +731,20 @@ impl MirType {
         Self::structure(Identifier::from(Self::VOID))
     }
 
+    /// A sized integer type
+    pub fn integer(width: IntWidth) -> Self {
+        Self::structure(Identifier::from(width.name()))
+    }
+
+    /// If this is a sized integer type, get its width
+    pub fn int_width(&self) -> Option<IntWidth> {
+        if self.is_pointer() {
+            None
+        } else {
+            IntWidth::from_name(&self.name)
+        }
+    }
+
     /// Is this type a pointer?
     pub fn is_pointer(&self) -> bool {
         self.ptr_level > 0
@@ -326,6 +782,8 @@ impl MirType {
         Ok(match self.name.as_str() {
             Self::VOID => 0,
             Self::BOOLEAN | Self::FLOAT | Self::CHAR => 1,
+            // Sized integers are stored in a single cell
+            other if IntWidth::from_name(other).is_some() => 1,
             other => {
                 if let Some(structure) = structs.get(other) {
                     structure.get_size()
@@ -346,12 +804,46 @@ impl MirType {
         if self.ptr_level > 0 {
             let mut result = self.clone();
             result.ptr_level -= 1;
+            if result.nullable_level > result.ptr_level {
+                result.nullable_level = 0;
+            }
             Ok(result)
         } else {
             Err(MirError::DereferenceNonPointer(self.clone()))
         }
     }
 
+    /// Mark this pointer type as possibly null
+    pub fn nullable(&self) -> Self {
+        let mut result = self.clone();
+        result.nullable_level = self.ptr_level;
+        result
+    }
+
+    /// Is this a pointer that may be null, and so cannot be dereferenced?
+    fn is_nullable(&self) -> bool {
+        self.is_pointer() && self.nullable_level == self.ptr_level
+    }
+
+    /// The type of a nullable pointer after it has been checked for null
+    fn checked(&self) -> Self {
+        let mut result = self.clone();
+        if self.is_nullable() {
+            result.nullable_level = 0;
+        }
+        result
+    }
+
+    /// Can a value of the `value` type be stored where this type is expected?
+    /// The types must be equal, and a pointer that may be null can't be
+    /// stored as a pointer that can't, unless it is a void pointer.
+    fn accepts(&self, value: &Self) -> bool {
+        self == value
+            && (value.nullable_level == 0
+                || value.nullable_level == self.nullable_level
+                || self.is_void_ptr())
+    }
+
     fn is_void_ptr(&self) -> bool {
         self.name == Self::VOID && self.ptr_level == 1
     }
@@ -359,7 +851,7 @@ impl MirType {
     fn is_structure(&self) -> bool {
         match self.name.as_str() {
             Self::VOID | Self::BOOLEAN | Self::FLOAT | Self::CHAR => false,
-            _ => !self.is_pointer(),
+            _ => !self.is_pointer() && self.int_width().is_none(),
         }
     }
 
@@ -384,8 +876,12 @@ impl PartialEq for MirType {
 
 impl Display for MirType {
     fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
-        for _ in 0..self.ptr_level {
-            write!(f, "&")?;
+        for level in (1..=self.ptr_level).rev() {
+            if level == self.nullable_level {
+                write!(f, "?")?;
+            } else {
+                write!(f, "&")?;
+            }
         }
         write!(f, "{}", self.name)
     }
@@ -407,6 +903,100 @@ impl MirProgram {
         self.1
     }
 
+    /// Get the number of functions in the program, including methods
+    pub fn function_count(&self) -> usize {
+        self.0
+            .iter()
+            .map(|decl| match decl {
+                MirDeclaration::Function(_) => 1,
+                MirDeclaration::Structure(structure) => structure.methods.len(),
+                MirDeclaration::Extern(_) => 0,
+            })
+            .sum()
+    }
+
+    /// Get the number of structures in the program
+    pub fn structure_count(&self) -> usize {
+        self.0
+            .iter()
+            .filter(|decl| match decl {
+                MirDeclaration::Structure(_) => true,
+                _ => false,
+            })
+            .count()
+    }
+
+    /// Get the number of statements in every function and method of
+    /// the program, including those in blocks and closures.
+    pub fn statement_count(&self) -> usize {
+        self.0
+            .iter()
+            .map(|decl| match decl {
+                MirDeclaration::Function(func) => func.statement_count(),
+                MirDeclaration::Structure(structure) => structure
+                    .methods
+                    .iter()
+                    .map(MirFunction::statement_count)
+                    .sum(),
+                MirDeclaration::Extern(_) => 0,
+            })
+            .sum()
+    }
+
+    /// Get the warnings about the program that can be found before assembling it
+    pub fn warnings(&self) -> Vec<MirWarning> {
+        let mut funcs = vec![];
+        for decl in &self.0 {
+            match decl {
+                MirDeclaration::Function(func) => funcs.push(func.clone()),
+                MirDeclaration::Structure(structure) => {
+                    for method in &structure.methods {
+                        funcs.push(method.as_method(&structure.to_mir_type()))
+                    }
+                }
+                MirDeclaration::Extern(_) => {}
+            }
+        }
+
+        // Declare the functions and structures to find the types of
+        // expressions. Errors are reported when the program is assembled.
+        let mut declared = BTreeMap::new();
+        let mut structs = BTreeMap::new();
+        for decl in &self.0 {
+            let _ = match decl {
+                MirDeclaration::Function(func) => func.declare(&mut declared),
+                MirDeclaration::Structure(structure) => {
+                    structure.declare(&mut declared, &mut structs)
+                }
+                MirDeclaration::Extern(_) => Ok(()),
+            };
+        }
+
+        let mut result = vec![];
+        let mut resolved = BTreeMap::new();
+        for func in funcs {
+            // Errors are reported when the function is assembled
+            if let Ok((func, warnings)) = func.resolve_scopes() {
+                result.extend(warnings);
+                resolved.insert(func.get_name(), func);
+            }
+        }
+
+        for func in resolved.values() {
+            for stmt in MirStatement::statements(&func.body) {
+                for expr in stmt.expressions() {
+                    for (callee_name, args) in expr.calls() {
+                        if let Some(callee) = resolved.get(callee_name) {
+                            result.extend(func.check_kept_arguments(callee, args, &structs))
+                        }
+                    }
+                }
+            }
+            result.extend(func.check_arithmetic(&declared, &structs))
+        }
+        result
+    }
+
     pub fn assemble(&self) -> Result<AsmProgram, MirError> {
         let Self(decls, memory_size) = self.clone();
         let mut externs = Vec::new();
@@ -446,7 +1036,7 @@ impl MirDeclaration {
     ) -> Result<Vec<AsmFunction>, MirError> {
         Ok(match self {
             Self::Structure(structure) => structure.assemble(funcs, structs)?,
-            Self::Function(func) => vec![func.assemble(funcs, structs)?],
+            Self::Function(func) => func.assemble(funcs, structs)?,
             _ => vec![],
         })
     }
@@ -456,20 +1046,51 @@ impl MirDeclaration {
 pub struct MirStructure {
     name: Identifier,
     size: i32,
+    members: Vec<Identifier>,
     methods: Vec<MirFunction>,
+    private_methods: Vec<Identifier>,
     movable: bool,
 }
 
 impl MirStructure {
-    pub fn new(name: Identifier, size: i32, methods: Vec<MirFunction>, movable: bool) -> Self {
+    pub fn new(
+        name: Identifier,
+        size: i32,
+        members: Vec<Identifier>,
+        methods: Vec<MirFunction>,
+        private_methods: Vec<Identifier>,
+        movable: bool,
+    ) -> Self {
         Self {
             name,
             size,
+            members,
             methods,
+            private_methods,
             movable,
         }
     }
 
+    /// Can this method only be called from within the structure's own methods?
+    fn is_private(&self, method_name: &Identifier) -> bool {
+        self.private_methods.contains(method_name)
+    }
+
+    /// Does this structure have each of these members? Unless `partial`
+    /// is set, the structure must have no other members.
+    fn has_members(&self, names: &[Identifier], partial: bool) -> bool {
+        names.iter().all(|name| self.members.contains(name))
+            && (partial || names.len() == self.members.len())
+    }
+
+    /// Is the function with this name one of the structure's methods?
+    fn has_method(&self, fn_name: &Identifier) -> bool {
+        let mir_type = self.to_mir_type();
+        self.methods
+            .iter()
+            .any(|method| &mir_type.method_to_function_name(&method.name) == fn_name)
+    }
+
     /// Must this type use the drop method?
     /// Types that use non-default copy OR drop constructors
     /// must be dropped.
@@ -522,6 +1143,9 @@ impl MirStructure {
             MirType::BOOLEAN | MirType::CHAR | MirType::FLOAT | MirType::VOID => {
                 return Err(MirError::PrimitiveTypeRedefined(self.name.clone()))
             }
+            name if IntWidth::from_name(name).is_some() => {
+                return Err(MirError::PrimitiveTypeRedefined(self.name.clone()))
+            }
             _ => {}
         }
 
@@ -551,7 +1175,7 @@ impl MirStructure {
         // After each function has been declared, go back and assemble them.
         // We do two passes to allow methods to depend on one another.
         for function in &self.methods {
-            result.push(function.as_method(&mir_type).assemble(funcs, structs)?);
+            result.extend(function.as_method(&mir_type).assemble(funcs, structs)?);
         }
 
         Ok(result)
@@ -564,6 +1188,11 @@ pub struct MirFunction {
     args: Vec<(Identifier, MirType)>,
     return_type: MirType,
     body: Vec<MirStatement>,
+    is_entry: bool,
+    /// The file and line the function is defined at, if it's written in Oak
+    location: Option<(String, usize)>,
+    /// Is the function marked with `#[export]`?
+    is_exported: bool,
 }
 
 impl MirFunction {
@@ -572,15 +1201,31 @@ impl MirFunction {
         args: Vec<(Identifier, MirType)>,
         return_type: MirType,
         body: Vec<MirStatement>,
+        is_entry: bool,
     ) -> Self {
         Self {
             name,
             args,
             return_type,
             body,
+            is_entry,
+            location: None,
+            is_exported: false,
         }
     }
 
+    /// Mark the function as exported from a shared library
+    pub fn exported(mut self, is_exported: bool) -> Self {
+        self.is_exported = is_exported;
+        self
+    }
+
+    /// Set the file and line the function is defined at
+    pub fn with_location(mut self, location: Option<(String, usize)>) -> Self {
+        self.location = location;
+        self
+    }
+
     /// Convert this function to a method of a structure.
     /// This essentially renames the function to:
     /// `STRUCTURE_NAME::FUNCTION_NAME`
@@ -590,24 +1235,268 @@ impl MirFunction {
         result
     }
 
-    /// Declare this function to the compiler WITHOUT assembling it
+    /// Convert a function defined inside of the function `parent` to a
+    /// closure. This renames the function to `PARENT::FUNCTION_NAME`, and
+    /// adds the parent's environment as the last parameter.
+    fn as_closure(&self, parent: &Identifier) -> Self {
+        let mut result = self.clone();
+        result.name = format!("{}::{}", parent, self.name);
+        result.args.push((
+            Identifier::from(AsmFunction::ENVIRONMENT),
+            MirType::void().refer(),
+        ));
+        result
+    }
+
+    /// Get the number of statements in this function, including
+    /// the statements in its blocks and in its closures.
+    fn statement_count(&self) -> usize {
+        MirStatement::statements(&self.body).len()
+            + MirStatement::closures(&self.body)
+                .into_iter()
+                .map(Self::statement_count)
+                .sum::<usize>()
+    }
+
+    /// Declare this function and its closures to the compiler WITHOUT assembling them
     fn declare(&self, funcs: &mut BTreeMap<Identifier, MirFunction>) -> Result<(), MirError> {
         // Check if the function has already been declared
         if funcs.contains_key(&self.name) {
-            Err(MirError::FunctionRedefined(self.get_name()))
-        } else {
-            funcs.insert(self.get_name(), self.clone());
-            Ok(())
+            return Err(MirError::FunctionRedefined(self.get_name()));
         }
+        funcs.insert(self.get_name(), self.clone());
+
+        for func in MirStatement::closures(&self.body) {
+            func.as_closure(&self.name).declare(funcs)?
+        }
+        Ok(())
     }
 
+    /// Get the indices of the parameters that outlive a call to this function,
+    /// because the function returns them, or stores them at an address
+    /// or in a foreign variable.
+    fn kept_parameters(&self) -> Vec<usize> {
+        // Is the expression the parameter itself, moved or not?
+        let is_param = |expr: &MirExpression, name: &Identifier| match expr {
+            MirExpression::Variable(var_name) => var_name == name,
+            MirExpression::Move(inner) => match inner.as_ref() {
+                MirExpression::Variable(var_name) => var_name == name,
+                _ => false,
+            },
+            _ => false,
+        };
+
+        let mut kept = vec![];
+        for stmt in MirStatement::statements(&self.body) {
+            match stmt {
+                MirStatement::Return(exprs) => kept.extend(exprs),
+                MirStatement::AssignAddress(_, value) => kept.push(value),
+                _ => {}
+            }
+            for expr in stmt.expressions() {
+                kept.extend(expr.foreign_stores());
+            }
+        }
+
+        let mut result = vec![];
+        for (i, (arg_name, _)) in self.args.iter().enumerate() {
+            if kept.iter().any(|expr| is_param(expr, arg_name)) {
+                result.push(i)
+            }
+        }
+        result
+    }
+
+    /// Warn about the non-movable variables this function passes by value
+    /// to the parameters of `callee` that outlive the call.
+    fn check_kept_arguments(
+        &self,
+        callee: &Self,
+        args: &[MirExpression],
+        structs: &BTreeMap<Identifier, MirStructure>,
+    ) -> Vec<MirWarning> {
+        let mut result = vec![];
+        for i in callee.kept_parameters() {
+            if let (Some(arg), Some((param, param_type))) = (args.get(i), callee.args.get(i)) {
+                // Only variables are copied when they're passed, temporary
+                // values and moved values are given to the callee outright.
+                let is_copied = match arg {
+                    MirExpression::Variable(_) | MirExpression::Deref(_) => true,
+                    _ => false,
+                };
+                if is_copied && !param_type.is_movable(structs) {
+                    result.push(MirWarning::KeptNonMovableArgument(
+                        self.get_name(),
+                        arg.clone(),
+                        callee.get_name(),
+                        param.clone(),
+                    ))
+                }
+            }
+        }
+        result
+    }
+
+    /// Warn about the arithmetic in a function that mixes `char` and `num`
+    /// operands. The types of the variables are found in the order they're
+    /// defined, and expressions whose types can't be found are skipped,
+    /// because their errors are reported when the function is assembled.
+    fn check_arithmetic(
+        &self,
+        funcs: &BTreeMap<Identifier, MirFunction>,
+        structs: &BTreeMap<Identifier, MirStructure>,
+    ) -> Vec<MirWarning> {
+        let mut vars = BTreeMap::new();
+        for (arg_name, arg_type) in &self.args {
+            vars.insert(arg_name.clone(), arg_type.clone());
+        }
+        let stmts = MirStatement::statements(&self.body);
+        for stmt in &stmts {
+            match stmt {
+                MirStatement::Define(var_name, var_type, _) => {
+                    vars.insert(var_name.clone(), var_type.clone());
+                }
+                MirStatement::AutoDefine(var_name, expr) => {
+                    if let Ok(var_type) = expr.get_type(&vars, funcs, structs) {
+                        vars.insert(var_name.clone(), var_type);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let mut result = vec![];
+        for stmt in &stmts {
+            for expr in stmt.expressions() {
+                for arithmetic in expr.mixed_char_arithmetic(&vars, funcs, structs) {
+                    result.push(MirWarning::MixedCharArithmetic(
+                        self.get_name(),
+                        arithmetic.clone(),
+                    ))
+                }
+            }
+        }
+        result
+    }
+
+    /// Give every variable in the function a unique name, so that variables
+    /// defined in a block don't overwrite variables of an enclosing block.
+    /// This also returns warnings for variables that shadow other variables.
+    fn resolve_scopes(&self) -> Result<(Self, Vec<MirWarning>), MirError> {
+        let mut scope = Scope::new(self.name.clone());
+        let mut result = self.clone();
+        result.body = self.resolve_body(&mut scope)?;
+        Ok((result, scope.warnings))
+    }
+
+    /// Define the arguments of the function in a scope,
+    /// and resolve the variables used in its body.
+    fn resolve_body(&self, scope: &mut Scope) -> Result<Vec<MirStatement>, MirError> {
+        for (arg_name, _) in &self.args {
+            scope.define(arg_name);
+        }
+        self.body
+            .iter()
+            .map(|stmt| stmt.resolve_scopes(scope))
+            .collect()
+    }
+
+    /// Check that the function never returns the address of one of its
+    /// arguments or variables, which are on the stack. The variables that
+    /// are given such an address are followed through the function's body.
+    /// This must be done after the variables are given their unique names.
+    ///
+    /// Types don't record whether an address is on the stack or the heap,
+    /// so only the addresses held directly by variables are followed. An
+    /// address that is stored through a pointer or in a structure's member,
+    /// or returned by another function, is not caught.
+    fn check_stack_pointers(&self) -> Result<(), MirError> {
+        // Each variable that holds the address of a variable on the stack
+        let mut pointers = BTreeMap::new();
+        for stmt in MirStatement::statements(&self.body) {
+            match stmt {
+                MirStatement::Define(var_name, _, expr)
+                | MirStatement::AutoDefine(var_name, expr)
+                | MirStatement::AssignVariable(var_name, expr) => {
+                    match expr.stack_address(&pointers).cloned() {
+                        Some(address) => pointers.insert(var_name.clone(), address),
+                        None => pointers.remove(var_name),
+                    };
+                }
+                MirStatement::Return(exprs) => {
+                    for expr in exprs {
+                        if let Some(address) = expr.stack_address(&pointers) {
+                            return Err(MirError::ReturnStackPointer(
+                                self.get_name(),
+                                Scope::original_name(address),
+                            ));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that the private members of a structure are only used by the
+    /// structure's own methods. A function that isn't one of its methods can't
+    /// use them, even if it has a `self` parameter that points to the structure.
+    /// This must be done after the body is assembled, when every variable's
+    /// type is known.
+    fn check_private_access(
+        &self,
+        vars: &BTreeMap<Identifier, MirType>,
+        funcs: &BTreeMap<Identifier, MirFunction>,
+        structs: &BTreeMap<Identifier, MirStructure>,
+    ) -> Result<(), MirError> {
+        let mut exprs = MirStatement::statements(&self.body)
+            .into_iter()
+            .flat_map(MirStatement::expressions)
+            .collect::<Vec<_>>();
+        while let Some(expr) = exprs.pop() {
+            if let MirExpression::Method(instance, method_name, _) = expr {
+                let instance_type = instance.get_type(vars, funcs, structs)?;
+                if let Some(structure) = structs.get(&instance_type.name) {
+                    if structure.is_private(method_name) && !structure.has_method(&self.name) {
+                        return Err(MirError::PrivateMemberAccess(
+                            structure.get_name(),
+                            method_name.clone(),
+                        ));
+                    }
+                }
+            }
+            exprs.extend(expr.children());
+        }
+        Ok(())
+    }
+
+    /// Assemble the function, followed by the closures defined inside of it
     fn assemble(
         &self,
         funcs: &BTreeMap<Identifier, MirFunction>,
         structs: &BTreeMap<Identifier, MirStructure>,
-    ) -> Result<AsmFunction, MirError> {
+    ) -> Result<Vec<AsmFunction>, MirError> {
+        let (resolved, _) = self.resolve_scopes()?;
+        resolved.assemble_resolved(&BTreeMap::new(), None, funcs, structs)
+    }
+
+    /// Assemble a function whose variables have been given their unique names.
+    /// A closure can use the `captured` variables of its `parent` function.
+    fn assemble_resolved(
+        &self,
+        captured: &BTreeMap<Identifier, MirType>,
+        parent: Option<&Identifier>,
+        funcs: &BTreeMap<Identifier, MirFunction>,
+        structs: &BTreeMap<Identifier, MirStructure>,
+    ) -> Result<Vec<AsmFunction>, MirError> {
+        self.check_stack_pointers()?;
+
         let mut asm_args = Vec::new();
         let mut vars = BTreeMap::new();
+        for (var_name, var_type) in captured {
+            vars.insert(AsmFunction::captured_name(var_name), var_type.clone());
+        }
         for (arg_name, arg_type) in &self.args {
             // Add the arguments to the function's arguments, and to the map of variables.
             // The map of variables are not used to determine the function's stack size
@@ -623,14 +1512,35 @@ impl MirFunction {
         // Assemble each statement in the body
         let mut asm_body = Vec::new();
         for stmt in &self.body {
-            asm_body.extend(stmt.assemble(&mut vars, funcs, structs, &mut instance_count, &mut 0)?);
+            asm_body.extend(stmt.assemble(&mut vars, funcs, structs, &mut instance_count)?);
             stmt.type_check(&vars, funcs, structs)?
         }
+        self.check_private_access(&vars, funcs, structs)?;
+
+        // Drop the arguments and the variables defined in the function's
+        // outermost block, unless they were dropped manually. Variables
+        // defined in other blocks are dropped when their block ends.
+        let args = self.args.iter().map(|(arg_name, _)| arg_name.clone());
+        let fn_vars = MirStatement::live_variables(&self.body, args.collect());
 
-        for var_name in vars.clone().keys() {
+        for var_name in &fn_vars {
             let var_drop =
                 MirExpression::Variable(var_name.clone()).call_drop(&vars, funcs, structs)?;
-            asm_body.extend(var_drop.assemble(&mut vars, funcs, structs, &mut instance_count, &mut 0)?);
+            asm_body.extend(var_drop.assemble(&mut vars, funcs, structs, &mut instance_count)?);
+        }
+
+        // If the function is non-void and doesn't return at the end of its body,
+        // then find the if statement that control can fall through.
+        if self.return_type != MirType::void() {
+            let returns_at_end = self.body.iter().any(|stmt| match stmt {
+                MirStatement::Return(_) => true,
+                _ => false,
+            });
+            if !returns_at_end {
+                if let Some(cond) = self.body.last().and_then(MirStatement::falls_through) {
+                    return Err(MirError::FallsThrough(self.get_name(), cond));
+                }
+            }
         }
 
         // Check return type
@@ -654,12 +1564,22 @@ impl MirFunction {
             return Err(MirError::NonVoidNoReturn(self.get_name()));
         }
 
-        Ok(AsmFunction::new(
+        let mut result = vec![AsmFunction::new(
             self.name.clone(),
             asm_args,
             self.return_type.to_asm_type(structs)?,
             asm_body,
-        ))
+            self.is_entry,
+            parent.cloned(),
+        )
+        .exported(self.is_exported)
+        .with_location(self.location.clone())];
+
+        // The closures can use every variable of this function
+        for closure in MirStatement::closures(&self.body) {
+            result.extend(closure.assemble_resolved(&vars, Some(&self.name), funcs, structs)?);
+        }
+        Ok(result)
     }
 
     fn get_name(&self) -> Identifier {
@@ -687,10 +1607,23 @@ pub enum MirStatement {
     /// Assign to an address
     AssignAddress(MirExpression, MirExpression),
 
-    /// A for loop
-    For(Box<Self>, MirExpression, Box<Self>, Vec<Self>),
+    /// A for loop. Every loop is given a
+    /// unique label when the scopes are resolved.
+    For(
+        Option<Identifier>,
+        Box<Self>,
+        MirExpression,
+        Box<Self>,
+        Vec<Self>,
+    ),
     /// A while loop
-    While(MirExpression, Vec<Self>),
+    While(Option<Identifier>, MirExpression, Vec<Self>),
+    /// Leave a loop, dropping the variables
+    /// defined in the blocks that are left
+    Break(Option<Identifier>, Vec<Identifier>),
+    /// Skip to the next iteration of a loop, dropping
+    /// the variables defined in the blocks that are left
+    Continue(Option<Identifier>, Vec<Identifier>),
     /// An if statement
     If(MirExpression, Vec<Self>),
     /// An if statement with an else branch
@@ -700,11 +1633,234 @@ pub enum MirStatement {
     Free(MirExpression, MirExpression),
     /// Return one or more expressions from a function
     Return(Vec<MirExpression>),
+    /// A function defined inside of another function. It can use
+    /// the variables of the enclosing function that are visible
+    /// where it is defined.
+    Function(MirFunction),
+    /// Check that the structure a destructuring `let` reads has the
+    /// members it names. Unless the flag is set, the structure must
+    /// have no other members. This generates no code.
+    CheckMembers(MirExpression, Vec<Identifier>, bool),
     /// Use a non-void expression
     Expression(MirExpression),
 }
 
 impl MirStatement {
+    /// Give every variable used in this statement its unique name in the function
+    fn resolve_scopes(&self, scope: &mut Scope) -> Result<Self, MirError> {
+        // Resolve the statements in a block, which has its own scope
+        fn block(body: &[MirStatement], scope: &mut Scope) -> Result<Vec<MirStatement>, MirError> {
+            scope.push();
+            let result = body
+                .iter()
+                .map(|stmt| stmt.resolve_scopes(scope))
+                .collect::<Result<_, _>>();
+            scope.pop();
+            result
+        }
+
+        Ok(match self {
+            // The value is resolved before the variable is defined,
+            // so that `let x = x + 1` uses the `x` of an enclosing block.
+            Self::Define(var_name, t, expr) => {
+                let expr = expr.resolve_scopes(scope)?;
+                Self::Define(scope.define(var_name), t.clone(), expr)
+            }
+            Self::AutoDefine(var_name, expr) => {
+                let expr = expr.resolve_scopes(scope)?;
+                Self::AutoDefine(scope.define(var_name), expr)
+            }
+            // Assigning to a captured variable stores through its address
+            Self::AssignVariable(var_name, expr) => match scope.get(var_name) {
+                Some(unique) => Self::AssignVariable(unique.clone(), expr.resolve_scopes(scope)?),
+                None => Self::AssignAddress(
+                    MirExpression::Captured(scope.capture(var_name)?),
+                    expr.resolve_scopes(scope)?,
+                ),
+            },
+            Self::AssignAddress(lhs, rhs) => {
+                Self::AssignAddress(lhs.resolve_scopes(scope)?, rhs.resolve_scopes(scope)?)
+            }
+
+            // A variable defined in the first statement of a for loop
+            // belongs to the enclosing block, so it can be used after the loop.
+            Self::For(label, pre, cond, post, body) => {
+                let pre = pre.resolve_scopes(scope)?;
+                let cond = cond.resolve_scopes(scope)?;
+                let post = post.resolve_scopes(scope)?;
+                let unique = scope.enter_loop(label);
+                let body = block(body, scope)?;
+                scope.leave_loop();
+                Self::For(Some(unique), Box::new(pre), cond, Box::new(post), body)
+            }
+            Self::While(label, cond, body) => {
+                let cond = cond.resolve_scopes(scope)?;
+                let unique = scope.enter_loop(label);
+                let body = block(body, scope)?;
+                scope.leave_loop();
+                Self::While(Some(unique), cond, body)
+            }
+            Self::Break(label, _) => {
+                let (unique, dropped) = scope.find_loop("break", label)?;
+                Self::Break(Some(unique), dropped)
+            }
+            Self::Continue(label, _) => {
+                let (unique, dropped) = scope.find_loop("continue", label)?;
+                Self::Continue(Some(unique), dropped)
+            }
+            Self::If(cond, body) => Self::If(cond.resolve_scopes(scope)?, block(body, scope)?),
+            Self::IfElse(cond, then_body, else_body) => Self::IfElse(
+                cond.resolve_scopes(scope)?,
+                block(then_body, scope)?,
+                block(else_body, scope)?,
+            ),
+
+            Self::Free(addr, size) => {
+                Self::Free(addr.resolve_scopes(scope)?, size.resolve_scopes(scope)?)
+            }
+            Self::Return(exprs) => Self::Return(
+                exprs
+                    .iter()
+                    .map(|expr| expr.resolve_scopes(scope))
+                    .collect::<Result<_, _>>()?,
+            ),
+            Self::Function(func) => Self::Function(scope.define_closure(func)?),
+            Self::CheckMembers(expr, members, partial) => {
+                Self::CheckMembers(expr.resolve_scopes(scope)?, members.clone(), *partial)
+            }
+            Self::Expression(expr) => {
+                let result = Self::Expression(expr.resolve_scopes(scope)?);
+                if let Some(var_name) = self.dropped_variable() {
+                    scope.drop_variable(var_name)?;
+                }
+                result
+            }
+        })
+    }
+
+    /// Get the functions defined inside of a function body, in any block
+    fn closures(body: &[Self]) -> Vec<&MirFunction> {
+        let mut result = vec![];
+        for stmt in body {
+            match stmt {
+                Self::Function(func) => result.push(func),
+                Self::For(_, _, _, _, body) | Self::While(_, _, body) | Self::If(_, body) => {
+                    result.extend(Self::closures(body))
+                }
+                Self::IfElse(_, then_body, else_body) => {
+                    result.extend(Self::closures(then_body));
+                    result.extend(Self::closures(else_body))
+                }
+                _ => {}
+            }
+        }
+        result
+    }
+
+    /// Get every statement of a function body, including the statements
+    /// in the blocks of loops and if statements, but not the statements
+    /// of closures.
+    fn statements(body: &[Self]) -> Vec<&Self> {
+        let mut result = vec![];
+        for stmt in body {
+            result.push(stmt);
+            match stmt {
+                Self::For(_, pre, _, post, body) => {
+                    result.push(pre.as_ref());
+                    result.push(post.as_ref());
+                    result.extend(Self::statements(body))
+                }
+                Self::While(_, _, body) | Self::If(_, body) => {
+                    result.extend(Self::statements(body))
+                }
+                Self::IfElse(_, then_body, else_body) => {
+                    result.extend(Self::statements(then_body));
+                    result.extend(Self::statements(else_body))
+                }
+                _ => {}
+            }
+        }
+        result
+    }
+
+    /// Get the expressions used by this statement itself,
+    /// not including those of the statements in its blocks
+    fn expressions(&self) -> Vec<&MirExpression> {
+        match self {
+            Self::Define(_, _, expr)
+            | Self::AutoDefine(_, expr)
+            | Self::AssignVariable(_, expr)
+            | Self::For(_, _, expr, _, _)
+            | Self::While(_, expr, _)
+            | Self::If(expr, _)
+            | Self::IfElse(expr, _, _)
+            | Self::CheckMembers(expr, _, _)
+            | Self::Expression(expr) => vec![expr],
+            Self::AssignAddress(lhs, rhs) => vec![lhs, rhs],
+            Self::Free(address, size) => vec![address, size],
+            Self::Return(exprs) => exprs.iter().collect(),
+            Self::Break(_, _) | Self::Continue(_, _) | Self::Function(_) => vec![],
+        }
+    }
+
+    /// Get the name of the variable this statement defines
+    /// in its enclosing block, if any
+    fn defined_variable(&self) -> Option<&Identifier> {
+        match self {
+            Self::Define(var_name, _, _) | Self::AutoDefine(var_name, _) => Some(var_name),
+            Self::For(_, pre, _, _, _) => pre.defined_variable(),
+            _ => None,
+        }
+    }
+
+    /// Get the name of the variable this statement drops with a call to its `drop` method
+    fn dropped_variable(&self) -> Option<&Identifier> {
+        match self {
+            Self::Expression(MirExpression::Method(instance, method_name, args))
+                if method_name == "drop" && args.is_empty() =>
+            {
+                match instance.as_ref() {
+                    MirExpression::Variable(var_name) => Some(var_name),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Get the variables that still need to be dropped at the end of a block,
+    /// given those that need to be dropped at its start. The variables defined
+    /// in the block are added, and the variables dropped manually are removed.
+    fn live_variables(body: &[Self], mut vars: BTreeSet<Identifier>) -> BTreeSet<Identifier> {
+        for stmt in body {
+            if let Some(var_name) = stmt.defined_variable() {
+                vars.insert(var_name.clone());
+            } else if let Some(var_name) = stmt.dropped_variable() {
+                vars.remove(var_name);
+            }
+        }
+        vars
+    }
+
+    /// Drop the variables defined directly in a block at the end of the block
+    fn drop_block(
+        body: &[Self],
+        vars: &mut BTreeMap<Identifier, MirType>,
+        funcs: &BTreeMap<Identifier, MirFunction>,
+        structs: &BTreeMap<Identifier, MirStructure>,
+        instance_count: &mut i32,
+    ) -> Result<Vec<AsmStatement>, MirError> {
+        let block_vars = Self::live_variables(body, BTreeSet::new());
+
+        let mut result = Vec::new();
+        for var_name in &block_vars {
+            let var_drop =
+                MirExpression::Variable(var_name.clone()).call_drop(vars, funcs, structs)?;
+            result.extend(var_drop.assemble(vars, funcs, structs, instance_count)?);
+        }
+        Ok(result)
+    }
+
     /// Get the type of a statement
     fn get_type(
         &self,
@@ -736,13 +1892,26 @@ impl MirStatement {
         structs: &BTreeMap<Identifier, MirStructure>,
     ) -> Result<bool, MirError> {
         match self {
-            Self::IfElse(_, then_body, else_body) => {
+            Self::IfElse(cond, then_body, else_body) => {
+                // A pointer checked by the condition can be returned
+                // as a pointer that can't be null, if it isn't assigned
+                // in the body of the branch
+                let mut then_vars = Cow::Borrowed(vars);
+                if let MirExpression::NotNull(var_name) = cond {
+                    if !Self::assigns(then_body, var_name) {
+                        then_vars = Self::narrowed_vars(
+                            vars,
+                            &Some(var_name.clone()).into_iter().collect(),
+                        );
+                    }
+                }
+
                 // For each statement in the body, check if the
                 // statement returns a valid expression
                 let mut then_valid = false;
                 for stmt in then_body {
                     let stmt_valid =
-                        stmt.has_valid_return(func_name, return_type, vars, funcs, structs)?;
+                        stmt.has_valid_return(func_name, return_type, &then_vars, funcs, structs)?;
                     // Verify the body hasnt returned yet if this statement returns
                     if !then_valid && stmt_valid {
                         then_valid = true;
@@ -793,7 +1962,7 @@ impl MirStatement {
                 // If there is only one return argument, check the individual
                 // expression's type against the return type.
                 } else if exprs.len() == 1
-                    && return_type != &exprs[0].get_type(&vars, funcs, structs)?
+                    && !return_type.accepts(&exprs[0].get_type(&vars, funcs, structs)?)
                 {
                     return Err(MirError::MismatchedReturnType(func_name.clone()));
                 }
@@ -813,7 +1982,7 @@ impl MirStatement {
             }
 
             // Loops MUST NOT return.
-            Self::While(_, body) | Self::For(_, _, _, body) => {
+            Self::While(_, _, body) | Self::For(_, _, _, _, body) => {
                 for stmt in body {
                     if stmt.has_return() {
                         return Err(MirError::LoopReturns(func_name.clone()));
@@ -825,10 +1994,39 @@ impl MirStatement {
         }
     }
 
+    /// If control can reach the end of this statement without returning,
+    /// get the condition of the innermost if statement it falls through.
+    fn falls_through(&self) -> Option<MirExpression> {
+        // Does every path through a block return?
+        fn block_falls_through(body: &[MirStatement]) -> Option<MirExpression> {
+            match body.last() {
+                Some(MirStatement::Return(_)) | None => None,
+                Some(stmt) => stmt.falls_through(),
+            }
+        }
+
+        match self {
+            // The path where the condition is false always falls through
+            Self::If(cond, _) => Some(cond.clone()),
+            Self::IfElse(cond, then_body, else_body) => {
+                let then_returns = then_body.iter().any(Self::has_return);
+                let else_returns = else_body.iter().any(Self::has_return);
+                if then_returns && else_returns {
+                    // Both branches return somewhere, so
+                    // check the paths inside of each branch
+                    block_falls_through(then_body).or_else(|| block_falls_through(else_body))
+                } else {
+                    Some(cond.clone())
+                }
+            }
+            _ => None,
+        }
+    }
+
     /// Does this statement eventually result in a return statement?
     fn has_return(&self) -> bool {
         match self {
-            Self::For(pre, _, post, body) => {
+            Self::For(_, pre, _, post, body) => {
                 let mut result = false;
                 for stmt in body {
                     result = result || stmt.has_return();
@@ -836,7 +2034,7 @@ impl MirStatement {
                 result || pre.has_return() || post.has_return()
             }
 
-            Self::While(_, body) => {
+            Self::While(_, _, body) => {
                 for stmt in body {
                     if stmt.has_return() {
                         return true;
@@ -873,6 +2071,95 @@ impl MirStatement {
         }
     }
 
+    /// The types of the variables, where the pointers that are known
+    /// not to be null have been checked.
+    fn narrowed_vars<'a>(
+        vars: &'a BTreeMap<Identifier, MirType>,
+        narrowed: &BTreeSet<Identifier>,
+    ) -> Cow<'a, BTreeMap<Identifier, MirType>> {
+        if narrowed.is_empty() {
+            return Cow::Borrowed(vars);
+        }
+
+        let mut result = vars.clone();
+        for var_name in narrowed {
+            if let Some(t) = result.get_mut(var_name) {
+                *t = t.checked();
+            }
+        }
+        Cow::Owned(result)
+    }
+
+    /// Does any statement in this block, or in the blocks nested
+    /// inside of it, assign to the variable?
+    fn assigns(body: &[Self], var_name: &Identifier) -> bool {
+        Self::statements(body).into_iter().any(|stmt| match stmt {
+            Self::AssignVariable(assigned, _) => assigned == var_name,
+            _ => false,
+        })
+    }
+
+    /// Type check the condition of a branch or a loop
+    fn type_check_condition(
+        cond: &MirExpression,
+        vars: &BTreeMap<Identifier, MirType>,
+        funcs: &BTreeMap<Identifier, MirFunction>,
+        structs: &BTreeMap<Identifier, MirStructure>,
+    ) -> Result<(), MirError> {
+        cond.type_check(vars, funcs, structs)?;
+
+        // Confirm the condition is a boolean
+        if cond.get_type(vars, funcs, structs)? != MirType::boolean() {
+            return Err(MirError::NonBooleanCondition(cond.clone()));
+        }
+        Ok(())
+    }
+
+    /// Type check the statements of a block in order. If the condition of
+    /// an if statement checks a pointer for null, `if p? { ... }`, then the
+    /// pointer can be dereferenced in its body until a value that may be null
+    /// is assigned to it, in the body or in any block nested inside of it.
+    fn type_check_block(
+        body: &[Self],
+        vars: &BTreeMap<Identifier, MirType>,
+        narrowed: &mut BTreeSet<Identifier>,
+        funcs: &BTreeMap<Identifier, MirFunction>,
+        structs: &BTreeMap<Identifier, MirStructure>,
+    ) -> Result<(), MirError> {
+        for stmt in body {
+            stmt.type_check_narrowed(vars, narrowed, funcs, structs)?
+        }
+        Ok(())
+    }
+
+    /// Type check a loop. The body may run again after it assigns a value
+    /// that may be null to a checked pointer, so the loop is checked again
+    /// without that pointer until no more pointers are assigned.
+    fn type_check_loop(
+        cond: &MirExpression,
+        body: &[Self],
+        post: Option<&Self>,
+        vars: &BTreeMap<Identifier, MirType>,
+        narrowed: &mut BTreeSet<Identifier>,
+        funcs: &BTreeMap<Identifier, MirFunction>,
+        structs: &BTreeMap<Identifier, MirStructure>,
+    ) -> Result<(), MirError> {
+        loop {
+            Self::type_check_condition(cond, &Self::narrowed_vars(vars, narrowed), funcs, structs)?;
+
+            let mut inner = narrowed.clone();
+            Self::type_check_block(body, vars, &mut inner, funcs, structs)?;
+            if let Some(post) = post {
+                post.type_check_narrowed(vars, &mut inner, funcs, structs)?;
+            }
+
+            if inner.is_superset(narrowed) {
+                return Ok(());
+            }
+            narrowed.retain(|var_name| inner.contains(var_name));
+        }
+    }
+
     /// This function type checks a statement. Code that may compile to valid assembly
     /// can still be riddled with type errors, and type errors fuel bugs and logic errors.
     /// Enforcing checks against badly formed expressions is very important for correctness.
@@ -882,16 +2169,33 @@ impl MirStatement {
         funcs: &BTreeMap<Identifier, MirFunction>,
         structs: &BTreeMap<Identifier, MirStructure>,
     ) -> Result<(), MirError> {
+        self.type_check_narrowed(vars, &mut BTreeSet::new(), funcs, structs)
+    }
+
+    /// Type check a statement, where the pointers in `narrowed` are known
+    /// not to be null. Assigning a value that may be null to one of them
+    /// removes it from `narrowed` for the statements that follow.
+    fn type_check_narrowed(
+        &self,
+        // The declared types of the variables
+        declared: &BTreeMap<Identifier, MirType>,
+        narrowed: &mut BTreeSet<Identifier>,
+        funcs: &BTreeMap<Identifier, MirFunction>,
+        structs: &BTreeMap<Identifier, MirStructure>,
+    ) -> Result<(), MirError> {
+        let vars = &*Self::narrowed_vars(declared, narrowed);
         match self {
             Self::Define(var_name, t, expr) => {
                 expr.type_check(vars, funcs, structs)?;
                 let rhs_type = expr.get_type(vars, funcs, structs)?;
                 // Check to see if the defined type is equal to the type
                 // of the right hand side of the assignment
-                if t != &rhs_type {
+                if !t.accepts(&rhs_type) {
                     // Return a mismatched type error
                     return Err(MirError::DefineMismatchedType(var_name.clone()));
                 }
+                // A loop may define the variable again after checking it
+                narrowed.remove(var_name);
             }
 
             Self::AutoDefine(var_name, expr) => {
@@ -905,6 +2209,7 @@ impl MirStatement {
                         expr.clone(),
                     ));
                 }
+                narrowed.remove(var_name);
             }
 
             Self::AssignAddress(lhs, rhs) => {
@@ -913,10 +2218,15 @@ impl MirStatement {
                 let lhs_type = lhs.get_type(vars, funcs, structs)?;
                 let rhs_type = rhs.get_type(vars, funcs, structs)?;
 
+                // Storing at an address dereferences it
+                if lhs_type.is_nullable() {
+                    return Err(MirError::DerefMaybeNull(lhs.clone()));
+                }
+
                 // Compare the left hand side and right hand side
                 // If the LHS is a void pointer, allow the assignment.
-                // If the type *LHS is equal to RHS, also allow the assignment.
-                if lhs_type != MirType::void().refer() && lhs_type.deref()? != rhs_type {
+                // If the type *LHS accepts the RHS, also allow the assignment.
+                if lhs_type != MirType::void().refer() && !lhs_type.deref()?.accepts(&rhs_type) {
                     // Return a mismatched type error
                     return Err(MirError::AssignMismatchedType(lhs.clone()));
                 }
@@ -926,10 +2236,12 @@ impl MirStatement {
                 rhs.type_check(vars, funcs, structs)?;
                 let rhs_type = rhs.get_type(vars, funcs, structs)?;
 
-                // Check to see if the variable has been defined
-                if let Some(lhs_type) = vars.get(var_name) {
+                // Check to see if the variable has been defined. The value
+                // is checked against the declared type of the variable,
+                // not the type it has after being checked for null.
+                if let Some(lhs_type) = declared.get(var_name) {
                     // Check the LHS and RHS types
-                    if lhs_type != &rhs_type {
+                    if !lhs_type.accepts(&rhs_type) {
                         // Return a mismatched type error
                         return Err(MirError::AssignMismatchedType(MirExpression::Variable(
                             var_name.clone(),
@@ -938,63 +2250,47 @@ impl MirStatement {
                 } else {
                     return Err(MirError::VariableNotDefined(var_name.clone()));
                 }
-            }
-
-            Self::For(pre, cond, post, body) => {
-                pre.type_check(vars, funcs, structs)?;
-                cond.type_check(vars, funcs, structs)?;
-                post.type_check(vars, funcs, structs)?;
 
-                // Confirm the condition is a boolean
-                if cond.get_type(vars, funcs, structs)? != MirType::boolean() {
-                    return Err(MirError::NonBooleanCondition(cond.clone()));
-                }
-
-                for stmt in body {
-                    stmt.type_check(vars, funcs, structs)?
+                if rhs_type.is_nullable() {
+                    narrowed.remove(var_name);
                 }
             }
 
-            Self::While(cond, body) => {
-                cond.type_check(vars, funcs, structs)?;
-
-                // Confirm the condition is a boolean
-                if cond.get_type(vars, funcs, structs)? != MirType::boolean() {
-                    return Err(MirError::NonBooleanCondition(cond.clone()));
-                }
+            Self::For(_, pre, cond, post, body) => {
+                pre.type_check_narrowed(declared, narrowed, funcs, structs)?;
+                Self::type_check_loop(cond, body, Some(post), declared, narrowed, funcs, structs)?
+            }
 
-                for stmt in body {
-                    stmt.type_check(vars, funcs, structs)?
-                }
+            Self::While(_, cond, body) => {
+                Self::type_check_loop(cond, body, None, declared, narrowed, funcs, structs)?
             }
 
             Self::If(cond, body) => {
-                cond.type_check(vars, funcs, structs)?;
-
-                // Confirm the condition is a boolean
-                if cond.get_type(vars, funcs, structs)? != MirType::boolean() {
-                    return Err(MirError::NonBooleanCondition(cond.clone()));
-                }
+                Self::type_check_condition(cond, vars, funcs, structs)?;
 
-                for stmt in body {
-                    stmt.type_check(vars, funcs, structs)?
+                let mut inner = narrowed.clone();
+                if let MirExpression::NotNull(var_name) = cond {
+                    inner.insert(var_name.clone());
                 }
+                Self::type_check_block(body, declared, &mut inner, funcs, structs)?;
+                narrowed.retain(|var_name| inner.contains(var_name));
             }
 
             Self::IfElse(cond, then_body, else_body) => {
-                cond.type_check(vars, funcs, structs)?;
+                Self::type_check_condition(cond, vars, funcs, structs)?;
 
-                // Confirm the condition is a boolean
-                if cond.get_type(vars, funcs, structs)? != MirType::boolean() {
-                    return Err(MirError::NonBooleanCondition(cond.clone()));
+                let mut then_narrowed = narrowed.clone();
+                if let MirExpression::NotNull(var_name) = cond {
+                    then_narrowed.insert(var_name.clone());
                 }
+                Self::type_check_block(then_body, declared, &mut then_narrowed, funcs, structs)?;
 
-                for stmt in then_body {
-                    stmt.type_check(vars, funcs, structs)?
-                }
-                for stmt in else_body {
-                    stmt.type_check(vars, funcs, structs)?
-                }
+                let mut else_narrowed = narrowed.clone();
+                Self::type_check_block(else_body, declared, &mut else_narrowed, funcs, structs)?;
+
+                narrowed.retain(|var_name| {
+                    then_narrowed.contains(var_name) && else_narrowed.contains(var_name)
+                });
             }
 
             Self::Return(exprs) => {
@@ -1013,9 +2309,23 @@ impl MirStatement {
                 }
             }
 
+            // A closure is type checked when it is assembled
+            Self::Function(_) => {}
+
+            Self::CheckMembers(expr, members, partial) => {
+                let t = expr.get_type(vars, funcs, structs)?;
+                match structs.get(&t.name) {
+                    Some(structure) if structure.has_members(members, *partial) => {}
+                    _ => return Err(MirError::UnknownDestructuredMembers(t, members.clone())),
+                }
+            }
+
+            // The loop is found when the scopes are resolved
+            Self::Break(_, _) | Self::Continue(_, _) => {}
+
             Self::Expression(expr) => {
                 expr.type_check(vars, funcs, structs)?;
-                if let MirExpression::ForeignCall(_, _) = expr {
+                if let MirExpression::ForeignCall(_, _, _) = expr {
                     // If the expression is a foreign call, then we
                     // trust that the user is calling a void foreign
                     // function.
@@ -1039,7 +2349,6 @@ impl MirStatement {
         // variable so that it may be dropped later. This counts the number of temporary
         // instances there currently are in the function.
         instance_count: &mut i32,
-        if_var_count: &mut i32,
     ) -> Result<Vec<AsmStatement>, MirError> {
         Ok(match self {
             /// Define a variable with a given type
@@ -1055,7 +2364,6 @@ impl MirStatement {
                     funcs,
                     structs,
                     instance_count,
-                    if_var_count,
                 )?);
                 // Allocate the variable on the stack, and store the
                 // expression at the variable's new address
@@ -1073,8 +2381,7 @@ impl MirStatement {
                 expr.get_type(vars, funcs, structs)?,
                 expr.call_copy(vars, funcs, structs)?,
             )
-            .assemble(vars, funcs, structs, instance_count,
-                if_var_count,)?,
+            .assemble(vars, funcs, structs, instance_count)?,
 
             /// Assign an expression to a defined variable
             Self::AssignVariable(var_name, expr) => {
@@ -1087,7 +2394,6 @@ impl MirStatement {
                         funcs,
                         structs,
                         instance_count,
-                        if_var_count,
                     )?);
                     // Store the expression at the address of the variable
                     result.extend(vec![
@@ -1110,150 +2416,130 @@ impl MirStatement {
                     funcs,
                     structs,
                     instance_count,
-                    if_var_count,
                 )?);
                 // Push the address to dereference onto the stack
-                result.extend(lhs.assemble(vars, funcs, structs, instance_count, if_var_count)?);
+                result.extend(lhs.assemble(vars, funcs, structs, instance_count)?);
                 result.push(AsmStatement::Assign(
                     rhs.get_type(vars, funcs, structs)?.to_asm_type(structs)?,
                 ));
                 result
             }
 
-            Self::For(pre, cond, post, body) => {
+            Self::For(label, pre, cond, post, body) => {
                 // Assemble the `pre` condition first so that
                 // if a variable is defined in this statement,
                 // it is defined for the rest of the loop.
-                let asm_pre = pre.assemble(vars, funcs, structs, instance_count,
-                    if_var_count,)?;
+                let asm_pre = pre.assemble(vars, funcs, structs, instance_count)?;
                 let mut asm_body = Vec::new();
                 for stmt in body {
-                    asm_body.extend(stmt.assemble(vars, funcs, structs, instance_count,
-                        if_var_count,)?);
+                    asm_body.extend(stmt.assemble(vars, funcs, structs, instance_count)?);
                 }
+                // The variables of the loop body are dropped at the end of each iteration
+                asm_body.extend(Self::drop_block(
+                    body,
+                    vars,
+                    funcs,
+                    structs,
+                    instance_count,
+                )?);
                 vec![AsmStatement::For(
+                    label.clone(),
                     asm_pre,
-                    cond.assemble(vars, funcs, structs, instance_count, if_var_count)?,
-                    post.assemble(vars, funcs, structs, instance_count,
-                        if_var_count,)?,
+                    cond.assemble(vars, funcs, structs, instance_count)?,
+                    post.assemble(vars, funcs, structs, instance_count)?,
                     asm_body,
                 )]
             }
 
-            Self::While(cond, body) => {
+            Self::While(label, cond, body) => {
                 let mut asm_body = Vec::new();
                 for stmt in body {
-                    asm_body.extend(stmt.assemble(vars, funcs, structs, instance_count,
-                        if_var_count)?);
+                    asm_body.extend(stmt.assemble(vars, funcs, structs, instance_count)?);
                 }
+                // The variables of the loop body are dropped at the end of each iteration
+                asm_body.extend(Self::drop_block(
+                    body,
+                    vars,
+                    funcs,
+                    structs,
+                    instance_count,
+                )?);
                 // Create a for loop using only a condition.
                 vec![AsmStatement::For(
+                    label.clone(),
                     vec![],
-                    cond.assemble(vars, funcs, structs, instance_count, if_var_count)?,
+                    cond.assemble(vars, funcs, structs, instance_count)?,
                     vec![],
                     asm_body,
                 )]
             }
 
             Self::If(cond, body) => {
-                *if_var_count += 1;
-                let if_var = *if_var_count;
-                
                 let mut asm_body = Vec::new();
                 for stmt in body {
-                    asm_body.extend(stmt.assemble(vars, funcs, structs, instance_count,
-                        if_var_count)?);
+                    asm_body.extend(stmt.assemble(vars, funcs, structs, instance_count)?);
                 }
+                asm_body.extend(Self::drop_block(
+                    body,
+                    vars,
+                    funcs,
+                    structs,
+                    instance_count,
+                )?);
 
-                // Use a variable to store the condition of the if statement
-                let mut pre = Vec::new();
-                pre.extend(cond.assemble(vars, funcs, structs, instance_count, if_var_count)?);
-                pre.extend(vec![
-                    AsmStatement::Define(Identifier::from(format!("%IF_VAR_{}%", if_var)), AsmType::float()),
-                    AsmStatement::Assign(AsmType::float()),
-                ]);
-
-                // At the end of the loop body, store zero in the condition variable
-                // to prevent the statement from doing more than one loop.
-                let mut post = Vec::new();
-                post.extend(vec![
-                    AsmStatement::Expression(vec![
-                        AsmExpression::Float(0.0),
-                        AsmExpression::Refer(Identifier::from(format!("%IF_VAR_{}%", if_var))),
-                    ]),
-                    AsmStatement::Assign(AsmType::float()),
-                ]);
-
-                vec![AsmStatement::For(
-                    pre,
-                    vec![AsmStatement::Expression(vec![AsmExpression::Variable(
-                        Identifier::from(format!("%IF_VAR_{}%", if_var)),
-                    )])],
-                    post,
+                vec![AsmStatement::If(
+                    cond.assemble(vars, funcs, structs, instance_count)?,
                     asm_body,
+                    vec![],
                 )]
             }
 
             Self::IfElse(cond, then_body, else_body) => {
-                *if_var_count += 1;
-                let if_var = *if_var_count;
-
                 let mut asm_then_body = Vec::new();
                 for stmt in then_body {
-                    asm_then_body.extend(stmt.assemble(vars, funcs, structs, instance_count, if_var_count)?);
+                    asm_then_body.extend(stmt.assemble(vars, funcs, structs, instance_count)?);
                 }
+                asm_then_body.extend(Self::drop_block(
+                    then_body,
+                    vars,
+                    funcs,
+                    structs,
+                    instance_count,
+                )?);
 
                 let mut asm_else_body = Vec::new();
                 for stmt in else_body {
-                    asm_else_body.extend(stmt.assemble(vars, funcs, structs, instance_count, if_var_count)?);
-                }
-
-                // Use a variable to store the condition of the if statement
-                let mut pre = Vec::new();
-                pre.extend(cond.assemble(vars, funcs, structs, instance_count, if_var_count)?);
-                pre.extend(vec![
-                    AsmStatement::Define(Identifier::from(format!("%IF_VAR_{}%", if_var)), AsmType::float()),
-                    AsmStatement::Assign(AsmType::float()),
-                    AsmStatement::Expression(vec![AsmExpression::Float(1.0)]),
-                    AsmStatement::Define(Identifier::from(format!("%ELSE_VAR_{}%", if_var)), AsmType::float()),
-                    AsmStatement::Assign(AsmType::float()),
-                ]);
+                    asm_else_body.extend(stmt.assemble(vars, funcs, structs, instance_count)?);
+                }
+                asm_else_body.extend(Self::drop_block(
+                    else_body,
+                    vars,
+                    funcs,
+                    structs,
+                    instance_count,
+                )?);
 
-                // At the end of the loop body, store zero in the condition variable
-                // to prevent the statement from doing more than one loop.
-                let mut post = Vec::new();
-                post.extend(vec![
-                    AsmStatement::Expression(vec![
-                        AsmExpression::Float(0.0),
-                        AsmExpression::Refer(Identifier::from(format!("%IF_VAR_{}%", if_var))),
-                    ]),
-                    AsmStatement::Assign(AsmType::float()),
-                    AsmStatement::Expression(vec![
-                        AsmExpression::Float(0.0),
-                        AsmExpression::Refer(Identifier::from(format!("%ELSE_VAR_{}%", if_var))),
-                    ]),
-                    AsmStatement::Assign(AsmType::float()),
-                ]);
+                vec![AsmStatement::If(
+                    cond.assemble(vars, funcs, structs, instance_count)?,
+                    asm_then_body,
+                    asm_else_body,
+                )]
+            }
 
-                // The resulting code for an if-else statement!
-                vec![
-                    AsmStatement::For(
-                        pre,
-                        vec![AsmStatement::Expression(vec![AsmExpression::Variable(
-                            Identifier::from(format!("%IF_VAR_{}%", if_var)),
-                        )])],
-                        post.clone(),
-                        asm_then_body,
-                    ),
-                    AsmStatement::For(
-                        vec![],
-                        vec![AsmStatement::Expression(vec![AsmExpression::Variable(
-                            Identifier::from(format!("%ELSE_VAR_{}%", if_var)),
-                        )])],
-                        post,
-                        asm_else_body,
-                    ),
-                ]
+            Self::Break(label, dropped) | Self::Continue(label, dropped) => {
+                let mut result = Vec::new();
+                for var_name in dropped {
+                    let var_drop = MirExpression::Variable(var_name.clone())
+                        .call_drop(vars, funcs, structs)?;
+                    result.extend(var_drop.assemble(vars, funcs, structs, instance_count)?);
+                }
+                // Every loop is given a label when the scopes are resolved
+                let label = label.clone().unwrap_or_default();
+                result.push(match self {
+                    Self::Break(_, _) => AsmStatement::Break(label),
+                    _ => AsmStatement::Continue(label),
+                });
+                result
             }
 
             Self::Return(exprs) => {
@@ -1264,7 +2550,6 @@ impl MirStatement {
                         funcs,
                         structs,
                         instance_count,
-                        if_var_count
                     )?)
                 }
                 result
@@ -1273,13 +2558,19 @@ impl MirStatement {
             /// Freeing an address does not return a value, so it is a statement.
             Self::Free(addr, size) => {
                 let mut result = Vec::new();
-                result.extend(size.assemble(vars, funcs, structs, instance_count, if_var_count)?);
-                result.extend(addr.assemble(vars, funcs, structs, instance_count, if_var_count)?);
+                result.extend(size.assemble(vars, funcs, structs, instance_count)?);
+                result.extend(addr.assemble(vars, funcs, structs, instance_count)?);
                 result.push(AsmStatement::Expression(vec![AsmExpression::Free]));
                 result
             }
 
-            Self::Expression(expr) => expr.assemble(vars, funcs, structs, instance_count, if_var_count)?,
+            /// Closures are assembled separately, after the function they are defined in
+            Self::Function(_) => vec![],
+
+            /// The members were already checked with the program's types
+            Self::CheckMembers(_, _, _) => vec![],
+
+            Self::Expression(expr) => expr.assemble(vars, funcs, structs, instance_count)?,
         })
     }
 }
@@ -1299,6 +2590,20 @@ pub enum MirExpression {
     Multiply(Box<Self>, Box<Self>),
     /// Divide two expressions
     Divide(Box<Self>, Box<Self>),
+    /// The remainder of dividing two expressions
+    Modulo(Box<Self>, Box<Self>),
+
+    /// Bitwise operations on two expressions. Each side is
+    /// converted to a 32 bit signed integer first, so the fraction
+    /// is discarded, and the integer wraps around like an `i32`.
+    BitAnd(Box<Self>, Box<Self>),
+    BitOr(Box<Self>, Box<Self>),
+    BitXor(Box<Self>, Box<Self>),
+    /// Shift the left hand side by the number of bits on the
+    /// right hand side. Only the lowest 5 bits of the count are
+    /// used, and shifting right keeps the sign.
+    ShiftLeft(Box<Self>, Box<Self>),
+    ShiftRight(Box<Self>, Box<Self>),
 
     /// Boolean not an expression
     Not(Box<Self>),
@@ -1322,6 +2627,8 @@ pub enum MirExpression {
 
     /// A string literal
     String(StringLiteral),
+    /// A character array literal
+    CharArray(Vec<Self>),
     /// A float literal
     Float(f64),
     /// A character literal
@@ -1337,6 +2644,12 @@ pub enum MirExpression {
     Variable(Identifier),
     /// A reference to a variable
     Refer(Identifier),
+    /// A reference to a variable that a closure captures
+    /// from the function it is defined in
+    Captured(Identifier),
+    /// The environment of the current function, which
+    /// its closures use to find the variables they capture
+    Closure,
     /// A dereferenced address
     Deref(Box<Self>),
 
@@ -1344,20 +2657,279 @@ pub enum MirExpression {
     TypeCast(Box<Self>, MirType),
     /// Allocated data on the heap
     Alloc(Box<Self>),
+    /// Fill `count` elements at a pointer with a value
+    Memset(Box<Self>, Box<Self>, Box<Self>),
+    /// Fill `count` elements at a pointer with zeros
+    Zero(Box<Self>, Box<Self>),
+    /// Write all of the buffered output to stdout
+    Flush,
+    /// Check that a pointer variable is not null
+    NotNull(Identifier),
 
     /// Call a function
     Call(Identifier, Vec<Self>),
-    /// Call a foreign function
-    ForeignCall(Identifier, Vec<Self>),
+    /// Call a foreign function, passing it the virtual
+    /// machine's context pointer if the flag is set
+    ForeignCall(Identifier, Vec<Self>, bool),
+    /// Read a foreign global variable
+    ForeignLoad(Identifier),
+    /// Write a single cell to a foreign global variable
+    ForeignStore(Identifier, Box<Self>),
     /// Call a method on an object
     Method(Box<Self>, Identifier, Vec<Self>),
     /// Index a pointer
     Index(Box<Self>, Box<Self>),
     /// A conditional expression
     Conditional(Box<Self>, Box<Self>, Box<Self>),
+    /// A block of raw VM instructions that bypasses type checking.
+    /// The type is the type the user declares the block to leave
+    /// on the stack.
+    RawAsm(MirType, Vec<AsmExpression>),
 }
 
 impl MirExpression {
+    /// Get the variable on the stack whose address this expression may evaluate
+    /// to, if any. `pointers` holds the variables that hold such addresses.
+    fn stack_address<'a>(
+        &'a self,
+        pointers: &'a BTreeMap<Identifier, Identifier>,
+    ) -> Option<&'a Identifier> {
+        match self {
+            Self::Refer(name) => Some(name),
+            Self::Variable(name) => pointers.get(name),
+            Self::Move(expr) | Self::TypeCast(expr, _) => expr.stack_address(pointers),
+            // Offsetting the address still points into the stack
+            Self::Add(l, r) | Self::Subtract(l, r) => l
+                .stack_address(pointers)
+                .or_else(|| r.stack_address(pointers)),
+            Self::Conditional(_, then, otherwise) => then
+                .stack_address(pointers)
+                .or_else(|| otherwise.stack_address(pointers)),
+            _ => None,
+        }
+    }
+
+    /// Give every variable used in this expression its unique name in the function
+    fn resolve_scopes(&self, scope: &Scope) -> Result<Self, MirError> {
+        let expr = |expr: &Self| -> Result<Box<Self>, MirError> {
+            Ok(Box::new(expr.resolve_scopes(scope)?))
+        };
+        let exprs = |exprs: &Vec<Self>| -> Result<Vec<Self>, MirError> {
+            exprs
+                .iter()
+                .map(|expr| expr.resolve_scopes(scope))
+                .collect()
+        };
+
+        Ok(match self {
+            // A variable that isn't defined in a closure is captured by it
+            Self::Variable(name) => match scope.get(name) {
+                Some(unique) => Self::Variable(unique.clone()),
+                None => Self::Deref(Box::new(Self::Captured(scope.capture(name)?))),
+            },
+            Self::Refer(name) => match scope.get(name) {
+                Some(unique) => Self::Refer(unique.clone()),
+                None => Self::Captured(scope.capture(name)?),
+            },
+            Self::NotNull(name) => Self::NotNull(scope.resolve(name)?),
+
+            Self::Move(e) => Self::Move(expr(e)?),
+            Self::Add(l, r) => Self::Add(expr(l)?, expr(r)?),
+            Self::Subtract(l, r) => Self::Subtract(expr(l)?, expr(r)?),
+            Self::Multiply(l, r) => Self::Multiply(expr(l)?, expr(r)?),
+            Self::Divide(l, r) => Self::Divide(expr(l)?, expr(r)?),
+            Self::Modulo(l, r) => Self::Modulo(expr(l)?, expr(r)?),
+            Self::BitAnd(l, r) => Self::BitAnd(expr(l)?, expr(r)?),
+            Self::BitOr(l, r) => Self::BitOr(expr(l)?, expr(r)?),
+            Self::BitXor(l, r) => Self::BitXor(expr(l)?, expr(r)?),
+            Self::ShiftLeft(l, r) => Self::ShiftLeft(expr(l)?, expr(r)?),
+            Self::ShiftRight(l, r) => Self::ShiftRight(expr(l)?, expr(r)?),
+            Self::Not(e) => Self::Not(expr(e)?),
+            Self::And(l, r) => Self::And(expr(l)?, expr(r)?),
+            Self::Or(l, r) => Self::Or(expr(l)?, expr(r)?),
+            Self::Greater(l, r) => Self::Greater(expr(l)?, expr(r)?),
+            Self::Less(l, r) => Self::Less(expr(l)?, expr(r)?),
+            Self::GreaterEqual(l, r) => Self::GreaterEqual(expr(l)?, expr(r)?),
+            Self::LessEqual(l, r) => Self::LessEqual(expr(l)?, expr(r)?),
+            Self::Equal(l, r) => Self::Equal(expr(l)?, expr(r)?),
+            Self::NotEqual(l, r) => Self::NotEqual(expr(l)?, expr(r)?),
+
+            Self::CharArray(elems) => Self::CharArray(exprs(elems)?),
+            Self::Deref(e) => Self::Deref(expr(e)?),
+            Self::TypeCast(e, t) => Self::TypeCast(expr(e)?, t.clone()),
+            Self::Alloc(e) => Self::Alloc(expr(e)?),
+            Self::Memset(ptr, value, count) => Self::Memset(expr(ptr)?, expr(value)?, expr(count)?),
+            Self::Zero(ptr, count) => Self::Zero(expr(ptr)?, expr(count)?),
+
+            // Closures are passed the environment they use as their last argument
+            Self::Call(name, args) => match scope.get_closure(name) {
+                Some((closure, env)) => {
+                    let mut args = exprs(args)?;
+                    args.push(env);
+                    Self::Call(closure, args)
+                }
+                None => Self::Call(name.clone(), exprs(args)?),
+            },
+            Self::ForeignCall(name, args, takes_context) => {
+                Self::ForeignCall(name.clone(), exprs(args)?, *takes_context)
+            }
+            Self::ForeignLoad(name) => Self::ForeignLoad(name.clone()),
+            Self::ForeignStore(name, value) => Self::ForeignStore(name.clone(), expr(value)?),
+            Self::Method(instance, name, args) => {
+                Self::Method(expr(instance)?, name.clone(), exprs(args)?)
+            }
+            Self::Index(ptr, idx) => Self::Index(expr(ptr)?, expr(idx)?),
+            Self::Conditional(cond, then, otherwise) => {
+                Self::Conditional(expr(cond)?, expr(then)?, expr(otherwise)?)
+            }
+
+            // Raw VM instructions may name variables too. Names that
+            // aren't Oak variables are left for the assembler to check.
+            Self::RawAsm(t, body) => Self::RawAsm(
+                t.clone(),
+                body.iter()
+                    .map(|instruction| match instruction {
+                        AsmExpression::Variable(name) => {
+                            AsmExpression::Variable(scope.get(name).unwrap_or(name).clone())
+                        }
+                        AsmExpression::Refer(name) => {
+                            AsmExpression::Refer(scope.get(name).unwrap_or(name).clone())
+                        }
+                        _ => instruction.clone(),
+                    })
+                    .collect(),
+            ),
+
+            Self::String(_)
+            | Self::Float(_)
+            | Self::Character(_)
+            | Self::True
+            | Self::False
+            | Self::Void
+            | Self::Flush
+            | Self::Captured(_)
+            | Self::Closure => self.clone(),
+        })
+    }
+
+    /// Get the expressions this expression is made of
+    fn children(&self) -> Vec<&Self> {
+        match self {
+            Self::Move(e)
+            | Self::Not(e)
+            | Self::Deref(e)
+            | Self::TypeCast(e, _)
+            | Self::Alloc(e)
+            | Self::ForeignStore(_, e) => vec![e],
+
+            Self::Add(l, r)
+            | Self::Subtract(l, r)
+            | Self::Multiply(l, r)
+            | Self::Divide(l, r)
+            | Self::Modulo(l, r)
+            | Self::BitAnd(l, r)
+            | Self::BitOr(l, r)
+            | Self::BitXor(l, r)
+            | Self::ShiftLeft(l, r)
+            | Self::ShiftRight(l, r)
+            | Self::And(l, r)
+            | Self::Or(l, r)
+            | Self::Greater(l, r)
+            | Self::Less(l, r)
+            | Self::GreaterEqual(l, r)
+            | Self::LessEqual(l, r)
+            | Self::Equal(l, r)
+            | Self::NotEqual(l, r)
+            | Self::Zero(l, r)
+            | Self::Index(l, r) => vec![l, r],
+
+            Self::Memset(a, b, c) | Self::Conditional(a, b, c) => vec![a, b, c],
+
+            Self::CharArray(exprs) | Self::Call(_, exprs) | Self::ForeignCall(_, exprs, _) => {
+                exprs.iter().collect()
+            }
+            Self::Method(instance, _, args) => {
+                let mut result = vec![instance.as_ref()];
+                result.extend(args);
+                result
+            }
+
+            Self::String(_)
+            | Self::Float(_)
+            | Self::Character(_)
+            | Self::True
+            | Self::False
+            | Self::Void
+            | Self::Variable(_)
+            | Self::Refer(_)
+            | Self::Captured(_)
+            | Self::Closure
+            | Self::Flush
+            | Self::NotNull(_)
+            | Self::ForeignLoad(_)
+            | Self::RawAsm(_, _) => vec![],
+        }
+    }
+
+    /// Get the name and arguments of every function called in this expression
+    fn calls(&self) -> Vec<(&Identifier, &[Self])> {
+        let mut result = vec![];
+        if let Self::Call(name, args) = self {
+            result.push((name, &args[..]))
+        }
+        for child in self.children() {
+            result.extend(child.calls())
+        }
+        result
+    }
+
+    /// Get the arithmetic in this expression with one `char` operand and
+    /// one `num` operand. Operands whose types can't be found are skipped.
+    fn mixed_char_arithmetic(
+        &self,
+        vars: &BTreeMap<Identifier, MirType>,
+        funcs: &BTreeMap<Identifier, MirFunction>,
+        structs: &BTreeMap<Identifier, MirStructure>,
+    ) -> Vec<&Self> {
+        let mut result = vec![];
+        match self {
+            Self::Add(lhs, rhs)
+            | Self::Subtract(lhs, rhs)
+            | Self::Multiply(lhs, rhs)
+            | Self::Divide(lhs, rhs)
+            | Self::Modulo(lhs, rhs) => {
+                if let (Ok(lhs_type), Ok(rhs_type)) = (
+                    lhs.get_type(vars, funcs, structs),
+                    rhs.get_type(vars, funcs, structs),
+                ) {
+                    let (char_type, num_type) = (MirType::character(), MirType::float());
+                    if (lhs_type == char_type && rhs_type == num_type)
+                        || (lhs_type == num_type && rhs_type == char_type)
+                    {
+                        result.push(self)
+                    }
+                }
+            }
+            _ => {}
+        }
+        for child in self.children() {
+            result.extend(child.mixed_char_arithmetic(vars, funcs, structs))
+        }
+        result
+    }
+
+    /// Get the values stored in foreign variables in this expression
+    fn foreign_stores(&self) -> Vec<&Self> {
+        let mut result = vec![];
+        if let Self::ForeignStore(_, value) = self {
+            result.push(value.as_ref())
+        }
+        for child in self.children() {
+            result.extend(child.foreign_stores())
+        }
+        result
+    }
+
     /// Get a new variable to store an instance of a method in
     fn get_instance_var(&self, instance_count: &mut i32) -> Identifier {
         *instance_count += 1;
@@ -1376,6 +2948,39 @@ impl MirExpression {
         Ok(self.get_type(vars, funcs, structs)?.is_movable(structs))
     }
 
+    /// If this is arithmetic on a structure that defines the matching
+    /// operator method, get the equivalent method call. For example,
+    /// `a + b` becomes `a.add(b)`. Arithmetic on numbers is left alone.
+    fn operator_method(
+        &self,
+        vars: &BTreeMap<Identifier, MirType>,
+        funcs: &BTreeMap<Identifier, MirFunction>,
+        structs: &BTreeMap<Identifier, MirStructure>,
+    ) -> Result<Option<Self>, MirError> {
+        let (lhs, rhs, method_name) = match self {
+            Self::Add(l, r) => (l, r, "add"),
+            Self::Subtract(l, r) => (l, r, "subtract"),
+            Self::Multiply(l, r) => (l, r, "multiply"),
+            Self::Divide(l, r) => (l, r, "divide"),
+            Self::Modulo(l, r) => (l, r, "modulo"),
+            _ => return Ok(None),
+        };
+
+        let lhs_type = lhs.get_type(vars, funcs, structs)?;
+        let method_name = Identifier::from(method_name);
+        if lhs_type.is_structure()
+            && funcs.contains_key(&lhs_type.method_to_function_name(&method_name))
+        {
+            Ok(Some(Self::Method(
+                lhs.clone(),
+                method_name,
+                vec![*rhs.clone()],
+            )))
+        } else {
+            Ok(None)
+        }
+    }
+
     fn is_a_copy(&self) -> bool {
         match self {
             Self::Method(_, name, _) if name == "copy" => true,
@@ -1450,6 +3055,11 @@ impl MirExpression {
         funcs: &BTreeMap<Identifier, MirFunction>,
         structs: &BTreeMap<Identifier, MirStructure>,
     ) -> Result<(), MirError> {
+        // Arithmetic on a structure is a call to one of its operator methods
+        if let Some(method_call) = self.operator_method(vars, funcs, structs)? {
+            return method_call.type_check(vars, funcs, structs);
+        }
+
         match self {
             Self::Conditional(cond, then, otherwise) => {
                 cond.type_check(vars, funcs, structs)?;
@@ -1498,6 +3108,12 @@ impl MirExpression {
             | Self::Subtract(lhs, rhs)
             | Self::Multiply(lhs, rhs)
             | Self::Divide(lhs, rhs)
+            | Self::Modulo(lhs, rhs)
+            | Self::BitAnd(lhs, rhs)
+            | Self::BitOr(lhs, rhs)
+            | Self::BitXor(lhs, rhs)
+            | Self::ShiftLeft(lhs, rhs)
+            | Self::ShiftRight(lhs, rhs)
             | Self::Greater(lhs, rhs)
             | Self::Less(lhs, rhs)
             | Self::GreaterEqual(lhs, rhs)
@@ -1526,11 +3142,42 @@ impl MirExpression {
                 }
             }
 
+            // Typecheck a `memset` or `zero` expression
+            Self::Memset(ptr, _, count) | Self::Zero(ptr, count) => {
+                ptr.type_check(vars, funcs, structs)?;
+                count.type_check(vars, funcs, structs)?;
+
+                let ptr_type = ptr.get_type(vars, funcs, structs)?;
+                if !ptr_type.is_pointer() || ptr_type.deref()?.get_size(structs)? == 0 {
+                    return Err(MirError::MemsetNonPointer(*ptr.clone()));
+                }
+                if count.get_type(vars, funcs, structs)? != MirType::float() {
+                    return Err(MirError::NonNumberMemsetCount(*count.clone()));
+                }
+
+                if let Self::Memset(_, value, _) = self {
+                    value.type_check(vars, funcs, structs)?;
+                    let value_type = value.get_type(vars, funcs, structs)?;
+                    if value_type != ptr_type.deref()? {
+                        return Err(MirError::MemsetMismatchedType(*ptr.clone(), *value.clone()));
+                    }
+                    // Every element is a copy of the same cells, so
+                    // values that must be dropped cannot be used.
+                    if !value_type.is_movable(structs) {
+                        return Err(MirError::MemsetNonMovable(*value.clone()));
+                    }
+                }
+            }
+
             // Typecheck an index expression
             Self::Index(ptr, idx) => {
                 ptr.type_check(vars, funcs, structs)?;
                 idx.type_check(vars, funcs, structs)?;
 
+                if ptr.get_type(vars, funcs, structs)?.is_nullable() {
+                    return Err(MirError::DerefMaybeNull(*ptr.clone()));
+                }
+
                 // Check if the index is a structure or of type `void`
                 if idx.get_type(vars, funcs, structs)?.get_size(structs)? != 1 {
                     return Err(MirError::NonNumberIndex(*idx.clone()));
@@ -1566,7 +3213,7 @@ impl MirExpression {
                     for ((_, param_type), arg_expr) in func.get_parameters().iter().zip(args) {
                         // If the parameters don't match the argument types,
                         // then throw an error.
-                        if param_type != &arg_expr.get_type(vars, funcs, structs)? {
+                        if !param_type.accepts(&arg_expr.get_type(vars, funcs, structs)?) {
                             return Err(MirError::ArgumentMismatchedType(self.clone()));
                         }
 
@@ -1584,6 +3231,11 @@ impl MirExpression {
                 // Get the name of the method
                 let fn_name = instance_type.method_to_function_name(method_name);
 
+                // Calling a method on a pointer dereferences it
+                if instance_type.is_nullable() {
+                    return Err(MirError::DerefMaybeNull(*expr.clone()));
+                }
+
                 if let Some(func) = funcs.get(&fn_name) {
                     // The list of parameters that the function expects
                     let mut params = func.get_parameters();
@@ -1610,7 +3262,7 @@ impl MirExpression {
                         for ((_, param_type), arg_expr) in params.iter().zip(args) {
                             // If the parameters don't match the argument types,
                             // then throw an error.
-                            if param_type != &arg_expr.get_type(vars, funcs, structs)? {
+                            if !param_type.accepts(&arg_expr.get_type(vars, funcs, structs)?) {
                                 return Err(MirError::ArgumentMismatchedType(self.clone()));
                             }
                             arg_expr.type_check(vars, funcs, structs)?
@@ -1624,18 +3276,61 @@ impl MirExpression {
             }
 
             // Typecheck a dereference or move expression
-            Self::Deref(expr) | Self::Move(expr) => expr.type_check(vars, funcs, structs)?,
+            Self::Deref(expr) => {
+                expr.type_check(vars, funcs, structs)?;
+                if expr.get_type(vars, funcs, structs)?.is_nullable() {
+                    return Err(MirError::DerefMaybeNull(*expr.clone()));
+                }
+            }
+            Self::Move(expr) => expr.type_check(vars, funcs, structs)?,
+
+            // Only pointers can be checked for null
+            Self::NotNull(var_name) => {
+                if !Self::Variable(var_name.clone())
+                    .get_type(vars, funcs, structs)?
+                    .is_pointer()
+                {
+                    return Err(MirError::NullCheckNonPointer(var_name.clone()));
+                }
+            }
+
+            // Every element of a character array must fit in a single cell.
+            // Numbers are allowed so that the array can be zero terminated.
+            Self::CharArray(exprs) => {
+                for expr in exprs {
+                    expr.type_check(vars, funcs, structs)?;
+                    let t = expr.get_type(vars, funcs, structs)?;
+                    if t != MirType::character() && t != MirType::float() {
+                        return Err(MirError::NonCharacterArrayElement(expr.clone()));
+                    }
+                }
+            }
+
+            // Only a single cell can be stored in a foreign variable
+            Self::ForeignStore(name, value) => {
+                value.type_check(vars, funcs, structs)?;
+                if value.get_type(vars, funcs, structs)?.get_size(structs)? != 1 {
+                    return Err(MirError::LargeForeignStore(name.clone(), *value.clone()));
+                }
+            }
 
             // Typecheck atomic expressions
-            Self::ForeignCall(_, _)
+            Self::ForeignCall(_, _, _)
+            | Self::ForeignLoad(_)
             | Self::Refer(_)
             | Self::Variable(_)
+            | Self::Captured(_)
+            | Self::Closure
             | Self::String(_)
             | Self::Float(_)
             | Self::Character(_)
             | Self::Void
+            | Self::Flush
             | Self::True
             | Self::False => {}
+
+            // Raw VM instructions are the user's responsibility
+            Self::RawAsm(_, _) => {}
         }
         Ok(())
     }
@@ -1649,8 +3344,12 @@ impl MirExpression {
         funcs: &BTreeMap<Identifier, MirFunction>,
         structs: &BTreeMap<Identifier, MirStructure>,
         instance_count: &mut i32,
-        if_var_count: &mut i32,
     ) -> Result<Vec<AsmStatement>, MirError> {
+        // Arithmetic on a structure is a call to one of its operator methods
+        if let Some(method_call) = self.operator_method(vars, funcs, structs)? {
+            return method_call.assemble(vars, funcs, structs, instance_count);
+        }
+
         Ok(match self {
             /// Turn the conditional expression into an if-else statement
             Self::Conditional(cond, then, otherwise) => MirStatement::IfElse(
@@ -1658,10 +3357,10 @@ impl MirExpression {
                 vec![MirStatement::Expression(*then.clone())],
                 vec![MirStatement::Expression(*otherwise.clone())],
             )
-            .assemble(vars, funcs, structs, instance_count, if_var_count)?,
+            .assemble(vars, funcs, structs, instance_count)?,
 
             /// A move does not change its inner value
-            Self::Move(expr) => expr.assemble(vars, funcs, structs, instance_count, if_var_count)?,
+            Self::Move(expr) => expr.assemble(vars, funcs, structs, instance_count)?,
 
             Self::True => vec![AsmStatement::Expression(vec![AsmExpression::Float(1.0)])],
             Self::False => vec![AsmStatement::Expression(vec![AsmExpression::Float(0.0)])],
@@ -1672,7 +3371,7 @@ impl MirExpression {
                 vec![MirStatement::Expression(MirExpression::Float(0.0))],
                 vec![MirStatement::Expression(MirExpression::Float(1.0))],
             )
-            .assemble(vars, funcs, structs, instance_count, if_var_count)?,
+            .assemble(vars, funcs, structs, instance_count)?,
 
             /// And two boolean values
             /// And is essentially boolean multiplication,
@@ -1683,7 +3382,7 @@ impl MirExpression {
                 vec![MirStatement::Expression(MirExpression::Float(1.0))],
                 vec![MirStatement::Expression(MirExpression::Float(0.0))],
             )
-            .assemble(vars, funcs, structs, instance_count, if_var_count)?,
+            .assemble(vars, funcs, structs, instance_count)?,
 
             /// Or two boolean values
             /// Or is essentially boolean addition,
@@ -1694,7 +3393,7 @@ impl MirExpression {
                 vec![MirStatement::Expression(MirExpression::Float(1.0))],
                 vec![MirStatement::Expression(MirExpression::Float(0.0))],
             )
-            .assemble(vars, funcs, structs, instance_count, if_var_count)?,
+            .assemble(vars, funcs, structs, instance_count)?,
 
             /// Are two numbers equal?
             /// I know this expression doesn't type check,
@@ -1704,7 +3403,7 @@ impl MirExpression {
                 vec![MirStatement::Expression(MirExpression::Float(0.0))],
                 vec![MirStatement::Expression(MirExpression::Float(1.0))],
             )
-            .assemble(vars, funcs, structs, instance_count, if_var_count)?,
+            .assemble(vars, funcs, structs, instance_count)?,
 
             /// Are two numbers not equal?
             /// I know this expression doesn't type check,
@@ -1714,23 +3413,39 @@ impl MirExpression {
                 vec![MirStatement::Expression(MirExpression::Float(1.0))],
                 vec![MirStatement::Expression(MirExpression::Float(0.0))],
             )
-            .assemble(vars, funcs, structs, instance_count, if_var_count)?,
+            .assemble(vars, funcs, structs, instance_count)?,
 
             /// A typecast is only a way to explicitly validate
             /// some kinds of typechecks. The typecast expression
-            /// has no change on the output code.
-            Self::TypeCast(expr, _) => expr.assemble(vars, funcs, structs, instance_count, if_var_count)?,
+            /// has no change on the output code, unless the value
+            /// is cast to a sized integer, which truncates it.
+            Self::TypeCast(expr, t) => {
+                let mut result = expr.assemble(vars, funcs, structs, instance_count)?;
+                if let Some(width) = t.int_width() {
+                    result.push(AsmStatement::Expression(vec![AsmExpression::Truncate(
+                        width.bits(),
+                        width.is_signed(),
+                    )]));
+                }
+                result
+            }
 
             /// Is the LHS greater than or equal the RHS?
             Self::GreaterEqual(l, r) => {
                 let mut result = Vec::new();
-                result.extend(l.assemble(vars, funcs, structs, instance_count, if_var_count)?);
-                result.extend(r.assemble(vars, funcs, structs, instance_count, if_var_count)?);
+                result.extend(l.assemble(vars, funcs, structs, instance_count)?);
+                result.extend(r.assemble(vars, funcs, structs, instance_count)?);
                 result.push(AsmStatement::Expression(vec![
                     // Subtract RHS from the LHS and check the sign
                     AsmExpression::Subtract,
                     AsmExpression::Sign,
-                    // If the sign was 1, then this expression is true.
+                    // If the sign was 0 or 1, then this expression is true.
+                    // Nudge the sign so that it is never 0, and take the sign
+                    // again to map it to -1 or 1.
+                    AsmExpression::Float(0.5),
+                    AsmExpression::Add,
+                    AsmExpression::Sign,
+                    // Then, map -1 or 1 to 0 or 1.
                     AsmExpression::Float(1.0),
                     AsmExpression::Add,
                     AsmExpression::Float(2.0),
@@ -1741,13 +3456,19 @@ impl MirExpression {
             /// Is the LHS greater than the RHS?
             Self::Greater(l, r) => {
                 let mut result = Vec::new();
-                result.extend(r.assemble(vars, funcs, structs, instance_count, if_var_count)?);
-                result.extend(l.assemble(vars, funcs, structs, instance_count, if_var_count)?);
+                result.extend(r.assemble(vars, funcs, structs, instance_count)?);
+                result.extend(l.assemble(vars, funcs, structs, instance_count)?);
                 result.push(AsmStatement::Expression(vec![
                     // Subtract LHS from the RHS and check the sign
                     AsmExpression::Subtract,
                     AsmExpression::Sign,
                     // If the sign was -1, then this expression is true.
+                    // Nudge the sign so that it is never 0, and take the sign
+                    // again to map it to -1 or 1.
+                    AsmExpression::Float(0.5),
+                    AsmExpression::Add,
+                    AsmExpression::Sign,
+                    // Then, map 1 or -1 to 0 or 1.
                     AsmExpression::Float(1.0),
                     AsmExpression::Subtract,
                     AsmExpression::Float(-2.0),
@@ -1758,13 +3479,19 @@ impl MirExpression {
             /// Is the LHS less than or equal to the RHS?
             Self::LessEqual(l, r) => {
                 let mut result = Vec::new();
-                result.extend(r.assemble(vars, funcs, structs, instance_count, if_var_count)?);
-                result.extend(l.assemble(vars, funcs, structs, instance_count, if_var_count)?);
+                result.extend(r.assemble(vars, funcs, structs, instance_count)?);
+                result.extend(l.assemble(vars, funcs, structs, instance_count)?);
                 result.push(AsmStatement::Expression(vec![
                     // Subtract LHS from the RHS and check the sign
                     AsmExpression::Subtract,
                     AsmExpression::Sign,
-                    // If the sign was 1, then this expression is true.
+                    // If the sign was 0 or 1, then this expression is true.
+                    // Nudge the sign so that it is never 0, and take the sign
+                    // again to map it to -1 or 1.
+                    AsmExpression::Float(0.5),
+                    AsmExpression::Add,
+                    AsmExpression::Sign,
+                    // Then, map -1 or 1 to 0 or 1.
                     AsmExpression::Float(1.0),
                     AsmExpression::Add,
                     AsmExpression::Float(2.0),
@@ -1775,13 +3502,19 @@ impl MirExpression {
             /// Is the LHS less than the RHS?
             Self::Less(l, r) => {
                 let mut result = Vec::new();
-                result.extend(l.assemble(vars, funcs, structs, instance_count, if_var_count)?);
-                result.extend(r.assemble(vars, funcs, structs, instance_count, if_var_count)?);
+                result.extend(l.assemble(vars, funcs, structs, instance_count)?);
+                result.extend(r.assemble(vars, funcs, structs, instance_count)?);
                 result.push(AsmStatement::Expression(vec![
                     // Subtract RHS from the LHS and check the sign
                     AsmExpression::Subtract,
                     AsmExpression::Sign,
                     // If the sign was -1, then this expression is true.
+                    // Nudge the sign so that it is never 0, and take the sign
+                    // again to map it to -1 or 1.
+                    AsmExpression::Float(0.5),
+                    AsmExpression::Add,
+                    AsmExpression::Sign,
+                    // Then, map 1 or -1 to 0 or 1.
                     AsmExpression::Float(1.0),
                     AsmExpression::Subtract,
                     AsmExpression::Float(-2.0),
@@ -1793,32 +3526,80 @@ impl MirExpression {
             /// Add two values
             Self::Add(l, r) => {
                 let mut result = Vec::new();
-                result.extend(l.assemble(vars, funcs, structs, instance_count, if_var_count)?);
-                result.extend(r.assemble(vars, funcs, structs, instance_count, if_var_count)?);
+                result.extend(l.assemble(vars, funcs, structs, instance_count)?);
+                result.extend(r.assemble(vars, funcs, structs, instance_count)?);
                 result.push(AsmStatement::Expression(vec![AsmExpression::Add]));
                 result
             }
             /// Multiply two values
             Self::Multiply(l, r) => {
                 let mut result = Vec::new();
-                result.extend(l.assemble(vars, funcs, structs, instance_count, if_var_count)?);
-                result.extend(r.assemble(vars, funcs, structs, instance_count, if_var_count)?);
+                result.extend(l.assemble(vars, funcs, structs, instance_count)?);
+                result.extend(r.assemble(vars, funcs, structs, instance_count)?);
                 result.push(AsmStatement::Expression(vec![AsmExpression::Multiply]));
                 result
             }
             /// Divide two values
             Self::Divide(l, r) => {
                 let mut result = Vec::new();
-                result.extend(l.assemble(vars, funcs, structs, instance_count, if_var_count)?);
-                result.extend(r.assemble(vars, funcs, structs, instance_count, if_var_count)?);
+                result.extend(l.assemble(vars, funcs, structs, instance_count)?);
+                result.extend(r.assemble(vars, funcs, structs, instance_count)?);
                 result.push(AsmStatement::Expression(vec![AsmExpression::Divide]));
                 result
             }
+            /// Take the remainder of dividing two values
+            Self::Modulo(l, r) => {
+                let mut result = Vec::new();
+                result.extend(l.assemble(vars, funcs, structs, instance_count)?);
+                result.extend(r.assemble(vars, funcs, structs, instance_count)?);
+                result.push(AsmStatement::Expression(vec![AsmExpression::Modulo]));
+                result
+            }
+            /// Bitwise and two values
+            Self::BitAnd(l, r) => {
+                let mut result = Vec::new();
+                result.extend(l.assemble(vars, funcs, structs, instance_count)?);
+                result.extend(r.assemble(vars, funcs, structs, instance_count)?);
+                result.push(AsmStatement::Expression(vec![AsmExpression::BitAnd]));
+                result
+            }
+            /// Bitwise or two values
+            Self::BitOr(l, r) => {
+                let mut result = Vec::new();
+                result.extend(l.assemble(vars, funcs, structs, instance_count)?);
+                result.extend(r.assemble(vars, funcs, structs, instance_count)?);
+                result.push(AsmStatement::Expression(vec![AsmExpression::BitOr]));
+                result
+            }
+            /// Bitwise exclusive or two values
+            Self::BitXor(l, r) => {
+                let mut result = Vec::new();
+                result.extend(l.assemble(vars, funcs, structs, instance_count)?);
+                result.extend(r.assemble(vars, funcs, structs, instance_count)?);
+                result.push(AsmStatement::Expression(vec![AsmExpression::BitXor]));
+                result
+            }
+            /// Shift a value left
+            Self::ShiftLeft(l, r) => {
+                let mut result = Vec::new();
+                result.extend(l.assemble(vars, funcs, structs, instance_count)?);
+                result.extend(r.assemble(vars, funcs, structs, instance_count)?);
+                result.push(AsmStatement::Expression(vec![AsmExpression::ShiftLeft]));
+                result
+            }
+            /// Shift a value right
+            Self::ShiftRight(l, r) => {
+                let mut result = Vec::new();
+                result.extend(l.assemble(vars, funcs, structs, instance_count)?);
+                result.extend(r.assemble(vars, funcs, structs, instance_count)?);
+                result.push(AsmStatement::Expression(vec![AsmExpression::ShiftRight]));
+                result
+            }
             /// Subtract two values
             Self::Subtract(l, r) => {
                 let mut result = Vec::new();
-                result.extend(l.assemble(vars, funcs, structs, instance_count, if_var_count)?);
-                result.extend(r.assemble(vars, funcs, structs, instance_count, if_var_count)?);
+                result.extend(l.assemble(vars, funcs, structs, instance_count)?);
+                result.extend(r.assemble(vars, funcs, structs, instance_count)?);
                 result.push(AsmStatement::Expression(vec![AsmExpression::Subtract]));
                 result
             }
@@ -1827,6 +3608,18 @@ impl MirExpression {
             Self::String(s) => vec![AsmStatement::Expression(vec![AsmExpression::String(
                 s.clone(),
             )])],
+            /// Push each element of a character array onto the stack,
+            /// then store them in the local scope and push their address
+            Self::CharArray(exprs) => {
+                let mut result = Vec::new();
+                for expr in exprs {
+                    result.extend(expr.assemble(vars, funcs, structs, instance_count)?);
+                }
+                result.push(AsmStatement::Expression(vec![AsmExpression::Array(
+                    exprs.len() as i32,
+                )]));
+                result
+            }
             /// Push a float onto the stack
             Self::Float(n) => vec![AsmStatement::Expression(vec![AsmExpression::Float(*n)])],
             /// Push a character on the stack
@@ -1845,10 +3638,18 @@ impl MirExpression {
             Self::Refer(var_name) => vec![AsmStatement::Expression(vec![AsmExpression::Refer(
                 var_name.clone(),
             )])],
+            /// Reference a variable in the stack frame of the enclosing function
+            Self::Captured(var_name) => {
+                vec![AsmStatement::Expression(vec![AsmExpression::Captured(
+                    var_name.clone(),
+                )])]
+            }
+            /// The environment of a function is its stack frame
+            Self::Closure => vec![AsmStatement::Expression(vec![AsmExpression::BasePtr])],
             /// Dereference a pointer
             Self::Deref(expr) => {
                 let mut result = Vec::new();
-                result.extend(expr.assemble(vars, funcs, structs, instance_count, if_var_count)?);
+                result.extend(expr.assemble(vars, funcs, structs, instance_count)?);
                 // The `Deref` instruction requires the size of the item in memory
                 // to push onto the stack. A pointer to the object has size 1, but
                 // the size of the type itself can vary. To get the size of the
@@ -1872,7 +3673,6 @@ impl MirExpression {
                         funcs,
                         structs,
                         instance_count,
-                        if_var_count,
                     )?);
                 }
                 // Call the function
@@ -1883,13 +3683,29 @@ impl MirExpression {
             }
 
             /// Call a foreign function
-            Self::ForeignCall(func_name, args) => {
+            Self::ForeignCall(func_name, args, takes_context) => {
                 let mut result = Vec::new();
                 for arg in args.iter().rev() {
-                    result.extend(arg.assemble(vars, funcs, structs, instance_count, if_var_count)?);
+                    result.extend(arg.assemble(vars, funcs, structs, instance_count)?);
                 }
-                result.push(AsmStatement::Expression(vec![AsmExpression::ForeignCall(
-                    func_name.clone(),
+                result.push(AsmStatement::Expression(vec![if *takes_context {
+                    AsmExpression::ForeignCallWithContext(func_name.clone())
+                } else {
+                    AsmExpression::ForeignCall(func_name.clone())
+                }]));
+                result
+            }
+
+            Self::ForeignLoad(var_name) => {
+                vec![AsmStatement::Expression(vec![AsmExpression::ForeignLoad(
+                    var_name.clone(),
+                )])]
+            }
+
+            Self::ForeignStore(var_name, value) => {
+                let mut result = value.assemble(vars, funcs, structs, instance_count)?;
+                result.push(AsmStatement::Expression(vec![AsmExpression::ForeignStore(
+                    var_name.clone(),
                 )]));
                 result
             }
@@ -1897,11 +3713,51 @@ impl MirExpression {
             /// Allocate data on the heap
             Self::Alloc(size_expr) => {
                 let mut result = Vec::new();
-                result.extend(size_expr.assemble(vars, funcs, structs, instance_count, if_var_count)?);
+                result.extend(size_expr.assemble(vars, funcs, structs, instance_count)?);
                 result.push(AsmStatement::Expression(vec![AsmExpression::Alloc]));
                 result
             }
 
+            /// Fill `count` elements at an address with copies of a value
+            Self::Memset(ptr, value, count) => {
+                let size = value.get_type(vars, funcs, structs)?.get_size(structs)?;
+                let mut result = Vec::new();
+                result.extend(ptr.assemble(vars, funcs, structs, instance_count)?);
+                result.extend(value.assemble(vars, funcs, structs, instance_count)?);
+                result.extend(count.assemble(vars, funcs, structs, instance_count)?);
+                result.push(AsmStatement::Expression(vec![AsmExpression::Memset(size)]));
+                result
+            }
+
+            /// Zeroing `count` elements is the same as filling
+            /// `count * size` cells with a single zero cell
+            Self::Zero(ptr, count) => {
+                let size = ptr
+                    .get_type(vars, funcs, structs)?
+                    .deref()?
+                    .get_size(structs)?;
+                let mut result = Vec::new();
+                result.extend(ptr.assemble(vars, funcs, structs, instance_count)?);
+                result.push(AsmStatement::Expression(vec![AsmExpression::Float(0.0)]));
+                result.extend(count.assemble(vars, funcs, structs, instance_count)?);
+                result.push(AsmStatement::Expression(vec![
+                    AsmExpression::Float(size as f64),
+                    AsmExpression::Multiply,
+                    AsmExpression::Memset(1),
+                ]));
+                result
+            }
+
+            /// Write the buffered output to stdout
+            Self::Flush => vec![AsmStatement::Expression(vec![AsmExpression::Flush])],
+
+            /// A pointer is not null if it is not zero
+            Self::NotNull(var_name) => Self::NotEqual(
+                Box::new(Self::Variable(var_name.clone())),
+                Box::new(Self::Float(0.0)),
+            )
+            .assemble(vars, funcs, structs, instance_count)?,
+
             /// Call a method on an object
             Self::Method(expr, method_name, args) => {
                 let instance_type = expr.get_type(vars, funcs, structs)?;
@@ -1917,7 +3773,6 @@ impl MirExpression {
                         funcs,
                         structs,
                         instance_count,
-                        if_var_count,
                     );
                 // Here the instance object must be a non-pointer type
                 // and also a variable. In this case, reference the
@@ -1931,7 +3786,6 @@ impl MirExpression {
                         funcs,
                         structs,
                         instance_count,
-                        if_var_count,
                     )?
 
                 // If the method is being called on a concrete type that isnt a variable,
@@ -1949,7 +3803,7 @@ impl MirExpression {
 
                     let mut result = Vec::new();
                     // Push the instance object
-                    result.extend(expr.assemble(vars, funcs, structs, instance_count, if_var_count)?);
+                    result.extend(expr.assemble(vars, funcs, structs, instance_count)?);
 
                     let self_type = instance_type.to_asm_type(structs)?;
                     result.extend(vec![
@@ -1966,7 +3820,6 @@ impl MirExpression {
                         funcs,
                         structs,
                         instance_count,
-                        if_var_count,
                     )?);
 
                     result
@@ -1980,7 +3833,7 @@ impl MirExpression {
 
                         let mut result = Vec::new();
                         // Push the instance object
-                        result.extend(expr.assemble(vars, funcs, structs, instance_count, if_var_count)?);
+                        result.extend(expr.assemble(vars, funcs, structs, instance_count)?);
 
                         let self_type = instance_type.to_asm_type(structs)?;
                         result.extend(vec![
@@ -1997,7 +3850,6 @@ impl MirExpression {
                             funcs,
                             structs,
                             instance_count,
-                            if_var_count,
                         )?);
 
                         result
@@ -2013,9 +3865,9 @@ impl MirExpression {
             Self::Index(ptr, idx) => {
                 let mut result = Vec::new();
                 // Push the array pointer on the stack
-                result.extend(ptr.assemble(vars, funcs, structs, instance_count, if_var_count)?);
+                result.extend(ptr.assemble(vars, funcs, structs, instance_count)?);
                 // Push the index of the array onto the stack
-                result.extend(idx.assemble(vars, funcs, structs, instance_count, if_var_count)?);
+                result.extend(idx.assemble(vars, funcs, structs, instance_count)?);
                 // Get the size of the array's inner type
                 let type_size = ptr
                     .get_type(vars, funcs, structs)?
@@ -2031,6 +3883,9 @@ impl MirExpression {
                 ]));
                 result
             }
+
+            /// Raw VM instructions are passed straight through to the ASM
+            Self::RawAsm(_, body) => vec![AsmStatement::Expression(body.clone())],
         })
     }
 
@@ -2041,8 +3896,21 @@ impl MirExpression {
         structs: &BTreeMap<Identifier, MirStructure>,
     ) -> Result<MirType, MirError> {
         Ok(match self {
-            /// Turn the conditional expression into an if-else statement
-            Self::Conditional(_, then, _) => then.get_type(vars, funcs, structs)?,
+            /// A conditional expression has the type shared by both of its branches.
+            /// If the branches disagree, there is no sound type to infer.
+            Self::Conditional(_, then, otherwise) => {
+                let then_type = then.get_type(vars, funcs, structs)?;
+                if then_type != otherwise.get_type(vars, funcs, structs)? {
+                    return Err(MirError::MismatchedConditionalBranchTypes(
+                        *then.clone(),
+                        *otherwise.clone(),
+                    ));
+                }
+                then_type
+            }
+
+            /// A raw asm block has whatever type the user declares
+            Self::RawAsm(t, _) => t.clone(),
 
             /// A move expression does not change the inner type
             Self::Move(expr) => expr.get_type(vars, funcs, structs)?,
@@ -2055,10 +3923,22 @@ impl MirExpression {
             /// expression is being cast to.
             Self::TypeCast(_, t) => t.clone(),
 
-            /// Arithmetic returns the type of the left hand side
-            Self::Add(l, _) | Self::Subtract(l, _) | Self::Multiply(l, _) | Self::Divide(l, _) => {
-                l.get_type(vars, funcs, structs)?
-            }
+            /// Arithmetic returns the type of the left hand side,
+            /// unless it calls one of a structure's operator methods
+            Self::Add(l, _)
+            | Self::Subtract(l, _)
+            | Self::Multiply(l, _)
+            | Self::Divide(l, _)
+            | Self::Modulo(l, _) => match self.operator_method(vars, funcs, structs)? {
+                Some(method_call) => method_call.get_type(vars, funcs, structs)?,
+                None => l.get_type(vars, funcs, structs)?,
+            },
+            /// Bitwise operations return the type of the left hand side
+            Self::BitAnd(l, _)
+            | Self::BitOr(l, _)
+            | Self::BitXor(l, _)
+            | Self::ShiftLeft(l, _)
+            | Self::ShiftRight(l, _) => l.get_type(vars, funcs, structs)?,
             /// Greater than, less than, greater or equal,
             /// and less than or equal expressions ALL return
             /// boolean values.
@@ -2073,14 +3953,17 @@ impl MirExpression {
             | Self::Not(_) => MirType::boolean(),
             /// Float literals have type `num`
             Self::Float(_) => MirType::float(),
-            /// String literals have type `&char`
-            Self::String(_) => MirType::character().refer(),
+            /// String literals and character arrays have type `&char`
+            Self::String(_) | Self::CharArray(_) => MirType::character().refer(),
             /// char literals have type `char`
             Self::Character(_) => MirType::character(),
             /// A void literal has type `void`
             Self::Void => MirType::void(),
             /// Allocating data on the heap returns a void pointer
             Self::Alloc(_) => MirType::void().refer(),
+            Self::Flush => MirType::void(),
+            Self::NotNull(_) => MirType::boolean(),
+            Self::Memset(_, _, _) | Self::Zero(_, _) => MirType::void(),
 
             /// Get the type of the instance, retrieve the method from the type,
             /// then get the return type of the method.
@@ -2118,7 +4001,8 @@ impl MirExpression {
 
             /// The type of foreign functions are unknown. The type system
             /// assumes they are of type &void.
-            Self::ForeignCall(_, _) => MirType::void().refer(),
+            Self::ForeignCall(_, _, _) | Self::ForeignLoad(_) => MirType::void().refer(),
+            Self::ForeignStore(_, _) => MirType::void(),
 
             /// Get the type of the variable
             Self::Variable(var_name) => {
@@ -2140,6 +4024,14 @@ impl MirExpression {
                     return Err(MirError::VariableNotDefined(var_name.clone()));
                 }
             }
+            Self::Captured(var_name) => {
+                if let Some(t) = vars.get(&AsmFunction::captured_name(var_name)) {
+                    t.refer()
+                } else {
+                    return Err(MirError::VariableNotDefined(var_name.clone()));
+                }
+            }
+            Self::Closure => MirType::void().refer(),
         })
     }
 }
@@ -2151,6 +4043,7 @@ impl Display for MirExpression {
                 write!(f, "{} ? {} : {}", cond, then, otherwise)
             }
             Self::Move(expr) => write!(f, "move({})", expr),
+            Self::RawAsm(t, body) => write!(f, "@asm({}) {:?}", t, body),
 
             Self::True => write!(f, "true"),
             Self::False => write!(f, "false"),
@@ -2164,6 +4057,12 @@ impl Display for MirExpression {
             Self::Subtract(lhs, rhs) => write!(f, "{}-{}", lhs, rhs),
             Self::Multiply(lhs, rhs) => write!(f, "{}*{}", lhs, rhs),
             Self::Divide(lhs, rhs) => write!(f, "{}/{}", lhs, rhs),
+            Self::Modulo(lhs, rhs) => write!(f, "{}%{}", lhs, rhs),
+            Self::BitAnd(lhs, rhs) => write!(f, "{}&{}", lhs, rhs),
+            Self::BitOr(lhs, rhs) => write!(f, "{}|{}", lhs, rhs),
+            Self::BitXor(lhs, rhs) => write!(f, "{}^{}", lhs, rhs),
+            Self::ShiftLeft(lhs, rhs) => write!(f, "{}<<{}", lhs, rhs),
+            Self::ShiftRight(lhs, rhs) => write!(f, "{}>>{}", lhs, rhs),
 
             Self::Equal(lhs, rhs) => write!(f, "{}=={}", lhs, rhs),
             Self::NotEqual(lhs, rhs) => write!(f, "{}!={}", lhs, rhs),
@@ -2173,11 +4072,22 @@ impl Display for MirExpression {
             Self::LessEqual(lhs, rhs) => write!(f, "{}<={}", lhs, rhs),
 
             Self::Alloc(size) => write!(f, "alloc({})", size),
+            Self::Memset(ptr, value, count) => write!(f, "memset({}, {}, {})", ptr, value, count),
+            Self::Zero(ptr, count) => write!(f, "zero({}, {})", ptr, count),
+            Self::Flush => write!(f, "flush()"),
+            Self::NotNull(name) => write!(f, "{}?", name),
 
             Self::Void => write!(f, "@"),
             Self::Character(ch) => write!(f, "'{}'", ch),
             Self::Float(n) => write!(f, "{}", n),
             Self::String(s) => write!(f, "{:?}", s),
+            Self::CharArray(exprs) => {
+                write!(f, "[")?;
+                for expr in exprs {
+                    write!(f, "{}, ", expr)?;
+                }
+                write!(f, "]")
+            }
 
             Self::Index(ptr, idx) => write!(f, "{}[{}]", ptr, idx),
             Self::Method(expr, method, args) => {
@@ -2194,16 +4104,19 @@ impl Display for MirExpression {
                 }
                 write!(f, ")")
             }
-            Self::ForeignCall(fn_name, args) => {
+            Self::ForeignCall(fn_name, args, _) => {
                 write!(f, "{}!(", fn_name)?;
                 for arg in args {
                     write!(f, "{}, ", arg)?;
                 }
                 write!(f, ")")
             }
+            Self::ForeignLoad(var_name) => write!(f, "{}!", var_name),
+            Self::ForeignStore(var_name, value) => write!(f, "{}! = {}", var_name, value),
             Self::Deref(ptr) => write!(f, "*{}", ptr),
-            Self::Refer(name) => write!(f, "&{}", name),
+            Self::Refer(name) | Self::Captured(name) => write!(f, "&{}", name),
             Self::Variable(name) => write!(f, "{}", name),
+            Self::Closure => write!(f, "<environment>"),
         }
     }
 }
@@ -0,0 +1,727 @@
+use super::{PanicStrategy, Target};
+use std::{
+    borrow::Cow,
+    cell::Cell,
+    env::{
+        self,
+        consts::{DLL_PREFIX, DLL_SUFFIX, EXE_SUFFIX},
+    },
+    fs::{remove_file, write},
+    io::{Error, ErrorKind, Result, Write},
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+
+/// The C target. Its options keep their defaults unless they are
+/// set with the builder methods below.
+#[derive(Clone, Default)]
+pub struct C {
+    /// Write the generated code to `out.c` instead of compiling it
+    emit_only: bool,
+    /// The C compiler binary to build the generated code with
+    compiler: Option<String>,
+    /// Run Oak functions on an explicit call stack instead of the native one
+    explicit_stack: bool,
+    /// Check for invalid frees at runtime
+    debug: bool,
+    /// Log each operation of the virtual machine to stderr
+    trace: bool,
+    /// Report the peak heap usage to stderr at exit
+    heap_stats: bool,
+    /// Write the entry point's body in place of its call
+    inline_entry: bool,
+    /// What the runtime does when it panics
+    panic_strategy: PanicStrategy,
+    /// Build a shared library of the exported functions instead of an executable
+    shared: bool,
+    /// The number of call sites assembled so far with an explicit call stack.
+    /// Each call site is given a unique ID to return to.
+    call_sites: Cell<usize>,
+}
+
+impl C {
+    /// The compiler used when neither `c_compiler` nor the `CC`
+    /// environment variable specify one.
+    const DEFAULT_COMPILER: &'static str = "gcc";
+
+    /// The start of the comment written before each function that
+    /// is defined in Oak, which names the function and its location.
+    const SOURCE_MARKER: &'static str = "// from ";
+
+    /// The name the C compiler gives the code it reads from stdin
+    const STDIN_NAME: &'static str = "<stdin>";
+
+    /// A C target with every option off, which builds an
+    /// executable with the compiler in `CC`, or gcc.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A C target that writes the generated code to `out.c`
+    /// without invoking gcc, so that it can be inspected or
+    /// built with a different toolchain.
+    pub fn emit_only() -> Self {
+        Self {
+            emit_only: true,
+            ..Self::default()
+        }
+    }
+
+    /// Build the generated code with the given C compiler binary
+    /// instead of gcc, such as `clang` or a cross compiler.
+    pub fn c_compiler(mut self, compiler: String) -> Self {
+        self.compiler = Some(compiler);
+        self
+    }
+
+    /// Assemble every Oak function into a single C function, and
+    /// keep track of where to return to on an explicit call stack.
+    /// This way, recursion depth is limited by the virtual machine's
+    /// memory rather than the size of the native stack.
+    pub fn explicit_stack(mut self) -> Self {
+        self.explicit_stack = true;
+        self
+    }
+
+    /// Check for double frees and frees of memory that was never allocated
+    /// at runtime, and panic when they happen.
+    pub fn debug(mut self) -> Self {
+        self.debug = true;
+        self
+    }
+
+    /// Log each operation of the virtual machine, such as a push
+    /// or a call, to stderr at runtime.
+    pub fn traced(mut self) -> Self {
+        self.trace = true;
+        self
+    }
+
+    /// Report the most cells allocated on the heap at once
+    /// to stderr when the program exits.
+    pub fn report_heap_stats(mut self) -> Self {
+        self.heap_stats = true;
+        self
+    }
+
+    /// Write the body of the entry point directly in `main`,
+    /// instead of calling it as a function.
+    pub fn inline_entry(mut self) -> Self {
+        self.inline_entry = true;
+        self
+    }
+
+    /// Choose what the runtime does when it panics. With
+    /// `PanicStrategy::ReturnError`, `main` returns the
+    /// error code instead of the process exiting.
+    pub fn panic_strategy(mut self, strategy: PanicStrategy) -> Self {
+        self.panic_strategy = strategy;
+        self
+    }
+
+    /// Build a shared library instead of an executable. The program
+    /// has no entry point, and each function marked with `#[export]`
+    /// can be called from other languages with native numbers.
+    pub fn shared(mut self) -> Self {
+        self.shared = true;
+        self
+    }
+
+    /// Get the C compiler binary to use. An explicitly specified
+    /// compiler takes priority over the `CC` environment variable.
+    fn compiler(&self) -> String {
+        match &self.compiler {
+            Some(compiler) => compiler.clone(),
+            None => match env::var("CC") {
+                Ok(compiler) if !compiler.is_empty() => compiler,
+                _ => String::from(Self::DEFAULT_COMPILER),
+            },
+        }
+    }
+
+    /// Point the C compiler's diagnostics about lines of the generated code
+    /// at the Oak functions they were generated from. The function of a line
+    /// is found from the nearest source marker above it.
+    fn map_diagnostics(code: &str, diagnostics: &str) -> String {
+        let lines = code.lines().collect::<Vec<_>>();
+        let mut result = String::new();
+        for diagnostic in diagnostics.lines() {
+            let rest = match diagnostic.strip_prefix(Self::STDIN_NAME) {
+                Some(rest) => rest,
+                None => {
+                    result += diagnostic;
+                    result += "\n";
+                    continue;
+                }
+            };
+
+            // The line number of the generated code, followed by the column
+            let mut parts = rest.splitn(4, ':');
+            parts.next();
+            let line = parts.next().and_then(|line| line.parse::<usize>().ok());
+            let column = parts.next();
+            let message = parts.next();
+
+            let source = line.and_then(|line| {
+                lines[..line.min(lines.len())]
+                    .iter()
+                    .rev()
+                    .find_map(|line| line.strip_prefix(Self::SOURCE_MARKER))
+            });
+            match (line, source, column, message) {
+                (Some(line), Some(source), Some(column), Some(message))
+                    if column.parse::<usize>().is_ok() =>
+                {
+                    result += &format!(
+                        "{}:{} (at line {} of the generated C)\n",
+                        source, message, line
+                    )
+                }
+                _ => {
+                    result += "the generated C";
+                    result += rest;
+                    result += "\n";
+                }
+            }
+        }
+        result
+    }
+}
+
+impl Target for C {
+    fn get_name(&self) -> char {
+        'c'
+    }
+
+    fn language(&self) -> &'static str {
+        "C"
+    }
+
+    fn is_standard(&self) -> bool {
+        true
+    }
+
+    fn std(&self) -> Cow<'static, str> {
+        Cow::Borrowed(include_str!("std/std.c"))
+    }
+
+    fn core_prelude(&self) -> Cow<'static, str> {
+        // The tracing code and the heap report in the core are only
+        // compiled when `TRACE` and `HEAP_STATS` are defined, and a
+        // panic only returns to `main` when `PANIC_RETURN` is defined.
+        // The runtime checks in the standard library need `DEBUG`.
+        let mut defines = String::new();
+        if self.debug {
+            defines += "#define DEBUG\n";
+        }
+        if self.trace {
+            defines += "#define TRACE\n";
+        }
+        if self.heap_stats {
+            defines += "#define HEAP_STATS\n";
+        }
+        if self.panic_strategy == PanicStrategy::ReturnError {
+            defines += "#define PANIC_RETURN\n";
+        }
+        if defines.is_empty() {
+            Cow::Borrowed(include_str!("core/core.c"))
+        } else {
+            Cow::Owned(defines + include_str!("core/core.c"))
+        }
+    }
+
+    // A shared library has no entry point to drop its machine at
+    // the end of, so the machine is dropped when it's unloaded
+    fn core_postlude(&self) -> Cow<'static, str> {
+        if self.shared_library() {
+            Cow::Borrowed(
+                "__attribute__((destructor)) static void shared_machine_drop(void) {\nif (shared_vm) machine_drop(shared_vm);\nshared_vm = NULL;\n}\n",
+            )
+        } else {
+            Cow::Borrowed("")
+        }
+    }
+
+    fn begin_entry_point(&self, global_scope_size: i32, memory_size: i32) -> String {
+        let mut result = format!(
+            "int main() {{\nmachine *vm = machine_new({}, {});\n",
+            global_scope_size,
+            global_scope_size + memory_size,
+        );
+        if self.panic_strategy == PanicStrategy::ReturnError {
+            // A panic jumps back here with its error code,
+            // which is returned after cleaning up the machine
+            result += "int status = setjmp(panic_handler);\nif (status != 0) {\nmachine_drop(vm);\nreturn status;\n}\n";
+        }
+        result
+    }
+
+    fn end_entry_point(&self) -> String {
+        String::from("\nmachine_drop(vm);\nreturn 0;\n}")
+    }
+
+    fn shared_library(&self) -> bool {
+        // With an explicit call stack, functions are labels in `machine_run`
+        self.shared && !self.explicit_stack
+    }
+
+    fn begin_exports(&self, global_scope_size: i32, memory_size: i32) -> String {
+        format!(
+            "machine *shared_vm = NULL;\nmachine *shared_machine() {{\nif (!shared_vm) shared_vm = machine_new({}, {});\nreturn shared_vm;\n}}\n",
+            global_scope_size,
+            global_scope_size + memory_size,
+        )
+    }
+
+    fn export_fn(&self, name: &str, arg_count: usize, call: String, returns: bool) -> String {
+        let params = (0..arg_count)
+            .map(|i| format!("double arg{}", i))
+            .collect::<Vec<_>>();
+        let mut result = format!(
+            "{} {}({}) {{\nmachine *vm = shared_machine();\n",
+            if returns { "double" } else { "void" },
+            name,
+            if params.is_empty() {
+                String::from("void")
+            } else {
+                params.join(", ")
+            }
+        );
+        for i in 0..arg_count {
+            result += &format!("machine_push(vm, arg{});\n", i);
+        }
+        result += &call;
+        if returns {
+            result += "return machine_pop(vm);\n";
+        }
+        result + "}\n"
+    }
+
+    fn check_stack(&self, size: i32) -> String {
+        format!("machine_check_stack(vm, {});\n", size)
+    }
+
+    fn establish_stack_frame(&self, arg_size: i32, local_scope_size: i32) -> String {
+        format!(
+            "machine_establish_stack_frame(vm, {}, {});\n",
+            arg_size, local_scope_size
+        )
+    }
+
+    fn end_stack_frame(&self, return_size: i32, local_scope_size: i32) -> String {
+        format!(
+            "machine_end_stack_frame(vm, {}, {});\n",
+            return_size, local_scope_size
+        )
+    }
+
+    fn load_base_ptr(&self) -> String {
+        String::from("machine_load_base_ptr(vm);\n")
+    }
+
+    fn push(&self, n: f64) -> String {
+        format!("machine_push(vm, {});\n", self.float_literal(n))
+    }
+
+    fn infinity(&self) -> String {
+        String::from("INFINITY")
+    }
+
+    fn nan(&self) -> String {
+        String::from("NAN")
+    }
+
+    fn add(&self) -> String {
+        String::from("machine_add(vm);\n")
+    }
+
+    fn subtract(&self) -> String {
+        String::from("machine_subtract(vm);\n")
+    }
+
+    fn multiply(&self) -> String {
+        String::from("machine_multiply(vm);\n")
+    }
+
+    fn divide(&self) -> String {
+        String::from("machine_divide(vm);\n")
+    }
+
+    fn modulus(&self) -> String {
+        String::from("machine_modulo(vm);\n")
+    }
+
+    fn bitand(&self) -> String {
+        String::from("machine_bitand(vm);\n")
+    }
+
+    fn bitor(&self) -> String {
+        String::from("machine_bitor(vm);\n")
+    }
+
+    fn bitxor(&self) -> String {
+        String::from("machine_bitxor(vm);\n")
+    }
+
+    fn shl(&self) -> String {
+        String::from("machine_shl(vm);\n")
+    }
+
+    fn shr(&self) -> String {
+        String::from("machine_shr(vm);\n")
+    }
+
+    fn sign(&self) -> String {
+        String::from("machine_sign(vm);\n")
+    }
+
+    fn truncate(&self, bits: i32, is_signed: bool) -> String {
+        format!("machine_truncate(vm, {}, {});\n", bits, is_signed)
+    }
+
+    fn allocate(&self) -> String {
+        String::from("machine_allocate(vm);\n")
+    }
+
+    fn debug_runtime(&self) -> bool {
+        self.debug
+    }
+
+    fn trace(&self) -> bool {
+        self.trace
+    }
+
+    fn heap_stats(&self) -> bool {
+        self.heap_stats
+    }
+
+    fn panic_strategy(&self) -> PanicStrategy {
+        self.panic_strategy
+    }
+
+    fn free(&self) -> String {
+        if self.debug {
+            String::from("machine_checked_free(vm);\n")
+        } else {
+            String::from("machine_free(vm);\n")
+        }
+    }
+
+    fn memset(&self, size: i32) -> String {
+        format!("machine_memset(vm, {});\n", size)
+    }
+
+    fn flush(&self) -> String {
+        String::from("machine_flush(vm);\n")
+    }
+
+    fn store(&self, size: i32) -> String {
+        if self.debug {
+            format!("machine_checked_store(vm, {});\n", size)
+        } else {
+            format!("machine_store(vm, {});\n", size)
+        }
+    }
+
+    fn emit_initialized_data(&self, address: i32, cells: &[f64]) -> String {
+        let cells = cells
+            .iter()
+            .map(|cell| self.float_literal(*cell))
+            .collect::<Vec<_>>();
+        format!(
+            "{{\nstatic const double data[] = {{{}}};\nmachine_store_data(vm, {}, data, {});\n}}\n",
+            cells.join(", "),
+            address,
+            cells.len()
+        ) + &self.push(address as f64)
+    }
+
+    /// C99 only guarantees that the first 31 characters
+    /// of an external identifier are significant.
+    fn max_identifier_length(&self) -> Option<usize> {
+        Some(31)
+    }
+
+    fn load(&self, size: i32) -> String {
+        if self.debug {
+            format!("machine_checked_load(vm, {});\n", size)
+        } else {
+            format!("machine_load(vm, {});\n", size)
+        }
+    }
+
+    fn load_byte(&self) -> String {
+        if self.debug {
+            String::from("machine_checked_load_byte(vm);\n")
+        } else {
+            String::from("machine_load_byte(vm);\n")
+        }
+    }
+
+    fn store_byte(&self) -> String {
+        if self.debug {
+            String::from("machine_checked_store_byte(vm);\n")
+        } else {
+            String::from("machine_store_byte(vm);\n")
+        }
+    }
+
+    fn random(&self) -> String {
+        String::from("machine_random(vm);\n")
+    }
+
+    fn seed_random(&self) -> String {
+        String::from("machine_seed_random(vm);\n")
+    }
+
+    fn clock(&self) -> String {
+        String::from("machine_clock(vm);\n")
+    }
+
+    fn source_location(&self, filename: &str, line: usize, name: &str) -> String {
+        format!(
+            "{}{}:{}: in fn {}\n",
+            Self::SOURCE_MARKER,
+            filename,
+            line,
+            name
+        )
+    }
+
+    fn fn_header(&self, name: String) -> String {
+        if self.explicit_stack {
+            String::new()
+        } else {
+            format!("void {}(machine* vm);\n", name)
+        }
+    }
+
+    fn begin_fn_definitions(&self) -> String {
+        if self.explicit_stack {
+            self.call_sites.set(0);
+            String::from("void machine_run(machine* vm) {\ngoto entry;\n")
+        } else {
+            String::new()
+        }
+    }
+
+    fn fn_definition(&self, name: String, body: String) -> String {
+        if self.explicit_stack {
+            // Each function is a label in `machine_run`, and returns by
+            // jumping back to the call site on top of the call stack.
+            format!("{}:\n{}goto dispatch;\n", name, body)
+        } else {
+            format!("void {}(machine* vm) {{\n{}}}\n", name, body)
+        }
+    }
+
+    fn native_return(&self) -> bool {
+        // With an explicit call stack, functions are labels in `machine_run`
+        !self.explicit_stack
+    }
+
+    fn end_stack_frame_returning(&self, local_scope_size: i32) -> String {
+        format!(
+            "return machine_end_stack_frame_returning(vm, {});\n",
+            local_scope_size
+        )
+    }
+
+    fn return_native(&self) -> String {
+        String::from("return machine_pop(vm);\n")
+    }
+
+    fn fn_header_returning(&self, name: String, _return_size: i32) -> String {
+        format!("double {}(machine* vm);\n", name)
+    }
+
+    fn fn_definition_returning(&self, name: String, body: String, _return_size: i32) -> String {
+        format!("double {}(machine* vm) {{\n{}}}\n", name, body)
+    }
+
+    fn call_fn_returning(&self, name: String) -> String {
+        format!("machine_push(vm, {}(vm));\n", name)
+    }
+
+    fn end_fn_definitions(&self, entry: String) -> String {
+        if self.explicit_stack {
+            let mut result = String::from("dispatch:\nswitch (machine_pop_call(vm)) {\n");
+            for id in 1..=self.call_sites.get() {
+                result += &format!("case {}: goto ret{};\n", id, id);
+            }
+            // The entry point was called with ID 0, so returning
+            // from it leaves `machine_run`.
+            result
+                + &format!(
+                    "default: return;\n}}\nentry:\nmachine_push_call(vm, 0);\ngoto {};\n}}\n",
+                    entry
+                )
+        } else {
+            String::new()
+        }
+    }
+
+    fn call_fn(&self, name: String) -> String {
+        if self.explicit_stack {
+            let id = self.call_sites.get() + 1;
+            self.call_sites.set(id);
+            format!(
+                "machine_push_call(vm, {});\ngoto {};\nret{}:;\n",
+                id, name, id
+            )
+        } else {
+            format!("{}(vm);\n", name)
+        }
+    }
+
+    fn inline_entry_point(&self) -> bool {
+        // With an explicit call stack, the entry point
+        // must be run as a label in `machine_run`
+        self.inline_entry && !self.explicit_stack
+    }
+
+    fn call_entry_point(&self, name: String) -> String {
+        if self.explicit_stack {
+            String::from("machine_run(vm);\n")
+        } else {
+            self.call_fn(name)
+        }
+    }
+
+    fn call_foreign_fn(&self, name: String) -> String {
+        format!("{}(vm);\n", name)
+    }
+
+    fn call_foreign_fn_with_context(&self, name: String) -> String {
+        format!("{}(vm, vm->context);\n", name)
+    }
+
+    fn load_foreign_var(&self, name: String) -> String {
+        format!("machine_push(vm, {});\n", name)
+    }
+
+    fn store_foreign_var(&self, name: String) -> String {
+        format!("{} = machine_pop(vm);\n", name)
+    }
+
+    fn begin_while(&self, _label: Option<&str>) -> String {
+        String::from("while (machine_pop(vm)) {\n")
+    }
+
+    // A loop's labels are only jumped to if it breaks or continues,
+    // so they're marked unused to keep gcc from warning about them
+    fn end_while_body(&self, label: Option<&str>) -> String {
+        match label {
+            Some(label) => format!("continue_{}: __attribute__((unused));\n", label),
+            None => String::new(),
+        }
+    }
+
+    fn end_while(&self, label: Option<&str>) -> String {
+        match label {
+            Some(label) => format!("}}\nbreak_{}: __attribute__((unused));\n", label),
+            None => String::from("}\n"),
+        }
+    }
+
+    fn break_while(&self, label: &str) -> String {
+        format!("goto break_{};\n", label)
+    }
+
+    fn continue_while(&self, label: &str) -> String {
+        format!("goto continue_{};\n", label)
+    }
+
+    fn begin_if(&self) -> String {
+        String::from("if (machine_pop(vm)) {\n")
+    }
+
+    fn begin_else(&self) -> String {
+        String::from("} else {\n")
+    }
+
+    fn end_if(&self) -> String {
+        String::from("}\n")
+    }
+
+    fn compile(&self, code: String) -> Result<PathBuf> {
+        if self.emit_only {
+            write("out.c", &code)?;
+            return Result::Ok(PathBuf::from("out.c"));
+        }
+
+        let output = if self.shared_library() {
+            format!("{}main{}", DLL_PREFIX, DLL_SUFFIX)
+        } else {
+            format!("main{}", EXE_SUFFIX)
+        };
+
+        let compiler = self.compiler();
+        let mut command = Command::new(&compiler);
+        command.arg("-O2");
+        if self.shared_library() {
+            command.args(&["-shared", "-fPIC"]);
+        }
+        let mut child = command
+            .args(&["-o", &output[..]])
+            .args(&["-x", "c", "-"])
+            // The runtime uses the math library for `fmod`
+            .arg("-lm")
+            .stdin(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn();
+
+        if let Err(error) = &child {
+            if error.kind() == ErrorKind::NotFound {
+                return Result::Err(Error::new(
+                    ErrorKind::NotFound,
+                    format!("C compiler \"{}\" not found", compiler),
+                ));
+            }
+        }
+
+        if let Ok(mut child) = child {
+            match child.stdin.as_mut() {
+                Some(stdin) => {
+                    if let Err(error) = stdin.write_all(code.as_bytes()) {
+                        return Result::Err(Error::new(
+                            ErrorKind::Other,
+                            "unable to open write to child stdin",
+                        ));
+                    }
+                }
+                None => {
+                    return Result::Err(Error::new(ErrorKind::Other, "unable to open child stdin"))
+                }
+            }
+
+            match child.wait_with_output() {
+                Ok(result) => {
+                    // Report the compiler's diagnostics in terms of the Oak source
+                    let diagnostics =
+                        Self::map_diagnostics(&code, &String::from_utf8_lossy(&result.stderr));
+                    if !result.status.success() {
+                        return Result::Err(Error::new(
+                            ErrorKind::Other,
+                            format!(
+                                "C compiler \"{}\" failed to compile the generated code\n{}",
+                                compiler, diagnostics
+                            ),
+                        ));
+                    }
+                    eprint!("{}", diagnostics);
+                    return Result::Ok(PathBuf::from(output));
+                }
+                Err(_) => {
+                    return Result::Err(Error::new(ErrorKind::Other, "unable to read child output"))
+                }
+            }
+        } else {
+            // child failed to execute
+            Result::Err(Error::new(
+                ErrorKind::Other,
+                format!("unable to spawn child {} proccess", compiler),
+            ))
+        }
+    }
+}
@@ -0,0 +1,703 @@
+#include <stdio.h>
+#include <stdlib.h>
+#include <stdbool.h>
+#include <math.h>
+#include <setjmp.h>
+#include <time.h>
+
+typedef struct machine {
+    double* memory;
+    bool*   allocated;
+    int     capacity;
+    int     stack_ptr;
+    int     base_ptr;
+    // The number of cells allocated on the heap, and the most
+    // that have been allocated at once during the program.
+    int     heap_used;
+    int     heap_peak;
+    // The host's state, which is passed to the foreign
+    // functions marked with `#[context]`. It starts as NULL.
+    void*   context;
+    // The call stack is only used when Oak functions
+    // are run with an explicit call stack.
+    int*    calls;
+    int     call_count;
+    int     call_capacity;
+} machine;
+
+
+///////////////////////////////////////////////////////////////////////
+///////////////////////////// Error codes /////////////////////////////
+///////////////////////////////////////////////////////////////////////
+const int STACK_HEAP_COLLISION = 1;
+const int NO_FREE_MEMORY       = 2;
+const int STACK_UNDERFLOW      = 3;
+const int INVALID_FREE         = 4;
+const int UNBALANCED_STACK     = 5;
+const int UNTERMINATED_STRING  = 6;
+const int INVALID_ACCESS       = 7;
+
+// When a program is compiled with the `return-error` panic strategy,
+// `PANIC_RETURN` is defined, and a panic jumps back to the entry point,
+// which returns the error code instead of exiting the process.
+#ifdef PANIC_RETURN
+jmp_buf panic_handler;
+#endif
+
+// Fatal error handler. Stops the program.
+void panic(int code) {
+    printf("panic: ");
+    switch (code) {
+        case 1: printf("stack and heap collision during push"); break;
+        case 2: printf("no free memory left"); break;
+        case 3: printf("stack underflow"); break;
+        case 4: printf("freed memory that is not allocated"); break;
+        case 5: printf("stack is not balanced at the end of the program"); break;
+        case 6: printf("string is not terminated before the end of memory"); break;
+        case 7: printf("accessed memory that is not allocated"); break;
+        default: printf("unknown error code");
+    }
+    printf("\n");
+#ifdef PANIC_RETURN
+    longjmp(panic_handler, code);
+#else
+    exit(code);
+#endif
+}
+
+///////////////////////////////////////////////////////////////////////
+///////////////////////////// Debug Info //////////////////////////////
+///////////////////////////////////////////////////////////////////////
+// Print out the state of the virtual machine's stack and heap
+void machine_dump(machine *vm) {
+    int i;
+    printf("stack: [ ");
+    for (i=0; i<vm->stack_ptr; i++)
+        printf("%g ", vm->memory[i]);
+    for (i=vm->stack_ptr; i<vm->capacity; i++)
+        printf("  ");
+    printf("]\nheap:  [ ");
+    for (i=0; i<vm->stack_ptr; i++)
+        printf("  ");
+    for (i=vm->stack_ptr; i<vm->capacity; i++)
+        printf("%g ", vm->memory[i]);
+    printf("]\nalloc: [ ");
+    for (i=0; i<vm->capacity; i++)
+        printf("%d ", vm->allocated[i]);
+    printf("]\n");
+    int total = 0;
+    for (i=0; i<vm->capacity; i++)
+        total += vm->allocated[i];
+    printf("STACK SIZE    %d\n", vm->stack_ptr);
+    printf("TOTAL ALLOC'D %d\n", total);
+}
+
+
+///////////////////////////////////////////////////////////////////////
+/////////////////////////////// Tracing ///////////////////////////////
+///////////////////////////////////////////////////////////////////////
+// When a program is compiled with `--trace`, `TRACE` is defined, and each
+// operation logs its effect to stderr. Operations are built out of other
+// operations, like `add` pushing its result, so only the outermost
+// operation is logged.
+#ifdef TRACE
+int trace_depth = 0;
+#define TRACE_ENTER() trace_depth++
+#define TRACE_EXIT(...) do { if (--trace_depth == 0) fprintf(stderr, __VA_ARGS__); } while (0)
+#else
+#define TRACE_ENTER()
+#define TRACE_EXIT(...)
+#endif
+
+
+/////////////////////////////////////////////////////////////////////////
+///////////////////// Stack manipulation operations /////////////////////
+/////////////////////////////////////////////////////////////////////////
+// Push a number onto the stack
+void machine_push(machine *vm, double n) {
+    TRACE_ENTER();
+    // If the memory at the stack pointer is allocated on the heap,
+    // then the stack pointer has collided with the heap.
+    // The program cannot continue without undefined behaviour,
+    // so the program must panic.
+    if (vm->allocated[vm->stack_ptr])
+        panic(STACK_HEAP_COLLISION);
+    
+    // If the memory isn't allocated, simply push the value onto the stack.
+    vm->memory[vm->stack_ptr++] = n;
+    TRACE_EXIT("push %g\n", n);
+}
+
+// Pop a number from the stack
+double machine_pop(machine *vm) {
+    // If the stack pointer can't decrement any further,
+    // the stack has underflowed.
+
+    // It is not possible for pure Oak to generate code that will
+    // cause a stack underflow. Foreign functions, or errors in
+    // the virtual machine implementation are SOLELY responsible
+    // for a stack underflow.
+    if (vm->stack_ptr == 0) {
+        panic(STACK_UNDERFLOW);
+    }
+    // Get the popped value
+    double result = vm->memory[--vm->stack_ptr];
+    // Overwrite the position on the stack with a zero
+    vm->memory[vm->stack_ptr] = 0;
+    return result;
+}
+
+// Write all of the buffered output to stdout
+void machine_flush(machine *vm) {
+    (void)vm;
+    fflush(stdout);
+}
+
+////////////////////////////////////////////////////////////////////////
+////////////////////// Constructor and destructor //////////////////////
+////////////////////////////////////////////////////////////////////////
+// The size of the buffer used for the program's output
+#define OUTPUT_BUFFER_SIZE 65536
+
+// Create new virtual machine
+machine *machine_new(int global_scope_size, int capacity) {
+    // Buffer all of the program's output. Otherwise, stdout is
+    // line buffered when writing to a terminal, which makes programs
+    // that print many characters very slow.
+    setvbuf(stdout, NULL, _IOFBF, OUTPUT_BUFFER_SIZE);
+
+    machine *result = malloc(sizeof(machine));
+    result->capacity  = capacity;
+    result->memory    = malloc(sizeof(double) * capacity);
+    result->allocated = malloc(sizeof(bool)   * capacity);
+    result->stack_ptr = 0;
+    result->heap_used = 0;
+    result->heap_peak = 0;
+    result->context   = NULL;
+    result->calls         = NULL;
+    result->call_count    = 0;
+    result->call_capacity = 0;
+    int i;
+    for (i=0; i<capacity; i++) {
+        result->memory[i] = 0;
+        result->allocated[i] = false;
+    }
+
+    for (i=0; i<global_scope_size; i++)
+        machine_push(result, 0);
+
+    result->base_ptr = 0;
+
+    return result;
+}
+
+// Check that the stack holds exactly `size` cells: the global scope, and
+// the value returned by the entry point. Anything else was left on the stack
+// by a foreign function or inline assembly. Used by the debug runtime.
+void machine_check_stack(machine *vm, int size) {
+    if (vm->stack_ptr != size) panic(UNBALANCED_STACK);
+}
+
+// Free the virtual machine's memory. This is called at the end of the program.
+// When a program is compiled with `--heap-stats`, `HEAP_STATS` is defined, and
+// the most cells that were allocated on the heap at once is reported to stderr.
+void machine_drop(machine *vm) {
+    // machine_dump(vm);
+    machine_flush(vm);
+#ifdef HEAP_STATS
+    fprintf(stderr, "heap high-water mark: %d cells\n", vm->heap_peak);
+#endif
+    free(vm->memory);
+    free(vm->allocated);
+    free(vm->calls);
+}
+
+////////////////////////////////////////////////////////////////////////
+////////////////////// Function memory management //////////////////////
+////////////////////////////////////////////////////////////////////////
+// Push the base pointer onto the stack
+void machine_load_base_ptr(machine *vm) {
+    // Get the virtual machine's current base pointer value,
+    // and push it onto the stack.
+    machine_push(vm, vm->base_ptr);
+}
+
+// Push the ID of the call site to return to onto the explicit call stack.
+// Every Oak call also pushes a base pointer onto the virtual machine's
+// stack, so the depth of the call stack is bounded by the machine's memory.
+void machine_push_call(machine *vm, int id) {
+    if (vm->call_count == vm->call_capacity) {
+        vm->call_capacity = vm->call_capacity ? vm->call_capacity * 2 : 256;
+        vm->calls = realloc(vm->calls, sizeof(int) * vm->call_capacity);
+    }
+    vm->calls[vm->call_count++] = id;
+}
+
+// Pop the ID of the call site to return to off of the explicit call stack.
+int machine_pop_call(machine *vm) {
+    if (vm->call_count == 0) panic(STACK_UNDERFLOW);
+    return vm->calls[--vm->call_count];
+}
+
+// Establish a new stack frame for a function with `arg_size`
+// number of cells as arguments.
+void machine_establish_stack_frame(machine *vm, int arg_size, int local_scope_size) {
+    TRACE_ENTER();
+    // Allocate some space to store the arguments' cells for later
+    double *args = malloc(arg_size * sizeof(double));
+    int i;
+    // Pop the arguments' values off of the stack
+    for (i=arg_size-1; i>=0; i--)
+        args[i] = machine_pop(vm);
+
+    // Push the current base pointer onto the stack so that
+    // when this function returns, it will be able to resume
+    // the current stack frame
+    machine_load_base_ptr(vm);
+
+    // Set the base pointer to the current stack pointer to 
+    // begin the stack frame at the current position on the stack.
+    vm->base_ptr = vm->stack_ptr;
+
+    // Allocate space for all the variables used in the local scope on the stack
+    for (i=0; i<local_scope_size; i++)
+        machine_push(vm, 0);
+
+    // Push the arguments back onto the stack for use by the current function
+    for (i=0; i<arg_size; i++)
+        machine_push(vm, args[i]);
+
+    // Free the space used to temporarily store the supplied arguments.
+    free(args);
+    TRACE_EXIT("call with %d argument cells and %d local cells\n", arg_size, local_scope_size);
+}
+
+// End a stack frame for a function with `return_size` number of cells
+// to return, and resume the parent stack frame.
+void machine_end_stack_frame(machine *vm, int return_size, int local_scope_size) {
+    TRACE_ENTER();
+    // Allocate some space to store the returned cells for later
+    double *return_val = malloc(return_size * sizeof(double));
+    int i;
+    // Pop the returned values off of the stack
+    for (i=return_size-1; i>=0; i--)
+        return_val[i] = machine_pop(vm);
+
+    // Discard the memory setup by the stack frame
+    for (i=0; i<local_scope_size; i++)
+        machine_pop(vm);
+    
+    // Retrieve the parent function's base pointer to resume the function
+    vm->base_ptr = machine_pop(vm);
+
+    // Finally, push the returned value back onto the stack for use by
+    // the parent function.
+    for (i=0; i<return_size; i++)
+        machine_push(vm, return_val[i]);
+
+    // Free the space used to temporarily store the returned value.
+    free(return_val);
+    TRACE_EXIT("return %d cells\n", return_size);
+}
+
+// End a stack frame of a function that returns a single cell, and return
+// the cell instead of pushing it back onto the stack. The caller pushes it.
+double machine_end_stack_frame_returning(machine *vm, int local_scope_size) {
+    TRACE_ENTER();
+    double return_val = machine_pop(vm);
+    int i;
+    // Discard the memory setup by the stack frame
+    for (i=0; i<local_scope_size; i++)
+        machine_pop(vm);
+
+    // Retrieve the parent function's base pointer to resume the function
+    vm->base_ptr = machine_pop(vm);
+    TRACE_EXIT("return 1 cells\n");
+    return return_val;
+}
+
+
+/////////////////////////////////////////////////////////////////////////
+///////////////////// Pointer and memory operations /////////////////////
+/////////////////////////////////////////////////////////////////////////
+// Pop the `size` parameter off of the stack, and return a pointer to `size` number of free cells.
+// The returned address is always the index of a whole cell, so allocations are cell-aligned.
+// If there isn't enough free memory, the null address zero is returned instead. The heap
+// always begins after the stack, so zero is never the address of an allocation.
+int machine_allocate(machine *vm) {
+    TRACE_ENTER();
+    // Get the size of the memory to allocate on the heap
+    int i, size=machine_pop(vm), addr=0, consecutive_free_cells=0;
+
+    // Starting at the end of the memory tape, find `size`
+    // number of consecutive cells that have not yet been
+    // allocated.
+    for (i=vm->capacity-1; i>vm->stack_ptr; i--) {
+        // If the memory hasn't been allocated, increment the counter.
+        // Otherwise, reset the counter.
+        if (!vm->allocated[i]) consecutive_free_cells++;
+        else consecutive_free_cells = 0;
+
+        // After we've found an address with the proper amount of memory left,
+        // return the address.
+        if (consecutive_free_cells == size) {
+            addr = i;
+            break;
+        }
+    }
+
+    // If the address is less than the stack pointer, the heap must be full.
+    // Push null, so that the program can check whether the allocation failed.
+    if (addr <= vm->stack_ptr) {
+        machine_push(vm, 0);
+        TRACE_EXIT("allocate %d cells failed\n", size);
+        return 0;
+    }
+
+    // Mark the address as allocated
+    for (i=0; i<size; i++)
+        vm->allocated[addr+i] = true;
+    vm->heap_used += size;
+    if (vm->heap_used > vm->heap_peak) vm->heap_peak = vm->heap_used;
+
+    // Push the address onto the stack
+    machine_push(vm, addr);
+    TRACE_EXIT("allocate %d cells at %d\n", size, addr);
+    return addr;
+}
+
+// Pop the `address` and `size` parameters off of the stack, and free the memory at `address` with size `size`.
+void machine_free(machine *vm) {
+    TRACE_ENTER();
+    // Get the address and size to free from the stack
+    int i, addr=machine_pop(vm), size=machine_pop(vm);
+    // Freeing null, from an allocation that failed, does nothing
+    if (addr == 0) size = 0;
+
+    // Mark the memory as unallocated, and zero each of the cells
+    for (i=0; i<size; i++) {
+        if (vm->allocated[addr+i]) vm->heap_used--;
+        vm->allocated[addr+i] = false;
+        vm->memory[addr+i] = 0;
+    }
+    TRACE_EXIT("free %d cells at %d\n", size, addr);
+}
+
+// Free memory like `machine_free`, but first check that every cell being freed
+// is allocated. This catches double frees, and frees of addresses that were never
+// allocated, before they can corrupt the `allocated` table. Used by the debug runtime.
+void machine_checked_free(machine *vm) {
+    TRACE_ENTER();
+    // Get the address and size to free from the stack
+    int i, addr=machine_pop(vm), size=machine_pop(vm);
+
+    // Freeing null, from an allocation that failed, does nothing
+    if (addr == 0) size = 0;
+    if (addr < 0 || addr + size > vm->capacity) panic(INVALID_FREE);
+    for (i=0; i<size; i++)
+        if (!vm->allocated[addr+i]) panic(INVALID_FREE);
+
+    // Mark the memory as unallocated, and zero each of the cells
+    for (i=0; i<size; i++) {
+        vm->allocated[addr+i] = false;
+        vm->memory[addr+i] = 0;
+    }
+    vm->heap_used -= size;
+    TRACE_EXIT("free %d cells at %d\n", size, addr);
+}
+
+// Pop an `address` parameter off of the stack, and a `value` parameter with size `size`.
+// Then store the `value` parameter at the memory address `address`.
+void machine_store(machine *vm, int size) {
+    TRACE_ENTER();
+    // Pop an address off of the stack
+    int i, addr=machine_pop(vm);
+
+    // Pop `size` number of cells from the stack,
+    // and store them at the address in the same order they were
+    // pushed onto the stack.
+    for (i=size-1; i>=0; i--) vm->memory[addr+i] = machine_pop(vm);
+    TRACE_EXIT("store %d cells at %d\n", size, addr);
+}
+
+// Panic unless `size` cells starting at `addr` can be accessed. They must be in memory,
+// and each cell at or above `stack_top` must be allocated on the heap, because the cells
+// between the stack and the heap are free. Used by the debug runtime.
+void machine_check_access(machine *vm, int addr, int size, int stack_top) {
+    int i;
+    if (addr < 0 || addr + size > vm->capacity) panic(INVALID_ACCESS);
+    for (i=0; i<size; i++)
+        if (addr+i >= stack_top && !vm->allocated[addr+i]) panic(INVALID_ACCESS);
+}
+
+// Store a value like `machine_store`, but first check that the
+// address can be accessed. Used by the debug runtime.
+void machine_checked_store(machine *vm, int size) {
+    TRACE_ENTER();
+    int i, addr=machine_pop(vm);
+    // The value being stored is popped off of the stack first
+    machine_check_access(vm, addr, size, vm->stack_ptr - size);
+    for (i=size-1; i>=0; i--) vm->memory[addr+i] = machine_pop(vm);
+    TRACE_EXIT("store %d cells at %d\n", size, addr);
+}
+
+// Pop a `count` parameter, a `value` parameter with size `size`, and an `address` parameter
+// off of the stack. Then store `count` copies of `value` one after another starting at `address`.
+void machine_memset(machine *vm, int size) {
+    int i, j, count=machine_pop(vm);
+    double *value = malloc(size * sizeof(double));
+    for (j=size-1; j>=0; j--) value[j] = machine_pop(vm);
+    int addr=machine_pop(vm);
+
+    for (i=0; i<count; i++)
+        for (j=0; j<size; j++)
+            vm->memory[addr+i*size+j] = value[j];
+
+    free(value);
+}
+
+// Copy `size` precomputed cells from `data` into memory starting at `address`.
+void machine_store_data(machine *vm, int address, const double *data, int size) {
+    int i;
+    for (i=0; i<size; i++) vm->memory[address+i] = data[i];
+}
+
+// Pop an `address` parameter off of the stack, and push the value at `address` with size `size` onto the stack.
+void machine_load(machine *vm, int size) {
+    TRACE_ENTER();
+    int i, addr=machine_pop(vm);
+    for (i=0; i<size; i++) machine_push(vm, vm->memory[addr+i]);
+    TRACE_EXIT("load %d cells from %d\n", size, addr);
+}
+
+// Load a value like `machine_load`, but first check that the
+// address can be accessed. Used by the debug runtime.
+void machine_checked_load(machine *vm, int size) {
+    TRACE_ENTER();
+    int i, addr=machine_pop(vm);
+    machine_check_access(vm, addr, size, vm->stack_ptr);
+    for (i=0; i<size; i++) machine_push(vm, vm->memory[addr+i]);
+    TRACE_EXIT("load %d cells from %d\n", size, addr);
+}
+
+// Each cell holds the bytes of a double, so the memory starting at
+// an address can be used as a C byte array. Pop an index and an
+// address, and push the byte at that index of the memory.
+void machine_load_byte(machine *vm) {
+    TRACE_ENTER();
+    int index=machine_pop(vm), addr=machine_pop(vm);
+    unsigned char byte = ((unsigned char*)&vm->memory[addr])[index];
+    machine_push(vm, byte);
+    TRACE_EXIT("load byte %d of %d -> %d\n", index, addr, byte);
+}
+
+// Pop a byte, an index, and an address, and store
+// the byte at that index of the memory at the address.
+void machine_store_byte(machine *vm) {
+    TRACE_ENTER();
+    unsigned char byte=(int)machine_pop(vm);
+    int index=machine_pop(vm), addr=machine_pop(vm);
+    ((unsigned char*)&vm->memory[addr])[index] = byte;
+    TRACE_EXIT("store byte %d at byte %d of %d\n", byte, index, addr);
+}
+
+// Panic unless the byte at `index` of the memory at `addr` can be accessed.
+// The index may be past the first cell, but neither it nor the address can be
+// negative, and the cell holding the byte must be accessible. Used by the debug runtime.
+void machine_check_byte_access(machine *vm, int addr, int index) {
+    if (addr < 0 || index < 0) panic(INVALID_ACCESS);
+    machine_check_access(vm, addr + index / (int)sizeof(double), 1, vm->stack_ptr);
+}
+
+// Load a byte like `machine_load_byte`, but first check that
+// the byte can be accessed. Used by the debug runtime.
+void machine_checked_load_byte(machine *vm) {
+    TRACE_ENTER();
+    int index=machine_pop(vm), addr=machine_pop(vm);
+    machine_check_byte_access(vm, addr, index);
+    unsigned char byte = ((unsigned char*)&vm->memory[addr])[index];
+    machine_push(vm, byte);
+    TRACE_EXIT("load byte %d of %d -> %d\n", index, addr, byte);
+}
+
+// Store a byte like `machine_store_byte`, but first check that
+// the byte can be accessed. Used by the debug runtime.
+void machine_checked_store_byte(machine *vm) {
+    TRACE_ENTER();
+    unsigned char byte=(int)machine_pop(vm);
+    int index=machine_pop(vm), addr=machine_pop(vm);
+    machine_check_byte_access(vm, addr, index);
+    ((unsigned char*)&vm->memory[addr])[index] = byte;
+    TRACE_EXIT("store byte %d at byte %d of %d\n", byte, index, addr);
+}
+
+// Push a random number in [0, 1). Like C's `rand`, the numbers
+// are the same in every run, unless the program is seeded.
+void machine_random(machine *vm) {
+    TRACE_ENTER();
+    double n = rand() / ((double)RAND_MAX + 1);
+    machine_push(vm, n);
+    TRACE_EXIT("random -> %g\n", n);
+}
+
+// Pop a seed, and start a new sequence of random numbers with it
+void machine_seed_random(machine *vm) {
+    TRACE_ENTER();
+    double seed = machine_pop(vm);
+    srand((unsigned int)(long long)seed);
+    TRACE_EXIT("seed random with %g\n", seed);
+}
+
+// Push the number of seconds since the program started. This
+// is the processor time used by the program, from C's `clock`.
+void machine_clock(machine *vm) {
+    TRACE_ENTER();
+    double seconds = (double)clock() / CLOCKS_PER_SEC;
+    machine_push(vm, seconds);
+    TRACE_EXIT("clock -> %g\n", seconds);
+}
+
+// Add the topmost numbers on the stack
+void machine_add(machine *vm) {
+    TRACE_ENTER();
+    double b = machine_pop(vm);
+    double a = machine_pop(vm);
+    machine_push(vm, a+b);
+    TRACE_EXIT("add %g %g -> %g\n", a, b, a+b);
+}
+
+// Subtract the topmost number on the stack from the second topmost number on the stack
+void machine_subtract(machine *vm) {
+    TRACE_ENTER();
+    double b = machine_pop(vm);
+    double a = machine_pop(vm);
+    machine_push(vm, a-b);
+    TRACE_EXIT("subtract %g %g -> %g\n", a, b, a-b);
+}
+
+// Multiply the topmost numbers on the stack
+void machine_multiply(machine *vm) {
+    TRACE_ENTER();
+    double b = machine_pop(vm);
+    double a = machine_pop(vm);
+    machine_push(vm, a*b);
+    TRACE_EXIT("multiply %g %g -> %g\n", a, b, a*b);
+}
+
+// Divide the second topmost number on the stack by the topmost number on the stack
+void machine_divide(machine *vm) {
+    TRACE_ENTER();
+    double b = machine_pop(vm);
+    double a = machine_pop(vm);
+    machine_push(vm, a/b);
+    TRACE_EXIT("divide %g %g -> %g\n", a, b, a/b);
+}
+
+// Push the remainder of dividing the second topmost number on the stack by the
+// topmost number on the stack. The remainder has the same sign as the dividend.
+void machine_modulo(machine *vm) {
+    TRACE_ENTER();
+    double b = machine_pop(vm);
+    double a = machine_pop(vm);
+    double n = fmod(a, b);
+    machine_push(vm, n);
+    TRACE_EXIT("modulo %g %g -> %g\n", a, b, n);
+}
+
+// Push 1 if the topmost number on the stack is positive, -1 if it is negative,
+// and 0 otherwise. Both `0` and `-0` have a sign of 0, and so does `NaN`.
+void machine_sign(machine *vm) {
+    TRACE_ENTER();
+    double x = machine_pop(vm);
+    if (x > 0) {
+        machine_push(vm, 1);
+    } else if (x < 0) {
+        machine_push(vm, -1);
+    } else {
+        machine_push(vm, 0);
+    }
+    TRACE_EXIT("sign %g -> %g\n", x, vm->memory[vm->stack_ptr-1]);
+}
+
+// Convert a number to an integer with `bits` bits. The fraction is discarded,
+// and the integer wraps around like a C integer of that width.
+double machine_wrap_integer(double x, int bits, bool is_signed) {
+    double range = (double)(1LL << bits), n = 0;
+    // Infinite numbers and NaN have no integer value, so they become zero.
+    // This is written without `fmod`, so that the math library isn't needed.
+    if (x - x == 0) {
+        // Find how many times the range fits in the number. Numbers this
+        // large have no fraction, and could overflow a `long long`.
+        double wraps = x / range;
+        if (wraps > -4503599627370496.0 && wraps < 4503599627370496.0)
+            wraps = (double)(long long)wraps;
+        n = (double)(long long)(x - wraps * range);
+        if (n < 0) n += range;
+        if (is_signed && n >= range / 2) n -= range;
+    }
+    return n;
+}
+
+// Pop a number, and push it as an integer with `bits` bits. The fraction is
+// discarded, and the integer wraps around like a C integer of that width.
+void machine_truncate(machine *vm, int bits, bool is_signed) {
+    TRACE_ENTER();
+    double x = machine_pop(vm), n = machine_wrap_integer(x, bits, is_signed);
+    machine_push(vm, n);
+    TRACE_EXIT("truncate %g to %d bits -> %g\n", x, bits, n);
+}
+
+// Pop a number as a 32 bit signed integer for the bitwise operations
+int machine_pop_int32(machine *vm) {
+    return (int)machine_wrap_integer(machine_pop(vm), 32, true);
+}
+
+// Bitwise and the second topmost number on the stack with the topmost number on the stack
+void machine_bitand(machine *vm) {
+    TRACE_ENTER();
+    int b = machine_pop_int32(vm);
+    int a = machine_pop_int32(vm);
+    machine_push(vm, a & b);
+    TRACE_EXIT("bitand %d %d -> %d\n", a, b, a & b);
+}
+
+// Bitwise or the second topmost number on the stack with the topmost number on the stack
+void machine_bitor(machine *vm) {
+    TRACE_ENTER();
+    int b = machine_pop_int32(vm);
+    int a = machine_pop_int32(vm);
+    machine_push(vm, a | b);
+    TRACE_EXIT("bitor %d %d -> %d\n", a, b, a | b);
+}
+
+// Bitwise exclusive or the second topmost number on the stack with the topmost number on the stack
+void machine_bitxor(machine *vm) {
+    TRACE_ENTER();
+    int b = machine_pop_int32(vm);
+    int a = machine_pop_int32(vm);
+    machine_push(vm, a ^ b);
+    TRACE_EXIT("bitxor %d %d -> %d\n", a, b, a ^ b);
+}
+
+// Shift the second topmost number on the stack left by the topmost number on the stack.
+// The shift is done on an unsigned integer, because shifting into the sign is undefined.
+void machine_shl(machine *vm) {
+    TRACE_ENTER();
+    int b = machine_pop_int32(vm);
+    int a = machine_pop_int32(vm);
+    int n = (int)((unsigned)a << (b & 31));
+    machine_push(vm, n);
+    TRACE_EXIT("shl %d %d -> %d\n", a, b, n);
+}
+
+// Shift the second topmost number on the stack right by the topmost number on the stack,
+// keeping its sign
+void machine_shr(machine *vm) {
+    TRACE_ENTER();
+    int b = machine_pop_int32(vm);
+    int a = machine_pop_int32(vm);
+    machine_push(vm, a >> (b & 31));
+    TRACE_EXIT("shr %d %d -> %d\n", a, b, a >> (b & 31));
+}
+
+
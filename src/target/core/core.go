@@ -0,0 +1,681 @@
+package main
+
+import (
+	"bufio"
+	"fmt"
+	"math"
+	"math/rand"
+	"os"
+	"time"
+)
+
+// Infinite and NaN numbers are pushed with the math package,
+// which must be used for the program to compile without them.
+var _ = math.Inf
+
+var READER = bufio.NewReader(os.Stdin)
+var WRITER = bufio.NewWriter(os.Stdout)
+
+// When a program is compiled with `--trace`, the compiler defines
+// `TRACE` as true, and each operation logs its effect to stderr.
+// Operations are built out of other operations, like `add` pushing
+// its result, so only the outermost operation is logged.
+var trace_depth = 0
+
+func trace_enter() {
+	if TRACE {
+		trace_depth += 1
+	}
+}
+
+// Is the operation that just finished the outermost one?
+func trace_exit() bool {
+	if TRACE {
+		trace_depth -= 1
+		return trace_depth == 0
+	}
+	return false
+}
+
+const STACK_HEAP_COLLISION = 1
+const NO_FREE_MEMORY = 2
+const STACK_UNDERFLOW = 3
+const INVALID_FREE = 4
+const UNBALANCED_STACK = 5
+const UNTERMINATED_STRING = 6
+const INVALID_ACCESS = 7
+
+func panic(code int) {
+	fmt.Fprint(WRITER, "panic: ")
+	switch code {
+	case 1:
+		fmt.Fprintln(WRITER, "stack and heap collision during push")
+		break
+	case 2:
+		fmt.Fprintln(WRITER, "no free memory left")
+		break
+	case 3:
+		fmt.Fprintln(WRITER, "stack underflow")
+		break
+	case 4:
+		fmt.Fprintln(WRITER, "freed memory that is not allocated")
+		break
+	case 5:
+		fmt.Fprintln(WRITER, "stack is not balanced at the end of the program")
+		break
+	case 6:
+		fmt.Fprintln(WRITER, "string is not terminated before the end of memory")
+		break
+	case 7:
+		fmt.Fprintln(WRITER, "accessed memory that is not allocated")
+		break
+	default:
+		fmt.Fprintln(WRITER, "unknown error code")
+	}
+	WRITER.Flush()
+	os.Exit(code)
+}
+
+type machine struct {
+	memory    []float64
+	allocated []bool
+	capacity  int
+	base_ptr  int
+	stack_ptr int
+	// The number of cells allocated on the heap, and the most
+	// that have been allocated at once during the program.
+	heap_used int
+	heap_peak int
+	// The host's state, which is passed to the foreign
+	// functions marked with `#[context]`. It starts as nil.
+	context interface{}
+	// The machine's random numbers, which start with the seed 1
+	rng *rand.Rand
+	// The time the machine was created, which the clock starts at
+	start time.Time
+}
+
+func machine_new(global_scope_size, capacity int) *machine {
+	memory := []float64{}
+	allocated := []bool{}
+	for i := 0; i < capacity; i++ {
+		memory = append(memory, 0)
+		allocated = append(allocated, false)
+	}
+	result := &machine{memory, allocated, capacity, 0, 0, 0, 0, nil, rand.New(rand.NewSource(1)), time.Now()}
+	for i := 0; i < global_scope_size; i++ {
+		result.push(0)
+	}
+	return result
+}
+
+// When a program is compiled with `--heap-stats`, the compiler defines
+// `HEAP_STATS` as true, and the most cells that were allocated on the
+// heap at once is reported to stderr at the end of the program.
+func (vm *machine) drop() {
+	vm.flush()
+	if HEAP_STATS {
+		fmt.Fprintf(os.Stderr, "heap high-water mark: %d cells\n", vm.heap_peak)
+	}
+	// fmt.Print("stack: [ ")
+	// for i:=0; i<vm.stack_ptr; i+=1 {
+	// 	fmt.Printf("%g ", vm.memory[i])
+	// }
+	// for i:=vm.stack_ptr; i<vm.capacity; i+=1 {
+	//     fmt.Print("  ")
+	// }
+	// fmt.Println("]")
+	// fmt.Print("heap:  [ ")
+	// for i:=0; i<vm.stack_ptr; i+=1 {
+	// 	fmt.Print("  ")
+	// }
+	// for i:=vm.stack_ptr; i<vm.capacity; i+=1 {
+	// 	fmt.Printf("%g ", vm.memory[i])
+	// }
+	// fmt.Println("]")
+	// fmt.Print("alloc: [ ")
+	// for i:=0; i<vm.capacity; i+=1 {
+	// 	if vm.allocated[i] {
+	// 		fmt.Printf("1 ")
+	// 	} else {
+	// 		fmt.Printf("0 ")
+	// 	}
+	// }
+	// fmt.Println("]")
+	// total := 0;
+	// for i:=0; i<vm.capacity; i+=1 {
+	//     if vm.allocated[i] {
+	// 		total += 1
+	// 	}
+	// }
+	// fmt.Println("STACK SIZE    %d\n", vm.stack_ptr);
+	// fmt.Println("TOTAL ALLOC'D %d\n", total);
+}
+
+// Write all of the buffered output to stdout
+func (vm *machine) flush() {
+	WRITER.Flush()
+}
+
+func (vm *machine) load_base_ptr() {
+	// Get the virtual machine's current base pointer value,
+	// and push it onto the stack.
+	vm.push(float64(vm.base_ptr))
+}
+
+func (vm *machine) establish_stack_frame(arg_size, local_scope_size int) {
+	trace_enter()
+	// Allocate some space to store the arguments' cells for later
+	args := make([]float64, arg_size)
+	// Pop the arguments' values off of the stack
+	for i := arg_size - 1; i >= 0; i -= 1 {
+		args[i] = vm.pop()
+	}
+
+	// Push the current base pointer onto the stack so that
+	// when this function returns, it will be able to resume
+	// the current stack frame
+	vm.load_base_ptr()
+
+	// Set the base pointer to the current stack pointer to
+	// begin the stack frame at the current position on the stack.
+	vm.base_ptr = vm.stack_ptr
+
+	// Allocate space for all the variables used in the local scope on the stack
+	for i := 0; i < local_scope_size; i += 1 {
+		vm.push(0.0)
+	}
+
+	// Push the arguments back onto the stack for use by the current function
+	for i := 0; i < arg_size; i += 1 {
+		vm.push(args[i])
+	}
+	if trace_exit() {
+		fmt.Fprintf(os.Stderr, "call with %d argument cells and %d local cells\n", arg_size, local_scope_size)
+	}
+}
+
+func (vm *machine) end_stack_frame(return_size, local_scope_size int) {
+	trace_enter()
+	// Allocate some space to store the returned cells for later
+	return_val := make([]float64, return_size)
+	// Pop the returned values off of the stack
+	for i := return_size - 1; i >= 0; i -= 1 {
+		return_val[i] = vm.pop()
+	}
+
+	// Discard the memory setup by the stack frame
+	for i := 0; i < local_scope_size; i += 1 {
+		vm.pop()
+	}
+
+	// Retrieve the parent function's base pointer to resume the function
+	vm.base_ptr = int(vm.pop())
+
+	// Finally, push the returned value back onto the stack for use by
+	// the parent function.
+	for i := 0; i < return_size; i += 1 {
+		vm.push(return_val[i])
+	}
+	if trace_exit() {
+		fmt.Fprintf(os.Stderr, "return %d cells\n", return_size)
+	}
+}
+
+func (vm *machine) push(n float64) {
+	trace_enter()
+	if vm.allocated[vm.stack_ptr] {
+		panic(STACK_HEAP_COLLISION)
+	}
+	vm.memory[vm.stack_ptr] = n
+	vm.stack_ptr += 1
+	if trace_exit() {
+		fmt.Fprintf(os.Stderr, "push %g\n", n)
+	}
+}
+
+func (vm *machine) pop() float64 {
+	if vm.stack_ptr == 0 {
+		panic(STACK_UNDERFLOW)
+	}
+	vm.stack_ptr -= 1
+	result := vm.memory[vm.stack_ptr]
+	vm.memory[vm.stack_ptr] = 0
+	return result
+}
+
+func (vm *machine) allocate() int {
+	trace_enter()
+	size := int(vm.pop())
+	addr := 0
+	consecutive_free_cells := 0
+
+	for i := vm.capacity - 1; i > vm.stack_ptr; i -= 1 {
+		if !vm.allocated[i] {
+			consecutive_free_cells += 1
+		} else {
+			consecutive_free_cells = 0
+		}
+
+		if consecutive_free_cells == size {
+			addr = i
+			break
+		}
+	}
+
+	// If the heap is full, push null, so that the
+	// program can check whether the allocation failed
+	if addr <= vm.stack_ptr {
+		vm.push(0)
+		if trace_exit() {
+			fmt.Fprintf(os.Stderr, "allocate %d cells failed\n", size)
+		}
+		return 0
+	}
+
+	for i := 0; i < size; i += 1 {
+		vm.allocated[addr+i] = true
+	}
+	vm.heap_used += size
+	if vm.heap_used > vm.heap_peak {
+		vm.heap_peak = vm.heap_used
+	}
+
+	vm.push(float64(addr))
+	if trace_exit() {
+		fmt.Fprintf(os.Stderr, "allocate %d cells at %d\n", size, addr)
+	}
+	return addr
+}
+
+func (vm *machine) free() {
+	trace_enter()
+	addr := int(vm.pop())
+	size := int(vm.pop())
+	// Freeing null, from an allocation that failed, does nothing
+	if addr == 0 {
+		size = 0
+	}
+
+	for i := 0; i < size; i += 1 {
+		if vm.allocated[addr+i] {
+			vm.heap_used -= 1
+		}
+		vm.allocated[addr+i] = false
+		vm.memory[addr+i] = 0
+	}
+	if trace_exit() {
+		fmt.Fprintf(os.Stderr, "free %d cells at %d\n", size, addr)
+	}
+}
+
+func (vm *machine) checked_free() {
+	trace_enter()
+	addr := int(vm.pop())
+	size := int(vm.pop())
+	// Freeing null, from an allocation that failed, does nothing
+	if addr == 0 {
+		size = 0
+	}
+
+	if addr < 0 || addr+size > vm.capacity {
+		panic(INVALID_FREE)
+	}
+	for i := 0; i < size; i += 1 {
+		if !vm.allocated[addr+i] {
+			panic(INVALID_FREE)
+		}
+	}
+
+	for i := 0; i < size; i += 1 {
+		vm.allocated[addr+i] = false
+		vm.memory[addr+i] = 0
+	}
+	vm.heap_used -= size
+	if trace_exit() {
+		fmt.Fprintf(os.Stderr, "free %d cells at %d\n", size, addr)
+	}
+}
+
+// Check that the stack holds exactly `size` cells: the global scope, and
+// the value returned by the entry point. Used by the debug runtime.
+func (vm *machine) check_stack(size int) {
+	if vm.stack_ptr != size {
+		panic(UNBALANCED_STACK)
+	}
+}
+
+func (vm *machine) load(size int) {
+	trace_enter()
+	addr := int(vm.pop())
+	for i := 0; i < size; i += 1 {
+		vm.push(vm.memory[addr+i])
+	}
+	if trace_exit() {
+		fmt.Fprintf(os.Stderr, "load %d cells from %d\n", size, addr)
+	}
+}
+
+func (vm *machine) store(size int) {
+	trace_enter()
+	addr := int(vm.pop())
+	for i := size - 1; i >= 0; i -= 1 {
+		vm.memory[addr+i] = vm.pop()
+	}
+	if trace_exit() {
+		fmt.Fprintf(os.Stderr, "store %d cells at %d\n", size, addr)
+	}
+}
+
+// Panic unless `size` cells starting at `addr` can be accessed. They must be in memory,
+// and each cell at or above `stack_top` must be allocated on the heap, because the cells
+// between the stack and the heap are free. Used by the debug runtime.
+func (vm *machine) check_access(addr, size, stack_top int) {
+	if addr < 0 || addr+size > vm.capacity {
+		panic(INVALID_ACCESS)
+	}
+	for i := 0; i < size; i += 1 {
+		if addr+i >= stack_top && !vm.allocated[addr+i] {
+			panic(INVALID_ACCESS)
+		}
+	}
+}
+
+func (vm *machine) checked_load(size int) {
+	trace_enter()
+	addr := int(vm.pop())
+	vm.check_access(addr, size, vm.stack_ptr)
+	for i := 0; i < size; i += 1 {
+		vm.push(vm.memory[addr+i])
+	}
+	if trace_exit() {
+		fmt.Fprintf(os.Stderr, "load %d cells from %d\n", size, addr)
+	}
+}
+
+func (vm *machine) checked_store(size int) {
+	trace_enter()
+	addr := int(vm.pop())
+	// The value being stored is popped off of the stack first
+	vm.check_access(addr, size, vm.stack_ptr-size)
+	for i := size - 1; i >= 0; i -= 1 {
+		vm.memory[addr+i] = vm.pop()
+	}
+	if trace_exit() {
+		fmt.Fprintf(os.Stderr, "store %d cells at %d\n", size, addr)
+	}
+}
+
+func (vm *machine) memset(size int) {
+	count := int(vm.pop())
+	value := make([]float64, size)
+	for j := size - 1; j >= 0; j -= 1 {
+		value[j] = vm.pop()
+	}
+	addr := int(vm.pop())
+	for i := 0; i < count; i += 1 {
+		for j := 0; j < size; j += 1 {
+			vm.memory[addr+i*size+j] = value[j]
+		}
+	}
+}
+
+func (vm *machine) store_data(address int, data []float64) {
+	copy(vm.memory[address:], data)
+}
+
+// Each cell holds the bytes of a double, in little-endian order like
+// the C backend on most machines, so the memory starting at an address
+// can be used as a byte array. Pop an index and an address, and push
+// the byte at that index of the memory.
+func (vm *machine) load_byte() {
+	trace_enter()
+	index := int(vm.pop())
+	addr := int(vm.pop())
+	bits := math.Float64bits(vm.memory[addr+index/8])
+	b := (bits >> (8 * uint(index%8))) & 0xff
+	vm.push(float64(b))
+	if trace_exit() {
+		fmt.Fprintf(os.Stderr, "load byte %d of %d -> %d\n", index, addr, b)
+	}
+}
+
+// Pop a byte, an index, and an address, and store
+// the byte at that index of the memory at the address.
+func (vm *machine) store_byte() {
+	trace_enter()
+	b := uint64(uint8(int(vm.pop())))
+	index := int(vm.pop())
+	addr := int(vm.pop())
+	shift := 8 * uint(index%8)
+	bits := math.Float64bits(vm.memory[addr+index/8])
+	bits = (bits &^ (0xff << shift)) | (b << shift)
+	vm.memory[addr+index/8] = math.Float64frombits(bits)
+	if trace_exit() {
+		fmt.Fprintf(os.Stderr, "store byte %d at byte %d of %d\n", b, index, addr)
+	}
+}
+
+// Panic unless the byte at `index` of the memory at `addr` can be accessed.
+// The index may be past the first cell, but neither it nor the address can be
+// negative, and the cell holding the byte must be accessible. Used by the debug runtime.
+func (vm *machine) check_byte_access(addr, index int) {
+	if addr < 0 || index < 0 {
+		panic(INVALID_ACCESS)
+	}
+	vm.check_access(addr+index/8, 1, vm.stack_ptr)
+}
+
+func (vm *machine) checked_load_byte() {
+	trace_enter()
+	index := int(vm.pop())
+	addr := int(vm.pop())
+	vm.check_byte_access(addr, index)
+	bits := math.Float64bits(vm.memory[addr+index/8])
+	b := (bits >> (8 * uint(index%8))) & 0xff
+	vm.push(float64(b))
+	if trace_exit() {
+		fmt.Fprintf(os.Stderr, "load byte %d of %d -> %d\n", index, addr, b)
+	}
+}
+
+func (vm *machine) checked_store_byte() {
+	trace_enter()
+	b := uint64(uint8(int(vm.pop())))
+	index := int(vm.pop())
+	addr := int(vm.pop())
+	vm.check_byte_access(addr, index)
+	shift := 8 * uint(index%8)
+	bits := math.Float64bits(vm.memory[addr+index/8])
+	bits = (bits &^ (0xff << shift)) | (b << shift)
+	vm.memory[addr+index/8] = math.Float64frombits(bits)
+	if trace_exit() {
+		fmt.Fprintf(os.Stderr, "store byte %d at byte %d of %d\n", b, index, addr)
+	}
+}
+
+// Push a random number in [0, 1). The numbers are the same
+// in every run of the program, unless it's seeded differently.
+func (vm *machine) random() {
+	trace_enter()
+	n := vm.rng.Float64()
+	vm.push(n)
+	if trace_exit() {
+		fmt.Fprintf(os.Stderr, "random -> %g\n", n)
+	}
+}
+
+// Pop a seed, and start a new sequence of random numbers with it
+func (vm *machine) seed_random() {
+	trace_enter()
+	seed := vm.pop()
+	vm.rng.Seed(int64(seed))
+	if trace_exit() {
+		fmt.Fprintf(os.Stderr, "seed random with %g\n", seed)
+	}
+}
+
+// Push the number of seconds since the program started
+func (vm *machine) clock() {
+	trace_enter()
+	seconds := time.Since(vm.start).Seconds()
+	vm.push(seconds)
+	if trace_exit() {
+		fmt.Fprintf(os.Stderr, "clock -> %g\n", seconds)
+	}
+}
+
+func (vm *machine) add() {
+	trace_enter()
+	b := vm.pop()
+	a := vm.pop()
+	vm.push(a + b)
+	if trace_exit() {
+		fmt.Fprintf(os.Stderr, "add %g %g -> %g\n", a, b, a+b)
+	}
+}
+
+func (vm *machine) subtract() {
+	trace_enter()
+	b := vm.pop()
+	a := vm.pop()
+	vm.push(a - b)
+	if trace_exit() {
+		fmt.Fprintf(os.Stderr, "subtract %g %g -> %g\n", a, b, a-b)
+	}
+}
+
+func (vm *machine) multiply() {
+	trace_enter()
+	b := vm.pop()
+	a := vm.pop()
+	vm.push(a * b)
+	if trace_exit() {
+		fmt.Fprintf(os.Stderr, "multiply %g %g -> %g\n", a, b, a*b)
+	}
+}
+
+func (vm *machine) divide() {
+	trace_enter()
+	b := vm.pop()
+	a := vm.pop()
+	vm.push(a / b)
+	if trace_exit() {
+		fmt.Fprintf(os.Stderr, "divide %g %g -> %g\n", a, b, a/b)
+	}
+}
+
+func (vm *machine) modulo() {
+	trace_enter()
+	b := vm.pop()
+	a := vm.pop()
+	vm.push(math.Mod(a, b))
+	if trace_exit() {
+		fmt.Fprintf(os.Stderr, "modulo %g %g -> %g\n", a, b, math.Mod(a, b))
+	}
+}
+
+func (vm *machine) sign() {
+	trace_enter()
+	x := vm.pop()
+	if x > 0 {
+		vm.push(1.0)
+	} else if x < 0 {
+		vm.push(-1.0)
+	} else {
+		vm.push(0.0)
+	}
+	if trace_exit() {
+		fmt.Fprintf(os.Stderr, "sign %g -> %g\n", x, vm.memory[vm.stack_ptr-1])
+	}
+}
+
+// Convert a number to an integer with `bits` bits. The fraction is
+// discarded, and the integer wraps around like a C integer of that width.
+func wrap_integer(x float64, bits int, is_signed bool) float64 {
+	size := math.Ldexp(1, bits)
+	// Infinite numbers and NaN have no integer value, so they become zero
+	n := 0.0
+	if !math.IsNaN(x) && !math.IsInf(x, 0) {
+		n = math.Mod(math.Trunc(x), size)
+		if n < 0 {
+			n += size
+		}
+		if is_signed && n >= size/2 {
+			n -= size
+		}
+	}
+	return n
+}
+
+// Pop a number, and push it as an integer with `bits` bits. The fraction is
+// discarded, and the integer wraps around like a C integer of that width.
+func (vm *machine) truncate(bits int, is_signed bool) {
+	trace_enter()
+	x := vm.pop()
+	n := wrap_integer(x, bits, is_signed)
+	vm.push(n)
+	if trace_exit() {
+		fmt.Fprintf(os.Stderr, "truncate %g to %d bits -> %g\n", x, bits, n)
+	}
+}
+
+// Pop a number as a 32 bit signed integer for the bitwise operations
+func (vm *machine) pop_int32() int32 {
+	return int32(wrap_integer(vm.pop(), 32, true))
+}
+
+func (vm *machine) bitand() {
+	trace_enter()
+	b := vm.pop_int32()
+	a := vm.pop_int32()
+	n := a & b
+	vm.push(float64(n))
+	if trace_exit() {
+		fmt.Fprintf(os.Stderr, "bitand %d %d -> %d\n", a, b, n)
+	}
+}
+
+func (vm *machine) bitor() {
+	trace_enter()
+	b := vm.pop_int32()
+	a := vm.pop_int32()
+	n := a | b
+	vm.push(float64(n))
+	if trace_exit() {
+		fmt.Fprintf(os.Stderr, "bitor %d %d -> %d\n", a, b, n)
+	}
+}
+
+func (vm *machine) bitxor() {
+	trace_enter()
+	b := vm.pop_int32()
+	a := vm.pop_int32()
+	n := a ^ b
+	vm.push(float64(n))
+	if trace_exit() {
+		fmt.Fprintf(os.Stderr, "bitxor %d %d -> %d\n", a, b, n)
+	}
+}
+
+func (vm *machine) shl() {
+	trace_enter()
+	b := vm.pop_int32()
+	a := vm.pop_int32()
+	n := a << uint(b&31)
+	vm.push(float64(n))
+	if trace_exit() {
+		fmt.Fprintf(os.Stderr, "shl %d %d -> %d\n", a, b, n)
+	}
+}
+
+func (vm *machine) shr() {
+	trace_enter()
+	b := vm.pop_int32()
+	a := vm.pop_int32()
+	n := a >> uint(b&31)
+	vm.push(float64(n))
+	if trace_exit() {
+		fmt.Fprintf(os.Stderr, "shr %d %d -> %d\n", a, b, n)
+	}
+}
@@ -0,0 +1,499 @@
+STACK_HEAP_COLLISION = 1
+NO_FREE_MEMORY = 2
+STACK_UNDERFLOW = 3
+INVALID_FREE = 4
+UNBALANCED_STACK = 5
+UNTERMINATED_STRING = 6
+INVALID_ACCESS = 7
+
+# Output is written to this buffer, and only
+# written to stdout when the machine is flushed.
+$output = String.new(encoding: Encoding::BINARY)
+
+def write_byte(byte)
+  flush_output if $output.bytesize >= 4096
+  $output << (byte & 0xff)
+end
+
+def write_bytes(bytes)
+  bytes.each_byte { |byte| write_byte(byte) }
+end
+
+def flush_output
+  $stdout.write($output)
+  $stdout.flush
+  $output.clear
+end
+
+# When a program is compiled with `--trace`, the compiler defines
+# `TRACE` as true, and each operation logs its effect to stderr.
+# Operations are built out of other operations, like `add` pushing
+# its result, so only the outermost operation is logged.
+$trace_depth = 0
+
+def trace_enter
+  $trace_depth += 1 if TRACE
+end
+
+# Is the operation that just finished the outermost one?
+def trace_exit
+  return false unless TRACE
+  $trace_depth -= 1
+  $trace_depth == 0
+end
+
+# Fatal error handler. Always exits the program.
+def panic(code)
+  write_bytes("panic: ")
+  write_bytes(case code
+    when 1 then "stack and heap collision during push\n"
+    when 2 then "no free memory left\n"
+    when 3 then "stack underflow\n"
+    when 4 then "freed memory that is not allocated\n"
+    when 5 then "stack is not balanced at the end of the program\n"
+    when 6 then "string is not terminated before the end of memory\n"
+    when 7 then "accessed memory that is not allocated\n"
+    else "unknown error code\n"
+  end)
+  flush_output
+  exit(code)
+end
+
+# Convert a cell to an address or a size. Cells that
+# aren't whole, positive numbers are never valid addresses.
+def to_index(n)
+  panic(INVALID_ACCESS) if !(n >= 0) || n >= 2**32
+  n.to_i
+end
+
+# Numbers are written like C's `%g`
+def format_number(n)
+  format("%g", n)
+end
+
+# Convert a number to an integer with `bits` bits. The fraction is
+# discarded, and the integer wraps around like a C integer of that width.
+def wrap_integer(x, bits, is_signed)
+  size = 2**bits
+  # Infinite numbers and NaN have no integer value, so they become zero
+  return 0 unless x.finite?
+  n = x.truncate % size
+  n -= size if is_signed && n >= size / 2
+  n
+end
+
+class Machine
+  attr_accessor :memory, :allocated, :capacity, :base_ptr, :stack_ptr
+  # The host's state, which is passed to the foreign
+  # functions marked with `#[context]`. It starts as nil.
+  attr_accessor :context
+
+  def initialize(global_scope_size, capacity)
+    @memory = Array.new(capacity, 0.0)
+    @allocated = Array.new(capacity, false)
+    @capacity = capacity
+    @base_ptr = 0
+    @stack_ptr = 0
+    # The number of cells allocated on the heap, and the most
+    # that have been allocated at once during the program.
+    @heap_used = 0
+    @heap_peak = 0
+    @context = nil
+    # The machine's random numbers, which start with the seed 1
+    @random = Random.new(1)
+    # The time the machine was created, which the clock starts at
+    @start = Process.clock_gettime(Process::CLOCK_MONOTONIC)
+    global_scope_size.times { push(0) }
+  end
+
+  # When a program is compiled with `--heap-stats`, the compiler defines
+  # `HEAP_STATS` as true, and the most cells that were allocated on the
+  # heap at once is reported to stderr at the end of the program.
+  def drop
+    flush
+    $stderr.puts "heap high-water mark: #{@heap_peak} cells" if HEAP_STATS
+  end
+
+  # Write all of the buffered output to stdout
+  def flush
+    flush_output
+  end
+
+  def load_base_ptr
+    # Get the virtual machine's current base pointer value,
+    # and push it onto the stack.
+    push(@base_ptr)
+  end
+
+  def establish_stack_frame(arg_size, local_scope_size)
+    trace_enter
+    # Pop the arguments' values off of the stack
+    args = Array.new(arg_size)
+    (arg_size - 1).downto(0) { |i| args[i] = pop }
+
+    # Push the current base pointer onto the stack so that
+    # when this function returns, it will be able to resume
+    # the current stack frame
+    load_base_ptr
+
+    # Set the base pointer to the current stack pointer to
+    # begin the stack frame at the current position on the stack.
+    @base_ptr = @stack_ptr
+
+    # Allocate space for all the variables used in the local scope on the stack
+    local_scope_size.times { push(0) }
+
+    # Push the arguments back onto the stack for use by the current function
+    args.each { |arg| push(arg) }
+    if trace_exit
+      $stderr.puts "call with #{arg_size} argument cells and #{local_scope_size} local cells"
+    end
+  end
+
+  def end_stack_frame(return_size, local_scope_size)
+    trace_enter
+    # Pop the returned values off of the stack
+    return_val = Array.new(return_size)
+    (return_size - 1).downto(0) { |i| return_val[i] = pop }
+
+    # Discard the memory setup by the stack frame
+    local_scope_size.times { pop }
+
+    # Retrieve the parent function's base pointer to resume the function
+    @base_ptr = to_index(pop)
+
+    # Finally, push the returned value back onto the stack for use by
+    # the parent function.
+    return_val.each { |cell| push(cell) }
+    $stderr.puts "return #{return_size} cells" if trace_exit
+  end
+
+  def push(n)
+    trace_enter
+    panic(STACK_HEAP_COLLISION) if @stack_ptr >= @capacity || @allocated[@stack_ptr]
+    # Every cell is a float, even if it's pushed as an integer
+    @memory[@stack_ptr] = n.to_f
+    @stack_ptr += 1
+    $stderr.puts "push #{format_number(n)}" if trace_exit
+  end
+
+  def pop
+    panic(STACK_UNDERFLOW) if @stack_ptr == 0
+    @stack_ptr -= 1
+    result = @memory[@stack_ptr]
+    @memory[@stack_ptr] = 0.0
+    result
+  end
+
+  def alloc
+    trace_enter
+    size = to_index(pop)
+    addr = 0
+    consecutive_free_cells = 0
+
+    i = @capacity
+    while i > @stack_ptr + 1
+      i -= 1
+      if @allocated[i]
+        consecutive_free_cells = 0
+      else
+        consecutive_free_cells += 1
+      end
+
+      if consecutive_free_cells == size
+        addr = i
+        break
+      end
+    end
+
+    # If the heap is full, push null, so that the
+    # program can check whether the allocation failed
+    if addr <= @stack_ptr
+      push(0)
+      $stderr.puts "allocate #{size} cells failed" if trace_exit
+      return
+    end
+
+    @allocated.fill(true, addr, size)
+    @heap_used += size
+    @heap_peak = @heap_used if @heap_used > @heap_peak
+
+    push(addr)
+    $stderr.puts "allocate #{size} cells at #{addr}" if trace_exit
+  end
+
+  def free
+    trace_enter
+    addr = to_index(pop)
+    size = to_index(pop)
+    # Freeing null, from an allocation that failed, does nothing
+    size = 0 if addr == 0
+
+    size.times do |i|
+      @heap_used -= 1 if @allocated[addr + i]
+      @allocated[addr + i] = false
+      @memory[addr + i] = 0.0
+    end
+    $stderr.puts "free #{size} cells at #{addr}" if trace_exit
+  end
+
+  def checked_free
+    trace_enter
+    addr = to_index(pop)
+    size = to_index(pop)
+    # Freeing null, from an allocation that failed, does nothing
+    size = 0 if addr == 0
+
+    panic(INVALID_FREE) if addr + size > @capacity
+    size.times { |i| panic(INVALID_FREE) unless @allocated[addr + i] }
+
+    @allocated.fill(false, addr, size)
+    @memory.fill(0.0, addr, size)
+    @heap_used -= size
+    $stderr.puts "free #{size} cells at #{addr}" if trace_exit
+  end
+
+  # Check that the stack holds exactly `size` cells: the global scope, and
+  # the value returned by the entry point. Used by the debug runtime.
+  def check_stack(size)
+    panic(UNBALANCED_STACK) if @stack_ptr != size
+  end
+
+  def load(size)
+    trace_enter
+    addr = to_index(pop)
+    size.times { |i| push(@memory[addr + i]) }
+    $stderr.puts "load #{size} cells from #{addr}" if trace_exit
+  end
+
+  def store(size)
+    trace_enter
+    addr = to_index(pop)
+    (size - 1).downto(0) { |i| @memory[addr + i] = pop }
+    $stderr.puts "store #{size} cells at #{addr}" if trace_exit
+  end
+
+  # Panic unless `size` cells starting at `addr` can be accessed. They must be in memory,
+  # and each cell at or above `stack_top` must be allocated on the heap, because the cells
+  # between the stack and the heap are free. Used by the debug runtime.
+  def check_access(addr, size, stack_top)
+    panic(INVALID_ACCESS) if addr + size > @capacity
+    size.times do |i|
+      panic(INVALID_ACCESS) if addr + i >= stack_top && !@allocated[addr + i]
+    end
+  end
+
+  def checked_load(size)
+    trace_enter
+    addr = to_index(pop)
+    check_access(addr, size, @stack_ptr)
+    size.times { |i| push(@memory[addr + i]) }
+    $stderr.puts "load #{size} cells from #{addr}" if trace_exit
+  end
+
+  def checked_store(size)
+    trace_enter
+    addr = to_index(pop)
+    # The value being stored is popped off of the stack first
+    panic(STACK_UNDERFLOW) if @stack_ptr < size
+    check_access(addr, size, @stack_ptr - size)
+    (size - 1).downto(0) { |i| @memory[addr + i] = pop }
+    $stderr.puts "store #{size} cells at #{addr}" if trace_exit
+  end
+
+  def memset(size)
+    count = to_index(pop)
+    value = Array.new(size)
+    (size - 1).downto(0) { |j| value[j] = pop }
+    addr = to_index(pop)
+    count.times { |i| @memory[addr + i * size, size] = value }
+  end
+
+  # Each cell holds the bytes of a double, in little-endian order like
+  # the C backend on most machines, so the memory starting at an address
+  # can be used as a byte array. Pop an index and an address, and push
+  # the byte at that index of the memory.
+  def load_byte
+    trace_enter
+    index = to_index(pop)
+    addr = to_index(pop)
+    byte = [@memory[addr + index / 8]].pack("E").bytes[index % 8]
+    push(byte)
+    $stderr.puts "load byte #{index} of #{addr} -> #{byte}" if trace_exit
+  end
+
+  # Pop a byte, an index, and an address, and store
+  # the byte at that index of the memory at the address.
+  def store_byte
+    trace_enter
+    byte = pop.to_i & 0xff
+    index = to_index(pop)
+    addr = to_index(pop)
+    bytes = [@memory[addr + index / 8]].pack("E").bytes
+    bytes[index % 8] = byte
+    @memory[addr + index / 8] = bytes.pack("C*").unpack("E")[0]
+    $stderr.puts "store byte #{byte} at byte #{index} of #{addr}" if trace_exit
+  end
+
+  # Load a byte like `load_byte`, but first check that the cell holding
+  # the byte can be accessed. Used by the debug runtime.
+  def checked_load_byte
+    trace_enter
+    index = to_index(pop)
+    addr = to_index(pop)
+    check_access(addr + index / 8, 1, @stack_ptr)
+    byte = [@memory[addr + index / 8]].pack("E").bytes[index % 8]
+    push(byte)
+    $stderr.puts "load byte #{index} of #{addr} -> #{byte}" if trace_exit
+  end
+
+  # Store a byte like `store_byte`, but first check that the cell
+  # holding the byte can be accessed. Used by the debug runtime.
+  def checked_store_byte
+    trace_enter
+    byte = pop.to_i & 0xff
+    index = to_index(pop)
+    addr = to_index(pop)
+    check_access(addr + index / 8, 1, @stack_ptr)
+    bytes = [@memory[addr + index / 8]].pack("E").bytes
+    bytes[index % 8] = byte
+    @memory[addr + index / 8] = bytes.pack("C*").unpack("E")[0]
+    $stderr.puts "store byte #{byte} at byte #{index} of #{addr}" if trace_exit
+  end
+
+  # Push a random number in [0, 1). The numbers are the same
+  # in every run of the program, unless it's seeded differently.
+  def random
+    trace_enter
+    n = @random.rand
+    push(n)
+    $stderr.puts "random -> #{format_number(n)}" if trace_exit
+  end
+
+  # Pop a seed, and start a new sequence of random numbers with it
+  def seed_random
+    trace_enter
+    seed = pop
+    # Infinite numbers and NaN have no integer value, so they become zero
+    @random = Random.new(seed.finite? ? seed.to_i : 0)
+    $stderr.puts "seed random with #{format_number(seed)}" if trace_exit
+  end
+
+  # Push the number of seconds since the program started
+  def clock
+    trace_enter
+    seconds = Process.clock_gettime(Process::CLOCK_MONOTONIC) - @start
+    push(seconds)
+    $stderr.puts "clock -> #{format_number(seconds)}" if trace_exit
+  end
+
+  def add
+    trace_enter
+    b = pop
+    a = pop
+    push(a + b)
+    $stderr.puts "add #{format_number(a)} #{format_number(b)} -> #{format_number(a + b)}" if trace_exit
+  end
+
+  def subtract
+    trace_enter
+    b = pop
+    a = pop
+    push(a - b)
+    $stderr.puts "subtract #{format_number(a)} #{format_number(b)} -> #{format_number(a - b)}" if trace_exit
+  end
+
+  def multiply
+    trace_enter
+    b = pop
+    a = pop
+    push(a * b)
+    $stderr.puts "multiply #{format_number(a)} #{format_number(b)} -> #{format_number(a * b)}" if trace_exit
+  end
+
+  def divide
+    trace_enter
+    b = pop
+    a = pop
+    push(a / b)
+    $stderr.puts "divide #{format_number(a)} #{format_number(b)} -> #{format_number(a / b)}" if trace_exit
+  end
+
+  # Ruby's `%` takes the sign of the divisor, but `remainder` takes
+  # the sign of the dividend like the other targets
+  def modulo
+    trace_enter
+    b = pop
+    a = pop
+    push(a.remainder(b))
+    $stderr.puts "modulo #{format_number(a)} #{format_number(b)} -> #{format_number(a.remainder(b))}" if trace_exit
+  end
+
+  def sign
+    trace_enter
+    x = pop
+    n = if x > 0 then 1 elsif x < 0 then -1 else 0 end
+    push(n)
+    $stderr.puts "sign #{format_number(x)} -> #{n}" if trace_exit
+  end
+
+  # Pop a number, and push it as an integer with `bits` bits. The fraction is
+  # discarded, and the integer wraps around like a C integer of that width.
+  def truncate(bits, is_signed)
+    trace_enter
+    x = pop
+    n = wrap_integer(x, bits, is_signed)
+    push(n)
+    $stderr.puts "truncate #{format_number(x)} to #{bits} bits -> #{n}" if trace_exit
+  end
+
+  # Pop a number as a 32 bit signed integer for the bitwise operations
+  def pop_int32
+    wrap_integer(pop, 32, true)
+  end
+
+  def bitand
+    trace_enter
+    b = pop_int32
+    a = pop_int32
+    n = a & b
+    push(n)
+    $stderr.puts "bitand #{a} #{b} -> #{n}" if trace_exit
+  end
+
+  def bitor
+    trace_enter
+    b = pop_int32
+    a = pop_int32
+    n = a | b
+    push(n)
+    $stderr.puts "bitor #{a} #{b} -> #{n}" if trace_exit
+  end
+
+  def bitxor
+    trace_enter
+    b = pop_int32
+    a = pop_int32
+    n = a ^ b
+    push(n)
+    $stderr.puts "bitxor #{a} #{b} -> #{n}" if trace_exit
+  end
+
+  def shl
+    trace_enter
+    b = pop_int32
+    a = pop_int32
+    n = wrap_integer(a << (b & 31), 32, true)
+    push(n)
+    $stderr.puts "shl #{a} #{b} -> #{n}" if trace_exit
+  end
+
+  def shr
+    trace_enter
+    b = pop_int32
+    a = pop_int32
+    n = a >> (b & 31)
+    push(n)
+    $stderr.puts "shr #{a} #{b} -> #{n}" if trace_exit
+  end
+end
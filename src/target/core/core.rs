@@ -0,0 +1,728 @@
+#![allow(warnings)]
+use std::{
+    io::{stdin, stdout, BufWriter, Read, Stdout, Write},
+    process::exit,
+};
+
+const STACK_HEAP_COLLISION: i32 = 1;
+const NO_FREE_MEMORY: i32 = 2;
+const STACK_UNDERFLOW: i32 = 3;
+const INVALID_FREE: i32 = 4;
+const UNBALANCED_STACK: i32 = 5;
+const UNTERMINATED_STRING: i32 = 6;
+const INVALID_ACCESS: i32 = 7;
+
+/// Convert a number to an integer with `bits` bits. The fraction is
+/// discarded, and the integer wraps around like a C integer of that width.
+fn wrap_integer(x: f64, bits: i32, is_signed: bool) -> f64 {
+    let size = 2f64.powi(bits);
+    // Infinite numbers and NaN have no integer value, so they become zero
+    let mut n = 0.0;
+    if x.is_finite() {
+        n = x.trunc() % size;
+        if n < 0.0 {
+            n += size;
+        }
+        if is_signed && n >= size / 2.0 {
+            n -= size;
+        }
+    }
+    n
+}
+
+/// Format a number like C's `%g`: six significant digits, without
+/// trailing zeros, in scientific notation when it is very large or small.
+fn format_number(n: f64) -> String {
+    if n.is_nan() {
+        return String::from("nan");
+    } else if n.is_infinite() {
+        return String::from(if n > 0.0 { "inf" } else { "-inf" });
+    } else if n == 0.0 {
+        return String::from(if n.is_sign_negative() { "-0" } else { "0" });
+    }
+
+    fn trim_zeros(digits: &str) -> &str {
+        if digits.contains('.') {
+            digits.trim_end_matches('0').trim_end_matches('.')
+        } else {
+            digits
+        }
+    }
+
+    // Round to six significant digits to find the exponent
+    let scientific = format!("{:.5e}", n);
+    let mut parts = scientific.splitn(2, 'e');
+    let mantissa = parts.next().unwrap();
+    let exponent: i32 = parts.next().unwrap().parse().unwrap();
+    if exponent < -4 || exponent >= 6 {
+        format!(
+            "{}e{}{:02}",
+            trim_zeros(mantissa),
+            if exponent < 0 { '-' } else { '+' },
+            exponent.abs()
+        )
+    } else {
+        String::from(trim_zeros(&format!("{:.*}", (5 - exponent) as usize, n)))
+    }
+}
+
+struct Machine {
+    memory: Vec<f64>,
+    allocated: Vec<bool>,
+    capacity: usize,
+    base_ptr: usize,
+    stack_ptr: usize,
+    /// The number of cells allocated on the heap
+    heap_used: usize,
+    /// The most cells that have been allocated on the heap at once
+    heap_peak: usize,
+    /// The host's state, which is passed to the foreign functions
+    /// marked with `#[context]`. It starts as a null pointer.
+    context: *mut std::ffi::c_void,
+    /// Output is buffered until the machine is flushed
+    writer: BufWriter<Stdout>,
+    /// When a program is compiled with `--trace`, the compiler defines
+    /// `TRACE` as true, and each operation logs its effect to stderr.
+    /// Operations are built out of other operations, like `add` pushing
+    /// its result, so only the outermost operation is logged.
+    trace_depth: usize,
+    /// The state of the machine's random numbers, which starts as 1
+    random_state: u32,
+    /// The time the machine was created, which the clock starts at
+    start: std::time::Instant,
+}
+
+impl Machine {
+    fn new(global_scope_size: usize, capacity: usize) -> Self {
+        let mut result = Self {
+            memory: vec![0.0; capacity],
+            allocated: vec![false; capacity],
+            capacity,
+            base_ptr: 0,
+            stack_ptr: 0,
+            heap_used: 0,
+            heap_peak: 0,
+            context: std::ptr::null_mut(),
+            writer: BufWriter::new(stdout()),
+            trace_depth: 0,
+            random_state: 1,
+            start: std::time::Instant::now(),
+        };
+        for _ in 0..global_scope_size {
+            result.push(0.0);
+        }
+        result
+    }
+
+    /// When a program is compiled with `--heap-stats`, the compiler defines
+    /// `HEAP_STATS` as true, and the most cells that were allocated on the
+    /// heap at once is reported to stderr at the end of the program.
+    fn drop(&mut self) {
+        self.flush();
+        if HEAP_STATS {
+            eprintln!("heap high-water mark: {} cells", self.heap_peak);
+        }
+    }
+
+    fn panic(&mut self, code: i32) -> ! {
+        let message = match code {
+            STACK_HEAP_COLLISION => "stack and heap collision during push",
+            NO_FREE_MEMORY => "no free memory left",
+            STACK_UNDERFLOW => "stack underflow",
+            INVALID_FREE => "freed memory that is not allocated",
+            UNBALANCED_STACK => "stack is not balanced at the end of the program",
+            UNTERMINATED_STRING => "string is not terminated before the end of memory",
+            INVALID_ACCESS => "accessed memory that is not allocated",
+            _ => "unknown error code",
+        };
+        let _ = writeln!(self.writer, "panic: {}", message);
+        self.flush();
+        if PANIC_RETURN {
+            // Unwind to the entry point without running the panic hook,
+            // which would print a message of its own
+            std::panic::resume_unwind(Box::new(code))
+        }
+        exit(code)
+    }
+
+    fn trace_enter(&mut self) {
+        if TRACE {
+            self.trace_depth += 1;
+        }
+    }
+
+    /// Is the operation that just finished the outermost one?
+    fn trace_exit(&mut self) -> bool {
+        if TRACE {
+            self.trace_depth -= 1;
+            self.trace_depth == 0
+        } else {
+            false
+        }
+    }
+
+    /// Write all of the buffered output to stdout
+    fn flush(&mut self) {
+        let _ = self.writer.flush();
+    }
+
+    fn load_base_ptr(&mut self) {
+        // Get the virtual machine's current base pointer value,
+        // and push it onto the stack.
+        self.push(self.base_ptr as f64)
+    }
+
+    fn establish_stack_frame(&mut self, arg_size: usize, local_scope_size: usize) {
+        self.trace_enter();
+        // Pop the arguments' values off of the stack
+        let mut args = vec![0.0; arg_size];
+        for i in (0..arg_size).rev() {
+            args[i] = self.pop();
+        }
+
+        // Push the current base pointer onto the stack so that
+        // when this function returns, it will be able to resume
+        // the current stack frame
+        self.load_base_ptr();
+
+        // Set the base pointer to the current stack pointer to
+        // begin the stack frame at the current position on the stack.
+        self.base_ptr = self.stack_ptr;
+
+        // Allocate space for all the variables used in the local scope on the stack
+        for _ in 0..local_scope_size {
+            self.push(0.0);
+        }
+
+        // Push the arguments back onto the stack for use by the current function
+        for arg in args {
+            self.push(arg);
+        }
+        if self.trace_exit() {
+            eprintln!(
+                "call with {} argument cells and {} local cells",
+                arg_size, local_scope_size
+            );
+        }
+    }
+
+    fn end_stack_frame(&mut self, return_size: usize, local_scope_size: usize) {
+        self.trace_enter();
+        // Pop the returned values off of the stack
+        let mut return_val = vec![0.0; return_size];
+        for i in (0..return_size).rev() {
+            return_val[i] = self.pop();
+        }
+
+        // Discard the memory setup by the stack frame
+        for _ in 0..local_scope_size {
+            self.pop();
+        }
+
+        // Retrieve the parent function's base pointer to resume the function
+        self.base_ptr = self.pop() as usize;
+
+        // Finally, push the returned value back onto the stack for use by
+        // the parent function.
+        for value in return_val {
+            self.push(value);
+        }
+        if self.trace_exit() {
+            eprintln!("return {} cells", return_size);
+        }
+    }
+
+    /// End a stack frame of a function that returns a single cell, and return
+    /// the cell instead of pushing it back onto the stack. The caller pushes it.
+    fn end_stack_frame_returning(&mut self, local_scope_size: usize) -> f64 {
+        self.trace_enter();
+        let return_val = self.pop();
+        // Discard the memory setup by the stack frame
+        for _ in 0..local_scope_size {
+            self.pop();
+        }
+
+        // Retrieve the parent function's base pointer to resume the function
+        self.base_ptr = self.pop() as usize;
+        if self.trace_exit() {
+            eprintln!("return 1 cells");
+        }
+        return_val
+    }
+
+    fn push(&mut self, n: f64) {
+        self.trace_enter();
+        if self.stack_ptr >= self.capacity || self.allocated[self.stack_ptr] {
+            self.panic(STACK_HEAP_COLLISION);
+        }
+        self.memory[self.stack_ptr] = n;
+        self.stack_ptr += 1;
+        if self.trace_exit() {
+            eprintln!("push {}", format_number(n));
+        }
+    }
+
+    fn pop(&mut self) -> f64 {
+        if self.stack_ptr == 0 {
+            self.panic(STACK_UNDERFLOW);
+        }
+        self.stack_ptr -= 1;
+        let result = self.memory[self.stack_ptr];
+        self.memory[self.stack_ptr] = 0.0;
+        result
+    }
+
+    fn allocate(&mut self) {
+        self.trace_enter();
+        let size = self.pop() as usize;
+        let mut addr = 0;
+        let mut consecutive_free_cells = 0;
+
+        for i in (self.stack_ptr + 1..self.capacity).rev() {
+            if !self.allocated[i] {
+                consecutive_free_cells += 1;
+            } else {
+                consecutive_free_cells = 0;
+            }
+
+            if consecutive_free_cells == size {
+                addr = i;
+                break;
+            }
+        }
+
+        // If the heap is full, push null, so that the
+        // program can check whether the allocation failed
+        if addr <= self.stack_ptr {
+            self.push(0.0);
+            if self.trace_exit() {
+                eprintln!("allocate {} cells failed", size);
+            }
+            return;
+        }
+
+        for i in 0..size {
+            self.allocated[addr + i] = true;
+        }
+        self.heap_used += size;
+        self.heap_peak = self.heap_peak.max(self.heap_used);
+
+        self.push(addr as f64);
+        if self.trace_exit() {
+            eprintln!("allocate {} cells at {}", size, addr);
+        }
+    }
+
+    fn free(&mut self) {
+        self.trace_enter();
+        let addr = self.pop() as usize;
+        let mut size = self.pop() as usize;
+        // Freeing null, from an allocation that failed, does nothing
+        if addr == 0 {
+            size = 0;
+        }
+
+        for i in 0..size {
+            if self.allocated[addr + i] {
+                self.heap_used -= 1;
+            }
+            self.allocated[addr + i] = false;
+            self.memory[addr + i] = 0.0;
+        }
+        if self.trace_exit() {
+            eprintln!("free {} cells at {}", size, addr);
+        }
+    }
+
+    fn checked_free(&mut self) {
+        self.trace_enter();
+        let addr = self.pop();
+        let mut size = self.pop() as usize;
+        // Freeing null, from an allocation that failed, does nothing
+        if addr == 0.0 {
+            size = 0;
+        }
+
+        if addr < 0.0 || addr as usize + size > self.capacity {
+            self.panic(INVALID_FREE);
+        }
+        let addr = addr as usize;
+        for i in 0..size {
+            if !self.allocated[addr + i] {
+                self.panic(INVALID_FREE);
+            }
+        }
+
+        for i in 0..size {
+            self.allocated[addr + i] = false;
+            self.memory[addr + i] = 0.0;
+        }
+        self.heap_used -= size;
+        if self.trace_exit() {
+            eprintln!("free {} cells at {}", size, addr);
+        }
+    }
+
+    /// Check that the stack holds exactly `size` cells: the global scope, and
+    /// the value returned by the entry point. Used by the debug runtime.
+    fn check_stack(&mut self, size: usize) {
+        if self.stack_ptr != size {
+            self.panic(UNBALANCED_STACK);
+        }
+    }
+
+    fn load(&mut self, size: usize) {
+        self.trace_enter();
+        let addr = self.pop() as usize;
+        for i in 0..size {
+            self.push(self.memory[addr + i]);
+        }
+        if self.trace_exit() {
+            eprintln!("load {} cells from {}", size, addr);
+        }
+    }
+
+    fn store(&mut self, size: usize) {
+        self.trace_enter();
+        let addr = self.pop() as usize;
+        for i in (0..size).rev() {
+            self.memory[addr + i] = self.pop();
+        }
+        if self.trace_exit() {
+            eprintln!("store {} cells at {}", size, addr);
+        }
+    }
+
+    /// Panic unless `size` cells starting at `addr` can be accessed. They must be in memory,
+    /// and each cell at or above `stack_top` must be allocated on the heap, because the cells
+    /// between the stack and the heap are free. Used by the debug runtime.
+    fn check_access(&mut self, addr: f64, size: usize, stack_top: usize) -> usize {
+        if addr < 0.0 || addr as usize + size > self.capacity {
+            self.panic(INVALID_ACCESS);
+        }
+        let addr = addr as usize;
+        for i in 0..size {
+            if addr + i >= stack_top && !self.allocated[addr + i] {
+                self.panic(INVALID_ACCESS);
+            }
+        }
+        addr
+    }
+
+    fn checked_load(&mut self, size: usize) {
+        self.trace_enter();
+        let addr = self.pop();
+        let addr = self.check_access(addr, size, self.stack_ptr);
+        for i in 0..size {
+            self.push(self.memory[addr + i]);
+        }
+        if self.trace_exit() {
+            eprintln!("load {} cells from {}", size, addr);
+        }
+    }
+
+    fn checked_store(&mut self, size: usize) {
+        self.trace_enter();
+        let addr = self.pop();
+        // The value being stored is popped off of the stack first
+        let addr = self.check_access(addr, size, self.stack_ptr.saturating_sub(size));
+        for i in (0..size).rev() {
+            self.memory[addr + i] = self.pop();
+        }
+        if self.trace_exit() {
+            eprintln!("store {} cells at {}", size, addr);
+        }
+    }
+
+    fn memset(&mut self, size: usize) {
+        let count = self.pop() as usize;
+        let mut value = vec![0.0; size];
+        for j in (0..size).rev() {
+            value[j] = self.pop();
+        }
+        let addr = self.pop() as usize;
+        for i in 0..count {
+            for j in 0..size {
+                self.memory[addr + i * size + j] = value[j];
+            }
+        }
+    }
+
+    fn store_data(&mut self, address: usize, data: &[f64]) {
+        self.memory[address..address + data.len()].copy_from_slice(data);
+    }
+
+    /// Each cell holds the bytes of a double, in little-endian order like
+    /// the C backend on most machines, so the memory starting at an address
+    /// can be used as a byte array. Pop an index and an address, and push
+    /// the byte at that index of the memory.
+    fn load_byte(&mut self) {
+        self.trace_enter();
+        let index = self.pop() as usize;
+        let addr = self.pop() as usize;
+        let bytes = self.memory[addr + index / 8].to_le_bytes();
+        let byte = bytes[index % 8];
+        self.push(byte as f64);
+        if self.trace_exit() {
+            eprintln!("load byte {} of {} -> {}", index, addr, byte);
+        }
+    }
+
+    /// Pop a byte, an index, and an address, and store
+    /// the byte at that index of the memory at the address.
+    fn store_byte(&mut self) {
+        self.trace_enter();
+        let byte = self.pop() as i64 as u8;
+        let index = self.pop() as usize;
+        let addr = self.pop() as usize;
+        let mut bytes = self.memory[addr + index / 8].to_le_bytes();
+        bytes[index % 8] = byte;
+        self.memory[addr + index / 8] = f64::from_le_bytes(bytes);
+        if self.trace_exit() {
+            eprintln!("store byte {} at byte {} of {}", byte, index, addr);
+        }
+    }
+
+    /// Panic unless the byte at `index` of the memory at `addr` can be accessed.
+    /// The index may be past the first cell, but neither it nor the address can be
+    /// negative, and the cell holding the byte must be accessible. Used by the debug runtime.
+    fn check_byte_access(&mut self, addr: f64, index: f64) -> (usize, usize) {
+        if addr < 0.0 || index < 0.0 {
+            self.panic(INVALID_ACCESS);
+        }
+        let (addr, index) = (addr as usize, index as usize);
+        self.check_access((addr + index / 8) as f64, 1, self.stack_ptr);
+        (addr, index)
+    }
+
+    fn checked_load_byte(&mut self) {
+        self.trace_enter();
+        let index = self.pop();
+        let addr = self.pop();
+        let (addr, index) = self.check_byte_access(addr, index);
+        let bytes = self.memory[addr + index / 8].to_le_bytes();
+        let byte = bytes[index % 8];
+        self.push(byte as f64);
+        if self.trace_exit() {
+            eprintln!("load byte {} of {} -> {}", index, addr, byte);
+        }
+    }
+
+    fn checked_store_byte(&mut self) {
+        self.trace_enter();
+        let byte = self.pop() as i64 as u8;
+        let index = self.pop();
+        let addr = self.pop();
+        let (addr, index) = self.check_byte_access(addr, index);
+        let mut bytes = self.memory[addr + index / 8].to_le_bytes();
+        bytes[index % 8] = byte;
+        self.memory[addr + index / 8] = f64::from_le_bytes(bytes);
+        if self.trace_exit() {
+            eprintln!("store byte {} at byte {} of {}", byte, index, addr);
+        }
+    }
+
+    /// Push a random number in [0, 1). There is no random number generator
+    /// in Rust's standard library, so this uses the Mulberry32 generator.
+    /// The numbers are the same in every run, unless the program is seeded.
+    fn random(&mut self) {
+        self.trace_enter();
+        self.random_state = self.random_state.wrapping_add(0x6d2b79f5);
+        let mut t = self.random_state;
+        t = (t ^ (t >> 15)).wrapping_mul(t | 1);
+        t ^= t.wrapping_add((t ^ (t >> 7)).wrapping_mul(t | 61));
+        let n = (t ^ (t >> 14)) as f64 / 4294967296.0;
+        self.push(n);
+        if self.trace_exit() {
+            eprintln!("random -> {}", n);
+        }
+    }
+
+    /// Pop a seed, and start a new sequence of random numbers with it
+    fn seed_random(&mut self) {
+        self.trace_enter();
+        let seed = self.pop();
+        self.random_state = seed as i64 as u32;
+        if self.trace_exit() {
+            eprintln!("seed random with {}", seed);
+        }
+    }
+
+    /// Push the number of seconds since the program started
+    fn clock(&mut self) {
+        self.trace_enter();
+        let seconds = self.start.elapsed().as_secs_f64();
+        self.push(seconds);
+        if self.trace_exit() {
+            eprintln!("clock -> {}", seconds);
+        }
+    }
+
+    fn add(&mut self) {
+        self.trace_enter();
+        let b = self.pop();
+        let a = self.pop();
+        self.push(a + b);
+        if self.trace_exit() {
+            eprintln!(
+                "add {} {} -> {}",
+                format_number(a),
+                format_number(b),
+                format_number(a + b)
+            );
+        }
+    }
+
+    fn subtract(&mut self) {
+        self.trace_enter();
+        let b = self.pop();
+        let a = self.pop();
+        self.push(a - b);
+        if self.trace_exit() {
+            eprintln!(
+                "subtract {} {} -> {}",
+                format_number(a),
+                format_number(b),
+                format_number(a - b)
+            );
+        }
+    }
+
+    fn multiply(&mut self) {
+        self.trace_enter();
+        let b = self.pop();
+        let a = self.pop();
+        self.push(a * b);
+        if self.trace_exit() {
+            eprintln!(
+                "multiply {} {} -> {}",
+                format_number(a),
+                format_number(b),
+                format_number(a * b)
+            );
+        }
+    }
+
+    fn divide(&mut self) {
+        self.trace_enter();
+        let b = self.pop();
+        let a = self.pop();
+        self.push(a / b);
+        if self.trace_exit() {
+            eprintln!(
+                "divide {} {} -> {}",
+                format_number(a),
+                format_number(b),
+                format_number(a / b)
+            );
+        }
+    }
+
+    fn modulo(&mut self) {
+        self.trace_enter();
+        let b = self.pop();
+        let a = self.pop();
+        self.push(a % b);
+        if self.trace_exit() {
+            eprintln!(
+                "modulo {} {} -> {}",
+                format_number(a),
+                format_number(b),
+                format_number(a % b)
+            );
+        }
+    }
+
+    fn sign(&mut self) {
+        self.trace_enter();
+        let x = self.pop();
+        let result = if x > 0.0 {
+            1.0
+        } else if x < 0.0 {
+            -1.0
+        } else {
+            0.0
+        };
+        self.push(result);
+        if self.trace_exit() {
+            eprintln!("sign {} -> {}", format_number(x), format_number(result));
+        }
+    }
+
+    /// Pop a number, and push it as an integer with `bits` bits. The fraction is
+    /// discarded, and the integer wraps around like a C integer of that width.
+    fn truncate(&mut self, bits: i32, is_signed: bool) {
+        self.trace_enter();
+        let x = self.pop();
+        let n = wrap_integer(x, bits, is_signed);
+        self.push(n);
+        if self.trace_exit() {
+            eprintln!(
+                "truncate {} to {} bits -> {}",
+                format_number(x),
+                bits,
+                format_number(n)
+            );
+        }
+    }
+
+    /// Pop a number as a 32 bit signed integer for the bitwise operations
+    fn pop_int32(&mut self) -> i32 {
+        wrap_integer(self.pop(), 32, true) as i32
+    }
+
+    fn bitand(&mut self) {
+        self.trace_enter();
+        let b = self.pop_int32();
+        let a = self.pop_int32();
+        let n = a & b;
+        self.push(n as f64);
+        if self.trace_exit() {
+            eprintln!("bitand {} {} -> {}", a, b, n);
+        }
+    }
+
+    fn bitor(&mut self) {
+        self.trace_enter();
+        let b = self.pop_int32();
+        let a = self.pop_int32();
+        let n = a | b;
+        self.push(n as f64);
+        if self.trace_exit() {
+            eprintln!("bitor {} {} -> {}", a, b, n);
+        }
+    }
+
+    fn bitxor(&mut self) {
+        self.trace_enter();
+        let b = self.pop_int32();
+        let a = self.pop_int32();
+        let n = a ^ b;
+        self.push(n as f64);
+        if self.trace_exit() {
+            eprintln!("bitxor {} {} -> {}", a, b, n);
+        }
+    }
+
+    fn shl(&mut self) {
+        self.trace_enter();
+        let b = self.pop_int32();
+        let a = self.pop_int32();
+        let n = a.wrapping_shl((b & 31) as u32);
+        self.push(n as f64);
+        if self.trace_exit() {
+            eprintln!("shl {} {} -> {}", a, b, n);
+        }
+    }
+
+    fn shr(&mut self) {
+        self.trace_enter();
+        let b = self.pop_int32();
+        let a = self.pop_int32();
+        let n = a >> (b & 31);
+        self.push(n as f64);
+        if self.trace_exit() {
+            eprintln!("shr {} {} -> {}", a, b, n);
+        }
+    }
+}
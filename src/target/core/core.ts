@@ -0,0 +1,606 @@
+interface machine {
+	memory: number[];
+	allocated: boolean[];
+	capacity: number;
+	stack_ptr: number;
+	base_ptr: number;
+	// The number of cells allocated on the heap, and the most
+	// that have been allocated at once during the program.
+	heap_used: number;
+	heap_peak: number;
+	// The host's state, which is passed to the foreign
+	// functions marked with `#[context]`. It starts as null.
+	context: any;
+	// The state of the machine's random numbers, which starts as 1
+	random_state: number;
+}
+
+///////////////////////////////////////////////////////////////////////
+///////////////////////////// Error codes /////////////////////////////
+///////////////////////////////////////////////////////////////////////
+const STACK_HEAP_COLLISION : number = 1;
+const NO_FREE_MEMORY : number	    = 2;
+const STACK_UNDERFLOW : number	    = 3;
+const INVALID_FREE : number	    = 4;
+const UNBALANCED_STACK : number    = 5;
+const INVALID_ACCESS : number      = 7;
+
+// console.log always ends its output with a newline, so the output
+// of an unfinished line is kept here until the line is finished.
+let OUTPUT: string = "";
+
+// Write text to the output. Only whole lines are logged.
+function machine_write(text: string): void {
+	OUTPUT += text;
+	let end = OUTPUT.lastIndexOf("\n");
+	if (end >= 0) {
+		console.log(OUTPUT.substring(0, end));
+		OUTPUT = OUTPUT.substring(end + 1);
+	}
+}
+
+// When a program is compiled with `--trace`, the compiler defines
+// `TRACE` as true, and each operation logs its effect to stderr.
+// Operations are built out of other operations, like `add` pushing
+// its result, so only the outermost operation is logged.
+let TRACE_DEPTH: number = 0;
+
+function trace_enter(): void {
+	if (TRACE) TRACE_DEPTH++;
+}
+
+// Is the operation that just finished the outermost one?
+function trace_exit(): boolean {
+	return TRACE && --TRACE_DEPTH === 0;
+}
+
+// Fatal error handler. Always exits program.
+function panic(code: number): void {
+	let message: string = "panic: ";
+	switch (code) {
+		case 1: message += "stack and heap collision during push"; break;
+		case 2: message += "no free memory left"; break;
+		case 3: message += "stack underflow"; break;
+		case 4: message += "freed memory that is not allocated"; break;
+		case 5: message += "stack is not balanced at the end of the program"; break;
+		case 7: message += "accessed memory that is not allocated"; break;
+		default: message += "unknown error code";
+	}
+	message += "\n";
+	//show the output of the unfinished line before the error
+	if (OUTPUT.length > 0) console.log(OUTPUT);
+	//throwing an error is the closest thing JavaScript has to exit() afaik
+	throw new Error(message);
+}
+
+// Create new virtual machine
+function machine_new(vars: number, capacity: number): machine {
+	let result: machine = {
+		capacity: capacity,
+		memory: Array<number>(capacity),
+		allocated: Array<boolean>(capacity),
+		stack_ptr: 0,
+		base_ptr: 0,
+		heap_used: 0,
+		heap_peak: 0,
+		context: null,
+		random_state: 1
+	};
+	
+	//initialize the memory and allocated arrays
+	for (let i = 0; i < capacity; i++) {
+		result.memory[i] = 0;
+		result.allocated[i] = false;
+	}
+
+	for (let i = 0; i < vars; i++)
+		machine_push(result, 0);
+
+	return result;
+}
+
+// Print out the state of the virtual machine's stack and heap
+function machine_dump(vm: machine): void {
+	let out = "";
+	let i:number;
+	out += "stack: [ ";
+	for (i=0; i<vm.stack_ptr; i++)
+		out += vm.memory[i];
+	for (i=vm.stack_ptr; i<vm.capacity; i++)
+		out += "  ";
+	console.log("]\nheap:  [ ");
+	for (i=0; i<vm.stack_ptr; i++)
+		out += "  ";
+	for (i=vm.stack_ptr; i<vm.capacity; i++)
+		out += `${vm.memory[i]} `;
+	out += "]\nalloc: [ ";
+	for (i=0; i<vm.capacity; i++)
+		out += `${vm.allocated[i]} `;
+	out += "]\n";
+	let total: number = 0;
+	for (i=0; i<vm.capacity; i++)
+		total += vm.allocated[i] ? 1 : 0;
+	out += `STACK SIZE	${vm.stack_ptr}\n`;
+	out += `TOTAL ALLOC'D ${total}\n`;
+	console.log(out);
+}
+
+// Write all of the buffered output to stdout. This logs
+// an unfinished line, which console.log ends with a newline.
+function machine_flush(vm: machine): void {
+	if (OUTPUT.length > 0) {
+		console.log(OUTPUT);
+		OUTPUT = "";
+	}
+}
+
+// Free the virtual machine's memory. This is called at the end of the program.
+// When a program is compiled with `--heap-stats`, the compiler defines `HEAP_STATS`
+// as true, and the most cells that were allocated on the heap at once is reported.
+function machine_drop(vm: machine): void {
+	machine_flush(vm);
+	if (HEAP_STATS) console.error(`heap high-water mark: ${vm.heap_peak} cells`);
+	//JS doesn't have manual memory management, so this function does nothing
+	//free(vm.memory);
+	//free(vm.allocated);
+}
+
+function machine_load_base_ptr(vm: machine): void {
+    // Get the virtual machine's current base pointer value,
+    // and push it onto the stack.
+    machine_push(vm, vm.base_ptr);
+}
+
+function machine_establish_stack_frame(
+	vm: machine, 
+	arg_size: number, 
+	local_scope_size: number
+): void {
+    trace_enter();
+    // Allocate some space to store the arguments' cells for later
+    let args = Array<number>(arg_size);
+    let i: number;
+    // Pop the arguments' values off of the stack
+    for (i=arg_size-1; i>=0; i--)
+        args[i] = machine_pop(vm);
+
+    // Push the current base pointer onto the stack so that
+    // when this function returns, it will be able to resume
+    // the current stack frame
+    machine_load_base_ptr(vm);
+
+    // Set the base pointer to the current stack pointer to 
+    // begin the stack frame at the current position on the stack.
+    vm.base_ptr = vm.stack_ptr;
+
+    // Allocate space for all the variables used in the local scope on the stack
+    for (i=0; i<local_scope_size; i++)
+        machine_push(vm, 0);
+
+    // Push the arguments back onto the stack for use by the current function
+    for (i=0; i<arg_size; i++)
+        machine_push(vm, args[i]);
+    if (trace_exit())
+        console.error(`call with ${arg_size} argument cells and ${local_scope_size} local cells`);
+}
+
+function machine_end_stack_frame(
+	vm: machine, 
+	return_size: number, 
+	local_scope_size: number
+): void {
+    trace_enter();
+    // Allocate some space to store the returned cells for later
+    let return_val = Array<number>(return_size);
+    let i: number;
+    // Pop the returned values off of the stack
+    for (i=return_size-1; i>=0; i--)
+        return_val[i] = machine_pop(vm);
+
+    // Discard the memory setup by the stack frame
+    for (i=0; i<local_scope_size; i++)
+        machine_pop(vm);
+    
+    // Retrieve the parent function's base pointer to resume the function
+    vm.base_ptr = machine_pop(vm);
+
+    // Finally, push the returned value back onto the stack for use by
+    // the parent function.
+    for (i=0; i<return_size; i++)
+        machine_push(vm, return_val[i]);
+    if (trace_exit())
+        console.error(`return ${return_size} cells`);
+}
+
+// Push a number onto the stack
+function machine_push(vm: machine, n: number): void {
+	trace_enter();
+	if (vm.allocated[vm.stack_ptr])
+		panic(STACK_HEAP_COLLISION);
+	vm.memory[vm.stack_ptr++] = n;
+	if (trace_exit()) console.error(`push ${n}`);
+}
+
+// Pop a number from the stack
+function machine_pop(vm: machine): number {
+	if (vm.stack_ptr === 0) {
+		panic(STACK_UNDERFLOW);
+	}
+	let result: number = vm.memory[vm.stack_ptr-1];
+	vm.memory[--vm.stack_ptr] = 0;
+	//--vm.stack_ptr;
+	return result;
+}
+
+// Pop the `size` parameter off of the stack, and return a pointer to `size` number of free cells.
+// The returned address is always the index of a whole cell, so allocations are cell-aligned.
+function machine_allocate(vm: machine): number {
+	trace_enter();
+	let size = machine_pop(vm);
+	let addr = 0;
+	let consecutive_free_cells = 0;
+
+	for (let i = vm.capacity-1; i > vm.stack_ptr; i--) {
+		if (!vm.allocated[i]) consecutive_free_cells++;
+		else consecutive_free_cells = 0;
+
+		if (consecutive_free_cells === size) {
+			addr = i;
+			break;
+		}
+	}
+
+	// If the heap is full, push null, so that the
+	// program can check whether the allocation failed
+	if (addr <= vm.stack_ptr) {
+		machine_push(vm, 0);
+		if (trace_exit()) console.error(`allocate ${size} cells failed`);
+		return 0;
+	}
+
+	for (let i = 0; i < size; i++)
+		vm.allocated[addr+i] = true;
+	vm.heap_used += size;
+	if (vm.heap_used > vm.heap_peak) vm.heap_peak = vm.heap_used;
+
+	machine_push(vm, addr);
+	if (trace_exit()) console.error(`allocate ${size} cells at ${addr}`);
+	return addr;
+}
+
+// Pop the `address` and `size` parameters off of the stack, and free the memory at `address` with size `size`.
+function machine_free(vm: machine): void {
+	trace_enter();
+	let addr = machine_pop(vm);
+	let size = machine_pop(vm);
+	// Freeing null, from an allocation that failed, does nothing
+	if (addr == 0) size = 0;
+
+	for (let i=0; i<size; i++) {
+		if (vm.allocated[addr+i]) vm.heap_used--;
+		vm.allocated[addr+i] = false;
+		vm.memory[addr+i] = 0;
+	}
+	if (trace_exit()) console.error(`free ${size} cells at ${addr}`);
+}
+
+// Free memory like `machine_free`, but first check that every cell being freed
+// is allocated. This catches double frees, and frees of addresses that were never
+// allocated, before they can corrupt the `allocated` table. Used by the debug runtime.
+function machine_checked_free(vm: machine): void {
+	trace_enter();
+	let addr = machine_pop(vm);
+	let size = machine_pop(vm);
+	// Freeing null, from an allocation that failed, does nothing
+	if (addr == 0) size = 0;
+
+	if (addr < 0 || addr + size > vm.capacity) panic(INVALID_FREE);
+	for (let i=0; i<size; i++) {
+		if (!vm.allocated[addr+i]) panic(INVALID_FREE);
+	}
+
+	for (let i=0; i<size; i++) {
+		vm.allocated[addr+i] = false;
+		vm.memory[addr+i] = 0;
+	}
+	vm.heap_used -= size;
+	if (trace_exit()) console.error(`free ${size} cells at ${addr}`);
+}
+
+// Check that the stack holds exactly `size` cells: the global scope, and
+// the value returned by the entry point. Used by the debug runtime.
+function machine_check_stack(vm: machine, size: number): void {
+	if (vm.stack_ptr != size) panic(UNBALANCED_STACK);
+}
+
+// Pop an `address` parameter off of the stack, and a `value` parameter with size `size`.
+// Then store the `value` parameter at the memory address `address`.
+function machine_store(vm: machine, size: number): void {
+	trace_enter();
+	let addr = machine_pop(vm);
+
+	for (let i = size-1; i >= 0; i--) vm.memory[addr+i] = machine_pop(vm);
+	if (trace_exit()) console.error(`store ${size} cells at ${addr}`);
+}
+
+// Panic unless `size` cells starting at `addr` can be accessed. They must be in memory,
+// and each cell at or above `stack_top` must be allocated on the heap, because the cells
+// between the stack and the heap are free. Used by the debug runtime.
+function machine_check_access(vm: machine, addr: number, size: number, stack_top: number): void {
+	if (addr < 0 || addr + size > vm.capacity) panic(INVALID_ACCESS);
+	for (let i=0; i<size; i++) {
+		if (addr+i >= stack_top && !vm.allocated[addr+i]) panic(INVALID_ACCESS);
+	}
+}
+
+// Store a value like `machine_store`, but first check that the
+// address can be accessed. Used by the debug runtime.
+function machine_checked_store(vm: machine, size: number): void {
+	trace_enter();
+	let addr = machine_pop(vm);
+	// The value being stored is popped off of the stack first
+	machine_check_access(vm, addr, size, vm.stack_ptr - size);
+	for (let i = size-1; i >= 0; i--) vm.memory[addr+i] = machine_pop(vm);
+	if (trace_exit()) console.error(`store ${size} cells at ${addr}`);
+}
+
+// Pop a `count` parameter, a `value` parameter with size `size`, and an `address` parameter
+// off of the stack. Then store `count` copies of `value` one after another starting at `address`.
+function machine_memset(vm: machine, size: number): void {
+	let count = machine_pop(vm);
+	let value: number[] = [];
+	for (let j = size-1; j >= 0; j--) value[j] = machine_pop(vm);
+	let addr = machine_pop(vm);
+
+	for (let i = 0; i < count; i++)
+		for (let j = 0; j < size; j++)
+			vm.memory[addr+i*size+j] = value[j];
+}
+
+// Copy precomputed cells from `data` into memory starting at `address`.
+function machine_store_data(vm: machine, address: number, data: Float64Array): void {
+	for (let i = 0; i < data.length; i++) vm.memory[address+i] = data[i];
+}
+
+// Pop an `address` parameter off of the stack, and push the value at `address` with size
+//`size` onto the stack.
+function machine_load(vm: machine, size: number): void {
+	trace_enter();
+	let addr = machine_pop(vm);
+
+	for (let i=0; i<size; i++) machine_push(vm, vm.memory[addr+i]);
+	if (trace_exit()) console.error(`load ${size} cells from ${addr}`);
+}
+
+// Load a value like `machine_load`, but first check that the
+// address can be accessed. Used by the debug runtime.
+function machine_checked_load(vm: machine, size: number): void {
+	trace_enter();
+	let addr = machine_pop(vm);
+	machine_check_access(vm, addr, size, vm.stack_ptr);
+	for (let i=0; i<size; i++) machine_push(vm, vm.memory[addr+i]);
+	if (trace_exit()) console.error(`load ${size} cells from ${addr}`);
+}
+
+// The bytes of a cell are read and written through this view
+const CELL_BYTES = new DataView(new ArrayBuffer(8));
+
+// Each cell holds the bytes of a double, in little-endian order like
+// the C backend on most machines, so the memory starting at an address
+// can be used as a byte array. Pop an index and an address, and push
+// the byte at that index of the memory.
+function machine_load_byte(vm: machine): void {
+	trace_enter();
+	let index = machine_pop(vm);
+	let addr = machine_pop(vm);
+	CELL_BYTES.setFloat64(0, vm.memory[addr+Math.floor(index/8)], true);
+	let byte = CELL_BYTES.getUint8(index%8);
+	machine_push(vm, byte);
+	if (trace_exit()) console.error(`load byte ${index} of ${addr} -> ${byte}`);
+}
+
+// Pop a byte, an index, and an address, and store the byte at that
+// index of the memory at the address. JavaScript may change the bytes
+// of a cell that is not a number, so those cells aren't kept exactly.
+function machine_store_byte(vm: machine): void {
+	trace_enter();
+	let byte = machine_pop(vm) & 0xff;
+	let index = machine_pop(vm);
+	let addr = machine_pop(vm);
+	CELL_BYTES.setFloat64(0, vm.memory[addr+Math.floor(index/8)], true);
+	CELL_BYTES.setUint8(index%8, byte);
+	vm.memory[addr+Math.floor(index/8)] = CELL_BYTES.getFloat64(0, true);
+	if (trace_exit()) console.error(`store byte ${byte} at byte ${index} of ${addr}`);
+}
+
+// Panic unless the byte at `index` of the memory at `addr` can be accessed.
+// The index may be past the first cell, but neither it nor the address can be
+// negative, and the cell holding the byte must be accessible. Used by the debug runtime.
+function machine_check_byte_access(vm: machine, addr: number, index: number): void {
+	if (addr < 0 || index < 0) panic(INVALID_ACCESS);
+	machine_check_access(vm, addr+Math.floor(index/8), 1, vm.stack_ptr);
+}
+
+// Load a byte like `machine_load_byte`, but first check that
+// the byte can be accessed. Used by the debug runtime.
+function machine_checked_load_byte(vm: machine): void {
+	trace_enter();
+	let index = machine_pop(vm);
+	let addr = machine_pop(vm);
+	machine_check_byte_access(vm, addr, index);
+	CELL_BYTES.setFloat64(0, vm.memory[addr+Math.floor(index/8)], true);
+	let byte = CELL_BYTES.getUint8(index%8);
+	machine_push(vm, byte);
+	if (trace_exit()) console.error(`load byte ${index} of ${addr} -> ${byte}`);
+}
+
+// Store a byte like `machine_store_byte`, but first check that
+// the byte can be accessed. Used by the debug runtime.
+function machine_checked_store_byte(vm: machine): void {
+	trace_enter();
+	let byte = machine_pop(vm) & 0xff;
+	let index = machine_pop(vm);
+	let addr = machine_pop(vm);
+	machine_check_byte_access(vm, addr, index);
+	CELL_BYTES.setFloat64(0, vm.memory[addr+Math.floor(index/8)], true);
+	CELL_BYTES.setUint8(index%8, byte);
+	vm.memory[addr+Math.floor(index/8)] = CELL_BYTES.getFloat64(0, true);
+	if (trace_exit()) console.error(`store byte ${byte} at byte ${index} of ${addr}`);
+}
+
+// Push a random number in [0, 1). `Math.random` can't be seeded,
+// so this uses the Mulberry32 generator, like the Rust runtime.
+// The numbers are the same in every run, unless the program is seeded.
+function machine_random(vm: machine): void {
+	trace_enter();
+	vm.random_state = (vm.random_state + 0x6d2b79f5) >>> 0;
+	let t = vm.random_state;
+	t = Math.imul(t ^ (t >>> 15), t | 1);
+	t ^= t + Math.imul(t ^ (t >>> 7), t | 61);
+	let n = ((t ^ (t >>> 14)) >>> 0) / 4294967296;
+	machine_push(vm, n);
+	if (trace_exit()) console.error(`random -> ${n}`);
+}
+
+// Pop a seed, and start a new sequence of random numbers with it
+function machine_seed_random(vm: machine): void {
+	trace_enter();
+	let seed = machine_pop(vm);
+	vm.random_state = seed >>> 0;
+	if (trace_exit()) console.error(`seed random with ${seed}`);
+}
+
+// Push the number of seconds since the program started.
+// `performance.now` counts milliseconds from the start of the process.
+function machine_clock(vm: machine): void {
+	trace_enter();
+	let seconds = performance.now() / 1000;
+	machine_push(vm, seconds);
+	if (trace_exit()) console.error(`clock -> ${seconds}`);
+}
+
+// Add the topmost numbers on the stack
+function machine_add(vm: machine): void {
+	trace_enter();
+	let b = machine_pop(vm);
+	let a = machine_pop(vm);
+	machine_push(vm, a+b);
+	if (trace_exit()) console.error(`add ${a} ${b} -> ${a+b}`);
+}
+
+// Subtract the topmost number on the stack from the second topmost number on the stack
+function machine_subtract(vm: machine): void {
+	trace_enter();
+	let b = machine_pop(vm);
+	let a = machine_pop(vm);
+	machine_push(vm, a-b);
+	if (trace_exit()) console.error(`subtract ${a} ${b} -> ${a-b}`);
+}
+
+// Multiply the topmost numbers on the stack
+function machine_multiply(vm: machine): void {
+	trace_enter();
+	let b = machine_pop(vm);
+	let a = machine_pop(vm);
+	machine_push(vm, a*b);
+	if (trace_exit()) console.error(`multiply ${a} ${b} -> ${a*b}`);
+}
+
+// Divide the second topmost number on the stack by the topmost number on the stack
+function machine_divide(vm: machine): void {
+	trace_enter();
+	let b = machine_pop(vm);
+	let a = machine_pop(vm);
+	machine_push(vm, a/b);
+	if (trace_exit()) console.error(`divide ${a} ${b} -> ${a/b}`);
+}
+
+// Push the remainder of dividing the second topmost number on the stack by the topmost number on the stack
+function machine_modulo(vm: machine): void {
+	trace_enter();
+	let b = machine_pop(vm);
+	let a = machine_pop(vm);
+	machine_push(vm, a%b);
+	if (trace_exit()) console.error(`modulo ${a} ${b} -> ${a%b}`);
+}
+
+// Push 1 if the topmost number on the stack is positive, -1 if it is negative,
+// and 0 otherwise. Both `0` and `-0` have a sign of 0, and so does `NaN`.
+function machine_sign(vm: machine): void {
+    trace_enter();
+    let x = machine_pop(vm);
+    if (x > 0) {
+        machine_push(vm, 1);
+    } else if (x < 0) {
+        machine_push(vm, -1);
+    } else {
+        machine_push(vm, 0);
+    }
+    if (trace_exit()) console.error(`sign ${x} -> ${vm.memory[vm.stack_ptr-1]}`);
+}
+
+// Pop a number, and push it as an integer with `bits` bits. The fraction is
+// discarded, and the integer wraps around like a C integer of that width.
+function machine_truncate(vm: machine, bits: number, is_signed: boolean): void {
+    trace_enter();
+    let x = machine_pop(vm);
+    let range = Math.pow(2, bits);
+    // Infinite numbers and NaN have no integer value, so they become zero
+    let n = 0;
+    if (isFinite(x)) {
+        n = Math.trunc(x) % range;
+        if (n < 0) n += range;
+        if (is_signed && n >= range / 2) n -= range;
+    }
+    machine_push(vm, n);
+    if (trace_exit()) console.error(`truncate ${x} to ${bits} bits -> ${n}`);
+}
+
+// JavaScript's bitwise operators convert their operands to 32 bit signed integers
+// already, discarding the fraction and wrapping around like the other targets.
+// Only the lowest 5 bits of the count of a shift are used, like the other targets.
+
+// Bitwise and the second topmost number on the stack with the topmost number on the stack
+function machine_bitand(vm: machine): void {
+	trace_enter();
+	let b = machine_pop(vm) | 0;
+	let a = machine_pop(vm) | 0;
+	machine_push(vm, a&b);
+	if (trace_exit()) console.error(`bitand ${a} ${b} -> ${a&b}`);
+}
+
+// Bitwise or the second topmost number on the stack with the topmost number on the stack
+function machine_bitor(vm: machine): void {
+	trace_enter();
+	let b = machine_pop(vm) | 0;
+	let a = machine_pop(vm) | 0;
+	machine_push(vm, a|b);
+	if (trace_exit()) console.error(`bitor ${a} ${b} -> ${a|b}`);
+}
+
+// Bitwise exclusive or the second topmost number on the stack with the topmost number on the stack
+function machine_bitxor(vm: machine): void {
+	trace_enter();
+	let b = machine_pop(vm) | 0;
+	let a = machine_pop(vm) | 0;
+	machine_push(vm, a^b);
+	if (trace_exit()) console.error(`bitxor ${a} ${b} -> ${a^b}`);
+}
+
+// Shift the second topmost number on the stack left by the topmost number on the stack
+function machine_shl(vm: machine): void {
+	trace_enter();
+	let b = machine_pop(vm) | 0;
+	let a = machine_pop(vm) | 0;
+	machine_push(vm, a<<b);
+	if (trace_exit()) console.error(`shl ${a} ${b} -> ${a<<b}`);
+}
+
+// Shift the second topmost number on the stack right by the topmost number on the stack, keeping its sign
+function machine_shr(vm: machine): void {
+	trace_enter();
+	let b = machine_pop(vm) | 0;
+	let a = machine_pop(vm) | 0;
+	machine_push(vm, a>>b);
+	if (trace_exit()) console.error(`shr ${a} ${b} -> ${a>>b}`);
+}
\ No newline at end of file
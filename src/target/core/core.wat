@@ -0,0 +1,600 @@
+(module
+  ;; WebAssembly has no I/O of its own, so the host running the
+  ;; module provides it through these functions. Output is buffered
+  ;; by the host, and only written to stdout when it's flushed.
+  (import "oak" "write_byte" (func $host_write_byte (param i32)))
+  (import "oak" "write_number" (func $host_write_number (param f64)))
+  (import "oak" "read_byte" (func $host_read_byte (result i32)))
+  (import "oak" "flush" (func $host_flush))
+  (import "oak" "exit" (func $host_exit (param i32)))
+  ;; The current time, in seconds
+  (import "oak" "now" (func $host_now (result f64)))
+
+  ;; The memory starts with the panic messages. The cells of the virtual
+  ;; machine follow them, eight bytes each, and after the cells is one
+  ;; byte for each cell marking whether it is allocated on the heap.
+  ;; The memory is grown to fit the cells when the machine is created.
+  (memory (export "memory") 1)
+  (global $cells i32 (i32.const 1024))
+  (global $capacity (mut i32) (i32.const 0))
+  (global $stack_ptr (mut i32) (i32.const 0))
+  (global $base_ptr (mut i32) (i32.const 0))
+  ;; The host's state, which is passed to the foreign
+  ;; functions marked with `#[context]`. It starts as zero.
+  (global $context (export "context") (mut i32) (i32.const 0))
+  ;; The state of the machine's random numbers, which starts as 1
+  (global $random_state (mut i32) (i32.const 1))
+  ;; The time the machine was created, which the clock starts at
+  (global $start (mut f64) (f64.const 0))
+
+  ;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;
+  ;;;;;;;;;;;;;;;;;;;;;;;;;;;; Error codes ;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;
+  ;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;
+  ;; The message for each error code is stored at 128 times the code
+  ;;   STACK_HEAP_COLLISION = 1
+  ;;   NO_FREE_MEMORY       = 2
+  ;;   STACK_UNDERFLOW      = 3
+  ;;   INVALID_FREE         = 4
+  ;;   UNBALANCED_STACK     = 5
+  ;;   UNTERMINATED_STRING  = 6
+  ;;   INVALID_ACCESS       = 7
+  (data (i32.const 0) "panic: \00")
+  (data (i32.const 64) "unknown error code\n\00")
+  (data (i32.const 128) "stack and heap collision during push\n\00")
+  (data (i32.const 256) "no free memory left\n\00")
+  (data (i32.const 384) "stack underflow\n\00")
+  (data (i32.const 512) "freed memory that is not allocated\n\00")
+  (data (i32.const 640) "stack is not balanced at the end of the program\n\00")
+  (data (i32.const 768) "string is not terminated before the end of memory\n\00")
+  (data (i32.const 896) "accessed memory that is not allocated\n\00")
+
+  ;; Write the null terminated string at a byte offset in memory
+  (func $write_string (param $offset i32)
+    (block $done
+      (loop $next
+        (br_if $done (i32.eqz (i32.load8_u (local.get $offset))))
+        (call $host_write_byte (i32.load8_u (local.get $offset)))
+        (local.set $offset (i32.add (local.get $offset) (i32.const 1)))
+        (br $next))))
+
+  ;; Fatal error handler. Always exits the program.
+  (func $panic (param $code i32)
+    (call $write_string (i32.const 0))
+    (call $write_string
+      (select
+        (i32.shl (local.get $code) (i32.const 7))
+        (i32.const 64)
+        (i32.and
+          (i32.ge_s (local.get $code) (i32.const 1))
+          (i32.le_s (local.get $code) (i32.const 7)))))
+    (call $host_flush)
+    (call $host_exit (local.get $code))
+    unreachable)
+
+  ;; The byte offset of a cell in memory
+  (func $cell (param $addr i32) (result i32)
+    (i32.add (global.get $cells) (i32.shl (local.get $addr) (i32.const 3))))
+
+  ;; The byte offset of the flag marking whether a cell is allocated
+  (func $flag (param $addr i32) (result i32)
+    (i32.add
+      (call $cell (global.get $capacity))
+      (local.get $addr)))
+
+  ;; Convert a cell to an address or a size. The fraction is discarded, numbers
+  ;; that don't fit in an integer are clamped to one that does, and NaN is zero.
+  (func $to_int (param $n f64) (result i32)
+    (i32.trunc_sat_f64_s (local.get $n)))
+
+  ;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;
+  ;;;;;;;;;;;;;;;;;;;;; Stack manipulation operations ;;;;;;;;;;;;;;;;;;;;;
+  ;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;
+  ;; Push a number onto the stack
+  (func $machine_push (param $n f64)
+    ;; If the memory at the stack pointer is allocated on the heap,
+    ;; then the stack pointer has collided with the heap.
+    (if (i32.or
+          (i32.ge_s (global.get $stack_ptr) (global.get $capacity))
+          (i32.load8_u (call $flag (global.get $stack_ptr))))
+      (then (call $panic (i32.const 1))))
+    (f64.store (call $cell (global.get $stack_ptr)) (local.get $n))
+    (global.set $stack_ptr (i32.add (global.get $stack_ptr) (i32.const 1))))
+
+  ;; Pop a number from the stack
+  (func $machine_pop (result f64)
+    (local $result f64)
+    (if (i32.eqz (global.get $stack_ptr))
+      (then (call $panic (i32.const 3))))
+    (global.set $stack_ptr (i32.sub (global.get $stack_ptr) (i32.const 1)))
+    (local.set $result (f64.load (call $cell (global.get $stack_ptr))))
+    ;; Overwrite the position on the stack with a zero
+    (f64.store (call $cell (global.get $stack_ptr)) (f64.const 0))
+    (local.get $result))
+
+  ;; Write all of the buffered output to stdout
+  (func $machine_flush
+    (call $host_flush))
+
+  (func $machine_new (param $global_scope_size i32) (param $capacity i32)
+    (local $pages i32)
+    (global.set $capacity (local.get $capacity))
+    ;; Grow the memory to fit every cell and its allocated flag
+    (local.set $pages
+      (i32.sub
+        (i32.div_u
+          (i32.add (call $flag (local.get $capacity)) (i32.const 65535))
+          (i32.const 65536))
+        (memory.size)))
+    (if (i32.gt_s (local.get $pages) (i32.const 0))
+      (then
+        (if (i32.eq (memory.grow (local.get $pages)) (i32.const -1))
+          (then (call $panic (i32.const 2))))))
+    (global.set $start (call $host_now))
+
+    (block $done
+      (loop $next
+        (br_if $done (i32.eqz (local.get $global_scope_size)))
+        (call $machine_push (f64.const 0))
+        (local.set $global_scope_size (i32.sub (local.get $global_scope_size) (i32.const 1)))
+        (br $next))))
+
+  ;; Check that the stack holds exactly `size` cells: the global scope, and
+  ;; the value returned by the entry point. Used by the debug runtime.
+  (func $machine_check_stack (param $size i32)
+    (if (i32.ne (global.get $stack_ptr) (local.get $size))
+      (then (call $panic (i32.const 5)))))
+
+  (func $machine_drop
+    (call $machine_flush))
+
+  (func $machine_load_base_ptr
+    ;; Get the virtual machine's current base pointer value,
+    ;; and push it onto the stack.
+    (call $machine_push (f64.convert_i32_s (global.get $base_ptr))))
+
+  (func $machine_establish_stack_frame (param $arg_size i32) (param $local_scope_size i32)
+    ;; The address of the first argument on the stack
+    (local $args i32)
+    (local $i i32)
+    (if (i32.lt_s (global.get $stack_ptr) (local.get $arg_size))
+      (then (call $panic (i32.const 3))))
+    (local.set $args (i32.sub (global.get $stack_ptr) (local.get $arg_size)))
+
+    ;; Make room on the stack for the current base pointer and the local
+    ;; scope, so that when this function returns, it will be able to
+    ;; resume the current stack frame
+    (local.set $i (i32.add (local.get $local_scope_size) (i32.const 1)))
+    (block $done
+      (loop $next
+        (br_if $done (i32.eqz (local.get $i)))
+        (call $machine_push (f64.const 0))
+        (local.set $i (i32.sub (local.get $i) (i32.const 1)))
+        (br $next)))
+
+    ;; Move the arguments above the local scope for use by the current function
+    (memory.copy
+      (call $cell (i32.add (local.get $args) (i32.add (local.get $local_scope_size) (i32.const 1))))
+      (call $cell (local.get $args))
+      (i32.shl (local.get $arg_size) (i32.const 3)))
+    (f64.store (call $cell (local.get $args)) (f64.convert_i32_s (global.get $base_ptr)))
+    (memory.fill
+      (call $cell (i32.add (local.get $args) (i32.const 1)))
+      (i32.const 0)
+      (i32.shl (local.get $local_scope_size) (i32.const 3)))
+
+    ;; Begin the stack frame after the stored base pointer
+    (global.set $base_ptr (i32.add (local.get $args) (i32.const 1))))
+
+  (func $machine_end_stack_frame (param $return_size i32) (param $local_scope_size i32)
+    ;; The address of the parent function's base pointer on the stack
+    (local $frame i32)
+    (local.set $frame
+      (i32.sub
+        (global.get $stack_ptr)
+        (i32.add (local.get $return_size) (i32.add (local.get $local_scope_size) (i32.const 1)))))
+    (if (i32.lt_s (local.get $frame) (i32.const 0))
+      (then (call $panic (i32.const 3))))
+
+    ;; Retrieve the parent function's base pointer to resume the function
+    (global.set $base_ptr (call $to_int (f64.load (call $cell (local.get $frame)))))
+
+    ;; Move the returned values over the stack frame for use by
+    ;; the parent function, and discard the rest of the frame
+    (memory.copy
+      (call $cell (local.get $frame))
+      (call $cell (i32.sub (global.get $stack_ptr) (local.get $return_size)))
+      (i32.shl (local.get $return_size) (i32.const 3)))
+    (memory.fill
+      (call $cell (i32.add (local.get $frame) (local.get $return_size)))
+      (i32.const 0)
+      (i32.shl (i32.add (local.get $local_scope_size) (i32.const 1)) (i32.const 3)))
+    (global.set $stack_ptr (i32.add (local.get $frame) (local.get $return_size))))
+
+  (func $machine_allocate
+    (local $size i32)
+    (local $addr i32)
+    (local $consecutive_free_cells i32)
+    (local $i i32)
+    (local.set $size (call $to_int (call $machine_pop)))
+
+    ;; Search the heap for a block of free cells, from the end of memory
+    (local.set $i (global.get $capacity))
+    (block $done
+      (loop $next
+        (br_if $done (i32.le_s (local.get $i) (i32.add (global.get $stack_ptr) (i32.const 1))))
+        (local.set $i (i32.sub (local.get $i) (i32.const 1)))
+        (if (i32.load8_u (call $flag (local.get $i)))
+          (then (local.set $consecutive_free_cells (i32.const 0)))
+          (else
+            (local.set $consecutive_free_cells
+              (i32.add (local.get $consecutive_free_cells) (i32.const 1)))))
+        (if (i32.eq (local.get $consecutive_free_cells) (local.get $size))
+          (then
+            (local.set $addr (local.get $i))
+            (br $done)))
+        (br $next)))
+
+    ;; If the heap is full, push null, so that the
+    ;; program can check whether the allocation failed
+    (if (i32.le_s (local.get $addr) (global.get $stack_ptr))
+      (then
+        (call $machine_push (f64.const 0))
+        (return)))
+
+    (memory.fill (call $flag (local.get $addr)) (i32.const 1) (local.get $size))
+    (call $machine_push (f64.convert_i32_s (local.get $addr))))
+
+  (func $machine_free
+    (local $addr i32)
+    (local $size i32)
+    (local.set $addr (call $to_int (call $machine_pop)))
+    (local.set $size (call $to_int (call $machine_pop)))
+    ;; Freeing null, from an allocation that failed, does nothing
+    (if (i32.eqz (local.get $addr))
+      (then (local.set $size (i32.const 0))))
+
+    (memory.fill (call $flag (local.get $addr)) (i32.const 0) (local.get $size))
+    (memory.fill
+      (call $cell (local.get $addr))
+      (i32.const 0)
+      (i32.shl (local.get $size) (i32.const 3))))
+
+  ;; Free memory, and panic if any of it was never allocated.
+  ;; Used by the debug runtime.
+  (func $machine_checked_free
+    (local $addr i32)
+    (local $size i32)
+    (local $i i32)
+    (local.set $addr (call $to_int (call $machine_pop)))
+    (local.set $size (call $to_int (call $machine_pop)))
+    ;; Freeing null, from an allocation that failed, does nothing
+    (if (i32.eqz (local.get $addr))
+      (then (local.set $size (i32.const 0))))
+
+    (if (i32.or
+          (i32.lt_s (local.get $addr) (i32.const 0))
+          (i32.gt_s (i32.add (local.get $addr) (local.get $size)) (global.get $capacity)))
+      (then (call $panic (i32.const 4))))
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_s (local.get $i) (local.get $size)))
+        (if (i32.eqz (i32.load8_u (call $flag (i32.add (local.get $addr) (local.get $i)))))
+          (then (call $panic (i32.const 4))))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $next)))
+
+    (call $machine_push (f64.convert_i32_s (local.get $size)))
+    (call $machine_push (f64.convert_i32_s (local.get $addr)))
+    (call $machine_free))
+
+  (func $machine_store (param $size i32)
+    (local $addr i32)
+    (local.set $addr (call $to_int (call $machine_pop)))
+    (block $done
+      (loop $next
+        (br_if $done (i32.eqz (local.get $size)))
+        (local.set $size (i32.sub (local.get $size) (i32.const 1)))
+        (f64.store
+          (call $cell (i32.add (local.get $addr) (local.get $size)))
+          (call $machine_pop))
+        (br $next))))
+
+  (func $machine_load (param $size i32)
+    (local $addr i32)
+    (local $i i32)
+    (local.set $addr (call $to_int (call $machine_pop)))
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_s (local.get $i) (local.get $size)))
+        (call $machine_push (f64.load (call $cell (i32.add (local.get $addr) (local.get $i)))))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $next))))
+
+  ;; Panic unless `size` cells starting at `addr` can be accessed. They must be in memory,
+  ;; and each cell at or above `stack_top` must be allocated on the heap, because the cells
+  ;; between the stack and the heap are free. Used by the debug runtime.
+  (func $machine_check_access (param $addr i32) (param $size i32) (param $stack_top i32)
+    (local $i i32)
+    (if (i32.or
+          (i32.lt_s (local.get $addr) (i32.const 0))
+          (i32.gt_s (i32.add (local.get $addr) (local.get $size)) (global.get $capacity)))
+      (then (call $panic (i32.const 7))))
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_s (local.get $i) (local.get $size)))
+        (if (i32.and
+              (i32.ge_s (i32.add (local.get $addr) (local.get $i)) (local.get $stack_top))
+              (i32.eqz (i32.load8_u (call $flag (i32.add (local.get $addr) (local.get $i))))))
+          (then (call $panic (i32.const 7))))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $next))))
+
+  (func $machine_checked_store (param $size i32)
+    (local $addr i32)
+    (local.set $addr (call $to_int (f64.load (call $cell (i32.sub (global.get $stack_ptr) (i32.const 1))))))
+    ;; The value being stored is popped off of the stack first
+    (if (i32.le_s (global.get $stack_ptr) (local.get $size))
+      (then (call $panic (i32.const 3))))
+    (call $machine_check_access
+      (local.get $addr)
+      (local.get $size)
+      (i32.sub (global.get $stack_ptr) (i32.add (local.get $size) (i32.const 1))))
+    (call $machine_store (local.get $size)))
+
+  (func $machine_checked_load (param $size i32)
+    (local $addr i32)
+    (local.set $addr (call $to_int (f64.load (call $cell (i32.sub (global.get $stack_ptr) (i32.const 1))))))
+    (call $machine_check_access
+      (local.get $addr)
+      (local.get $size)
+      (i32.sub (global.get $stack_ptr) (i32.const 1)))
+    (call $machine_load (local.get $size)))
+
+  (func $machine_memset (param $size i32)
+    (local $count i32)
+    (local $value i32)
+    (local $addr i32)
+    (local $i i32)
+    (local.set $count (call $to_int (call $machine_pop)))
+    ;; The value is left on the stack, and copied from there
+    (local.set $value (i32.sub (global.get $stack_ptr) (local.get $size)))
+    (local.set $addr (call $to_int (f64.load (call $cell (i32.sub (local.get $value) (i32.const 1))))))
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_s (local.get $i) (local.get $count)))
+        (memory.copy
+          (call $cell (i32.add (local.get $addr) (i32.mul (local.get $i) (local.get $size))))
+          (call $cell (local.get $value))
+          (i32.shl (local.get $size) (i32.const 3)))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $next)))
+
+    ;; Pop the value and the address
+    (local.set $i (i32.add (local.get $size) (i32.const 1)))
+    (block $done
+      (loop $next
+        (br_if $done (i32.eqz (local.get $i)))
+        (drop (call $machine_pop))
+        (local.set $i (i32.sub (local.get $i) (i32.const 1)))
+        (br $next))))
+
+  ;; Each cell holds the bytes of a little-endian double, like the
+  ;; C backend on most machines, so the memory starting at an address
+  ;; can be used as a byte array. Pop an index and an address, and push
+  ;; the byte at that index of the memory.
+  (func $machine_load_byte
+    (local $index i32)
+    (local $addr i32)
+    (local.set $index (call $to_int (call $machine_pop)))
+    (local.set $addr (call $to_int (call $machine_pop)))
+    (call $machine_push
+      (f64.convert_i32_u
+        (i32.load8_u (i32.add (call $cell (local.get $addr)) (local.get $index))))))
+
+  ;; Pop a byte, an index, and an address, and store
+  ;; the byte at that index of the memory at the address.
+  (func $machine_store_byte
+    (local $byte i32)
+    (local $index i32)
+    (local $addr i32)
+    (local.set $byte (call $to_int (call $machine_pop)))
+    (local.set $index (call $to_int (call $machine_pop)))
+    (local.set $addr (call $to_int (call $machine_pop)))
+    (i32.store8
+      (i32.add (call $cell (local.get $addr)) (local.get $index))
+      (local.get $byte)))
+
+  ;; Panic unless the byte at `index` of the memory at `addr` can be accessed.
+  ;; The index may be past the first cell, but neither it nor the address can be
+  ;; negative, and the cell holding the byte must be accessible. Used by the debug runtime.
+  (func $machine_check_byte_access (param $addr i32) (param $index i32) (param $stack_top i32)
+    (if (i32.or
+          (i32.lt_s (local.get $addr) (i32.const 0))
+          (i32.lt_s (local.get $index) (i32.const 0)))
+      (then (call $panic (i32.const 7))))
+    (call $machine_check_access
+      (i32.add (local.get $addr) (i32.div_s (local.get $index) (i32.const 8)))
+      (i32.const 1)
+      (local.get $stack_top)))
+
+  (func $machine_checked_load_byte
+    ;; The index and the address are checked before they're popped
+    (call $machine_check_byte_access
+      (call $to_int (f64.load (call $cell (i32.sub (global.get $stack_ptr) (i32.const 2)))))
+      (call $to_int (f64.load (call $cell (i32.sub (global.get $stack_ptr) (i32.const 1)))))
+      (i32.sub (global.get $stack_ptr) (i32.const 2)))
+    (call $machine_load_byte))
+
+  (func $machine_checked_store_byte
+    ;; The byte, the index, and the address are checked before they're popped
+    (call $machine_check_byte_access
+      (call $to_int (f64.load (call $cell (i32.sub (global.get $stack_ptr) (i32.const 3)))))
+      (call $to_int (f64.load (call $cell (i32.sub (global.get $stack_ptr) (i32.const 2)))))
+      (i32.sub (global.get $stack_ptr) (i32.const 3)))
+    (call $machine_store_byte))
+
+  ;; Push a random number in [0, 1), using the Mulberry32 generator.
+  ;; The numbers are the same in every run of the program, unless
+  ;; it's seeded differently.
+  (func $machine_random
+    (local $t i32)
+    (global.set $random_state (i32.add (global.get $random_state) (i32.const 0x6d2b79f5)))
+    (local.set $t (global.get $random_state))
+    (local.set $t
+      (i32.mul
+        (i32.xor (local.get $t) (i32.shr_u (local.get $t) (i32.const 15)))
+        (i32.or (local.get $t) (i32.const 1))))
+    (local.set $t
+      (i32.xor
+        (local.get $t)
+        (i32.add
+          (local.get $t)
+          (i32.mul
+            (i32.xor (local.get $t) (i32.shr_u (local.get $t) (i32.const 7)))
+            (i32.or (local.get $t) (i32.const 61))))))
+    (call $machine_push
+      (f64.div
+        (f64.convert_i32_u (i32.xor (local.get $t) (i32.shr_u (local.get $t) (i32.const 14))))
+        (f64.const 4294967296))))
+
+  ;; Pop a seed, and start a new sequence of random numbers with it
+  (func $machine_seed_random
+    (global.set $random_state (i32.wrap_i64 (i64.trunc_sat_f64_s (call $machine_pop)))))
+
+  ;; Push the number of seconds since the program started
+  (func $machine_clock
+    (call $machine_push (f64.sub (call $host_now) (global.get $start))))
+
+  (func $machine_add
+    (local $b f64)
+    (local.set $b (call $machine_pop))
+    (call $machine_push (f64.add (call $machine_pop) (local.get $b))))
+
+  (func $machine_subtract
+    (local $b f64)
+    (local.set $b (call $machine_pop))
+    (call $machine_push (f64.sub (call $machine_pop) (local.get $b))))
+
+  (func $machine_multiply
+    (local $b f64)
+    (local.set $b (call $machine_pop))
+    (call $machine_push (f64.mul (call $machine_pop) (local.get $b))))
+
+  (func $machine_divide
+    (local $b f64)
+    (local.set $b (call $machine_pop))
+    (call $machine_push (f64.div (call $machine_pop) (local.get $b))))
+
+  ;; WebAssembly has no remainder instruction for floats, so the remainder is
+  ;; found like `fmod`: the largest multiple of the divisor by a power of two
+  ;; that fits is subtracted from the dividend, then the next smaller one, and
+  ;; so on. Each subtraction is exact, so the remainder is too. It has the same
+  ;; sign as the dividend.
+  (func $fmod (param $a f64) (param $b f64) (result f64)
+    (local $r f64)
+    (local $m f64)
+    (local $d f64)
+    ;; An infinite dividend or a zero divisor has no remainder
+    (if (i32.or
+          (f64.ne (f64.sub (local.get $a) (local.get $a)) (f64.const 0))
+          (i32.or
+            (f64.eq (local.get $b) (f64.const 0))
+            (f64.ne (local.get $b) (local.get $b))))
+      (then (return (f64.const nan))))
+    (local.set $r (f64.abs (local.get $a)))
+    (local.set $m (f64.abs (local.get $b)))
+    ;; Dividing by a larger or infinite number leaves the dividend as it is
+    (if (f64.lt (local.get $r) (local.get $m))
+      (then (return (local.get $a))))
+    ;; Find the largest multiple to subtract, which is at most the dividend
+    (local.set $d (local.get $m))
+    (block $found
+      (loop $double
+        (br_if $found (f64.gt (f64.mul (local.get $d) (f64.const 2)) (local.get $r)))
+        (local.set $d (f64.mul (local.get $d) (f64.const 2)))
+        (br $double)))
+    ;; Subtract each smaller multiple that still fits, down to the divisor
+    (block $done
+      (loop $subtract
+        (if (f64.ge (local.get $r) (local.get $d))
+          (then (local.set $r (f64.sub (local.get $r) (local.get $d)))))
+        (br_if $done (f64.eq (local.get $d) (local.get $m)))
+        (local.set $d (f64.div (local.get $d) (f64.const 2)))
+        (br $subtract)))
+    (f64.copysign (local.get $r) (local.get $a)))
+
+  (func $machine_modulo
+    (local $b f64)
+    (local.set $b (call $machine_pop))
+    (call $machine_push (call $fmod (call $machine_pop) (local.get $b))))
+
+  (func $machine_sign
+    (local $x f64)
+    (local.set $x (call $machine_pop))
+    (call $machine_push
+      (f64.convert_i32_s
+        (i32.sub
+          (f64.gt (local.get $x) (f64.const 0))
+          (f64.lt (local.get $x) (f64.const 0))))))
+
+  ;; Convert a number to an integer that wraps around at `size`, which is two
+  ;; to the power of its width in bits. The fraction is discarded, like a C integer.
+  (func $wrap_integer (param $x f64) (param $size f64) (param $is_signed i32) (result f64)
+    (local $n f64)
+    ;; Infinite numbers and NaN have no integer value, so they become zero
+    (if (f64.eq (f64.sub (local.get $x) (local.get $x)) (f64.const 0))
+      (then
+        (local.set $n (f64.trunc (local.get $x)))
+        (local.set $n
+          (f64.sub
+            (local.get $n)
+            (f64.mul
+              (local.get $size)
+              (f64.floor (f64.div (local.get $n) (local.get $size))))))
+        (if (i32.and
+              (local.get $is_signed)
+              (f64.ge (local.get $n) (f64.div (local.get $size) (f64.const 2))))
+          (then (local.set $n (f64.sub (local.get $n) (local.get $size)))))))
+    (local.get $n))
+
+  ;; Pop a number, and push it as an integer that wraps around at `size`, which is
+  ;; two to the power of its width in bits. The fraction is discarded, like a C integer.
+  (func $machine_truncate (param $size f64) (param $is_signed i32)
+    (call $machine_push
+      (call $wrap_integer (call $machine_pop) (local.get $size) (local.get $is_signed))))
+
+  ;; Pop a number as a 32 bit signed integer for the bitwise operations
+  (func $pop_int32 (result i32)
+    (i32.trunc_f64_s
+      (call $wrap_integer (call $machine_pop) (f64.const 4294967296) (i32.const 1))))
+
+  (func $machine_bitand
+    (local $b i32)
+    (local.set $b (call $pop_int32))
+    (call $machine_push
+      (f64.convert_i32_s (i32.and (call $pop_int32) (local.get $b)))))
+
+  (func $machine_bitor
+    (local $b i32)
+    (local.set $b (call $pop_int32))
+    (call $machine_push
+      (f64.convert_i32_s (i32.or (call $pop_int32) (local.get $b)))))
+
+  (func $machine_bitxor
+    (local $b i32)
+    (local.set $b (call $pop_int32))
+    (call $machine_push
+      (f64.convert_i32_s (i32.xor (call $pop_int32) (local.get $b)))))
+
+  ;; Only the lowest 5 bits of the count of a shift are used
+  (func $machine_shl
+    (local $b i32)
+    (local.set $b (call $pop_int32))
+    (call $machine_push
+      (f64.convert_i32_s (i32.shl (call $pop_int32) (local.get $b)))))
+
+  (func $machine_shr
+    (local $b i32)
+    (local.set $b (call $pop_int32))
+    (call $machine_push
+      (f64.convert_i32_s (i32.shr_s (call $pop_int32) (local.get $b)))))
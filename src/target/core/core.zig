@@ -0,0 +1,694 @@
+const std = @import("std");
+
+const STACK_HEAP_COLLISION = 1;
+const NO_FREE_MEMORY = 2;
+const STACK_UNDERFLOW = 3;
+const INVALID_FREE = 4;
+const UNBALANCED_STACK = 5;
+const UNTERMINATED_STRING = 6;
+const INVALID_ACCESS = 7;
+
+const allocator = std.heap.page_allocator;
+
+// Output is written to this buffer, and only
+// written to stdout when the machine is flushed.
+var output: [4096]u8 = undefined;
+var output_len: usize = 0;
+
+fn write_byte(byte: u8) void {
+    if (output_len == output.len) {
+        flush_output();
+    }
+    output[output_len] = byte;
+    output_len += 1;
+}
+
+fn write_bytes(bytes: []const u8) void {
+    for (bytes) |byte| {
+        write_byte(byte);
+    }
+}
+
+fn flush_output() void {
+    std.io.getStdOut().writeAll(output[0..output_len]) catch {};
+    output_len = 0;
+}
+
+// When a program is compiled with `--trace`, the compiler defines
+// `TRACE` as true, and each operation logs its effect to stderr.
+// Operations are built out of other operations, like `add` pushing
+// its result, so only the outermost operation is logged.
+var trace_depth: usize = 0;
+
+fn trace_enter() void {
+    if (TRACE) {
+        trace_depth += 1;
+    }
+}
+
+// Is the operation that just finished the outermost one?
+fn trace_exit() bool {
+    if (TRACE) {
+        trace_depth -= 1;
+        return trace_depth == 0;
+    }
+    return false;
+}
+
+// This isn't named `panic`, because a `panic` declared
+// in the root file replaces Zig's own panic handler.
+fn machine_panic(code: u8) noreturn {
+    write_bytes("panic: ");
+    write_bytes(switch (code) {
+        1 => "stack and heap collision during push\n",
+        2 => "no free memory left\n",
+        3 => "stack underflow\n",
+        4 => "freed memory that is not allocated\n",
+        5 => "stack is not balanced at the end of the program\n",
+        6 => "string is not terminated before the end of memory\n",
+        7 => "accessed memory that is not allocated\n",
+        else => "unknown error code\n",
+    });
+    flush_output();
+    std.process.exit(code);
+}
+
+// Convert a number to an integer with `bits` bits. The fraction is
+// discarded, and the integer wraps around like a C integer of that width.
+fn wrap_integer(x: f64, bits: usize, is_signed: bool) f64 {
+    const size = std.math.pow(f64, 2, @floatFromInt(bits));
+    // Infinite numbers and NaN have no integer value, so they become zero
+    var n: f64 = 0;
+    if (!std.math.isNan(x) and !std.math.isInf(x)) {
+        n = @mod(@trunc(x), size);
+        if (is_signed and n >= size / 2) {
+            n -= size;
+        }
+    }
+    return n;
+}
+
+// Convert a cell to an address or a size. Cells that
+// aren't whole, positive numbers are never valid addresses.
+fn to_index(n: f64) usize {
+    if (!(n >= 0) or n >= @as(f64, @floatFromInt(std.math.maxInt(u32)))) {
+        machine_panic(INVALID_ACCESS);
+    }
+    return @intFromFloat(n);
+}
+
+const Machine = struct {
+    memory: []f64,
+    allocated: []bool,
+    capacity: usize,
+    base_ptr: usize,
+    stack_ptr: usize,
+    // The number of cells allocated on the heap, and the most
+    // that have been allocated at once during the program.
+    heap_used: usize,
+    heap_peak: usize,
+    // The host's state, which is passed to the foreign
+    // functions marked with `#[context]`. It starts as null.
+    context: ?*anyopaque,
+    // The state of the machine's random numbers, which starts as 1
+    random_state: u32,
+    // The time the machine was created, which the clock starts at
+    start: i128,
+
+    fn new(global_scope_size: usize, capacity: usize) *Machine {
+        const vm = allocator.create(Machine) catch machine_panic(NO_FREE_MEMORY);
+        const memory = allocator.alloc(f64, capacity) catch machine_panic(NO_FREE_MEMORY);
+        const allocated = allocator.alloc(bool, capacity) catch machine_panic(NO_FREE_MEMORY);
+        @memset(memory, 0);
+        @memset(allocated, false);
+        vm.* = Machine{
+            .memory = memory,
+            .allocated = allocated,
+            .capacity = capacity,
+            .base_ptr = 0,
+            .stack_ptr = 0,
+            .heap_used = 0,
+            .heap_peak = 0,
+            .context = null,
+            .random_state = 1,
+            .start = std.time.nanoTimestamp(),
+        };
+        var i: usize = 0;
+        while (i < global_scope_size) : (i += 1) {
+            vm.push(0);
+        }
+        return vm;
+    }
+
+    // When a program is compiled with `--heap-stats`, the compiler defines
+    // `HEAP_STATS` as true, and the most cells that were allocated on the
+    // heap at once is reported to stderr at the end of the program.
+    fn drop(vm: *Machine) void {
+        vm.flush();
+        if (HEAP_STATS) {
+            std.debug.print("heap high-water mark: {d} cells\n", .{vm.heap_peak});
+        }
+        allocator.free(vm.memory);
+        allocator.free(vm.allocated);
+        allocator.destroy(vm);
+    }
+
+    // Write all of the buffered output to stdout
+    fn flush(vm: *Machine) void {
+        _ = vm;
+        flush_output();
+    }
+
+    fn load_base_ptr(vm: *Machine) void {
+        // Get the virtual machine's current base pointer value,
+        // and push it onto the stack.
+        vm.push(@floatFromInt(vm.base_ptr));
+    }
+
+    fn establish_stack_frame(vm: *Machine, arg_size: usize, local_scope_size: usize) void {
+        trace_enter();
+        // Allocate some space to store the arguments' cells for later
+        const args = allocator.alloc(f64, arg_size) catch machine_panic(NO_FREE_MEMORY);
+        defer allocator.free(args);
+        // Pop the arguments' values off of the stack
+        var i: usize = arg_size;
+        while (i > 0) {
+            i -= 1;
+            args[i] = vm.pop();
+        }
+
+        // Push the current base pointer onto the stack so that
+        // when this function returns, it will be able to resume
+        // the current stack frame
+        vm.load_base_ptr();
+
+        // Set the base pointer to the current stack pointer to
+        // begin the stack frame at the current position on the stack.
+        vm.base_ptr = vm.stack_ptr;
+
+        // Allocate space for all the variables used in the local scope on the stack
+        i = 0;
+        while (i < local_scope_size) : (i += 1) {
+            vm.push(0);
+        }
+
+        // Push the arguments back onto the stack for use by the current function
+        for (args) |arg| {
+            vm.push(arg);
+        }
+        if (trace_exit()) {
+            std.debug.print("call with {d} argument cells and {d} local cells\n", .{ arg_size, local_scope_size });
+        }
+    }
+
+    fn end_stack_frame(vm: *Machine, return_size: usize, local_scope_size: usize) void {
+        trace_enter();
+        // Allocate some space to store the returned cells for later
+        const return_val = allocator.alloc(f64, return_size) catch machine_panic(NO_FREE_MEMORY);
+        defer allocator.free(return_val);
+        // Pop the returned values off of the stack
+        var i: usize = return_size;
+        while (i > 0) {
+            i -= 1;
+            return_val[i] = vm.pop();
+        }
+
+        // Discard the memory setup by the stack frame
+        i = 0;
+        while (i < local_scope_size) : (i += 1) {
+            _ = vm.pop();
+        }
+
+        // Retrieve the parent function's base pointer to resume the function
+        vm.base_ptr = to_index(vm.pop());
+
+        // Finally, push the returned value back onto the stack for use by
+        // the parent function.
+        for (return_val) |cell| {
+            vm.push(cell);
+        }
+        if (trace_exit()) {
+            std.debug.print("return {d} cells\n", .{return_size});
+        }
+    }
+
+    fn push(vm: *Machine, n: f64) void {
+        trace_enter();
+        if (vm.stack_ptr >= vm.capacity or vm.allocated[vm.stack_ptr]) {
+            machine_panic(STACK_HEAP_COLLISION);
+        }
+        vm.memory[vm.stack_ptr] = n;
+        vm.stack_ptr += 1;
+        if (trace_exit()) {
+            std.debug.print("push {d}\n", .{n});
+        }
+    }
+
+    fn pop(vm: *Machine) f64 {
+        if (vm.stack_ptr == 0) {
+            machine_panic(STACK_UNDERFLOW);
+        }
+        vm.stack_ptr -= 1;
+        const result = vm.memory[vm.stack_ptr];
+        vm.memory[vm.stack_ptr] = 0;
+        return result;
+    }
+
+    fn alloc(vm: *Machine) void {
+        trace_enter();
+        const size = to_index(vm.pop());
+        var addr: usize = 0;
+        var consecutive_free_cells: usize = 0;
+
+        var i: usize = vm.capacity;
+        while (i > vm.stack_ptr + 1) {
+            i -= 1;
+            if (!vm.allocated[i]) {
+                consecutive_free_cells += 1;
+            } else {
+                consecutive_free_cells = 0;
+            }
+
+            if (consecutive_free_cells == size) {
+                addr = i;
+                break;
+            }
+        }
+
+        // If the heap is full, push null, so that the
+        // program can check whether the allocation failed
+        if (addr <= vm.stack_ptr) {
+            vm.push(0);
+            if (trace_exit()) {
+                std.debug.print("allocate {d} cells failed\n", .{size});
+            }
+            return;
+        }
+
+        @memset(vm.allocated[addr .. addr + size], true);
+        vm.heap_used += size;
+        if (vm.heap_used > vm.heap_peak) {
+            vm.heap_peak = vm.heap_used;
+        }
+
+        vm.push(@floatFromInt(addr));
+        if (trace_exit()) {
+            std.debug.print("allocate {d} cells at {d}\n", .{ size, addr });
+        }
+    }
+
+    fn free(vm: *Machine) void {
+        trace_enter();
+        const addr = to_index(vm.pop());
+        var size = to_index(vm.pop());
+        // Freeing null, from an allocation that failed, does nothing
+        if (addr == 0) {
+            size = 0;
+        }
+
+        var i: usize = 0;
+        while (i < size) : (i += 1) {
+            if (vm.allocated[addr + i]) {
+                vm.heap_used -= 1;
+            }
+            vm.allocated[addr + i] = false;
+            vm.memory[addr + i] = 0;
+        }
+        if (trace_exit()) {
+            std.debug.print("free {d} cells at {d}\n", .{ size, addr });
+        }
+    }
+
+    fn checked_free(vm: *Machine) void {
+        trace_enter();
+        const addr = to_index(vm.pop());
+        var size = to_index(vm.pop());
+        // Freeing null, from an allocation that failed, does nothing
+        if (addr == 0) {
+            size = 0;
+        }
+
+        if (addr + size > vm.capacity) {
+            machine_panic(INVALID_FREE);
+        }
+        for (vm.allocated[addr .. addr + size]) |is_allocated| {
+            if (!is_allocated) {
+                machine_panic(INVALID_FREE);
+            }
+        }
+
+        @memset(vm.allocated[addr .. addr + size], false);
+        @memset(vm.memory[addr .. addr + size], 0);
+        vm.heap_used -= size;
+        if (trace_exit()) {
+            std.debug.print("free {d} cells at {d}\n", .{ size, addr });
+        }
+    }
+
+    // Check that the stack holds exactly `size` cells: the global scope, and
+    // the value returned by the entry point. Used by the debug runtime.
+    fn check_stack(vm: *Machine, size: usize) void {
+        if (vm.stack_ptr != size) {
+            machine_panic(UNBALANCED_STACK);
+        }
+    }
+
+    fn load(vm: *Machine, size: usize) void {
+        trace_enter();
+        const addr = to_index(vm.pop());
+        var i: usize = 0;
+        while (i < size) : (i += 1) {
+            vm.push(vm.memory[addr + i]);
+        }
+        if (trace_exit()) {
+            std.debug.print("load {d} cells from {d}\n", .{ size, addr });
+        }
+    }
+
+    fn store(vm: *Machine, size: usize) void {
+        trace_enter();
+        const addr = to_index(vm.pop());
+        var i: usize = size;
+        while (i > 0) {
+            i -= 1;
+            vm.memory[addr + i] = vm.pop();
+        }
+        if (trace_exit()) {
+            std.debug.print("store {d} cells at {d}\n", .{ size, addr });
+        }
+    }
+
+    // Panic unless `size` cells starting at `addr` can be accessed. They must be in memory,
+    // and each cell at or above `stack_top` must be allocated on the heap, because the cells
+    // between the stack and the heap are free. Used by the debug runtime.
+    fn check_access(vm: *Machine, addr: usize, size: usize, stack_top: usize) void {
+        if (addr + size > vm.capacity) {
+            machine_panic(INVALID_ACCESS);
+        }
+        var i: usize = 0;
+        while (i < size) : (i += 1) {
+            if (addr + i >= stack_top and !vm.allocated[addr + i]) {
+                machine_panic(INVALID_ACCESS);
+            }
+        }
+    }
+
+    fn checked_load(vm: *Machine, size: usize) void {
+        trace_enter();
+        const addr = to_index(vm.pop());
+        vm.check_access(addr, size, vm.stack_ptr);
+        var i: usize = 0;
+        while (i < size) : (i += 1) {
+            vm.push(vm.memory[addr + i]);
+        }
+        if (trace_exit()) {
+            std.debug.print("load {d} cells from {d}\n", .{ size, addr });
+        }
+    }
+
+    fn checked_store(vm: *Machine, size: usize) void {
+        trace_enter();
+        const addr = to_index(vm.pop());
+        // The value being stored is popped off of the stack first
+        if (vm.stack_ptr < size) {
+            machine_panic(STACK_UNDERFLOW);
+        }
+        vm.check_access(addr, size, vm.stack_ptr - size);
+        var i: usize = size;
+        while (i > 0) {
+            i -= 1;
+            vm.memory[addr + i] = vm.pop();
+        }
+        if (trace_exit()) {
+            std.debug.print("store {d} cells at {d}\n", .{ size, addr });
+        }
+    }
+
+    fn memset(vm: *Machine, size: usize) void {
+        const count = to_index(vm.pop());
+        const value = allocator.alloc(f64, size) catch machine_panic(NO_FREE_MEMORY);
+        defer allocator.free(value);
+        var j: usize = size;
+        while (j > 0) {
+            j -= 1;
+            value[j] = vm.pop();
+        }
+        const addr = to_index(vm.pop());
+        var i: usize = 0;
+        while (i < count) : (i += 1) {
+            @memcpy(vm.memory[addr + i * size .. addr + (i + 1) * size], value);
+        }
+    }
+
+    fn store_data(vm: *Machine, address: usize, data: []const f64) void {
+        @memcpy(vm.memory[address .. address + data.len], data);
+    }
+
+    // Each cell holds the bytes of a double, in little-endian order like
+    // the C backend on most machines, so the memory starting at an address
+    // can be used as a byte array. Pop an index and an address, and push
+    // the byte at that index of the memory.
+    fn load_byte(vm: *Machine) void {
+        trace_enter();
+        const index = to_index(vm.pop());
+        const addr = to_index(vm.pop());
+        const bits: u64 = @bitCast(vm.memory[addr + index / 8]);
+        const shift: u6 = @intCast(8 * (index % 8));
+        const byte: u8 = @truncate(bits >> shift);
+        vm.push(@floatFromInt(byte));
+        if (trace_exit()) {
+            std.debug.print("load byte {d} of {d} -> {d}\n", .{ index, addr, byte });
+        }
+    }
+
+    // Pop a byte, an index, and an address, and store
+    // the byte at that index of the memory at the address.
+    fn store_byte(vm: *Machine) void {
+        trace_enter();
+        const byte: u64 = @as(u8, @intFromFloat(@mod(vm.pop(), 256)));
+        const index = to_index(vm.pop());
+        const addr = to_index(vm.pop());
+        const shift: u6 = @intCast(8 * (index % 8));
+        var bits: u64 = @bitCast(vm.memory[addr + index / 8]);
+        bits = (bits & ~(@as(u64, 0xff) << shift)) | (byte << shift);
+        vm.memory[addr + index / 8] = @bitCast(bits);
+        if (trace_exit()) {
+            std.debug.print("store byte {d} at byte {d} of {d}\n", .{ byte, index, addr });
+        }
+    }
+
+    // Load a byte like `load_byte`, but first check that the cell holding
+    // the byte can be accessed. Used by the debug runtime.
+    fn checked_load_byte(vm: *Machine) void {
+        trace_enter();
+        const index = to_index(vm.pop());
+        const addr = to_index(vm.pop());
+        vm.check_access(addr + index / 8, 1, vm.stack_ptr);
+        const bits: u64 = @bitCast(vm.memory[addr + index / 8]);
+        const shift: u6 = @intCast(8 * (index % 8));
+        const byte: u8 = @truncate(bits >> shift);
+        vm.push(@floatFromInt(byte));
+        if (trace_exit()) {
+            std.debug.print("load byte {d} of {d} -> {d}\n", .{ index, addr, byte });
+        }
+    }
+
+    // Store a byte like `store_byte`, but first check that the cell
+    // holding the byte can be accessed. Used by the debug runtime.
+    fn checked_store_byte(vm: *Machine) void {
+        trace_enter();
+        const byte: u64 = @as(u8, @intFromFloat(@mod(vm.pop(), 256)));
+        const index = to_index(vm.pop());
+        const addr = to_index(vm.pop());
+        vm.check_access(addr + index / 8, 1, vm.stack_ptr);
+        const shift: u6 = @intCast(8 * (index % 8));
+        var bits: u64 = @bitCast(vm.memory[addr + index / 8]);
+        bits = (bits & ~(@as(u64, 0xff) << shift)) | (byte << shift);
+        vm.memory[addr + index / 8] = @bitCast(bits);
+        if (trace_exit()) {
+            std.debug.print("store byte {d} at byte {d} of {d}\n", .{ byte, index, addr });
+        }
+    }
+
+    // Push a random number in [0, 1), with the Mulberry32 generator like
+    // the Rust runtime. The numbers are the same in every run, unless the
+    // program is seeded.
+    fn random(vm: *Machine) void {
+        trace_enter();
+        vm.random_state +%= 0x6d2b79f5;
+        var t = vm.random_state;
+        t = (t ^ (t >> 15)) *% (t | 1);
+        t ^= t +% ((t ^ (t >> 7)) *% (t | 61));
+        const n = @as(f64, @floatFromInt(t ^ (t >> 14))) / 4294967296.0;
+        vm.push(n);
+        if (trace_exit()) {
+            std.debug.print("random -> {d}\n", .{n});
+        }
+    }
+
+    // Pop a seed, and start a new sequence of random numbers with it
+    fn seed_random(vm: *Machine) void {
+        trace_enter();
+        const seed = vm.pop();
+        // Infinite numbers and NaN have no integer value, so they become zero
+        var n: f64 = 0;
+        if (!std.math.isNan(seed) and !std.math.isInf(seed)) {
+            n = @mod(@trunc(seed), 4294967296.0);
+        }
+        vm.random_state = @intFromFloat(n);
+        if (trace_exit()) {
+            std.debug.print("seed random with {d}\n", .{seed});
+        }
+    }
+
+    // Push the number of seconds since the program started
+    fn clock(vm: *Machine) void {
+        trace_enter();
+        const elapsed = std.time.nanoTimestamp() - vm.start;
+        const seconds = @as(f64, @floatFromInt(elapsed)) / std.time.ns_per_s;
+        vm.push(seconds);
+        if (trace_exit()) {
+            std.debug.print("clock -> {d}\n", .{seconds});
+        }
+    }
+
+    fn add(vm: *Machine) void {
+        trace_enter();
+        const b = vm.pop();
+        const a = vm.pop();
+        vm.push(a + b);
+        if (trace_exit()) {
+            std.debug.print("add {d} {d} -> {d}\n", .{ a, b, a + b });
+        }
+    }
+
+    fn subtract(vm: *Machine) void {
+        trace_enter();
+        const b = vm.pop();
+        const a = vm.pop();
+        vm.push(a - b);
+        if (trace_exit()) {
+            std.debug.print("subtract {d} {d} -> {d}\n", .{ a, b, a - b });
+        }
+    }
+
+    fn multiply(vm: *Machine) void {
+        trace_enter();
+        const b = vm.pop();
+        const a = vm.pop();
+        vm.push(a * b);
+        if (trace_exit()) {
+            std.debug.print("multiply {d} {d} -> {d}\n", .{ a, b, a * b });
+        }
+    }
+
+    fn divide(vm: *Machine) void {
+        trace_enter();
+        const b = vm.pop();
+        const a = vm.pop();
+        vm.push(a / b);
+        if (trace_exit()) {
+            std.debug.print("divide {d} {d} -> {d}\n", .{ a, b, a / b });
+        }
+    }
+
+    fn modulo(vm: *Machine) void {
+        trace_enter();
+        const b = vm.pop();
+        const a = vm.pop();
+        vm.push(@rem(a, b));
+        if (trace_exit()) {
+            std.debug.print("modulo {d} {d} -> {d}\n", .{ a, b, @rem(a, b) });
+        }
+    }
+
+    fn sign(vm: *Machine) void {
+        trace_enter();
+        const x = vm.pop();
+        if (x > 0) {
+            vm.push(1);
+        } else if (x < 0) {
+            vm.push(-1);
+        } else {
+            vm.push(0);
+        }
+        if (trace_exit()) {
+            std.debug.print("sign {d} -> {d}\n", .{ x, vm.memory[vm.stack_ptr - 1] });
+        }
+    }
+
+    // Pop a number, and push it as an integer with `bits` bits. The fraction is
+    // discarded, and the integer wraps around like a C integer of that width.
+    fn truncate(vm: *Machine, bits: usize, is_signed: bool) void {
+        trace_enter();
+        const x = vm.pop();
+        const n = wrap_integer(x, bits, is_signed);
+        vm.push(n);
+        if (trace_exit()) {
+            std.debug.print("truncate {d} to {d} bits -> {d}\n", .{ x, bits, n });
+        }
+    }
+
+    // Pop a number as a 32 bit signed integer for the bitwise operations
+    fn pop_int32(vm: *Machine) i32 {
+        return @intFromFloat(wrap_integer(vm.pop(), 32, true));
+    }
+
+    fn bitand(vm: *Machine) void {
+        trace_enter();
+        const b = vm.pop_int32();
+        const a = vm.pop_int32();
+        const n = a & b;
+        vm.push(@floatFromInt(n));
+        if (trace_exit()) {
+            std.debug.print("bitand {d} {d} -> {d}\n", .{ a, b, n });
+        }
+    }
+
+    fn bitor(vm: *Machine) void {
+        trace_enter();
+        const b = vm.pop_int32();
+        const a = vm.pop_int32();
+        const n = a | b;
+        vm.push(@floatFromInt(n));
+        if (trace_exit()) {
+            std.debug.print("bitor {d} {d} -> {d}\n", .{ a, b, n });
+        }
+    }
+
+    fn bitxor(vm: *Machine) void {
+        trace_enter();
+        const b = vm.pop_int32();
+        const a = vm.pop_int32();
+        const n = a ^ b;
+        vm.push(@floatFromInt(n));
+        if (trace_exit()) {
+            std.debug.print("bitxor {d} {d} -> {d}\n", .{ a, b, n });
+        }
+    }
+
+    // The bits shifted past the sign are lost, so the shift is done on an unsigned integer
+    fn shl(vm: *Machine) void {
+        trace_enter();
+        const b = vm.pop_int32();
+        const a = vm.pop_int32();
+        const shift: u5 = @intCast(b & 31);
+        const n: i32 = @bitCast(@as(u32, @bitCast(a)) << shift);
+        vm.push(@floatFromInt(n));
+        if (trace_exit()) {
+            std.debug.print("shl {d} {d} -> {d}\n", .{ a, b, n });
+        }
+    }
+
+    fn shr(vm: *Machine) void {
+        trace_enter();
+        const b = vm.pop_int32();
+        const a = vm.pop_int32();
+        const shift: u5 = @intCast(b & 31);
+        const n = a >> shift;
+        vm.push(@floatFromInt(n));
+        if (trace_exit()) {
+            std.debug.print("shr {d} {d} -> {d}\n", .{ a, b, n });
+        }
+    }
+};
@@ -0,0 +1,452 @@
+use std::{borrow::Cow, path::PathBuf};
+
+mod c;
+pub use c::C;
+mod go;
+pub use go::Go;
+mod rb;
+pub use rb::Ruby;
+mod rs;
+pub use rs::Rust;
+mod ts;
+pub use ts::TS;
+mod wat;
+pub use wat::Wat;
+mod zig;
+pub use zig::Zig;
+
+/// Indent code whose blocks are delimited by curly braces.
+/// Braces inside of string and character literals are ignored.
+fn indent_braces(code: &str, indent: &str) -> String {
+    let mut result = String::new();
+    let mut depth: usize = 0;
+    for line in code.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        // Count the braces that open and close blocks on this line
+        let (mut opens, mut closes) = (0, 0);
+        let mut quote = None;
+        let mut escaped = false;
+        for ch in line.chars() {
+            match quote {
+                Some(_) if escaped => escaped = false,
+                Some(_) if ch == '\\' => escaped = true,
+                Some(q) if ch == q => quote = None,
+                Some(_) => {}
+                None => match ch {
+                    '"' | '\'' | '`' => quote = Some(ch),
+                    '{' => opens += 1,
+                    '}' => closes += 1,
+                    _ => {}
+                },
+            }
+        }
+
+        // A line starting with a closing brace belongs to the outer block
+        let level = if line.starts_with('}') {
+            depth.saturating_sub(1)
+        } else {
+            depth
+        };
+        result += &indent.repeat(level);
+        result += line;
+        result += "\n";
+        depth = (depth + opens).saturating_sub(closes);
+    }
+    result
+}
+
+/// Cut an identifier down to `limit` characters, ending it with an
+/// underscore and the hexadecimal FNV-1a hash of the whole identifier.
+fn shorten_identifier(name: &str, limit: usize) -> String {
+    let mut hash: u32 = 0x811c_9dc5;
+    for byte in name.bytes() {
+        hash ^= byte as u32;
+        hash = hash.wrapping_mul(0x0100_0193);
+    }
+    let hash = format!("_{:08x}", hash);
+
+    // Compiler generated identifiers are ASCII, so they can be cut at any byte
+    let kept = limit.saturating_sub(hash.len());
+    let mut result = String::from(&name[..kept]) + &hash;
+    result.truncate(limit);
+    result
+}
+
+/// A description of how a program was built, which
+/// `Target::manifest` writes at the start of the output code.
+pub struct BuildInfo {
+    /// The version of the compiler
+    pub version: &'static str,
+    /// The human readable name of the output language
+    pub target: &'static str,
+    /// The time the program was compiled at
+    pub timestamp: String,
+    /// The name of the input file
+    pub filename: String,
+    /// The number of cells of memory for the stack and heap
+    pub memory_size: i32,
+}
+
+impl BuildInfo {
+    /// Get each line of the manifest, without the comment syntax
+    /// of any language, so that each target can write it as a comment.
+    pub fn lines(&self) -> Vec<String> {
+        vec![
+            format!("generated by oakc {}", self.version),
+            format!("target: {}", self.target),
+            format!("compiled at: {}", self.timestamp),
+            format!("source: {}", self.filename),
+            format!("memory: {} cells", self.memory_size),
+        ]
+    }
+}
+
+/// What the virtual machine does when it panics, such as
+/// when the stack collides with the heap.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PanicStrategy {
+    /// Print the error and exit the process with its code
+    Abort,
+    /// Print the error and return its code from the entry point,
+    /// so that a host running the program isn't killed with it
+    ReturnError,
+}
+
+impl Default for PanicStrategy {
+    fn default() -> Self {
+        Self::Abort
+    }
+}
+
+pub trait Target {
+    fn get_name(&self) -> char;
+    /// The human readable name of the output language
+    fn language(&self) -> &'static str;
+    fn is_standard(&self) -> bool;
+
+    /// The runtime code is baked into the compiler, so it is
+    /// borrowed rather than copied each time it is used.
+    fn std(&self) -> Cow<'static, str>;
+    fn core_prelude(&self) -> Cow<'static, str>;
+    /// The runtime code written after the generated code. This
+    /// cleans up after a program that exits without reaching the
+    /// end of its entry point, such as writing the output that's
+    /// still buffered when a shared library is unloaded.
+    fn core_postlude(&self) -> Cow<'static, str>;
+
+    /// A comment recording how the program was built. This
+    /// is written at the very start of the output code.
+    fn manifest(&self, info: &BuildInfo) -> String {
+        // Every output language so far has `//` line comments
+        info.lines()
+            .iter()
+            .map(|line| format!("// {}\n", line))
+            .collect()
+    }
+
+    fn begin_entry_point(&self, global_scope_size: i32, memory_size: i32) -> String;
+    fn end_entry_point(&self) -> String;
+    /// Panic if the stack does not hold exactly `size` cells. This is
+    /// written before `end_entry_point` by the debug runtime, to catch
+    /// foreign functions and inline assembly that leave cells behind.
+    fn check_stack(&self, size: i32) -> String;
+
+    fn establish_stack_frame(&self, arg_size: i32, local_scope_size: i32) -> String;
+    fn end_stack_frame(&self, return_size: i32, local_scope_size: i32) -> String;
+    fn load_base_ptr(&self) -> String;
+
+    fn push(&self, n: f64) -> String;
+
+    /// The output code for positive infinity. Numbers that aren't
+    /// finite have no literal syntax, so each target names them itself.
+    fn infinity(&self) -> String;
+    /// The output code for a value that is not a number
+    fn nan(&self) -> String;
+
+    /// Write a number as a literal in the output code
+    fn float_literal(&self, n: f64) -> String {
+        if n.is_nan() {
+            self.nan()
+        } else if n.is_infinite() {
+            if n > 0.0 {
+                self.infinity()
+            } else {
+                format!("-{}", self.infinity())
+            }
+        } else if n == 0.0 && n.is_sign_negative() {
+            // `-0` is read as the integer zero, which loses the sign
+            String::from("-0.0")
+        } else if n != 0.0 && (n.abs() >= 1e16 || n.abs() < 1e-6) {
+            // Otherwise, these are written out with hundreds of digits, and
+            // large whole numbers are read as integer literals that overflow.
+            format!("{:e}", n)
+        } else {
+            n.to_string()
+        }
+    }
+
+    fn add(&self) -> String;
+    /// Pop `b` and then `a` off of the stack, and push `a - b`: the second
+    /// topmost number minus the topmost number. Comparisons are assembled
+    /// as the sign of a subtraction, so a target that subtracts in the
+    /// other order would invert every `<` and `>`.
+    fn subtract(&self) -> String;
+    fn multiply(&self) -> String;
+    /// Pop `b` and then `a` off of the stack, and push `a / b`
+    fn divide(&self) -> String;
+    /// Pop `b` and then `a` off of the stack, and push the remainder
+    /// of `a / b`, which has the same sign as `a`
+    fn modulus(&self) -> String;
+    /// Pop `b` and then `a` off of the stack, and push the bitwise and of
+    /// `a` and `b`. Both are converted to 32 bit signed integers first:
+    /// the fraction is discarded, the integer wraps around like an `i32`,
+    /// and infinite numbers and `NaN` become `0`.
+    fn bitand(&self) -> String;
+    /// Pop `b` and then `a` off of the stack, and push the bitwise or of
+    /// `a` and `b`, as 32 bit signed integers like `bitand`
+    fn bitor(&self) -> String;
+    /// Pop `b` and then `a` off of the stack, and push the bitwise exclusive
+    /// or of `a` and `b`, as 32 bit signed integers like `bitand`
+    fn bitxor(&self) -> String;
+    /// Pop `b` and then `a` off of the stack, and push `a` shifted left by
+    /// `b` bits, as 32 bit signed integers like `bitand`. Only the lowest
+    /// 5 bits of `b` are used, and the bits shifted past the sign are lost.
+    fn shl(&self) -> String;
+    /// Pop `b` and then `a` off of the stack, and push `a` shifted right by
+    /// `b` bits, as 32 bit signed integers like `bitand`. Only the lowest
+    /// 5 bits of `b` are used, and the sign of `a` is kept.
+    fn shr(&self) -> String;
+    /// Pop a number, and push `1` if it is positive,
+    /// `-1` if it is negative, and `0` otherwise
+    fn sign(&self) -> String;
+    /// Pop a number, and push it as an integer with `bits` bits, discarding
+    /// its fraction and wrapping it around like a C integer of that width.
+    /// This is used to cast to the sized integer types.
+    fn truncate(&self, bits: i32, is_signed: bool) -> String;
+
+    /// The alignment of every address returned by `allocate`,
+    /// measured in memory cells. This is exposed to Oak code
+    /// as the `ALIGNMENT` constant.
+    fn alignment(&self) -> i32 {
+        // Every backend's memory is an array of cells,
+        // and allocations always begin at a whole cell.
+        1
+    }
+
+    /// Does the generated runtime check for misuse of memory,
+    /// such as freeing the same memory twice? Release builds
+    /// leave these checks out.
+    fn debug_runtime(&self) -> bool {
+        false
+    }
+
+    /// Does the generated runtime log each operation of the virtual
+    /// machine to stderr? Each target's core runtime only includes
+    /// the logging code when this is set.
+    fn trace(&self) -> bool {
+        false
+    }
+
+    /// Does the generated runtime keep track of the most cells allocated
+    /// on the heap at once, and report it to stderr when the program exits?
+    fn heap_stats(&self) -> bool {
+        false
+    }
+
+    /// What the generated runtime does when it panics. Targets
+    /// that can't return from a panic always abort.
+    fn panic_strategy(&self) -> PanicStrategy {
+        PanicStrategy::Abort
+    }
+
+    fn allocate(&self) -> String;
+    fn free(&self) -> String;
+    /// Pop a count, a value with size `size`, and an address off of the stack,
+    /// and store `count` copies of the value starting at the address.
+    fn memset(&self, size: i32) -> String;
+    /// Output is buffered by the core VMs. Flushing writes
+    /// everything buffered so far to stdout.
+    fn flush(&self) -> String;
+    fn store(&self, size: i32) -> String;
+    /// Store a block of precomputed cells at an address, and push the address.
+    /// By default, each cell is pushed and then they are all stored at once,
+    /// but a target can write the cells as a single data declaration instead.
+    fn emit_initialized_data(&self, address: i32, cells: &[f64]) -> String {
+        let mut result = String::new();
+        for cell in cells {
+            result += &self.push(*cell);
+        }
+        result
+            + &self.push(address as f64)
+            + &self.store(cells.len() as i32)
+            + &self.push(address as f64)
+    }
+    fn load(&self, size: i32) -> String;
+    /// Pop an index and an address, and push the byte at that index of the
+    /// memory starting at the address. Each cell holds the bytes of a double,
+    /// so a buffer of cells is also a byte array, which can be passed to
+    /// foreign functions that expect C strings.
+    fn load_byte(&self) -> String;
+    /// Pop a byte, an index, and an address, and store the byte
+    /// at that index of the memory starting at the address
+    fn store_byte(&self) -> String;
+    /// Push a random number in [0, 1). The numbers are the same in
+    /// every run of the program, unless it's seeded differently.
+    fn random(&self) -> String;
+    /// Pop a seed, and start a new sequence of random numbers with it
+    fn seed_random(&self) -> String;
+    /// Push the number of seconds since the program started
+    fn clock(&self) -> String;
+
+    /// The most characters of an identifier that the output
+    /// language is sure to tell apart, if it has a limit.
+    fn max_identifier_length(&self) -> Option<usize> {
+        None
+    }
+
+    /// Fit an identifier generated by the compiler within the target's
+    /// `max_identifier_length`. A name that's too long is cut short and
+    /// ends with a hash of the whole name, so different names stay different.
+    fn identifier(&self, name: String) -> String {
+        match self.max_identifier_length() {
+            Some(limit) if name.len() > limit => shorten_identifier(&name, limit),
+            _ => name,
+        }
+    }
+
+    /// Is the output built as a shared library, with exported
+    /// functions that other languages call instead of an entry point?
+    fn shared_library(&self) -> bool {
+        false
+    }
+
+    /// Begin the exported functions of a shared library, after every
+    /// function is defined. They share a single virtual machine.
+    fn begin_exports(&self, _global_scope_size: i32, _memory_size: i32) -> String {
+        String::new()
+    }
+
+    /// Define an exported function with the Oak function's name, which
+    /// pushes its arguments onto the stack and runs `call`. If it returns
+    /// a value, the cell `call` leaves on the stack is returned.
+    fn export_fn(&self, _name: &str, _arg_count: usize, _call: String, _returns: bool) -> String {
+        String::new()
+    }
+
+    /// Mark where a function is defined in the Oak source, before its
+    /// definition, so that the output code can be traced back to it.
+    fn source_location(&self, _filename: &str, _line: usize, _name: &str) -> String {
+        String::new()
+    }
+
+    fn fn_header(&self, name: String) -> String;
+    fn fn_definition(&self, name: String, body: String) -> String;
+    fn call_fn(&self, name: String) -> String;
+
+    /// Can a function that returns a single cell return it as a native
+    /// value, instead of leaving it on the virtual machine's stack? Only
+    /// leaf functions use this calling convention, and the methods below
+    /// are only used for them when this is true.
+    fn native_return(&self) -> bool {
+        false
+    }
+
+    /// End the stack frame of a function that returns a single cell
+    /// natively, and return the cell from the function.
+    fn end_stack_frame_returning(&self, local_scope_size: i32) -> String {
+        self.end_stack_frame(1, local_scope_size)
+    }
+
+    /// Pop a cell, and return it natively from a function
+    /// that returns without a stack frame to end.
+    fn return_native(&self) -> String {
+        String::new()
+    }
+
+    /// Declare a function that returns a single cell natively
+    fn fn_header_returning(&self, name: String, _return_size: i32) -> String {
+        self.fn_header(name)
+    }
+
+    /// Define a function whose body ends with `end_stack_frame_returning`
+    fn fn_definition_returning(&self, name: String, body: String, _return_size: i32) -> String {
+        self.fn_definition(name, body)
+    }
+
+    /// Call a function defined with `fn_definition_returning`,
+    /// and push the cell it returns onto the stack.
+    fn call_fn_returning(&self, name: String) -> String {
+        self.call_fn(name)
+    }
+
+    /// Begin the code that contains every function's definition.
+    /// This lets a target wrap all of its functions in a single routine.
+    fn begin_fn_definitions(&self) -> String {
+        String::new()
+    }
+
+    /// End the code that contains every function's definition.
+    /// The `entry` function is the one called by `call_entry_point`.
+    fn end_fn_definitions(&self, _entry: String) -> String {
+        String::new()
+    }
+
+    /// Should the body of the program's entry point be written directly
+    /// between `begin_entry_point` and `end_entry_point`, instead of
+    /// defining it as a function and calling it? The body still
+    /// establishes and ends its own stack frame.
+    fn inline_entry_point(&self) -> bool {
+        false
+    }
+
+    /// Call the entry point of the program from the code
+    /// between `begin_entry_point` and `end_entry_point`.
+    fn call_entry_point(&self, name: String) -> String {
+        self.call_fn(name)
+    }
+    fn call_foreign_fn(&self, name: String) -> String;
+    /// Call a foreign function marked with `#[context]`. Along with the
+    /// virtual machine, it is passed the machine's context pointer, which
+    /// the host sets to give its foreign functions access to its own state.
+    fn call_foreign_fn_with_context(&self, name: String) -> String;
+
+    /// Push the value of a foreign global variable, converted to a cell
+    fn load_foreign_var(&self, name: String) -> String;
+    /// Pop a cell, and store it in a foreign global variable,
+    /// converting it to the variable's type
+    fn store_foreign_var(&self, name: String) -> String;
+
+    /// Begin a loop that runs while the value popped off of the stack is not
+    /// zero. The label is unique in the program, so that `break_while` and
+    /// `continue_while` can leave loops other than the innermost one.
+    fn begin_while(&self, label: Option<&str>) -> String;
+    /// End the body of a loop. `continue_while` jumps here, before
+    /// the condition of the loop is pushed for the next iteration.
+    fn end_while_body(&self, label: Option<&str>) -> String;
+    fn end_while(&self, label: Option<&str>) -> String;
+    /// Leave the loop with this label
+    fn break_while(&self, label: &str) -> String;
+    /// Skip to the end of the body of the loop with this label
+    fn continue_while(&self, label: &str) -> String;
+
+    /// Begin an if statement that runs its body if the
+    /// value popped off of the stack is not zero
+    fn begin_if(&self) -> String;
+    /// End the body of an if statement, and begin its else body
+    fn begin_else(&self) -> String;
+    fn end_if(&self) -> String;
+
+    /// Format the generated code so that it's readable when debugging.
+    /// Targets where whitespace is significant must override this.
+    fn pretty_print(&self, code: String) -> String {
+        indent_braces(&code, "    ")
+    }
+
+    /// Write the output code and build it, returning
+    /// the path of the artifact that was produced.
+    fn compile(&self, code: String) -> std::io::Result<PathBuf>;
+}
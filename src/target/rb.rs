@@ -0,0 +1,392 @@
+use super::{BuildInfo, Target};
+use std::{
+    borrow::Cow,
+    fs::{remove_file, write},
+    io::{Error, ErrorKind, Result},
+    path::PathBuf,
+    process::Command,
+};
+
+/// The Ruby target. Its options keep their defaults unless they are
+/// set with the builder methods below.
+#[derive(Clone, Copy, Default)]
+pub struct Ruby {
+    /// Check for invalid frees at runtime
+    debug: bool,
+    /// Log each operation of the virtual machine to stderr
+    trace: bool,
+    /// Report the peak heap usage to stderr at exit
+    heap_stats: bool,
+    /// Write the entry point's body in place of its call
+    inline_entry: bool,
+}
+
+impl Ruby {
+    /// A Ruby target with every option off.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check for double frees and frees of memory that was never allocated
+    /// at runtime, and panic when they happen.
+    pub fn debug(mut self) -> Self {
+        self.debug = true;
+        self
+    }
+
+    /// Log each operation of the virtual machine, such as a push
+    /// or a call, to stderr at runtime.
+    pub fn traced(mut self) -> Self {
+        self.trace = true;
+        self
+    }
+
+    /// Report the most cells allocated on the heap at once
+    /// to stderr when the program exits.
+    pub fn report_heap_stats(mut self) -> Self {
+        self.heap_stats = true;
+        self
+    }
+
+    /// Write the body of the entry point directly in the program's
+    /// entry point, instead of calling it as a function.
+    pub fn inline_entry(mut self) -> Self {
+        self.inline_entry = true;
+        self
+    }
+}
+
+impl Target for Ruby {
+    fn get_name(&self) -> char {
+        'b'
+    }
+
+    fn language(&self) -> &'static str {
+        "Ruby"
+    }
+
+    fn is_standard(&self) -> bool {
+        true
+    }
+
+    fn std(&self) -> Cow<'static, str> {
+        Cow::Borrowed(include_str!("std/std.rb"))
+    }
+
+    fn core_prelude(&self) -> Cow<'static, str> {
+        // The tracing code in the core is only run when `TRACE` is true,
+        // and the heap is only reported when `HEAP_STATS` is true
+        Cow::Owned(format!(
+            "{}\nTRACE = {}\nHEAP_STATS = {}\nDEBUG = {}\n",
+            include_str!("core/core.rb"),
+            self.trace,
+            self.heap_stats,
+            self.debug
+        ))
+    }
+
+    fn core_postlude(&self) -> Cow<'static, str> {
+        Cow::Borrowed("")
+    }
+
+    fn manifest(&self, info: &BuildInfo) -> String {
+        info.lines()
+            .iter()
+            .map(|line| format!("# {}\n", line))
+            .collect()
+    }
+
+    fn begin_entry_point(&self, global_scope_size: i32, memory_size: i32) -> String {
+        format!(
+            "\nvm = Machine.new({}, {})\n",
+            global_scope_size,
+            global_scope_size + memory_size,
+        )
+    }
+
+    fn end_entry_point(&self) -> String {
+        String::from("\nvm.drop\n")
+    }
+
+    fn check_stack(&self, size: i32) -> String {
+        format!("vm.check_stack({})\n", size)
+    }
+
+    fn establish_stack_frame(&self, arg_size: i32, local_scope_size: i32) -> String {
+        format!(
+            "vm.establish_stack_frame({}, {})\n",
+            arg_size, local_scope_size
+        )
+    }
+
+    fn end_stack_frame(&self, return_size: i32, local_scope_size: i32) -> String {
+        format!(
+            "vm.end_stack_frame({}, {})\n",
+            return_size, local_scope_size
+        )
+    }
+
+    fn load_base_ptr(&self) -> String {
+        String::from("vm.load_base_ptr\n")
+    }
+
+    fn push(&self, n: f64) -> String {
+        format!("vm.push({})\n", self.float_literal(n))
+    }
+
+    fn infinity(&self) -> String {
+        String::from("Float::INFINITY")
+    }
+
+    fn nan(&self) -> String {
+        String::from("Float::NAN")
+    }
+
+    fn add(&self) -> String {
+        String::from("vm.add\n")
+    }
+
+    fn subtract(&self) -> String {
+        String::from("vm.subtract\n")
+    }
+
+    fn multiply(&self) -> String {
+        String::from("vm.multiply\n")
+    }
+
+    fn divide(&self) -> String {
+        String::from("vm.divide\n")
+    }
+
+    fn modulus(&self) -> String {
+        String::from("vm.modulo\n")
+    }
+
+    fn bitand(&self) -> String {
+        String::from("vm.bitand\n")
+    }
+
+    fn bitor(&self) -> String {
+        String::from("vm.bitor\n")
+    }
+
+    fn bitxor(&self) -> String {
+        String::from("vm.bitxor\n")
+    }
+
+    fn shl(&self) -> String {
+        String::from("vm.shl\n")
+    }
+
+    fn shr(&self) -> String {
+        String::from("vm.shr\n")
+    }
+
+    fn sign(&self) -> String {
+        String::from("vm.sign\n")
+    }
+
+    fn truncate(&self, bits: i32, is_signed: bool) -> String {
+        format!("vm.truncate({}, {})\n", bits, is_signed)
+    }
+
+    fn allocate(&self) -> String {
+        String::from("vm.alloc\n")
+    }
+
+    fn debug_runtime(&self) -> bool {
+        self.debug
+    }
+
+    fn trace(&self) -> bool {
+        self.trace
+    }
+
+    fn heap_stats(&self) -> bool {
+        self.heap_stats
+    }
+
+    fn free(&self) -> String {
+        if self.debug {
+            String::from("vm.checked_free\n")
+        } else {
+            String::from("vm.free\n")
+        }
+    }
+
+    fn memset(&self, size: i32) -> String {
+        format!("vm.memset({})\n", size)
+    }
+
+    fn flush(&self) -> String {
+        String::from("vm.flush\n")
+    }
+
+    fn store(&self, size: i32) -> String {
+        if self.debug {
+            format!("vm.checked_store({})\n", size)
+        } else {
+            format!("vm.store({})\n", size)
+        }
+    }
+
+    fn load(&self, size: i32) -> String {
+        if self.debug {
+            format!("vm.checked_load({})\n", size)
+        } else {
+            format!("vm.load({})\n", size)
+        }
+    }
+
+    fn load_byte(&self) -> String {
+        if self.debug {
+            String::from("vm.checked_load_byte\n")
+        } else {
+            String::from("vm.load_byte\n")
+        }
+    }
+
+    fn store_byte(&self) -> String {
+        if self.debug {
+            String::from("vm.checked_store_byte\n")
+        } else {
+            String::from("vm.store_byte\n")
+        }
+    }
+
+    fn random(&self) -> String {
+        String::from("vm.random\n")
+    }
+
+    fn seed_random(&self) -> String {
+        String::from("vm.seed_random\n")
+    }
+
+    fn clock(&self) -> String {
+        String::from("vm.clock\n")
+    }
+
+    fn fn_header(&self, _name: String) -> String {
+        String::new()
+    }
+
+    fn fn_definition(&self, name: String, body: String) -> String {
+        format!("\ndef {}(vm)\n{}\nend\n", name, body)
+    }
+
+    fn inline_entry_point(&self) -> bool {
+        self.inline_entry
+    }
+
+    fn call_fn(&self, name: String) -> String {
+        format!("{}(vm)\n", name)
+    }
+
+    fn call_foreign_fn(&self, name: String) -> String {
+        format!("{}(vm)\n", name)
+    }
+
+    fn call_foreign_fn_with_context(&self, name: String) -> String {
+        format!("{}(vm, vm.context)\n", name)
+    }
+
+    // Methods can't see the variables defined outside of them,
+    // so foreign variables are Ruby's global variables
+    fn load_foreign_var(&self, name: String) -> String {
+        format!("vm.push(${})\n", name)
+    }
+
+    fn store_foreign_var(&self, name: String) -> String {
+        format!("${} = vm.pop\n", name)
+    }
+
+    // Ruby has no labeled loops, so a labeled loop is wrapped in a
+    // `catch` block that `break_while` throws to, and its body is
+    // wrapped in another that `continue_while` throws to.
+    fn begin_while(&self, label: Option<&str>) -> String {
+        match label {
+            Some(label) => format!(
+                "catch(:{}) do\nwhile vm.pop != 0 do\ncatch(:{}_body) do\n",
+                label, label
+            ),
+            None => String::from("while vm.pop != 0 do\n"),
+        }
+    }
+
+    fn end_while_body(&self, label: Option<&str>) -> String {
+        match label {
+            Some(_) => String::from("end\n"),
+            None => String::new(),
+        }
+    }
+
+    fn end_while(&self, label: Option<&str>) -> String {
+        match label {
+            Some(_) => String::from("end\nend\n"),
+            None => String::from("end\n"),
+        }
+    }
+
+    fn break_while(&self, label: &str) -> String {
+        format!("throw :{}\n", label)
+    }
+
+    fn continue_while(&self, label: &str) -> String {
+        format!("throw :{}_body\n", label)
+    }
+
+    fn begin_if(&self) -> String {
+        String::from("if vm.pop != 0\n")
+    }
+
+    fn begin_else(&self) -> String {
+        String::from("else\n")
+    }
+
+    fn end_if(&self) -> String {
+        String::from("end\n")
+    }
+
+    // Blocks are delimited by keywords instead of curly braces
+    fn pretty_print(&self, code: String) -> String {
+        let mut result = String::new();
+        let mut depth: usize = 0;
+        for line in code.lines() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            if line == "end" || line == "else" {
+                depth = depth.saturating_sub(1);
+            }
+            result += &"  ".repeat(depth);
+            result += line;
+            result += "\n";
+            if line.starts_with("def ")
+                || line.starts_with("if ")
+                || line.ends_with(" do")
+                || line == "else"
+            {
+                depth += 1;
+            }
+        }
+        result
+    }
+
+    fn compile(&self, code: String) -> Result<PathBuf> {
+        // Ruby is interpreted, so the output code is only checked for syntax errors
+        if write("main.rb", code).is_ok() {
+            if let Ok(output) = Command::new("ruby").arg("-c").arg("main.rb").output() {
+                if output.status.success() {
+                    return Result::Ok(PathBuf::from("main.rb"));
+                }
+            }
+            let _ = remove_file("main.rb");
+        }
+        Result::Err(Error::new(
+            ErrorKind::Other,
+            "could not check output ruby code. is ruby installed?",
+        ))
+    }
+}
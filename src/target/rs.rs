@@ -0,0 +1,441 @@
+use super::{PanicStrategy, Target};
+use std::{
+    borrow::Cow,
+    env::consts::EXE_SUFFIX,
+    fs::{remove_file, write},
+    io::{Error, ErrorKind, Result},
+    path::PathBuf,
+    process::Command,
+};
+
+/// The Rust target. Its options keep their defaults unless they are
+/// set with the builder methods below.
+#[derive(Clone, Copy, Default)]
+pub struct Rust {
+    /// Check for invalid frees at runtime
+    debug: bool,
+    /// Log each operation of the virtual machine to stderr
+    trace: bool,
+    /// Report the peak heap usage to stderr at exit
+    heap_stats: bool,
+    /// Write the entry point's body in place of its call
+    inline_entry: bool,
+    /// What the runtime does when it panics
+    panic_strategy: PanicStrategy,
+}
+
+impl Rust {
+    /// A Rust target with every option off.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check for double frees and frees of memory that was never allocated
+    /// at runtime, and panic when they happen.
+    pub fn debug(mut self) -> Self {
+        self.debug = true;
+        self
+    }
+
+    /// Log each operation of the virtual machine, such as a push
+    /// or a call, to stderr at runtime.
+    pub fn traced(mut self) -> Self {
+        self.trace = true;
+        self
+    }
+
+    /// Report the most cells allocated on the heap at once
+    /// to stderr when the program exits.
+    pub fn report_heap_stats(mut self) -> Self {
+        self.heap_stats = true;
+        self
+    }
+
+    /// Write the body of the entry point directly in the program's
+    /// entry point, instead of calling it as a function.
+    pub fn inline_entry(mut self) -> Self {
+        self.inline_entry = true;
+        self
+    }
+
+    /// Choose what the runtime does when it panics. With
+    /// `PanicStrategy::ReturnError`, a panic unwinds to `main`,
+    /// which exits with the error code after cleaning up.
+    pub fn panic_strategy(mut self, strategy: PanicStrategy) -> Self {
+        self.panic_strategy = strategy;
+        self
+    }
+}
+
+impl Target for Rust {
+    fn get_name(&self) -> char {
+        'r'
+    }
+
+    fn language(&self) -> &'static str {
+        "Rust"
+    }
+
+    fn is_standard(&self) -> bool {
+        true
+    }
+
+    fn std(&self) -> Cow<'static, str> {
+        Cow::Borrowed(include_str!("std/std.rs"))
+    }
+
+    fn core_prelude(&self) -> Cow<'static, str> {
+        // The tracing code in the core is only run when `TRACE` is true,
+        // the heap is only reported when `HEAP_STATS` is true, and a
+        // panic only unwinds to `main` when `PANIC_RETURN` is true. The
+        // runtime checks in the standard library only run when `DEBUG` is true.
+        // The core begins with crate attributes, so this must come after it.
+        Cow::Owned(format!(
+            "{}\nconst TRACE: bool = {};\nconst HEAP_STATS: bool = {};\nconst PANIC_RETURN: bool = {};\nconst DEBUG: bool = {};\n",
+            include_str!("core/core.rs"),
+            self.trace,
+            self.heap_stats,
+            self.panic_strategy == PanicStrategy::ReturnError,
+            self.debug
+        ))
+    }
+
+    fn core_postlude(&self) -> Cow<'static, str> {
+        Cow::Borrowed("")
+    }
+
+    fn begin_entry_point(&self, global_scope_size: i32, memory_size: i32) -> String {
+        let mut result = format!(
+            "fn main() {{\nlet vm = &mut Machine::new({}, {});\n",
+            global_scope_size,
+            global_scope_size + memory_size,
+        );
+        if self.panic_strategy == PanicStrategy::ReturnError {
+            // The program is run in a closure, so that
+            // a panic can unwind out of it to here
+            result +=
+                "let status = match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {\n";
+        }
+        result
+    }
+
+    fn end_entry_point(&self) -> String {
+        if self.panic_strategy == PanicStrategy::ReturnError {
+            // A panic unwinds with its error code
+            String::from(
+                "\n})) {\nOk(()) => 0,\nErr(code) => code.downcast::<i32>().map(|code| *code).unwrap_or(1),\n};\nvm.drop();\nstd::process::exit(status);\n}",
+            )
+        } else {
+            String::from("\nvm.drop();\n}")
+        }
+    }
+
+    fn check_stack(&self, size: i32) -> String {
+        format!("vm.check_stack({});\n", size)
+    }
+
+    fn establish_stack_frame(&self, arg_size: i32, local_scope_size: i32) -> String {
+        format!(
+            "vm.establish_stack_frame({}, {});\n",
+            arg_size, local_scope_size
+        )
+    }
+
+    fn end_stack_frame(&self, return_size: i32, local_scope_size: i32) -> String {
+        format!(
+            "vm.end_stack_frame({}, {});\n",
+            return_size, local_scope_size
+        )
+    }
+
+    fn load_base_ptr(&self) -> String {
+        String::from("vm.load_base_ptr();\n")
+    }
+
+    fn push(&self, n: f64) -> String {
+        format!("vm.push({});\n", self.float_literal(n))
+    }
+
+    fn infinity(&self) -> String {
+        String::from("f64::INFINITY")
+    }
+
+    fn nan(&self) -> String {
+        String::from("f64::NAN")
+    }
+
+    fn float_literal(&self, n: f64) -> String {
+        if n.is_nan() {
+            self.nan()
+        } else if n.is_infinite() {
+            if n > 0.0 {
+                self.infinity()
+            } else {
+                format!("-{}", self.infinity())
+            }
+        } else {
+            // Whole numbers must be written with a decimal point to be
+            // read as floats, which the debug formatting always does.
+            format!("{:?}", n)
+        }
+    }
+
+    fn add(&self) -> String {
+        String::from("vm.add();\n")
+    }
+
+    fn subtract(&self) -> String {
+        String::from("vm.subtract();\n")
+    }
+
+    fn multiply(&self) -> String {
+        String::from("vm.multiply();\n")
+    }
+
+    fn divide(&self) -> String {
+        String::from("vm.divide();\n")
+    }
+
+    fn modulus(&self) -> String {
+        String::from("vm.modulo();\n")
+    }
+
+    fn bitand(&self) -> String {
+        String::from("vm.bitand();\n")
+    }
+
+    fn bitor(&self) -> String {
+        String::from("vm.bitor();\n")
+    }
+
+    fn bitxor(&self) -> String {
+        String::from("vm.bitxor();\n")
+    }
+
+    fn shl(&self) -> String {
+        String::from("vm.shl();\n")
+    }
+
+    fn shr(&self) -> String {
+        String::from("vm.shr();\n")
+    }
+
+    fn sign(&self) -> String {
+        String::from("vm.sign();\n")
+    }
+
+    fn truncate(&self, bits: i32, is_signed: bool) -> String {
+        format!("vm.truncate({}, {});\n", bits, is_signed)
+    }
+
+    fn allocate(&self) -> String {
+        String::from("vm.allocate();\n")
+    }
+
+    fn debug_runtime(&self) -> bool {
+        self.debug
+    }
+
+    fn trace(&self) -> bool {
+        self.trace
+    }
+
+    fn heap_stats(&self) -> bool {
+        self.heap_stats
+    }
+
+    fn panic_strategy(&self) -> PanicStrategy {
+        self.panic_strategy
+    }
+
+    fn free(&self) -> String {
+        if self.debug {
+            String::from("vm.checked_free();\n")
+        } else {
+            String::from("vm.free();\n")
+        }
+    }
+
+    fn memset(&self, size: i32) -> String {
+        format!("vm.memset({});\n", size)
+    }
+
+    fn flush(&self) -> String {
+        String::from("vm.flush();\n")
+    }
+
+    fn store(&self, size: i32) -> String {
+        if self.debug {
+            format!("vm.checked_store({});\n", size)
+        } else {
+            format!("vm.store({});\n", size)
+        }
+    }
+
+    fn emit_initialized_data(&self, address: i32, cells: &[f64]) -> String {
+        let cells = cells
+            .iter()
+            .map(|cell| self.float_literal(*cell))
+            .collect::<Vec<_>>();
+        format!("vm.store_data({}, &[{}]);\n", address, cells.join(", "))
+            + &self.push(address as f64)
+    }
+
+    fn load(&self, size: i32) -> String {
+        if self.debug {
+            format!("vm.checked_load({});\n", size)
+        } else {
+            format!("vm.load({});\n", size)
+        }
+    }
+
+    fn load_byte(&self) -> String {
+        if self.debug {
+            String::from("vm.checked_load_byte();\n")
+        } else {
+            String::from("vm.load_byte();\n")
+        }
+    }
+
+    fn store_byte(&self) -> String {
+        if self.debug {
+            String::from("vm.checked_store_byte();\n")
+        } else {
+            String::from("vm.store_byte();\n")
+        }
+    }
+
+    fn random(&self) -> String {
+        String::from("vm.random();\n")
+    }
+
+    fn seed_random(&self) -> String {
+        String::from("vm.seed_random();\n")
+    }
+
+    fn clock(&self) -> String {
+        String::from("vm.clock();\n")
+    }
+
+    fn fn_header(&self, name: String) -> String {
+        String::new()
+    }
+
+    fn fn_definition(&self, name: String, body: String) -> String {
+        format!("fn {}(vm: &mut Machine) {{\n{}}}\n", name, body)
+    }
+
+    fn native_return(&self) -> bool {
+        true
+    }
+
+    fn end_stack_frame_returning(&self, local_scope_size: i32) -> String {
+        format!(
+            "return vm.end_stack_frame_returning({});\n",
+            local_scope_size
+        )
+    }
+
+    fn return_native(&self) -> String {
+        String::from("return vm.pop();\n")
+    }
+
+    fn fn_definition_returning(&self, name: String, body: String, _return_size: i32) -> String {
+        format!("fn {}(vm: &mut Machine) -> f64 {{\n{}}}\n", name, body)
+    }
+
+    fn call_fn_returning(&self, name: String) -> String {
+        // The machine is borrowed by the call, so the
+        // cell is pushed after the call returns
+        format!("let cell = {}(vm);\nvm.push(cell);\n", name)
+    }
+
+    fn inline_entry_point(&self) -> bool {
+        self.inline_entry
+    }
+
+    fn call_fn(&self, name: String) -> String {
+        format!("{}(vm);\n", name)
+    }
+
+    fn call_foreign_fn(&self, name: String) -> String {
+        format!("{}(vm);\n", name)
+    }
+
+    fn call_foreign_fn_with_context(&self, name: String) -> String {
+        // The machine is borrowed by the call, so the
+        // context pointer is read before the call
+        format!("let context = vm.context;\n{}(vm, context);\n", name)
+    }
+
+    fn load_foreign_var(&self, name: String) -> String {
+        // Foreign globals are `static mut` items, which are unsafe to use
+        format!("vm.push(unsafe {{ {} }} as f64);\n", name)
+    }
+
+    fn store_foreign_var(&self, name: String) -> String {
+        // The cell is converted to the type of the static
+        format!("let cell = vm.pop();\nunsafe {{ {} = cell as _; }}\n", name)
+    }
+
+    fn begin_while(&self, label: Option<&str>) -> String {
+        match label {
+            Some(label) => format!(
+                "'{}: while vm.pop() != 0.0 {{\n'{}_body: loop {{\n",
+                label, label
+            ),
+            None => String::from("while vm.pop() != 0.0 {\n"),
+        }
+    }
+
+    // The body is a loop that always runs once, so
+    // that breaking out of it skips to its end
+    fn end_while_body(&self, label: Option<&str>) -> String {
+        match label {
+            Some(_) => String::from("break;\n}\n"),
+            None => String::new(),
+        }
+    }
+
+    fn end_while(&self, _label: Option<&str>) -> String {
+        String::from("}\n")
+    }
+
+    fn break_while(&self, label: &str) -> String {
+        format!("break '{};\n", label)
+    }
+
+    fn continue_while(&self, label: &str) -> String {
+        format!("break '{}_body;\n", label)
+    }
+
+    fn begin_if(&self) -> String {
+        String::from("if vm.pop() != 0.0 {\n")
+    }
+
+    fn begin_else(&self) -> String {
+        String::from("} else {\n")
+    }
+
+    fn end_if(&self) -> String {
+        String::from("}\n")
+    }
+
+    fn compile(&self, code: String) -> Result<PathBuf> {
+        let output = format!("main{}", EXE_SUFFIX);
+        if let Ok(_) = write("main.rs", code) {
+            if let Ok(result) = Command::new("rustc")
+                .args(&["--edition", "2018", "-O", "-o", &output[..], "main.rs"])
+                .output()
+            {
+                if result.status.success() && remove_file("main.rs").is_ok() {
+                    return Result::Ok(PathBuf::from(output));
+                }
+            }
+        }
+        Result::Err(Error::new(
+            ErrorKind::Other,
+            "could not compile output rust code. is rustc installed?",
+        ))
+    }
+}
@@ -0,0 +1,39 @@
+
+void prn(machine *vm) {
+    double n = machine_pop(vm);
+    printf("%g", n);
+}
+
+void prs(machine *vm) {
+    double addr = machine_pop(vm);
+    int i;
+    for (i=addr; vm->memory[i]; i++) {
+        putchar((unsigned char)vm->memory[i]);
+#ifdef DEBUG
+        // An unterminated string would be read past the end of memory
+        if (i + 1 >= vm->capacity) panic(UNTERMINATED_STRING);
+#endif
+    }
+}
+
+// Characters are written as raw bytes, even control characters
+void prc(machine *vm) {
+    double n = machine_pop(vm);
+    putchar((unsigned char)n);
+}
+
+void prend(machine *vm) {
+    (void)vm;
+    printf("\n");
+}
+
+void getch(machine *vm) {
+    // Make sure any prompt is shown before waiting for input
+    machine_flush(vm);
+    char ch = getchar();
+    if (ch == '\r') {
+        ch = getchar();
+    }
+    machine_push(vm, ch);
+}
+
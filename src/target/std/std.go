@@ -0,0 +1,38 @@
+
+func prn(vm *machine) {
+	n := vm.pop()
+	fmt.Fprintf(WRITER, "%g", n)
+}
+
+func prs(vm *machine) {
+	addr := int(vm.pop())
+	for i := addr; vm.memory[i] != 0.0; i += 1 {
+		WRITER.WriteByte(byte(int(vm.memory[i])))
+		// An unterminated string would be read past the end of memory
+		if DEBUG && i+1 >= vm.capacity {
+			panic(UNTERMINATED_STRING)
+		}
+	}
+}
+
+// Characters are written as raw bytes. Formatting them with `%c`
+// would encode the characters above 127 as multiple bytes.
+func prc(vm *machine) {
+	n := vm.pop()
+	WRITER.WriteByte(byte(int(n)))
+}
+
+func prend(vm *machine) {
+	fmt.Fprint(WRITER, "\n")
+}
+
+func getch(vm *machine) {
+	// Make sure any prompt is shown before waiting for input
+	vm.flush()
+	ch, _ := READER.ReadByte()
+	if ch == '\r' {
+		ch, _ = READER.ReadByte()
+	}
+
+	vm.push(float64(ch))
+}
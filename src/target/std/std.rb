@@ -0,0 +1,33 @@
+def prn(vm)
+  n = vm.pop
+  write_bytes(format_number(n))
+end
+
+def prs(vm)
+  addr = to_index(vm.pop)
+  i = addr
+  while vm.memory[i] != 0
+    write_byte(vm.memory[i].to_i)
+    # An unterminated string would be read past the end of memory
+    panic(UNTERMINATED_STRING) if i + 1 >= vm.capacity
+    i += 1
+  end
+end
+
+# Characters are written as raw bytes, even control characters
+def prc(vm)
+  n = vm.pop
+  write_byte(n.to_i)
+end
+
+def prend(vm)
+  write_byte(10)
+end
+
+def getch(vm)
+  # Make sure any prompt is shown before waiting for input
+  vm.flush
+  ch = $stdin.getbyte || 0
+  ch = $stdin.getbyte || 0 if ch == 13
+  vm.push(ch)
+end
@@ -0,0 +1,38 @@
+fn prn(vm: &mut Machine) {
+    let n = vm.pop();
+    let _ = write!(vm.writer, "{}", format_number(n));
+}
+
+fn prs(vm: &mut Machine) {
+    let addr = vm.pop() as usize;
+    let mut i = addr;
+    while vm.memory[i] != 0.0 {
+        let _ = vm.writer.write_all(&[vm.memory[i] as i64 as u8]);
+        // An unterminated string would be read past the end of memory
+        if DEBUG && i + 1 >= vm.capacity {
+            vm.panic(UNTERMINATED_STRING);
+        }
+        i += 1;
+    }
+}
+
+// Characters are written as raw bytes, even control characters
+fn prc(vm: &mut Machine) {
+    let n = vm.pop();
+    let _ = vm.writer.write_all(&[n as i64 as u8]);
+}
+
+fn prend(vm: &mut Machine) {
+    let _ = vm.writer.write_all(b"\n");
+}
+
+fn getch(vm: &mut Machine) {
+    // Make sure any prompt is shown before waiting for input
+    vm.flush();
+    let mut ch = [0];
+    let _ = stdin().read(&mut ch);
+    if ch[0] == b'\r' {
+        let _ = stdin().read(&mut ch);
+    }
+    vm.push(ch[0] as f64);
+}
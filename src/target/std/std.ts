@@ -0,0 +1,49 @@
+//print a number
+function prn(vm: machine): void {
+	let n = machine_pop(vm);
+	machine_write(String(n));
+}
+
+//print a null-terminated string, writing each character as a raw byte
+function prs(vm: machine): void {
+	let addr = machine_pop(vm);
+	let out = "";
+	for (let i=addr; vm.memory[i]; i++) {
+		out += String.fromCharCode(vm.memory[i] & 255);
+	}
+	machine_write(out);
+}
+
+//print a char as a raw byte
+function prc(vm: machine): void {
+	let n = machine_pop(vm);
+	machine_write(String.fromCharCode(n & 255));
+}
+
+//print a newline, which logs the line written so far
+function prend(vm: machine): void {
+	machine_write("\n");
+}
+
+async function getch(vm: machine): Promise<void> {
+	//https://stackoverflow.com/questions/44746592/is-there-a-way-to-write-async-await-code-that-responds-to-onkeypress-events
+	async function readKey(): Promise<KeyboardEvent>{
+		return new Promise(resolve => {
+			window.addEventListener('keypress', resolve, {once:true});
+		});
+	}
+	let key: string = (await readKey()).key;
+	let ch: number;
+
+	if (key === "Enter") { //make sure pressing enter always gives \n
+		ch = "\n".charCodeAt(0);
+	} else if (key.length > 1){ //if the key is not a single character (arrow keys, etc.)
+		//find a way to make this non-recursive
+		getch(vm);
+	} else {
+		ch = key.charCodeAt(0);
+	}
+	machine_push(vm, ch);
+}
+
+
@@ -0,0 +1,31 @@
+  (func $prn
+    (call $host_write_number (call $machine_pop)))
+
+  (func $prs
+    (local $addr i32)
+    (local.set $addr (call $to_int (call $machine_pop)))
+    (block $done
+      (loop $next
+        (br_if $done (f64.eq (f64.load (call $cell (local.get $addr))) (f64.const 0)))
+        (call $host_write_byte (call $to_int (f64.load (call $cell (local.get $addr)))))
+        ;; An unterminated string would be read past the end of memory
+        (if (i32.ge_s (i32.add (local.get $addr) (i32.const 1)) (global.get $capacity))
+          (then (call $panic (i32.const 6))))
+        (local.set $addr (i32.add (local.get $addr) (i32.const 1)))
+        (br $next))))
+
+  ;; Characters are written as raw bytes, even control characters
+  (func $prc
+    (call $host_write_byte (call $to_int (call $machine_pop))))
+
+  (func $prend
+    (call $host_write_byte (i32.const 10)))
+
+  (func $getch
+    (local $ch i32)
+    ;; Make sure any prompt is shown before waiting for input
+    (call $machine_flush)
+    (local.set $ch (call $host_read_byte))
+    (if (i32.eq (local.get $ch) (i32.const 13))
+      (then (local.set $ch (call $host_read_byte))))
+    (call $machine_push (f64.convert_i32_s (local.get $ch))))
@@ -0,0 +1,40 @@
+fn prn(vm: *Machine) void {
+    const n = vm.pop();
+    var buffer: [64]u8 = undefined;
+    const digits = std.fmt.bufPrint(&buffer, "{d}", .{n}) catch return;
+    write_bytes(digits);
+}
+
+fn prs(vm: *Machine) void {
+    var i = to_index(vm.pop());
+    while (vm.memory[i] != 0) : (i += 1) {
+        write_byte(@intFromFloat(@mod(vm.memory[i], 256)));
+        // An unterminated string would be read past the end of memory
+        if (DEBUG and i + 1 >= vm.capacity) {
+            machine_panic(UNTERMINATED_STRING);
+        }
+    }
+}
+
+// Characters are written as raw bytes, so
+// the characters above 127 are not encoded.
+fn prc(vm: *Machine) void {
+    write_byte(@intFromFloat(@mod(vm.pop(), 256)));
+}
+
+fn prend(vm: *Machine) void {
+    _ = vm;
+    write_byte('\n');
+}
+
+fn getch(vm: *Machine) void {
+    // Make sure any prompt is shown before waiting for input
+    vm.flush();
+    const reader = std.io.getStdIn().reader();
+    var ch = reader.readByte() catch 0;
+    if (ch == '\r') {
+        ch = reader.readByte() catch 0;
+    }
+
+    vm.push(@floatFromInt(ch));
+}
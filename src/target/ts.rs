@@ -0,0 +1,368 @@
+use super::Target;
+use std::{
+    borrow::Cow,
+    env::consts::EXE_SUFFIX,
+    fs::{remove_file, write},
+    io::{Error, ErrorKind, Result, Write},
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+
+/// The TypeScript target. Its options keep their defaults unless they are
+/// set with the builder methods below.
+#[derive(Clone, Copy, Default)]
+pub struct TS {
+    /// Check for invalid frees at runtime
+    debug: bool,
+    /// Log each operation of the virtual machine to stderr
+    trace: bool,
+    /// Report the peak heap usage to stderr at exit
+    heap_stats: bool,
+    /// Write the entry point's body in place of its call
+    inline_entry: bool,
+}
+
+impl TS {
+    /// A TypeScript target with every option off.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check for double frees and frees of memory that was never allocated
+    /// at runtime, and panic when they happen.
+    pub fn debug(mut self) -> Self {
+        self.debug = true;
+        self
+    }
+
+    /// Log each operation of the virtual machine, such as a push
+    /// or a call, to stderr at runtime.
+    pub fn traced(mut self) -> Self {
+        self.trace = true;
+        self
+    }
+
+    /// Report the most cells allocated on the heap at once
+    /// to stderr when the program exits.
+    pub fn report_heap_stats(mut self) -> Self {
+        self.heap_stats = true;
+        self
+    }
+
+    /// Write the body of the entry point directly in the program's
+    /// entry point, instead of calling it as a function.
+    pub fn inline_entry(mut self) -> Self {
+        self.inline_entry = true;
+        self
+    }
+}
+
+impl Target for TS {
+    fn get_name(&self) -> char {
+        't'
+    }
+
+    fn language(&self) -> &'static str {
+        "TypeScript"
+    }
+
+    fn is_standard(&self) -> bool {
+        true
+    }
+
+    fn std(&self) -> Cow<'static, str> {
+        Cow::Borrowed(include_str!("std/std.ts"))
+    }
+
+    fn core_prelude(&self) -> Cow<'static, str> {
+        // The tracing code in the core is only run when `TRACE` is true,
+        // and the heap is only reported when `HEAP_STATS` is true
+        Cow::Owned(format!(
+            "const TRACE: boolean = {};\nconst HEAP_STATS: boolean = {};\n{}",
+            self.trace,
+            self.heap_stats,
+            include_str!("core/core.ts")
+        ))
+    }
+
+    fn core_postlude(&self) -> Cow<'static, str> {
+        Cow::Borrowed("")
+    }
+
+    fn begin_entry_point(&self, global_scope_size: i32, memory_size: i32) -> String {
+        format!(
+            "async function OAKmain():Promise<void> {{\nlet vm = machine_new({}, {});\n",
+            global_scope_size,
+            global_scope_size + memory_size,
+        )
+    }
+
+    fn end_entry_point(&self) -> String {
+        String::from("\nmachine_drop(vm);\n}\nOAKmain();")
+    }
+
+    fn check_stack(&self, size: i32) -> String {
+        format!("machine_check_stack(vm, {});\n", size)
+    }
+
+    fn establish_stack_frame(&self, arg_size: i32, local_scope_size: i32) -> String {
+        format!(
+            "machine_establish_stack_frame(vm, {}, {});\n",
+            arg_size, local_scope_size
+        )
+    }
+
+    fn end_stack_frame(&self, return_size: i32, local_scope_size: i32) -> String {
+        format!(
+            "machine_end_stack_frame(vm, {}, {});\n",
+            return_size, local_scope_size
+        )
+    }
+
+    fn load_base_ptr(&self) -> String {
+        String::from("machine_load_base_ptr(vm);\n")
+    }
+
+    fn push(&self, n: f64) -> String {
+        format!("machine_push(vm, {});\n", self.float_literal(n))
+    }
+
+    fn infinity(&self) -> String {
+        String::from("Infinity")
+    }
+
+    fn nan(&self) -> String {
+        String::from("NaN")
+    }
+
+    fn add(&self) -> String {
+        String::from("machine_add(vm);\n")
+    }
+
+    fn subtract(&self) -> String {
+        String::from("machine_subtract(vm);\n")
+    }
+
+    fn multiply(&self) -> String {
+        String::from("machine_multiply(vm);\n")
+    }
+
+    fn divide(&self) -> String {
+        String::from("machine_divide(vm);\n")
+    }
+
+    fn modulus(&self) -> String {
+        String::from("machine_modulo(vm);\n")
+    }
+
+    fn bitand(&self) -> String {
+        String::from("machine_bitand(vm);\n")
+    }
+
+    fn bitor(&self) -> String {
+        String::from("machine_bitor(vm);\n")
+    }
+
+    fn bitxor(&self) -> String {
+        String::from("machine_bitxor(vm);\n")
+    }
+
+    fn shl(&self) -> String {
+        String::from("machine_shl(vm);\n")
+    }
+
+    fn shr(&self) -> String {
+        String::from("machine_shr(vm);\n")
+    }
+
+    fn sign(&self) -> String {
+        String::from("machine_sign(vm);\n")
+    }
+
+    fn truncate(&self, bits: i32, is_signed: bool) -> String {
+        format!("machine_truncate(vm, {}, {});\n", bits, is_signed)
+    }
+
+    fn allocate(&self) -> String {
+        String::from("machine_allocate(vm);\n")
+    }
+
+    fn debug_runtime(&self) -> bool {
+        self.debug
+    }
+
+    fn trace(&self) -> bool {
+        self.trace
+    }
+
+    fn heap_stats(&self) -> bool {
+        self.heap_stats
+    }
+
+    fn free(&self) -> String {
+        if self.debug {
+            String::from("machine_checked_free(vm);\n")
+        } else {
+            String::from("machine_free(vm);\n")
+        }
+    }
+
+    fn memset(&self, size: i32) -> String {
+        format!("machine_memset(vm, {});\n", size)
+    }
+
+    fn flush(&self) -> String {
+        String::from("machine_flush(vm);\n")
+    }
+
+    fn store(&self, size: i32) -> String {
+        if self.debug {
+            format!("machine_checked_store(vm, {});\n", size)
+        } else {
+            format!("machine_store(vm, {});\n", size)
+        }
+    }
+
+    fn emit_initialized_data(&self, address: i32, cells: &[f64]) -> String {
+        let cells = cells
+            .iter()
+            .map(|cell| self.float_literal(*cell))
+            .collect::<Vec<_>>();
+        format!(
+            "machine_store_data(vm, {}, new Float64Array([{}]));\n",
+            address,
+            cells.join(", ")
+        ) + &self.push(address as f64)
+    }
+
+    fn load(&self, size: i32) -> String {
+        if self.debug {
+            format!("machine_checked_load(vm, {});\n", size)
+        } else {
+            format!("machine_load(vm, {});\n", size)
+        }
+    }
+
+    fn load_byte(&self) -> String {
+        if self.debug {
+            String::from("machine_checked_load_byte(vm);\n")
+        } else {
+            String::from("machine_load_byte(vm);\n")
+        }
+    }
+
+    fn store_byte(&self) -> String {
+        if self.debug {
+            String::from("machine_checked_store_byte(vm);\n")
+        } else {
+            String::from("machine_store_byte(vm);\n")
+        }
+    }
+
+    fn random(&self) -> String {
+        String::from("machine_random(vm);\n")
+    }
+
+    fn seed_random(&self) -> String {
+        String::from("machine_seed_random(vm);\n")
+    }
+
+    fn clock(&self) -> String {
+        String::from("machine_clock(vm);\n")
+    }
+
+    fn fn_header(&self, name: String) -> String {
+        String::from("")
+    }
+
+    fn fn_definition(&self, name: String, body: String) -> String {
+        format!(
+            "async function {}(vm: machine): void {{\n{}}}\n",
+            name, body
+        )
+    }
+
+    fn inline_entry_point(&self) -> bool {
+        self.inline_entry
+    }
+
+    fn call_fn(&self, name: String) -> String {
+        format!("await {}(vm);\n", name)
+    }
+
+    fn call_foreign_fn(&self, name: String) -> String {
+        format!("await {}(vm);\n", name)
+    }
+
+    fn call_foreign_fn_with_context(&self, name: String) -> String {
+        format!("await {}(vm, vm.context);\n", name)
+    }
+
+    fn load_foreign_var(&self, name: String) -> String {
+        format!("machine_push(vm, {});\n", name)
+    }
+
+    fn store_foreign_var(&self, name: String) -> String {
+        format!("{} = machine_pop(vm);\n", name)
+    }
+
+    fn begin_while(&self, label: Option<&str>) -> String {
+        match label {
+            Some(label) => format!(
+                "{}: while (machine_pop(vm)) {{\n{}_body: {{\n",
+                label, label
+            ),
+            None => String::from("while (machine_pop(vm)) {\n"),
+        }
+    }
+
+    fn end_while_body(&self, label: Option<&str>) -> String {
+        match label {
+            Some(_) => String::from("}\n"),
+            None => String::new(),
+        }
+    }
+
+    fn end_while(&self, _label: Option<&str>) -> String {
+        String::from("}\n")
+    }
+
+    fn break_while(&self, label: &str) -> String {
+        format!("break {};\n", label)
+    }
+
+    // Leaving the labeled block around the body skips to its end
+    fn continue_while(&self, label: &str) -> String {
+        format!("break {}_body;\n", label)
+    }
+
+    fn begin_if(&self) -> String {
+        String::from("if (machine_pop(vm)) {\n")
+    }
+
+    fn begin_else(&self) -> String {
+        String::from("} else {\n")
+    }
+
+    fn end_if(&self) -> String {
+        String::from("}\n")
+    }
+
+    fn compile(&self, code: String) -> Result<PathBuf> {
+        if let Ok(_) = write("OUTPUT.ts", code) {
+            if let Ok(_) = Command::new("tsc")
+                .arg("OUTPUT.ts")
+                .arg("--outFile")
+                .arg("main.js")
+                .arg("--target")
+                .arg("ES2017")
+                .output()
+            {
+                if let Ok(_) = remove_file("OUTPUT.ts") {
+                    return Result::Ok(PathBuf::from("main.js"));
+                }
+            }
+        }
+        Result::Err(Error::new(ErrorKind::Other, "error compiling "))
+    }
+}
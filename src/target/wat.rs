@@ -0,0 +1,420 @@
+use super::{BuildInfo, Target};
+use std::{
+    borrow::Cow,
+    cell::{Cell, RefCell},
+    fs::{remove_file, write},
+    io::{Error, ErrorKind, Result},
+    path::PathBuf,
+    process::Command,
+};
+
+/// The WebAssembly text target. Its options keep their defaults unless they are
+/// set with the builder methods below.
+#[derive(Clone, Default)]
+pub struct Wat {
+    /// Check for invalid frees at runtime
+    debug: bool,
+    /// Write the entry point's body in place of its call
+    inline_entry: bool,
+    /// The number of unlabeled loops begun so far in the function
+    /// being assembled. Each is given a unique label to branch to.
+    loops: Cell<usize>,
+    /// The labels of the loops that haven't been ended yet, innermost last.
+    /// A loop is begun and ended by separate calls, so the label that
+    /// `begin_while` gives it is kept here until `end_while`.
+    open_loops: RefCell<Vec<String>>,
+}
+
+impl Wat {
+    /// A WebAssembly text target with every option off.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check for double frees and frees of memory that was never allocated
+    /// at runtime, and panic when they happen.
+    pub fn debug(mut self) -> Self {
+        self.debug = true;
+        self
+    }
+
+    /// Write the body of the entry point directly in the program's
+    /// entry point, instead of calling it as a function.
+    pub fn inline_entry(mut self) -> Self {
+        self.inline_entry = true;
+        self
+    }
+}
+
+impl Target for Wat {
+    fn get_name(&self) -> char {
+        'w'
+    }
+
+    fn language(&self) -> &'static str {
+        "WebAssembly"
+    }
+
+    fn is_standard(&self) -> bool {
+        true
+    }
+
+    fn std(&self) -> Cow<'static, str> {
+        Cow::Borrowed(include_str!("std/std.wat"))
+    }
+
+    fn core_prelude(&self) -> Cow<'static, str> {
+        Cow::Borrowed(include_str!("core/core.wat"))
+    }
+
+    // The module is opened by the core prelude
+    fn core_postlude(&self) -> Cow<'static, str> {
+        Cow::Borrowed(")\n")
+    }
+
+    fn manifest(&self, info: &BuildInfo) -> String {
+        info.lines()
+            .iter()
+            .map(|line| format!(";; {}\n", line))
+            .collect()
+    }
+
+    fn begin_entry_point(&self, global_scope_size: i32, memory_size: i32) -> String {
+        format!(
+            "\n(func $main (export \"_start\")\ni32.const {}\ni32.const {}\ncall $machine_new\n",
+            global_scope_size,
+            global_scope_size + memory_size
+        )
+    }
+
+    fn end_entry_point(&self) -> String {
+        String::from("call $machine_drop\n)\n")
+    }
+
+    fn check_stack(&self, size: i32) -> String {
+        format!("i32.const {}\ncall $machine_check_stack\n", size)
+    }
+
+    fn establish_stack_frame(&self, arg_size: i32, local_scope_size: i32) -> String {
+        format!(
+            "i32.const {}\ni32.const {}\ncall $machine_establish_stack_frame\n",
+            arg_size, local_scope_size
+        )
+    }
+
+    fn end_stack_frame(&self, return_size: i32, local_scope_size: i32) -> String {
+        format!(
+            "i32.const {}\ni32.const {}\ncall $machine_end_stack_frame\n",
+            return_size, local_scope_size
+        )
+    }
+
+    fn load_base_ptr(&self) -> String {
+        String::from("call $machine_load_base_ptr\n")
+    }
+
+    fn push(&self, n: f64) -> String {
+        format!("f64.const {}\ncall $machine_push\n", self.float_literal(n))
+    }
+
+    fn infinity(&self) -> String {
+        String::from("inf")
+    }
+
+    fn nan(&self) -> String {
+        String::from("nan")
+    }
+
+    fn add(&self) -> String {
+        String::from("call $machine_add\n")
+    }
+
+    fn subtract(&self) -> String {
+        String::from("call $machine_subtract\n")
+    }
+
+    fn multiply(&self) -> String {
+        String::from("call $machine_multiply\n")
+    }
+
+    fn divide(&self) -> String {
+        String::from("call $machine_divide\n")
+    }
+
+    fn modulus(&self) -> String {
+        String::from("call $machine_modulo\n")
+    }
+
+    fn bitand(&self) -> String {
+        String::from("call $machine_bitand\n")
+    }
+
+    fn bitor(&self) -> String {
+        String::from("call $machine_bitor\n")
+    }
+
+    fn bitxor(&self) -> String {
+        String::from("call $machine_bitxor\n")
+    }
+
+    fn shl(&self) -> String {
+        String::from("call $machine_shl\n")
+    }
+
+    fn shr(&self) -> String {
+        String::from("call $machine_shr\n")
+    }
+
+    fn sign(&self) -> String {
+        String::from("call $machine_sign\n")
+    }
+
+    // The runtime wraps integers around at the number of values
+    // of their width, which is too large for an `i32` at 32 bits
+    fn truncate(&self, bits: i32, is_signed: bool) -> String {
+        format!(
+            "f64.const {}\ni32.const {}\ncall $machine_truncate\n",
+            self.float_literal(2f64.powi(bits)),
+            is_signed as i32
+        )
+    }
+
+    fn allocate(&self) -> String {
+        String::from("call $machine_allocate\n")
+    }
+
+    fn debug_runtime(&self) -> bool {
+        self.debug
+    }
+
+    fn free(&self) -> String {
+        if self.debug {
+            String::from("call $machine_checked_free\n")
+        } else {
+            String::from("call $machine_free\n")
+        }
+    }
+
+    fn memset(&self, size: i32) -> String {
+        format!("i32.const {}\ncall $machine_memset\n", size)
+    }
+
+    fn flush(&self) -> String {
+        String::from("call $machine_flush\n")
+    }
+
+    fn store(&self, size: i32) -> String {
+        if self.debug {
+            format!("i32.const {}\ncall $machine_checked_store\n", size)
+        } else {
+            format!("i32.const {}\ncall $machine_store\n", size)
+        }
+    }
+
+    fn load(&self, size: i32) -> String {
+        if self.debug {
+            format!("i32.const {}\ncall $machine_checked_load\n", size)
+        } else {
+            format!("i32.const {}\ncall $machine_load\n", size)
+        }
+    }
+
+    fn load_byte(&self) -> String {
+        if self.debug {
+            String::from("call $machine_checked_load_byte\n")
+        } else {
+            String::from("call $machine_load_byte\n")
+        }
+    }
+
+    fn store_byte(&self) -> String {
+        if self.debug {
+            String::from("call $machine_checked_store_byte\n")
+        } else {
+            String::from("call $machine_store_byte\n")
+        }
+    }
+
+    fn random(&self) -> String {
+        String::from("call $machine_random\n")
+    }
+
+    fn seed_random(&self) -> String {
+        String::from("call $machine_seed_random\n")
+    }
+
+    fn clock(&self) -> String {
+        String::from("call $machine_clock\n")
+    }
+
+    fn fn_header(&self, _name: String) -> String {
+        String::new()
+    }
+
+    fn fn_definition(&self, name: String, body: String) -> String {
+        // The next function's loops are numbered from zero again
+        self.loops.set(0);
+        format!("\n(func ${}\n{})\n", name, body)
+    }
+
+    fn inline_entry_point(&self) -> bool {
+        self.inline_entry
+    }
+
+    fn call_fn(&self, name: String) -> String {
+        format!("call ${}\n", name)
+    }
+
+    // Foreign functions are WebAssembly functions
+    // without parameters, which use the machine directly
+    fn call_foreign_fn(&self, name: String) -> String {
+        format!("call ${}\n", name)
+    }
+
+    fn call_foreign_fn_with_context(&self, name: String) -> String {
+        format!("global.get $context\ncall ${}\n", name)
+    }
+
+    fn load_foreign_var(&self, name: String) -> String {
+        format!("global.get ${}\ncall $machine_push\n", name)
+    }
+
+    fn store_foreign_var(&self, name: String) -> String {
+        format!("call $machine_pop\nglobal.set ${}\n", name)
+    }
+
+    // A loop is a `loop` block, wrapped in a block that `break_while`
+    // branches out of. Its body is wrapped in another block that
+    // `continue_while` branches out of, to the loop's condition.
+    fn begin_while(&self, label: Option<&str>) -> String {
+        let label = match label {
+            Some(label) => String::from(label),
+            None => {
+                let id = self.loops.get();
+                self.loops.set(id + 1);
+                format!("while{}", id)
+            }
+        };
+        let result = format!(
+            "block $break_{}\nloop $loop_{}\ncall $machine_pop\nf64.const 0\nf64.eq\nbr_if $break_{}\nblock $continue_{}\n",
+            label, label, label, label
+        );
+        self.open_loops.borrow_mut().push(label);
+        result
+    }
+
+    fn end_while_body(&self, _label: Option<&str>) -> String {
+        String::from("end\n")
+    }
+
+    fn end_while(&self, _label: Option<&str>) -> String {
+        match self.open_loops.borrow_mut().pop() {
+            Some(label) => format!("br $loop_{}\nend\nend\n", label),
+            None => String::new(),
+        }
+    }
+
+    fn break_while(&self, label: &str) -> String {
+        format!("br $break_{}\n", label)
+    }
+
+    fn continue_while(&self, label: &str) -> String {
+        format!("br $continue_{}\n", label)
+    }
+
+    fn begin_if(&self) -> String {
+        String::from("call $machine_pop\nf64.const 0\nf64.ne\nif\n")
+    }
+
+    fn begin_else(&self) -> String {
+        String::from("else\n")
+    }
+
+    fn end_if(&self) -> String {
+        String::from("end\n")
+    }
+
+    // Blocks are delimited by parentheses in the runtime, and by
+    // the `block`, `loop`, `if`, `else`, and `end` instructions
+    // in the generated code
+    fn pretty_print(&self, code: String) -> String {
+        let mut result = String::new();
+        let mut depth: usize = 0;
+        for line in code.lines() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            // Count the parentheses outside of strings and comments
+            let (mut opens, mut closes) = (0, 0);
+            let mut in_string = false;
+            let mut escaped = false;
+            let mut chars = line.chars().peekable();
+            while let Some(ch) = chars.next() {
+                if in_string {
+                    if escaped {
+                        escaped = false
+                    } else if ch == '\\' {
+                        escaped = true
+                    } else if ch == '"' {
+                        in_string = false
+                    }
+                } else if ch == ';' && chars.peek() == Some(&';') {
+                    break;
+                } else {
+                    match ch {
+                        '"' => in_string = true,
+                        '(' => opens += 1,
+                        ')' => closes += 1,
+                        _ => {}
+                    }
+                }
+            }
+
+            let instruction = line.split_whitespace().next().unwrap_or("");
+            let level = if line.starts_with(')') || instruction == "end" || instruction == "else" {
+                depth.saturating_sub(1)
+            } else {
+                depth
+            };
+            result += &"  ".repeat(level);
+            result += line;
+            result += "\n";
+
+            depth = (depth + opens).saturating_sub(closes);
+            match instruction {
+                "block" | "loop" | "if" => depth += 1,
+                "end" => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+        }
+        result
+    }
+
+    fn compile(&self, code: String) -> Result<PathBuf> {
+        if write("main.wat", code).is_ok() {
+            match Command::new("wat2wasm")
+                .arg("main.wat")
+                .arg("-o")
+                .arg("main.wasm")
+                .output()
+            {
+                Ok(output) => {
+                    if remove_file("main.wat").is_ok() && output.status.success() {
+                        return Result::Ok(PathBuf::from("main.wasm"));
+                    }
+                }
+                // Without wat2wasm, the text format is the output
+                Err(ref e) if e.kind() == ErrorKind::NotFound => {
+                    return Result::Ok(PathBuf::from("main.wat"));
+                }
+                Err(_) => {}
+            }
+        }
+        Result::Err(Error::new(
+            ErrorKind::Other,
+            "could not compile output wat code",
+        ))
+    }
+}
@@ -0,0 +1,383 @@
+use super::Target;
+use std::{
+    borrow::Cow,
+    env::consts::EXE_SUFFIX,
+    fs::{remove_file, write},
+    io::{Error, ErrorKind, Result},
+    path::PathBuf,
+    process::Command,
+};
+
+/// The Zig target. Its options keep their defaults unless they are
+/// set with the builder methods below.
+#[derive(Clone, Copy, Default)]
+pub struct Zig {
+    /// Check for invalid frees at runtime
+    debug: bool,
+    /// Log each operation of the virtual machine to stderr
+    trace: bool,
+    /// Report the peak heap usage to stderr at exit
+    heap_stats: bool,
+    /// Write the entry point's body in place of its call
+    inline_entry: bool,
+}
+
+impl Zig {
+    /// A Zig target with every option off.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check for double frees and frees of memory that was never allocated
+    /// at runtime, and panic when they happen.
+    pub fn debug(mut self) -> Self {
+        self.debug = true;
+        self
+    }
+
+    /// Log each operation of the virtual machine, such as a push
+    /// or a call, to stderr at runtime.
+    pub fn traced(mut self) -> Self {
+        self.trace = true;
+        self
+    }
+
+    /// Report the most cells allocated on the heap at once
+    /// to stderr when the program exits.
+    pub fn report_heap_stats(mut self) -> Self {
+        self.heap_stats = true;
+        self
+    }
+
+    /// Write the body of the entry point directly in the program's
+    /// entry point, instead of calling it as a function.
+    pub fn inline_entry(mut self) -> Self {
+        self.inline_entry = true;
+        self
+    }
+}
+
+impl Target for Zig {
+    fn get_name(&self) -> char {
+        'z'
+    }
+
+    fn language(&self) -> &'static str {
+        "Zig"
+    }
+
+    fn is_standard(&self) -> bool {
+        true
+    }
+
+    fn std(&self) -> Cow<'static, str> {
+        Cow::Borrowed(include_str!("std/std.zig"))
+    }
+
+    fn core_prelude(&self) -> Cow<'static, str> {
+        // The tracing code in the core is only run when `TRACE` is true,
+        // the heap is only reported when `HEAP_STATS` is true, and the
+        // runtime checks in the standard library only run when `DEBUG` is true
+        Cow::Owned(format!(
+            "{}\nconst TRACE = {};\nconst HEAP_STATS = {};\nconst DEBUG = {};\n",
+            include_str!("core/core.zig"),
+            self.trace,
+            self.heap_stats,
+            self.debug
+        ))
+    }
+
+    fn core_postlude(&self) -> Cow<'static, str> {
+        Cow::Borrowed("")
+    }
+
+    fn begin_entry_point(&self, global_scope_size: i32, memory_size: i32) -> String {
+        format!(
+            "pub fn main() void {{\nconst vm = Machine.new({}, {});\n",
+            global_scope_size,
+            global_scope_size + memory_size,
+        )
+    }
+
+    fn end_entry_point(&self) -> String {
+        String::from("\nvm.drop();\n}")
+    }
+
+    fn check_stack(&self, size: i32) -> String {
+        format!("vm.check_stack({});\n", size)
+    }
+
+    fn establish_stack_frame(&self, arg_size: i32, local_scope_size: i32) -> String {
+        format!(
+            "vm.establish_stack_frame({}, {});\n",
+            arg_size, local_scope_size
+        )
+    }
+
+    fn end_stack_frame(&self, return_size: i32, local_scope_size: i32) -> String {
+        format!(
+            "vm.end_stack_frame({}, {});\n",
+            return_size, local_scope_size
+        )
+    }
+
+    fn load_base_ptr(&self) -> String {
+        String::from("vm.load_base_ptr();\n")
+    }
+
+    fn push(&self, n: f64) -> String {
+        format!("vm.push({});\n", self.float_literal(n))
+    }
+
+    fn infinity(&self) -> String {
+        String::from("std.math.inf(f64)")
+    }
+
+    fn nan(&self) -> String {
+        String::from("std.math.nan(f64)")
+    }
+
+    fn add(&self) -> String {
+        String::from("vm.add();\n")
+    }
+
+    fn subtract(&self) -> String {
+        String::from("vm.subtract();\n")
+    }
+
+    fn multiply(&self) -> String {
+        String::from("vm.multiply();\n")
+    }
+
+    fn divide(&self) -> String {
+        String::from("vm.divide();\n")
+    }
+
+    fn modulus(&self) -> String {
+        String::from("vm.modulo();\n")
+    }
+
+    fn bitand(&self) -> String {
+        String::from("vm.bitand();\n")
+    }
+
+    fn bitor(&self) -> String {
+        String::from("vm.bitor();\n")
+    }
+
+    fn bitxor(&self) -> String {
+        String::from("vm.bitxor();\n")
+    }
+
+    fn shl(&self) -> String {
+        String::from("vm.shl();\n")
+    }
+
+    fn shr(&self) -> String {
+        String::from("vm.shr();\n")
+    }
+
+    fn sign(&self) -> String {
+        String::from("vm.sign();\n")
+    }
+
+    fn truncate(&self, bits: i32, is_signed: bool) -> String {
+        format!("vm.truncate({}, {});\n", bits, is_signed)
+    }
+
+    fn allocate(&self) -> String {
+        String::from("vm.alloc();\n")
+    }
+
+    fn debug_runtime(&self) -> bool {
+        self.debug
+    }
+
+    fn trace(&self) -> bool {
+        self.trace
+    }
+
+    fn heap_stats(&self) -> bool {
+        self.heap_stats
+    }
+
+    fn free(&self) -> String {
+        if self.debug {
+            String::from("vm.checked_free();\n")
+        } else {
+            String::from("vm.free();\n")
+        }
+    }
+
+    fn memset(&self, size: i32) -> String {
+        format!("vm.memset({});\n", size)
+    }
+
+    fn flush(&self) -> String {
+        String::from("vm.flush();\n")
+    }
+
+    fn store(&self, size: i32) -> String {
+        if self.debug {
+            format!("vm.checked_store({});\n", size)
+        } else {
+            format!("vm.store({});\n", size)
+        }
+    }
+
+    fn emit_initialized_data(&self, address: i32, cells: &[f64]) -> String {
+        let cells = cells
+            .iter()
+            .map(|cell| self.float_literal(*cell))
+            .collect::<Vec<_>>();
+        format!(
+            "vm.store_data({}, &[_]f64{{ {} }});\n",
+            address,
+            cells.join(", ")
+        ) + &self.push(address as f64)
+    }
+
+    fn load(&self, size: i32) -> String {
+        if self.debug {
+            format!("vm.checked_load({});\n", size)
+        } else {
+            format!("vm.load({});\n", size)
+        }
+    }
+
+    fn load_byte(&self) -> String {
+        if self.debug {
+            String::from("vm.checked_load_byte();\n")
+        } else {
+            String::from("vm.load_byte();\n")
+        }
+    }
+
+    fn store_byte(&self) -> String {
+        if self.debug {
+            String::from("vm.checked_store_byte();\n")
+        } else {
+            String::from("vm.store_byte();\n")
+        }
+    }
+
+    fn random(&self) -> String {
+        String::from("vm.random();\n")
+    }
+
+    fn seed_random(&self) -> String {
+        String::from("vm.seed_random();\n")
+    }
+
+    fn clock(&self) -> String {
+        String::from("vm.clock();\n")
+    }
+
+    fn fn_header(&self, _name: String) -> String {
+        String::new()
+    }
+
+    fn fn_definition(&self, name: String, body: String) -> String {
+        // Zig rejects parameters that are never used, so
+        // a function with an empty body discards the machine
+        let body = if body.trim().is_empty() {
+            String::from("_ = vm;")
+        } else {
+            body
+        };
+        format!("\n\nfn {}(vm: *Machine) void {{\n{}\n}}\n", name, body)
+    }
+
+    fn inline_entry_point(&self) -> bool {
+        self.inline_entry
+    }
+
+    fn call_fn(&self, name: String) -> String {
+        format!("{}(vm);\n", name)
+    }
+
+    fn call_foreign_fn(&self, name: String) -> String {
+        format!("{}(vm);\n", name)
+    }
+
+    fn call_foreign_fn_with_context(&self, name: String) -> String {
+        format!("{}(vm, vm.context);\n", name)
+    }
+
+    fn load_foreign_var(&self, name: String) -> String {
+        format!("vm.push({});\n", name)
+    }
+
+    fn store_foreign_var(&self, name: String) -> String {
+        // Zig does not convert numbers implicitly, so
+        // foreign variables must be `f64`s
+        format!("{} = vm.pop();\n", name)
+    }
+
+    // The body is a labeled block, so that breaking out of it skips to
+    // its end. Zig rejects labels that are never used, so the block and
+    // the loop are always left through their labels at their ends.
+    fn begin_while(&self, label: Option<&str>) -> String {
+        match label {
+            Some(label) => format!("{}: while (vm.pop() != 0) {{\n{}_body: {{\n", label, label),
+            None => String::from("while (vm.pop() != 0) {\n"),
+        }
+    }
+
+    fn end_while_body(&self, label: Option<&str>) -> String {
+        match label {
+            Some(label) => format!("break :{}_body;\n}}\n", label),
+            None => String::new(),
+        }
+    }
+
+    fn end_while(&self, label: Option<&str>) -> String {
+        match label {
+            Some(label) => format!("continue :{};\n}}\n", label),
+            None => String::from("}\n"),
+        }
+    }
+
+    // Zig rejects code after a `break` as unreachable,
+    // which the condition of the `if` hides from it
+    fn break_while(&self, label: &str) -> String {
+        format!("if (true) break :{};\n", label)
+    }
+
+    fn continue_while(&self, label: &str) -> String {
+        format!("if (true) break :{}_body;\n", label)
+    }
+
+    fn begin_if(&self) -> String {
+        String::from("if (vm.pop() != 0) {\n")
+    }
+
+    fn begin_else(&self) -> String {
+        String::from("} else {\n")
+    }
+
+    fn end_if(&self) -> String {
+        String::from("}\n")
+    }
+
+    fn compile(&self, code: String) -> Result<PathBuf> {
+        if write("main.zig", code).is_ok() {
+            if let Ok(output) = Command::new("zig")
+                .arg("build-exe")
+                .arg("main.zig")
+                .output()
+            {
+                if remove_file("main.zig").is_ok() && output.status.success() {
+                    // `zig build-exe` names the executable after the source file,
+                    // and may leave its object file next to it
+                    let _ = remove_file("main.o");
+                    return Result::Ok(PathBuf::from(format!("main{}", EXE_SUFFIX)));
+                }
+            }
+        }
+        Result::Err(Error::new(
+            ErrorKind::Other,
+            "could not compile output zig code. is zig installed?",
+        ))
+    }
+}
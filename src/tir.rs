@@ -3,14 +3,15 @@ use std::{
     fmt::{Display, Error, Formatter},
     fs::read_to_string,
     path::PathBuf,
-    process::exit,
 };
 
 use crate::{
+    asm::AsmExpression,
     hir::{
-        HirConstant, HirDeclaration, HirExpression, HirFunction, HirProgram, HirStatement,
-        HirStructure, HirType,
+        HirConstant, HirDeclaration, HirError, HirExpression, HirFunction, HirProgram,
+        HirStatement, HirStructure, HirType,
     },
+    mir::IntWidth,
     parse, Identifier, StringLiteral, Target,
 };
 
@@ -33,6 +34,46 @@ pub enum TirError {
     /// The compiler is only allowed to call this method.
     /// This is to prevent memory leaks.
     ExplicitCopy,
+    /// A destructuring `let` can only be used as a statement in a body,
+    /// because it defines more than one variable.
+    MisplacedDestructure,
+    /// A for-each loop is used, but no structure implements
+    /// the `has_next` and `next` methods to iterate over.
+    NoIteratorDefined,
+    /// A generic function is called with a type argument,
+    /// but no generic function with that name is defined.
+    GenericFunctionNotDefined(Identifier),
+    /// Generic functions instantiate each other without end,
+    /// such as `f<T>` calling `f::<&T>`.
+    TooManyGenericInstances(Identifier),
+    /// A structure or one of its members is aligned to
+    /// a boundary that isn't a positive number of cells.
+    InvalidAlignment(Identifier, i32),
+    /// A parameter without a default value comes after one with
+    /// a default value, so it could never be left out of a call.
+    MissingDefault(Identifier, Identifier),
+    /// A method has a parameter with a default value. Only
+    /// functions can have parameters with default values.
+    MethodDefault(Identifier, Identifier),
+    /// A method is marked with `#[export]`. Only functions
+    /// can be exported from a shared library.
+    MethodExport(Identifier, Identifier),
+    /// Named arguments are passed to a function that isn't
+    /// defined, so its parameter names aren't known.
+    KeywordCallNotDefined(Identifier),
+    /// A positional argument comes after a named argument in a call.
+    PositionalAfterKeyword(Identifier),
+    /// A named argument doesn't match any of the function's parameters.
+    UnknownKeyword(Identifier, Identifier),
+    /// A parameter is given more than one argument in a call.
+    DuplicateArgument(Identifier, Identifier),
+    /// A call with named arguments leaves out a
+    /// parameter that doesn't have a default value.
+    MissingArgument(Identifier, Identifier),
+    /// An included file isn't in any of the directories searched for it.
+    IncludeFileNotFound(String, Vec<PathBuf>),
+    /// A foreign file isn't in any of the directories searched for it.
+    ExternFileNotFound(String, Vec<PathBuf>),
 }
 
 impl Display for TirError {
@@ -52,10 +93,94 @@ impl Display for TirError {
                 write!(f, "type '{}' is not defined", type_name)
             }
             Self::ExplicitCopy => write!(f, "cannot explicitly call copy constructors"),
+            Self::MisplacedDestructure => {
+                write!(
+                    f,
+                    "destructuring 'let' statements can only be used in a body"
+                )
+            }
+            Self::NoIteratorDefined => write!(
+                f,
+                "for-each loops require a type with the methods 'has_next(self: &T) -> bool' and 'next(self: &T)'"
+            ),
+            Self::GenericFunctionNotDefined(name) => {
+                write!(f, "generic function '{}' is not defined", name)
+            }
+            Self::TooManyGenericInstances(name) => write!(
+                f,
+                "the generic function '{}' is compiled for too many types. does it call itself with a different type argument?",
+                name
+            ),
+            Self::InvalidAlignment(type_name, alignment) => write!(
+                f,
+                "type '{}' is aligned to '{}' cells. alignments must be at least one cell",
+                type_name, alignment
+            ),
+            Self::MissingDefault(fn_name, arg) => write!(
+                f,
+                "the parameter '{}' of the function '{}' must have a default value, because a parameter before it has one",
+                arg, fn_name
+            ),
+            Self::MethodDefault(type_name, method) => write!(
+                f,
+                "the method '{}' of type '{}' has a default parameter value, but only functions can have them",
+                method, type_name
+            ),
+            Self::MethodExport(type_name, method) => write!(
+                f,
+                "the method '{}' of type '{}' is marked with '#[export]', but only functions can be exported",
+                method, type_name
+            ),
+            Self::KeywordCallNotDefined(fn_name) => write!(
+                f,
+                "named arguments are passed to the function '{}', which is not defined",
+                fn_name
+            ),
+            Self::PositionalAfterKeyword(fn_name) => write!(
+                f,
+                "a positional argument comes after a named argument in the call to '{}'",
+                fn_name
+            ),
+            Self::UnknownKeyword(fn_name, keyword) => write!(
+                f,
+                "the function '{}' has no parameter named '{}'",
+                fn_name, keyword
+            ),
+            Self::DuplicateArgument(fn_name, arg) => write!(
+                f,
+                "the parameter '{}' is given more than one argument in the call to '{}'",
+                arg, fn_name
+            ),
+            Self::MissingArgument(fn_name, arg) => write!(
+                f,
+                "the call to '{}' is missing an argument for the parameter '{}'",
+                fn_name, arg
+            ),
+            Self::IncludeFileNotFound(filename, searched) => write!(
+                f,
+                "could not find included file '{}', searched in {}",
+                filename,
+                quote_paths(searched)
+            ),
+            Self::ExternFileNotFound(filename, searched) => write!(
+                f,
+                "could not find foreign file '{}', searched in {}",
+                filename,
+                quote_paths(searched)
+            ),
         }
     }
 }
 
+/// Quote each path in a list, and separate them with commas
+fn quote_paths(paths: &[PathBuf]) -> String {
+    paths
+        .iter()
+        .map(|path| format!("'{}'", path.display()))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 #[derive(Clone, Debug)]
 pub struct TirProgram(Vec<TirDeclaration>, i32);
 
@@ -68,6 +193,13 @@ impl TirProgram {
         &mut self.0
     }
 
+    /// Set the memory used for the stack and heap
+    /// when the program doesn't set it with `#[memory(n)]`
+    pub fn set_memory_size(&mut self, memory_size: i32) -> &mut Self {
+        self.1 = memory_size;
+        self
+    }
+
     /// Add a prefix to every include statement in this program.
     /// This is used to include files in other directories.
     pub fn set_include_dir(&mut self, include_dir: &PathBuf) -> &mut Self {
@@ -96,124 +228,311 @@ impl TirProgram {
         self
     }
 
+    /// Compile this program to HIR. The includes and conditional compilation
+    /// flags are expanded in a copy of the program's declarations, so the same
+    /// program can be compiled again, such as once for each target.
+    /// Included and foreign files are looked for in the directories of the
+    /// `search_path` first, and then in the working directory.
     pub fn compile(
-        &mut self,
+        &self,
         cwd: &PathBuf,
+        search_path: &[PathBuf],
         constants: &mut BTreeMap<Identifier, TirConstant>,
     ) -> Result<HirProgram, TirError> {
-        let mut hir_decls = vec![];
+        let mut decls = self.0.clone();
+        let mut hir_decls;
+
+        loop {
+            hir_decls = vec![];
+            // Iterate over the declarations and retreive the constants
+            for decl in &decls {
+                if let TirDeclaration::Constant(doc, name, constant) = decl {
+                    constants.insert(name.clone(), constant.clone());
+                    hir_decls.push(HirDeclaration::Constant(
+                        doc.clone(),
+                        name.clone(),
+                        constant.clone(),
+                    ))
+                }
+            }
 
-        // Iterate over the declarations and retreive the constants
-        for decl in self.get_declarations() {
-            if let TirDeclaration::Constant(doc, name, constant) = decl {
-                constants.insert(name.clone(), constant.clone());
-                hir_decls.push(HirDeclaration::Constant(
-                    doc.clone(),
-                    name.clone(),
-                    constant.clone(),
-                ))
+            // Find the first declaration that adds other declarations to the program
+            let position = decls.iter().position(|decl| match decl {
+                TirDeclaration::Include(_)
+                | TirDeclaration::If(_, _)
+                | TirDeclaration::IfElse(_, _, _) => true,
+                _ => false,
+            });
+
+            if let Some(i) = position {
+                // Remove the directive so it does not get computed again,
+                // and add the declarations it expands to in its place.
+                let decl = decls.remove(i);
+                let expansion =
+                    Self::expand(&decl, &decls, &hir_decls, cwd, search_path, constants)?;
+                decls.extend(expansion);
+            } else {
+                break;
             }
         }
 
-        for (i, decl) in self.get_declarations().clone().iter().enumerate() {
+        let expanded = Self(decls, self.1);
+        for decl in &expanded.0 {
             match decl {
-                TirDeclaration::Include(filename) => {
-                    let filename = filename.clone();
-                    // This takes the path of the file in the `include` flag
-                    // and appends it to the directory of the file which is
-                    // including it.
-                    //
-                    // So, if `src/main.ok` includes "lib/all.ok",
-                    // `file_path` will be equal to "src/lib/all.ok"
-                    let file_path = cwd.join(filename.clone());
-                    if let Ok(contents) = read_to_string(file_path.clone()) {
-                        // Get the directory of the included file.
-
-                        // If `src/main.ok` includes "lib/all.ok",
-                        // `include_path` will be equal to "src/lib/"
-                        let include_path = if let Some(dir) = file_path.parent() {
-                            PathBuf::from(dir)
-                        } else {
-                            PathBuf::from("./")
-                        };
-
-                        // Remove the include directive so it does not get computed again
-                        self.get_declarations().remove(i);
-
-                        // Add the contents of the included file to this file
-                        self.get_declarations().extend(
-                            parse(&filename, contents)
-                                // The included file might be in a different folder.
-                                // So, compile the included file with the file's folder
-                                // as the working directory.
-                                .set_include_dir(&match include_path.strip_prefix(cwd) {
-                                    Ok(path) => path.to_path_buf(),
-                                    Err(_) => include_path,
-                                })
-                                .get_declarations()
-                                .clone(),
-                        );
-
-                        // Use recursion to deal with new include directives
-                        return self.compile(cwd, constants);
-                    } else {
-                        eprintln!("error: could not include file '{:?}'", file_path);
-                        exit(1);
-                    }
+                TirDeclaration::Constant(_, _, _) => {}
+                TirDeclaration::Extern(filename) => {
+                    let file_path =
+                        Self::find_file(filename, cwd, search_path).map_err(|searched| {
+                            TirError::ExternFileNotFound(filename.clone(), searched)
+                        })?;
+                    // A file in the working directory keeps its name, which
+                    // is joined with the working directory when it's compiled
+                    let file_path = match file_path.strip_prefix(cwd) {
+                        Ok(path) => path.to_path_buf(),
+                        Err(_) => file_path,
+                    };
+                    hir_decls.push(HirDeclaration::Extern(
+                        file_path.to_string_lossy().to_string(),
+                    ))
+                }
+                TirDeclaration::ExternVar(doc, foreign_name, name, t) => {
+                    hir_decls.extend(Self::extern_var_accessors(doc, foreign_name, name, t))
                 }
+                _ => hir_decls.push(decl.to_hir_decl(cwd, &expanded.0)?),
+            }
+        }
 
-                TirDeclaration::If(cond, code) => {
-                    // Remove the include directive so it does not get computed again
-                    self.get_declarations().remove(i);
+        hir_decls.extend(expanded.instantiate_generics()?);
+        Ok(HirProgram::new(hir_decls, self.1))
+    }
 
-                    if let Ok(val) = cond.to_value(&hir_decls, constants) {
-                        // If the constant expression evaluates to true,
-                        // Then add the contents of the block to this program.
-                        if val != 0.0 {
-                            self.get_declarations()
-                                .extend(code.clone().get_declarations().clone());
-                        }
-                    }
+    /// Create the functions that read and write a foreign global variable.
+    /// The getter has the binding's name, and the setter prefixes it with `set_`.
+    fn extern_var_accessors(
+        doc: &Option<String>,
+        foreign_name: &String,
+        name: &Identifier,
+        t: &TirType,
+    ) -> Vec<HirDeclaration> {
+        let hir_type = t.to_hir_type();
+        let value = String::from("value");
+        // Sized integers are truncated to their width before they're stored
+        let mut stored = HirExpression::Variable(value.clone());
+        if let TirType::Integer(_) = t {
+            stored = HirExpression::TypeCast(Box::new(stored), hir_type.clone());
+        }
 
-                    // Use recursion to deal with new include directives
-                    return self.compile(cwd, constants);
-                }
+        vec![
+            HirDeclaration::Function(HirFunction::new(
+                doc.clone(),
+                name.clone(),
+                vec![],
+                hir_type.clone(),
+                vec![HirStatement::Return(vec![
+                    // Foreign variables are loaded as &void, like the results of foreign
+                    // functions. Casting them also checks that they fit in a single cell.
+                    HirExpression::TypeCast(
+                        Box::new(HirExpression::ForeignLoad(foreign_name.clone())),
+                        hir_type.clone(),
+                    ),
+                ])],
+                false,
+            )),
+            HirDeclaration::Function(HirFunction::new(
+                doc.clone(),
+                format!("set_{}", name),
+                vec![(value, hir_type)],
+                HirType::Void,
+                vec![HirStatement::Expression(HirExpression::ForeignStore(
+                    foreign_name.clone(),
+                    Box::new(stored),
+                ))],
+                false,
+            )),
+        ]
+    }
+
+    /// Find the file named by an include or extern directive. The directories
+    /// of the search path are looked in first, and then the working directory.
+    /// If the file isn't in any of them, the directories searched are returned.
+    fn find_file(
+        filename: &str,
+        cwd: &PathBuf,
+        search_path: &[PathBuf],
+    ) -> Result<PathBuf, Vec<PathBuf>> {
+        for dir in search_path {
+            let file_path = dir.join(filename);
+            if file_path.is_file() {
+                // A relative search directory is relative to where the compiler
+                // is run, instead of the working directory. So, the path is made
+                // absolute before the files that it includes are joined to it.
+                return Ok(file_path.canonicalize().unwrap_or(file_path));
+            }
+        }
+
+        // This takes the path of the file in the directive
+        // and appends it to the directory of the file which is
+        // including it.
+        //
+        // So, if `src/main.ok` includes "lib/all.ok",
+        // `file_path` will be equal to "src/lib/all.ok"
+        let file_path = cwd.join(filename);
+        if file_path.is_file() {
+            Ok(file_path)
+        } else {
+            let mut searched = search_path.to_vec();
+            searched.push(cwd.clone());
+            Err(searched)
+        }
+    }
 
-                TirDeclaration::IfElse(cond, then_code, else_code) => {
-                    // Remove the include directive so it does not get computed again
-                    self.get_declarations().remove(i);
+    /// Get the declarations that an include or conditional compilation
+    /// flag adds to the program. `decls` are the rest of the program's
+    /// declarations, and `hir_decls` are its constants.
+    fn expand(
+        decl: &TirDeclaration,
+        decls: &Vec<TirDeclaration>,
+        hir_decls: &Vec<HirDeclaration>,
+        cwd: &PathBuf,
+        search_path: &[PathBuf],
+        constants: &BTreeMap<Identifier, TirConstant>,
+    ) -> Result<Vec<TirDeclaration>, TirError> {
+        Ok(match decl {
+            TirDeclaration::Include(filename) => {
+                let file_path =
+                    Self::find_file(filename, cwd, search_path).map_err(|searched| {
+                        TirError::IncludeFileNotFound(filename.clone(), searched)
+                    })?;
+                if let Ok(contents) = read_to_string(file_path.clone()) {
+                    // Get the directory of the included file.
+
+                    // If `src/main.ok` includes "lib/all.ok",
+                    // `include_path` will be equal to "src/lib/"
+                    let include_path = if let Some(dir) = file_path.parent() {
+                        PathBuf::from(dir)
+                    } else {
+                        PathBuf::from("./")
+                    };
 
                     // Add the contents of the included file to this file
-                    if let Ok(val) = cond.to_value(&hir_decls, constants) {
-                        // If the constant expression evaluates to true,
-                        if val != 0.0 {
-                            // Then add the contents of the block to this program.
-                            self.get_declarations()
-                                .extend(then_code.clone().get_declarations().clone());
-                        } else {
-                            // Otherwise, add the contents of the `else` block
-                            // to this program.
-                            self.get_declarations()
-                                .extend(else_code.clone().get_declarations().clone());
-                        }
-                    }
+                    parse(filename, contents)
+                        // The included file might be in a different folder.
+                        // So, compile the included file with the file's folder
+                        // as the working directory.
+                        .set_include_dir(&match include_path.strip_prefix(cwd) {
+                            Ok(path) => path.to_path_buf(),
+                            Err(_) => include_path,
+                        })
+                        .0
+                        .clone()
+                } else {
+                    return Err(TirError::IncludeFileNotFound(
+                        filename.clone(),
+                        vec![file_path],
+                    ));
+                }
+            }
 
-                    // Use recursion to deal with new include directives
-                    return self.compile(cwd, constants);
+            TirDeclaration::If(cond, code) => {
+                let mut outlines = hir_decls.clone();
+                outlines.extend(outline_structures(decls));
+                match cond.to_value(&outlines, constants) {
+                    // If the constant expression evaluates to true,
+                    // Then add the contents of the block to this program.
+                    Ok(val) if val != 0.0 => code.0.clone(),
+                    _ => vec![],
                 }
-                _ => {}
             }
-        }
 
+            TirDeclaration::IfElse(cond, then_code, else_code) => {
+                let mut outlines = hir_decls.clone();
+                outlines.extend(outline_structures(decls));
+                match cond.to_value(&outlines, constants) {
+                    // If the constant expression evaluates to true,
+                    // Then add the contents of the block to this program.
+                    Ok(val) if val != 0.0 => then_code.0.clone(),
+                    // Otherwise, add the contents of the `else` block
+                    // to this program.
+                    Ok(_) => else_code.0.clone(),
+                    _ => vec![],
+                }
+            }
+
+            _ => vec![],
+        })
+    }
+
+    /// The most copies of generic functions that can be compiled.
+    /// This stops generic functions that instantiate each other
+    /// without end from hanging the compiler.
+    const MAX_GENERIC_INSTANCES: usize = 1024;
+
+    /// Compile a copy of each generic function for every
+    /// type argument that it is called with.
+    fn instantiate_generics(&self) -> Result<Vec<HirDeclaration>, TirError> {
+        // Find the generic function calls in the rest of the program
+        let mut worklist = vec![];
         for decl in &self.0 {
             match decl {
-                TirDeclaration::Constant(_, _, _) => {}
-                _ => hir_decls.push(decl.to_hir_decl(cwd, &self.0)?),
+                TirDeclaration::Function(func) => {
+                    func.monomorphize(None, &mut worklist);
+                }
+                TirDeclaration::Structure(structure) => {
+                    for method in &structure.methods {
+                        method.monomorphize(None, &mut worklist);
+                    }
+                }
+                _ => {}
             }
         }
 
-        Ok(HirProgram::new(hir_decls, self.1))
+        let mut instantiated = vec![];
+        let mut result = vec![];
+        while let Some((name, t)) = worklist.pop() {
+            if instantiated.contains(&(name.clone(), t.clone())) {
+                continue;
+            }
+            if instantiated.len() >= Self::MAX_GENERIC_INSTANCES {
+                return Err(TirError::TooManyGenericInstances(name));
+            }
+
+            let mut template = None;
+            for decl in &self.0 {
+                if let TirDeclaration::GenericFunction(param, func) = decl {
+                    if func.name == name {
+                        template = Some((param, func));
+                    }
+                }
+            }
+
+            if let Some((param, func)) = template {
+                // Generic calls in the new copy are added to the worklist
+                let mut instance = func.monomorphize(Some((param, &t)), &mut worklist);
+                instance.name = TirFunction::generic_name(&name, &t);
+                result.push(HirDeclaration::Function(instance.to_hir_fn(&self.0)?));
+            } else {
+                return Err(TirError::GenericFunctionNotDefined(name));
+            }
+            instantiated.push((name, t));
+        }
+        Ok(result)
+    }
+}
+
+/// Convert each structure in a list of declarations into an HIR structure
+/// without any methods, so that the sizes of types can be found before
+/// the structures are compiled. A structure whose members aren't defined
+/// yet is left out.
+fn outline_structures(decls: &Vec<TirDeclaration>) -> Vec<HirDeclaration> {
+    let mut result = vec![];
+    for decl in decls {
+        if let TirDeclaration::Structure(structure) = decl {
+            if let Ok(outline) = structure.to_hir_outline(decls) {
+                result.push(HirDeclaration::Structure(outline));
+            }
+        }
     }
+    result
 }
 
 /// This is purely a standin for HIR's declaration
@@ -224,6 +543,9 @@ pub enum TirDeclaration {
     DocumentHeader(String),
     Constant(Option<String>, Identifier, TirConstant),
     Function(TirFunction),
+    /// A function with a type parameter, such as `fn max<T>(a: T, b: T) -> T`.
+    /// A copy of the function is compiled for each type it is called with.
+    GenericFunction(Identifier, TirFunction),
     Structure(TirStructure),
     Assert(TirConstant),
     /// Use the `if` compiler flag to use
@@ -236,26 +558,40 @@ pub enum TirDeclaration {
     Extern(String),
     /// This is the first kind of flag computed in TIR.
     /// It creates a typed binding to a foreign function in an `extern` file.
-    /// This variant has 5 values,
+    /// This variant has 7 values,
     /// 1. The doc string
     /// 2. The foreign function name to bind
     /// 3. The name of the bound Oak function. This is the name that
     ///    the function will be called with.
     /// 4. The typed parameters of the function
     /// 5. The return type of the function
+    /// 6. Whether the function is marked with `#[context]`, and
+    ///    is passed the virtual machine's context pointer
+    /// 7. The file and line the binding is declared at
     ExternFunction(
         Option<String>,
         String,
         String,
         Vec<(Identifier, TirType)>,
         TirType,
+        bool,
+        Option<(String, usize)>,
     ),
+    /// A typed binding to a foreign global variable in an `extern` file.
+    /// This variant has 4 values,
+    /// 1. The doc string
+    /// 2. The foreign variable name to bind
+    /// 3. The name of the Oak function that reads the variable. The
+    ///    variable is written with the same name prefixed with `set_`.
+    /// 4. The type of the variable
+    ExternVar(Option<String>, String, String, TirType),
     /// This is the only other flag that is computed in TIR. This
     /// copies and pastes another Oak file in place of this declaration.
     Include(String),
-    Memory(i32),
+    Memory(HirConstant),
     RequireStd,
     NoStd,
+    NoCore,
 }
 
 impl TirDeclaration {
@@ -270,6 +606,8 @@ impl TirDeclaration {
                 HirDeclaration::Constant(doc.clone(), name.clone(), constant.clone())
             }
             Self::Function(func) => HirDeclaration::Function(func.to_hir_fn(decls)?),
+            // Generic functions are compiled when they are instantiated
+            Self::GenericFunction(_, _) => HirDeclaration::Pass,
             Self::Structure(structure) => {
                 HirDeclaration::Structure(structure.clone().to_hir_struct(decls)?)
             }
@@ -280,54 +618,75 @@ impl TirDeclaration {
 
             Self::Extern(file) => HirDeclaration::Extern(file.clone()),
 
-            Self::ExternFunction(doc, foreign_name, name, params, return_type) => {
-                let mut hir_return_type = return_type.to_hir_type();
-                let mut hir_params = vec![];
-                let mut hir_args = vec![];
-                // Create a list of HIR parameters, and the arguments
-                // to supply to the foreign function.
-                for (param, t) in params {
-                    hir_params.push((param.clone(), t.to_hir_type()));
-                    hir_args.push(HirExpression::Variable(param.clone()))
-                }
-
-                HirDeclaration::Function(HirFunction::new(
-                    doc.clone(),
-                    name.clone(),
-                    hir_params,
-                    hir_return_type.clone(),
-                    vec![
-                        // If the return type is not void, then return the result
-                        // of the foreign function
-                        if *return_type != TirType::Void {
-                            HirStatement::Return(vec![
-                                // Foreign functions, by default, return &void for casting purposes
-                                // To get the value we want, we cast it to the requested return type.
-                                HirExpression::TypeCast(
-                                    Box::new(HirExpression::ForeignCall(
-                                        foreign_name.clone(),
-                                        hir_args,
-                                    )),
-                                    hir_return_type,
-                                ),
-                            ])
-                        } else {
-                            HirStatement::Expression(HirExpression::ForeignCall(
-                                foreign_name.clone(),
-                                hir_args,
-                            ))
-                        },
-                    ],
-                ))
+            Self::ExternFunction(
+                doc,
+                foreign_name,
+                name,
+                params,
+                return_type,
+                takes_context,
+                location,
+            ) => {
+                // Every parameter of the wrapper function is passed
+                // on to the foreign function, so none of them go unused.
+                let hir_params = params
+                    .iter()
+                    .map(|(param, t)| (param.clone(), t.to_hir_type()))
+                    .collect();
+                let hir_args = params
+                    .iter()
+                    .map(|(param, t)| {
+                        let arg = HirExpression::Variable(param.clone());
+                        match t {
+                            // Sized integers are truncated to their width before
+                            // they're passed to the foreign function
+                            TirType::Integer(_) => {
+                                HirExpression::TypeCast(Box::new(arg), t.to_hir_type())
+                            }
+                            _ => arg,
+                        }
+                    })
+                    .collect();
+                let call =
+                    HirExpression::ForeignCall(foreign_name.clone(), hir_args, *takes_context);
+
+                let hir_return_type = return_type.to_hir_type();
+                let body = if *return_type == TirType::Void {
+                    HirStatement::Expression(call)
+                } else {
+                    // Foreign functions, by default, return &void for casting purposes
+                    // To get the value we want, we cast it to the requested return type.
+                    // A sized integer is also truncated to its width.
+                    HirStatement::Return(vec![HirExpression::TypeCast(
+                        Box::new(call),
+                        hir_return_type.clone(),
+                    )])
+                };
+
+                HirDeclaration::Function(
+                    HirFunction::new(
+                        doc.clone(),
+                        name.clone(),
+                        hir_params,
+                        hir_return_type,
+                        vec![body],
+                        false,
+                    )
+                    .with_location(location.clone()),
+                )
             }
 
+            // The accessors of foreign variables are created when the program is compiled
+            Self::ExternVar(_, _, _, _) => HirDeclaration::Pass,
+
             /// In HIR, do nothing in place of an include statement
             Self::IfElse(_, _, _) | Self::If(_, _) | Self::Include(_) => HirDeclaration::Pass,
 
-            Self::Memory(n) => HirDeclaration::Memory(*n),
+            Self::Memory(size) => HirDeclaration::Memory(size.clone()),
 
             Self::RequireStd => HirDeclaration::RequireStd,
             Self::NoStd => HirDeclaration::NoStd,
+            Self::NoCore => HirDeclaration::NoCore,
         })
     }
 }
@@ -338,14 +697,27 @@ impl TirDeclaration {
 #[derive(Clone, Debug, PartialEq)]
 pub enum TirType {
     Pointer(Box<Self>),
+    /// A pointer that may be null, written `?T`
+    Nullable(Box<Self>),
     Void,
     Float,
     Boolean,
     Character,
+    Integer(IntWidth),
     Structure(Identifier),
 }
 
 impl TirType {
+    /// Replace a generic function's type parameter with its type argument
+    fn substitute(&self, param: Option<(&Identifier, &TirType)>) -> Self {
+        match (self, param) {
+            (Self::Pointer(inner), _) => Self::Pointer(Box::new(inner.substitute(param))),
+            (Self::Nullable(inner), _) => Self::Nullable(Box::new(inner.substitute(param))),
+            (Self::Structure(name), Some((param, t))) if name == param => t.clone(),
+            _ => self.clone(),
+        }
+    }
+
     /// Is this type a structure?
     fn is_structure(&self) -> bool {
         match self {
@@ -375,6 +747,24 @@ impl TirType {
         }
     }
 
+    /// The boundary, in cells, that this type is aligned to
+    /// when it's a member of a structure.
+    fn get_alignment(&self, decls: &Vec<TirDeclaration>) -> Result<i32, TirError> {
+        if let Self::Structure(name) = self {
+            for decl in decls {
+                if let TirDeclaration::Structure(structure) = decl {
+                    if name == structure.get_name() {
+                        return structure.get_alignment(decls);
+                    }
+                }
+            }
+            Err(TirError::StructureNotDefined(name.clone()))
+        } else {
+            // Every other type is a single cell, or no cells
+            Ok(1)
+        }
+    }
+
     /// Add a reference to this type
     fn refer(&self) -> Self {
         Self::Pointer(Box::new(self.clone()))
@@ -384,10 +774,12 @@ impl TirType {
     pub fn to_hir_type(&self) -> HirType {
         match self {
             Self::Pointer(inner) => HirType::Pointer(Box::new(inner.to_hir_type())),
+            Self::Nullable(inner) => HirType::Nullable(Box::new(inner.to_hir_type())),
             Self::Void => HirType::Void,
             Self::Float => HirType::Float,
             Self::Boolean => HirType::Boolean,
             Self::Character => HirType::Character,
+            Self::Integer(width) => HirType::Integer(*width),
             Self::Structure(name) => HirType::Structure(name.clone()),
         }
     }
@@ -406,6 +798,16 @@ pub struct TirFunction {
     return_type: TirType,
     /// The function's body statements
     body: Vec<TirStatement>,
+    /// Is the function the entry point of the program?
+    is_entry: bool,
+    /// The constant default value of each parameter, if it has one.
+    /// A call can leave out the trailing arguments with default values.
+    defaults: Vec<Option<TirConstant>>,
+    /// The file and line the function is defined at, if it's written in Oak
+    location: Option<(String, usize)>,
+    /// Is the function marked with `#[export]`, to be called
+    /// from other languages when compiled as a shared library?
+    is_exported: bool,
 }
 
 impl TirFunction {
@@ -415,6 +817,7 @@ impl TirFunction {
         args: Vec<(Identifier, TirType)>,
         return_type: TirType,
         body: Vec<TirStatement>,
+        is_entry: bool,
     ) -> Self {
         Self {
             doc,
@@ -422,6 +825,44 @@ impl TirFunction {
             args,
             return_type,
             body,
+            is_entry,
+            defaults: vec![],
+            location: None,
+            is_exported: false,
+        }
+    }
+
+    /// Mark the function as exported from a shared library
+    pub fn exported(mut self, is_exported: bool) -> Self {
+        self.is_exported = is_exported;
+        self
+    }
+
+    /// Mark the function as defined at a line of a file
+    pub fn at(mut self, filename: &str, line: usize) -> Self {
+        self.location = Some((filename.to_string(), line));
+        self
+    }
+
+    /// Give the function's parameters default values
+    pub fn with_defaults(mut self, defaults: Vec<Option<TirConstant>>) -> Self {
+        self.defaults = defaults;
+        self
+    }
+
+    /// Does any of the function's parameters have a default value?
+    fn has_defaults(&self) -> bool {
+        self.defaults.iter().any(Option::is_some)
+    }
+
+    /// Add the default values of the parameters that a
+    /// call to this function leaves out to its arguments.
+    fn fill_defaults(&self, args: &mut Vec<TirExpression>) {
+        for default in self.defaults.iter().skip(args.len()) {
+            match default {
+                Some(constant) => args.push(TirExpression::Constant(constant.clone())),
+                None => break,
+            }
         }
     }
 
@@ -446,21 +887,21 @@ impl TirFunction {
     fn member_method(
         // The type of the structure the method is being defined for
         self_type: &Identifier,
-        // The list of members that came before this member. This is
-        // to determine the offset of the member in the structure's memory.
-        previous_member_types: &Vec<TirType>,
+        // The offset of the member in the structure's memory, or
+        // `None` if the member is at the start of the structure.
+        offset: Option<TirConstant>,
         // The name of this member
         member_name: &Identifier,
         // This member's type
         member_type: &TirType,
     ) -> Self {
-        // Add the size of all the previous members to the self pointer
+        // Add the offset of this member to the self pointer
         // to get the address of this member.
         let mut fn_return = TirExpression::Variable(Identifier::from("self"));
-        for t in previous_member_types {
+        if let Some(offset) = offset {
             fn_return = TirExpression::Add(
                 Box::new(fn_return.clone()),
-                Box::new(TirExpression::SizeOf(t.clone())),
+                Box::new(TirExpression::Constant(offset)),
             );
         }
 
@@ -477,15 +918,41 @@ impl TirFunction {
                 Box::new(fn_return),
                 member_type.refer().clone(),
             )])],
+            false,
         )
     }
 
-    /// Generate a copy constructor for a type.
-    fn copy_constructor(members: &Vec<(Identifier, TirType)>, structure: &Identifier) -> Self {
+    /// Generate a copy constructor for a type. `padding` is the number of
+    /// padding cells before each member, followed by the number of padding
+    /// cells at the end of the structure.
+    fn copy_constructor(
+        members: &Vec<(Identifier, TirType)>,
+        structure: &Identifier,
+        padding: &Vec<i32>,
+    ) -> Self {
         let struct_t = TirType::Structure(structure.clone());
         let mut result = vec![];
 
-        if members.len() == 1 {
+        if padding.iter().any(|cells| *cells > 0) {
+            // If the structure has padding, then the members are
+            // returned with a zero for each padding cell.
+
+            // This generates the following code:
+            // ```
+            // return [self->member_1, 0, 0, self->member_2, ...];
+            // ```
+            let zeros =
+                |cells: i32| (0..cells).map(|_| TirExpression::Constant(TirConstant::Float(0.0)));
+            for ((member, _), cells) in members.iter().zip(padding) {
+                result.extend(zeros(*cells));
+                result.push(TirExpression::Deref(Box::new(TirExpression::Method(
+                    Box::new(TirExpression::Variable(Identifier::from("self"))),
+                    member.clone(),
+                    vec![],
+                ))))
+            }
+            result.extend(zeros(padding[members.len()]));
+        } else if members.len() == 1 {
             // If the number of members is one, then
             // the returned value NEEDS to be cast to pass MIR typechecks.
             let member_name = members[0].0.clone();
@@ -526,6 +993,7 @@ impl TirFunction {
             vec![(Identifier::from("self"), struct_t.refer())],
             struct_t,
             vec![TirStatement::Return(result)],
+            false,
         )
     }
 
@@ -558,6 +1026,7 @@ impl TirFunction {
             vec![(Identifier::from("self"), struct_t.refer())],
             TirType::Void,
             result,
+            false,
         )
     }
 
@@ -603,8 +1072,44 @@ impl TirFunction {
         return Ok(false);
     }
 
+    /// The name of a generic function's copy for a given type argument
+    fn generic_name(name: &Identifier, t: &TirType) -> Identifier {
+        format!("{}<{}>", name, t.to_hir_type())
+    }
+
+    /// Copy this function, replacing the type parameter `param` with its type
+    /// argument, and add every generic function call it makes to `instances`.
+    fn monomorphize(
+        &self,
+        param: Option<(&Identifier, &TirType)>,
+        instances: &mut Vec<(Identifier, TirType)>,
+    ) -> Self {
+        let mut result = self.clone();
+        result.args = self
+            .args
+            .iter()
+            .map(|(name, t)| (name.clone(), t.substitute(param)))
+            .collect();
+        result.return_type = self.return_type.substitute(param);
+        result.body = self
+            .body
+            .iter()
+            .map(|stmt| stmt.monomorphize(param, instances))
+            .collect();
+        result
+    }
+
     /// Convert this function into an HIR function
     fn to_hir_fn(&self, decls: &Vec<TirDeclaration>) -> Result<HirFunction, TirError> {
+        // Only the trailing parameters can have default values
+        if let Some(first) = self.defaults.iter().position(Option::is_some) {
+            for (i, (arg, _)) in self.args.iter().enumerate().skip(first) {
+                if let None | Some(None) = self.defaults.get(i) {
+                    return Err(TirError::MissingDefault(self.name.clone(), arg.clone()));
+                }
+            }
+        }
+
         // Convert the parameter types to HIR types
         let mut args = vec![];
         for (arg, t) in &self.args {
@@ -614,7 +1119,7 @@ impl TirFunction {
         // Convert the function statements to HIR statements
         let mut body = vec![];
         for stmt in &self.body {
-            body.push(stmt.to_hir_stmt(decls)?)
+            body.extend(stmt.to_hir_stmts(decls)?)
         }
 
         Ok(HirFunction::new(
@@ -623,7 +1128,10 @@ impl TirFunction {
             args,
             self.return_type.to_hir_type(),
             body,
-        ))
+            self.is_entry,
+        )
+        .exported(self.is_exported)
+        .with_location(self.location.clone()))
     }
 }
 
@@ -636,8 +1144,15 @@ pub struct TirStructure {
     name: Identifier,
     /// The structure's members
     members: Vec<(Identifier, TirType)>,
+    /// The members marked `priv`, which can only be
+    /// accessed from within the structure's methods
+    private_members: Vec<Identifier>,
     /// The structure's methods
     methods: Vec<TirFunction>,
+    /// The boundary, in cells, set with `#[align(n)]` on the structure
+    alignment: i32,
+    /// The members aligned with `#[align(n)]`, and their alignments
+    aligned_members: Vec<(Identifier, i32)>,
 }
 
 impl TirStructure {
@@ -645,14 +1160,89 @@ impl TirStructure {
         doc: Option<String>,
         name: Identifier,
         members: Vec<(Identifier, TirType)>,
+        private_members: Vec<Identifier>,
         methods: Vec<TirFunction>,
+        alignment: i32,
+        aligned_members: Vec<(Identifier, i32)>,
     ) -> Self {
         Self {
             doc,
             name,
             members,
+            private_members,
             methods,
+            alignment,
+            aligned_members,
+        }
+    }
+
+    /// The boundary, in cells, that this structure is aligned to. A structure
+    /// is aligned to the largest alignment of itself and its members.
+    fn get_alignment(&self, decls: &Vec<TirDeclaration>) -> Result<i32, TirError> {
+        let mut result = self.alignment;
+        for (name, _) in &self.members {
+            result = result.max(self.get_member_alignment(name, decls)?);
+        }
+        Ok(result)
+    }
+
+    /// The boundary, in cells, that a member's offset is aligned to
+    fn get_member_alignment(
+        &self,
+        member: &Identifier,
+        decls: &Vec<TirDeclaration>,
+    ) -> Result<i32, TirError> {
+        let mut result = 1;
+        for (name, t) in &self.members {
+            if name == member {
+                result = t.get_alignment(decls)?;
+            }
         }
+        for (name, alignment) in &self.aligned_members {
+            if name == member {
+                result = result.max(*alignment);
+            }
+        }
+        Ok(result)
+    }
+
+    /// Get the offset of each member in the structure's memory, and the
+    /// size of the structure. Each member is padded to its alignment, and
+    /// the size is padded to the structure's alignment, so that every
+    /// element of an array of the structure is aligned.
+    fn get_layout(
+        &self,
+        decls: &Vec<TirDeclaration>,
+    ) -> Result<(Vec<HirConstant>, HirConstant), TirError> {
+        // Check that every alignment is a positive number of cells
+        for alignment in std::iter::once(&self.alignment)
+            .chain(self.aligned_members.iter().map(|(_, alignment)| alignment))
+        {
+            if *alignment < 1 {
+                return Err(TirError::InvalidAlignment(self.name.clone(), *alignment));
+            }
+        }
+
+        let align = |constant: HirConstant, alignment: i32| {
+            if alignment > 1 {
+                HirConstant::AlignTo(Box::new(constant), alignment)
+            } else {
+                constant
+            }
+        };
+
+        let mut offsets = vec![];
+        let mut size = HirConstant::Float(0.0);
+        for (name, t) in &self.members {
+            let offset = align(size, self.get_member_alignment(name, decls)?);
+            offsets.push(offset.clone());
+            // Add the size of the member to the size of the structure
+            size = HirConstant::Add(
+                Box::new(offset),
+                Box::new(HirConstant::SizeOf(t.to_hir_type())),
+            );
+        }
+        Ok((offsets, align(size, self.get_alignment(decls)?)))
     }
 
     /// Get the name of the structure
@@ -660,6 +1250,22 @@ impl TirStructure {
         &self.name
     }
 
+    /// Does this structure implement the methods used by for-each loops?
+    /// `fn has_next(self: &T) -> bool` and `fn next(self: &T) -> E`
+    fn is_iterator(&self) -> bool {
+        let self_type = TirType::Structure(self.name.clone()).refer();
+        let is_method =
+            |method: &TirFunction| method.args.len() == 1 && method.args[0].1 == self_type;
+        let has_next = self.methods.iter().any(|method| {
+            method.name == "has_next" && is_method(method) && method.return_type == TirType::Boolean
+        });
+        let next = self
+            .methods
+            .iter()
+            .any(|method| method.name == "next" && is_method(method));
+        has_next && next
+    }
+
     /// Can this type be moved without making a new copy?
     fn is_movable(&self, decls: &Vec<TirDeclaration>) -> Result<bool, TirError> {
         /// Does this type manually implement copy and drop?
@@ -689,6 +1295,25 @@ impl TirStructure {
         Ok(default_copy && default_drop)
     }
 
+    /// The names of the structure's members, in order
+    fn member_names(&self) -> Vec<Identifier> {
+        self.members.iter().map(|(name, _)| name.clone()).collect()
+    }
+
+    /// Convert the structure into an HIR structure without any methods.
+    /// This is used to evaluate constants before the program is compiled.
+    fn to_hir_outline(&self, decls: &Vec<TirDeclaration>) -> Result<HirStructure, TirError> {
+        Ok(HirStructure::new(
+            self.doc.clone(),
+            self.name.clone(),
+            self.get_layout(decls)?.1,
+            self.member_names(),
+            vec![],
+            vec![],
+            self.is_movable(decls)?,
+        ))
+    }
+
     fn to_hir_struct(&mut self, decls: &Vec<TirDeclaration>) -> Result<HirStructure, TirError> {
         // Check if the structure is movable BEFORE the copy
         // and drop functions are automatically added. If the
@@ -696,37 +1321,51 @@ impl TirStructure {
         // then `is_movable` will automatically be false.
         let is_movable = self.is_movable(decls)?;
         // Add the object's `copy` and `drop` methods.
-        self.add_copy_and_drop()?;
+        self.add_copy_and_drop(decls)?;
 
         // Create the list of methods for the new HIR structure
         let mut methods = vec![];
 
-        // Store all the previous member's types for each member
-        // to create a getter/setter method for each member.
-        let mut previous_member_types = vec![];
-
-        // Keep track of the size of the structure
-        let mut size = HirConstant::Float(0.0);
+        // Find where each member is stored to create
+        // a getter/setter method for each member.
+        let (offsets, size) = self.get_layout(decls)?;
 
-        for (name, t) in &self.members {
+        for (i, ((name, t), offset)) in self.members.iter().zip(offsets).enumerate() {
+            // The first member is always at the start of the structure
+            let offset = if i == 0 { None } else { Some(offset) };
             // Add the member function to the list of methods
-            methods.push(
-                TirFunction::member_method(&self.name, &previous_member_types, name, t)
-                    .to_hir_fn(decls)?,
-            );
-            // Add the size of the member to the size of the structure
-            size = HirConstant::Add(
-                Box::new(size.clone()),
-                Box::new(HirConstant::SizeOf(t.to_hir_type())),
-            );
-            // Add this member's type to the list of
-            // previous member's types.
-            previous_member_types.push(t.clone())
+            methods.push(TirFunction::member_method(&self.name, offset, name, t).to_hir_fn(decls)?);
         }
 
-        // In addition to the member methods,
-        // add each of the structures explicit methods
+        // In addition to the member methods, add each of the structure's
+        // explicit methods in the order they're written. The `copy` and `drop`
+        // methods always come last, whether they're written or generated,
+        // so that the methods are assembled in the same order every time.
+        let mut copies = vec![];
+        let mut drops = vec![];
         for method in &self.methods {
+            if method.has_defaults() {
+                return Err(TirError::MethodDefault(
+                    self.name.clone(),
+                    method.name.clone(),
+                ));
+            }
+            if method.is_exported {
+                return Err(TirError::MethodExport(
+                    self.name.clone(),
+                    method.name.clone(),
+                ));
+            }
+
+            if method.is_valid_copy(&self.name)? {
+                copies.push(method)
+            } else if method.is_valid_drop(&self.name)? {
+                drops.push(method)
+            } else {
+                methods.push(method.to_hir_fn(decls)?)
+            }
+        }
+        for method in copies.into_iter().chain(drops) {
             methods.push(method.to_hir_fn(decls)?)
         }
 
@@ -734,13 +1373,44 @@ impl TirStructure {
             self.doc.clone(),
             self.name.clone(),
             size,
+            self.member_names(),
             methods,
+            // The getter methods for private members are private
+            self.private_members.clone(),
             is_movable,
         ))
     }
 
+    /// Get the number of padding cells before each member of the
+    /// structure, followed by the number of padding cells at its end.
+    fn get_padding(&self, decls: &Vec<TirDeclaration>) -> Result<Vec<i32>, TirError> {
+        // A structure without any alignment has no padding
+        if self.get_alignment(decls)? == 1 {
+            return Ok(vec![0; self.members.len() + 1]);
+        }
+
+        let (offsets, size) = self.get_layout(decls)?;
+        let outlines = outline_structures(decls);
+        let value = |constant: &HirConstant| match constant.to_value(&outlines, &BTreeMap::new()) {
+            Ok(n) => Ok(n as i32),
+            Err(HirError::TypeNotDefined(name)) => Err(TirError::StructureNotDefined(name)),
+            Err(_) => Err(TirError::StructureNotDefined(self.name.clone())),
+        };
+
+        let mut result = vec![];
+        // The end of the previous member
+        let mut end = 0;
+        for ((_, t), offset) in self.members.iter().zip(offsets) {
+            let offset = value(&offset)?;
+            result.push(offset - end);
+            end = offset + value(&HirConstant::SizeOf(t.to_hir_type()))?;
+        }
+        result.push(value(&size)? - end);
+        Ok(result)
+    }
+
     /// Add the default copy and drop methods to this structure
-    fn add_copy_and_drop(&mut self) -> Result<(), TirError> {
+    fn add_copy_and_drop(&mut self, decls: &Vec<TirDeclaration>) -> Result<(), TirError> {
         // To prevent multiple method definitions,
         // determine whether or not the copy and
         // drop methods have already been defined.
@@ -757,8 +1427,12 @@ impl TirStructure {
         // If the structure does not have a copy method,
         // add a default copy constructor to the list of methods.
         if !has_copy {
-            self.methods
-                .push(TirFunction::copy_constructor(&self.members, &self.name));
+            let padding = self.get_padding(decls)?;
+            self.methods.push(TirFunction::copy_constructor(
+                &self.members,
+                &self.name,
+                &padding,
+            ));
         }
 
         // If the structure does not have a drop method,
@@ -780,6 +1454,9 @@ pub enum TirStatement {
     Define(Identifier, TirType, TirExpression),
     /// An HIR let expression with an automatically assigned type
     AutoDefine(Identifier, TirExpression),
+    /// Define a variable for each of a structure's members `let {month, day, ..} = date`.
+    /// The flag marks whether the remaining members are ignored with `..`.
+    Destructure(Vec<Identifier>, bool, TirExpression),
     /// A variable assignment
     AssignVariable(Identifier, TirExpression),
     /// Add to a variable
@@ -790,6 +1467,8 @@ pub enum TirStatement {
     MultiplyAssignVariable(Identifier, TirExpression),
     /// Divide from a variable
     DivideAssignVariable(Identifier, TirExpression),
+    /// Take the remainder of a variable
+    ModuloAssignVariable(Identifier, TirExpression),
     /// An assignment to a dereferenced address
     AssignAddress(TirExpression, TirExpression),
     /// Add to the value a pointer points to
@@ -800,14 +1479,26 @@ pub enum TirStatement {
     MultiplyAssignAddress(TirExpression, TirExpression),
     /// Divide the value a pointer points to
     DivideAssignAddress(TirExpression, TirExpression),
+    /// Take the remainder of the value a pointer points to
+    ModuloAssignAddress(TirExpression, TirExpression),
 
     /// An HIR for loop `for (let i=0; i<10; i=i+1) {...}`
     For(Box<Self>, TirExpression, Box<Self>, Vec<Self>),
     /// An HIR for loop `for i in 0..10 {...}`
     ForRange(Identifier, TirExpression, TirExpression, Vec<Self>),
+    /// A for-each loop over any type with `has_next` and `next` methods `for item in items {...}`
+    ForEach(Identifier, TirExpression, Vec<Self>),
 
     /// An HIR while loop
     While(TirExpression, Vec<Self>),
+    /// A loop with a label, such as `outer: while ...`,
+    /// that `break` and `continue` can name
+    Labeled(Identifier, Box<Self>),
+    /// Leave the innermost loop, or the loop with the label
+    Break(Option<Identifier>),
+    /// Skip to the next iteration of the innermost
+    /// loop, or of the loop with the label
+    Continue(Option<Identifier>),
     /// An HIR if statement
     If(TirExpression, Vec<Self>),
     /// An HIR if statement with an else clause
@@ -824,12 +1515,156 @@ pub enum TirStatement {
     Free(TirExpression, TirExpression),
     /// Return one or more values at the end of a function
     Return(Vec<TirExpression>),
+    /// A function defined inside of another function
+    Function(TirFunction),
 
     /// Any expression
     Expression(TirExpression),
 }
 
 impl TirStatement {
+    /// Replace a generic function's type parameter with its type argument,
+    /// and add every generic function call to `instances`.
+    fn monomorphize(
+        &self,
+        param: Option<(&Identifier, &TirType)>,
+        instances: &mut Vec<(Identifier, TirType)>,
+    ) -> Self {
+        let mut expr = |expr: &TirExpression, instances: &mut Vec<(Identifier, TirType)>| {
+            expr.monomorphize(param, instances)
+        };
+        let body = |body: &Vec<Self>, instances: &mut Vec<(Identifier, TirType)>| {
+            body.iter()
+                .map(|stmt| stmt.monomorphize(param, instances))
+                .collect::<Vec<_>>()
+        };
+
+        match self {
+            Self::Define(name, t, e) => {
+                Self::Define(name.clone(), t.substitute(param), expr(e, instances))
+            }
+            Self::AutoDefine(name, e) => Self::AutoDefine(name.clone(), expr(e, instances)),
+            Self::Destructure(names, partial, e) => {
+                Self::Destructure(names.clone(), *partial, expr(e, instances))
+            }
+            Self::AssignVariable(name, e) => Self::AssignVariable(name.clone(), expr(e, instances)),
+            Self::AddAssignVariable(name, e) => {
+                Self::AddAssignVariable(name.clone(), expr(e, instances))
+            }
+            Self::SubtractAssignVariable(name, e) => {
+                Self::SubtractAssignVariable(name.clone(), expr(e, instances))
+            }
+            Self::MultiplyAssignVariable(name, e) => {
+                Self::MultiplyAssignVariable(name.clone(), expr(e, instances))
+            }
+            Self::DivideAssignVariable(name, e) => {
+                Self::DivideAssignVariable(name.clone(), expr(e, instances))
+            }
+            Self::ModuloAssignVariable(name, e) => {
+                Self::ModuloAssignVariable(name.clone(), expr(e, instances))
+            }
+            Self::AssignAddress(l, r) => {
+                Self::AssignAddress(expr(l, instances), expr(r, instances))
+            }
+            Self::AddAssignAddress(l, r) => {
+                Self::AddAssignAddress(expr(l, instances), expr(r, instances))
+            }
+            Self::SubtractAssignAddress(l, r) => {
+                Self::SubtractAssignAddress(expr(l, instances), expr(r, instances))
+            }
+            Self::MultiplyAssignAddress(l, r) => {
+                Self::MultiplyAssignAddress(expr(l, instances), expr(r, instances))
+            }
+            Self::DivideAssignAddress(l, r) => {
+                Self::DivideAssignAddress(expr(l, instances), expr(r, instances))
+            }
+            Self::ModuloAssignAddress(l, r) => {
+                Self::ModuloAssignAddress(expr(l, instances), expr(r, instances))
+            }
+
+            Self::For(pre, cond, post, b) => Self::For(
+                Box::new(pre.monomorphize(param, instances)),
+                expr(cond, instances),
+                Box::new(post.monomorphize(param, instances)),
+                body(b, instances),
+            ),
+            Self::ForRange(name, from, to, b) => Self::ForRange(
+                name.clone(),
+                expr(from, instances),
+                expr(to, instances),
+                body(b, instances),
+            ),
+            Self::ForEach(name, iterator, b) => {
+                Self::ForEach(name.clone(), expr(iterator, instances), body(b, instances))
+            }
+
+            Self::While(cond, b) => Self::While(expr(cond, instances), body(b, instances)),
+            Self::Labeled(label, stmt) => {
+                Self::Labeled(label.clone(), Box::new(stmt.monomorphize(param, instances)))
+            }
+            Self::Break(label) => Self::Break(label.clone()),
+            Self::Continue(label) => Self::Continue(label.clone()),
+            Self::If(cond, b) => Self::If(expr(cond, instances), body(b, instances)),
+            Self::IfElse(cond, then_body, else_body) => Self::IfElse(
+                expr(cond, instances),
+                body(then_body, instances),
+                body(else_body, instances),
+            ),
+            Self::IfElifElse(cond, then_body, elifs, else_body) => Self::IfElifElse(
+                expr(cond, instances),
+                body(then_body, instances),
+                elifs
+                    .iter()
+                    .map(|(cond, b)| (expr(cond, instances), body(b, instances)))
+                    .collect(),
+                body(else_body, instances),
+            ),
+
+            Self::Free(addr, size) => Self::Free(expr(addr, instances), expr(size, instances)),
+            Self::Return(exprs) => Self::Return(exprs.iter().map(|e| expr(e, instances)).collect()),
+            Self::Function(func) => Self::Function(func.monomorphize(param, instances)),
+            Self::Expression(e) => Self::Expression(expr(e, instances)),
+        }
+    }
+
+    /// Lower a statement in a body into one or more HIR statements.
+    fn to_hir_stmts(&self, decls: &Vec<TirDeclaration>) -> Result<Vec<HirStatement>, TirError> {
+        match self {
+            Self::Destructure(members, partial, expr) => {
+                // Bind the structure to a variable so that it is only evaluated once.
+                // The name can't be written in Oak, so it can't clash with the user's
+                // variables.
+                let tmp = format!("{{{}}}", members.join(", "));
+                let mut result = vec![
+                    HirStatement::AutoDefine(tmp.clone(), expr.to_hir_expr(decls)?),
+                    // The structure's type is only known once the
+                    // program is type checked, so its members are
+                    // checked against the names then.
+                    HirStatement::CheckMembers(
+                        HirExpression::Variable(tmp.clone()),
+                        members.clone(),
+                        *partial,
+                    ),
+                ];
+
+                // Then, read each member with its getter method
+                // `let month = tmp->month;`
+                for member in members {
+                    result.push(HirStatement::AutoDefine(
+                        member.clone(),
+                        HirExpression::Deref(Box::new(HirExpression::Method(
+                            Box::new(HirExpression::Variable(tmp.clone())),
+                            member.clone(),
+                            vec![],
+                        ))),
+                    ))
+                }
+                Ok(result)
+            }
+            _ => Ok(vec![self.to_hir_stmt(decls)?]),
+        }
+    }
+
     fn to_hir_stmt(&self, decls: &Vec<TirDeclaration>) -> Result<HirStatement, TirError> {
         Ok(match self {
             Self::Define(name, t, expr) => {
@@ -838,6 +1673,7 @@ impl TirStatement {
             Self::AutoDefine(name, expr) => {
                 HirStatement::AutoDefine(name.clone(), expr.to_hir_expr(decls)?)
             }
+            Self::Destructure(_, _, _) => return Err(TirError::MisplacedDestructure),
             Self::AssignVariable(name, expr) => {
                 HirStatement::AssignVariable(name.clone(), expr.to_hir_expr(decls)?)
             }
@@ -869,6 +1705,13 @@ impl TirStatement {
                     Box::new(expr.to_hir_expr(decls)?),
                 ),
             ),
+            Self::ModuloAssignVariable(name, expr) => HirStatement::AssignVariable(
+                name.clone(),
+                HirExpression::Modulo(
+                    Box::new(HirExpression::Variable(name.clone())),
+                    Box::new(expr.to_hir_expr(decls)?),
+                ),
+            ),
             Self::AssignAddress(addr, expr) => {
                 HirStatement::AssignAddress(addr.to_hir_expr(decls)?, expr.to_hir_expr(decls)?)
             }
@@ -900,21 +1743,30 @@ impl TirStatement {
                     Box::new(expr.to_hir_expr(decls)?),
                 ),
             ),
+            Self::ModuloAssignAddress(addr, expr) => HirStatement::AssignAddress(
+                addr.to_hir_expr(decls)?,
+                HirExpression::Modulo(
+                    Box::new(HirExpression::Deref(Box::new(addr.to_hir_expr(decls)?))),
+                    Box::new(expr.to_hir_expr(decls)?),
+                ),
+            ),
 
             Self::For(pre, cond, post, body) => HirStatement::For(
+                None,
                 Box::new(pre.to_hir_stmt(decls)?),
                 cond.to_hir_expr(decls)?,
                 Box::new(post.to_hir_stmt(decls)?),
                 {
                     let mut result = vec![];
                     for stmt in body {
-                        result.push(stmt.to_hir_stmt(decls)?)
+                        result.extend(stmt.to_hir_stmts(decls)?)
                     }
                     result
                 },
             ),
 
             Self::ForRange(var, from, to, body) => HirStatement::For(
+                None,
                 Box::new(HirStatement::Define(
                     var.clone(),
                     HirType::Float,
@@ -934,24 +1786,101 @@ impl TirStatement {
                 {
                     let mut result = vec![];
                     for stmt in body {
-                        result.push(stmt.to_hir_stmt(decls)?)
+                        result.extend(stmt.to_hir_stmts(decls)?)
                     }
                     result
                 },
             ),
 
-            Self::While(cond, body) => HirStatement::While(cond.to_hir_expr(decls)?, {
+            Self::ForEach(item, iterator, body) => {
+                // Confirm that some structure implements the iterator methods.
+                // The type of the iterator is checked against the structure
+                // when the method calls are type checked.
+                let mut found = false;
+                for decl in decls {
+                    if let TirDeclaration::Structure(structure) = decl {
+                        if structure.is_iterator() {
+                            found = true;
+                            break;
+                        }
+                    }
+                }
+                if !found {
+                    return Err(TirError::NoIteratorDefined);
+                }
+
+                // If the iterator is a variable, iterate over it in place so that it
+                // keeps its state after the loop. Otherwise, store the iterator in a
+                // variable whose name can't be written in Oak.
+                let (pre, iterator) = match iterator {
+                    TirExpression::Variable(_) => (
+                        HirStatement::Expression(HirExpression::Void),
+                        iterator.clone(),
+                    ),
+                    _ => {
+                        let tmp = format!("{{for {} in}}", item);
+                        (
+                            HirStatement::AutoDefine(tmp.clone(), iterator.to_hir_expr(decls)?),
+                            TirExpression::Variable(tmp),
+                        )
+                    }
+                };
+
+                // This generates the following code:
+                // ```
+                // for (pre; iterator.has_next(); @) {
+                //     let item = iterator.next();
+                //     ...
+                // }
+                // ```
+                let mut result = vec![HirStatement::AutoDefine(
+                    item.clone(),
+                    TirExpression::Method(
+                        Box::new(iterator.clone()),
+                        Identifier::from("next"),
+                        vec![],
+                    )
+                    .to_hir_expr(decls)?,
+                )];
+                for stmt in body {
+                    result.extend(stmt.to_hir_stmts(decls)?)
+                }
+
+                HirStatement::For(
+                    None,
+                    Box::new(pre),
+                    TirExpression::Method(Box::new(iterator), Identifier::from("has_next"), vec![])
+                        .to_hir_expr(decls)?,
+                    Box::new(HirStatement::Expression(HirExpression::Void)),
+                    result,
+                )
+            }
+
+            Self::While(cond, body) => HirStatement::While(None, cond.to_hir_expr(decls)?, {
                 let mut result = vec![];
                 for stmt in body {
-                    result.push(stmt.to_hir_stmt(decls)?)
+                    result.extend(stmt.to_hir_stmts(decls)?)
                 }
                 result
             }),
 
+            // Every kind of loop is lowered to a for or while loop
+            Self::Labeled(label, stmt) => match stmt.to_hir_stmt(decls)? {
+                HirStatement::For(_, pre, cond, post, body) => {
+                    HirStatement::For(Some(label.clone()), pre, cond, post, body)
+                }
+                HirStatement::While(_, cond, body) => {
+                    HirStatement::While(Some(label.clone()), cond, body)
+                }
+                other => other,
+            },
+            Self::Break(label) => HirStatement::Break(label.clone()),
+            Self::Continue(label) => HirStatement::Continue(label.clone()),
+
             Self::If(cond, body) => HirStatement::If(cond.to_hir_expr(decls)?, {
                 let mut result = vec![];
                 for stmt in body {
-                    result.push(stmt.to_hir_stmt(decls)?)
+                    result.extend(stmt.to_hir_stmts(decls)?)
                 }
                 result
             }),
@@ -961,14 +1890,14 @@ impl TirStatement {
                 {
                     let mut result = vec![];
                     for stmt in then_body {
-                        result.push(stmt.to_hir_stmt(decls)?)
+                        result.extend(stmt.to_hir_stmts(decls)?)
                     }
                     result
                 },
                 {
                     let mut result = vec![];
                     for stmt in else_body {
-                        result.push(stmt.to_hir_stmt(decls)?)
+                        result.extend(stmt.to_hir_stmts(decls)?)
                     }
                     result
                 },
@@ -998,6 +1927,7 @@ impl TirStatement {
                 }
                 result
             }),
+            Self::Function(func) => HirStatement::Function(func.to_hir_fn(decls)?),
 
             Self::Expression(expr) => HirStatement::Expression(expr.to_hir_expr(decls)?),
         })
@@ -1015,6 +1945,13 @@ pub enum TirExpression {
     Subtract(Box<Self>, Box<Self>),
     Multiply(Box<Self>, Box<Self>),
     Divide(Box<Self>, Box<Self>),
+    Modulo(Box<Self>, Box<Self>),
+
+    BitAnd(Box<Self>, Box<Self>),
+    BitOr(Box<Self>, Box<Self>),
+    BitXor(Box<Self>, Box<Self>),
+    ShiftLeft(Box<Self>, Box<Self>),
+    ShiftRight(Box<Self>, Box<Self>),
 
     Not(Box<Self>),
     And(Box<Self>, Box<Self>),
@@ -1035,19 +1972,174 @@ pub enum TirExpression {
     False,
     Character(char),
     String(StringLiteral),
+    CharArray(Vec<Self>),
     Variable(Identifier),
 
     TypeCast(Box<Self>, TirType),
     Alloc(Box<Self>),
+    /// Fill `count` elements at a pointer with a value
+    Memset(Box<Self>, Box<Self>, Box<Self>),
+    /// Fill `count` elements at a pointer with zeros
+    Zero(Box<Self>, Box<Self>),
+    Flush,
+    /// Check that a pointer variable is not null, `p?`
+    NotNull(Identifier),
 
     Call(Identifier, Vec<Self>),
+    /// A call with arguments named by their parameters, `make(year: 2024)`.
+    /// The named arguments must come after the positional ones.
+    KeywordCall(Identifier, Vec<(Option<Identifier>, Self)>),
+    /// A call to a generic function with a type argument `max::<num>(a, b)`
+    GenericCall(Identifier, TirType, Vec<Self>),
     ForeignCall(Identifier, Vec<Self>),
     Method(Box<Self>, Identifier, Vec<Self>),
     Index(Box<Self>, Box<Self>),
     Conditional(Box<Self>, Box<Self>, Box<Self>),
+    /// A block of raw VM instructions with the given result type
+    RawAsm(TirType, Vec<AsmExpression>),
 }
 
 impl TirExpression {
+    /// Put the arguments of a call with named arguments in the order of
+    /// the function's parameters. The parameters that aren't given an
+    /// argument get their default values. The named arguments are evaluated
+    /// in the order of the parameters, not in the order they're written.
+    fn order_args(
+        name: &Identifier,
+        params: &Vec<(Identifier, TirType)>,
+        defaults: &[Option<TirConstant>],
+        args: &Vec<(Option<Identifier>, Self)>,
+    ) -> Result<Vec<Self>, TirError> {
+        let mut result = vec![];
+        let mut named = BTreeMap::new();
+        for (keyword, arg) in args {
+            match keyword {
+                Some(keyword) => {
+                    let index = match params.iter().position(|(param, _)| param == keyword) {
+                        Some(index) => index,
+                        None => {
+                            return Err(TirError::UnknownKeyword(name.clone(), keyword.clone()))
+                        }
+                    };
+                    if index < result.len() || named.insert(index, arg).is_some() {
+                        return Err(TirError::DuplicateArgument(name.clone(), keyword.clone()));
+                    }
+                }
+                None if !named.is_empty() => {
+                    return Err(TirError::PositionalAfterKeyword(name.clone()))
+                }
+                None => result.push(arg.clone()),
+            }
+        }
+
+        for (index, (param, _)) in params.iter().enumerate().skip(result.len()) {
+            match (named.get(&index), defaults.get(index)) {
+                (Some(arg), _) => result.push((*arg).clone()),
+                (None, Some(Some(default))) => result.push(Self::Constant(default.clone())),
+                (None, _) => return Err(TirError::MissingArgument(name.clone(), param.clone())),
+            }
+        }
+        Ok(result)
+    }
+
+    /// Replace a generic function's type parameter with its type argument,
+    /// and add every generic function call to `instances`.
+    fn monomorphize(
+        &self,
+        param: Option<(&Identifier, &TirType)>,
+        instances: &mut Vec<(Identifier, TirType)>,
+    ) -> Self {
+        let expr = |expr: &Self, instances: &mut Vec<(Identifier, TirType)>| {
+            Box::new(expr.monomorphize(param, instances))
+        };
+        let exprs = |exprs: &Vec<Self>, instances: &mut Vec<(Identifier, TirType)>| {
+            exprs
+                .iter()
+                .map(|expr| expr.monomorphize(param, instances))
+                .collect::<Vec<_>>()
+        };
+
+        match self {
+            Self::IsMovable(t) => Self::IsMovable(t.substitute(param)),
+            Self::SizeOf(t) => Self::SizeOf(t.substitute(param)),
+            Self::Move(e) => Self::Move(expr(e, instances)),
+
+            Self::Add(l, r) => Self::Add(expr(l, instances), expr(r, instances)),
+            Self::Subtract(l, r) => Self::Subtract(expr(l, instances), expr(r, instances)),
+            Self::Multiply(l, r) => Self::Multiply(expr(l, instances), expr(r, instances)),
+            Self::Divide(l, r) => Self::Divide(expr(l, instances), expr(r, instances)),
+            Self::Modulo(l, r) => Self::Modulo(expr(l, instances), expr(r, instances)),
+
+            Self::BitAnd(l, r) => Self::BitAnd(expr(l, instances), expr(r, instances)),
+            Self::BitOr(l, r) => Self::BitOr(expr(l, instances), expr(r, instances)),
+            Self::BitXor(l, r) => Self::BitXor(expr(l, instances), expr(r, instances)),
+            Self::ShiftLeft(l, r) => Self::ShiftLeft(expr(l, instances), expr(r, instances)),
+            Self::ShiftRight(l, r) => Self::ShiftRight(expr(l, instances), expr(r, instances)),
+
+            Self::Not(e) => Self::Not(expr(e, instances)),
+            Self::And(l, r) => Self::And(expr(l, instances), expr(r, instances)),
+            Self::Or(l, r) => Self::Or(expr(l, instances), expr(r, instances)),
+
+            Self::Greater(l, r) => Self::Greater(expr(l, instances), expr(r, instances)),
+            Self::Less(l, r) => Self::Less(expr(l, instances), expr(r, instances)),
+            Self::GreaterEqual(l, r) => Self::GreaterEqual(expr(l, instances), expr(r, instances)),
+            Self::LessEqual(l, r) => Self::LessEqual(expr(l, instances), expr(r, instances)),
+            Self::Equal(l, r) => Self::Equal(expr(l, instances), expr(r, instances)),
+            Self::NotEqual(l, r) => Self::NotEqual(expr(l, instances), expr(r, instances)),
+
+            Self::Deref(e) => Self::Deref(expr(e, instances)),
+            Self::CharArray(elems) => Self::CharArray(exprs(elems, instances)),
+
+            Self::TypeCast(e, t) => Self::TypeCast(expr(e, instances), t.substitute(param)),
+            Self::Alloc(e) => Self::Alloc(expr(e, instances)),
+            Self::Memset(ptr, value, count) => Self::Memset(
+                expr(ptr, instances),
+                expr(value, instances),
+                expr(count, instances),
+            ),
+            Self::Zero(ptr, count) => Self::Zero(expr(ptr, instances), expr(count, instances)),
+
+            Self::Call(name, args) => Self::Call(name.clone(), exprs(args, instances)),
+            Self::KeywordCall(name, args) => Self::KeywordCall(
+                name.clone(),
+                args.iter()
+                    .map(|(keyword, arg)| (keyword.clone(), arg.monomorphize(param, instances)))
+                    .collect(),
+            ),
+            Self::GenericCall(name, t, args) => {
+                let t = t.substitute(param);
+                instances.push((name.clone(), t.clone()));
+                Self::GenericCall(name.clone(), t, exprs(args, instances))
+            }
+            Self::ForeignCall(name, args) => {
+                Self::ForeignCall(name.clone(), exprs(args, instances))
+            }
+            Self::Method(instance, name, args) => Self::Method(
+                expr(instance, instances),
+                name.clone(),
+                exprs(args, instances),
+            ),
+            Self::Index(ptr, idx) => Self::Index(expr(ptr, instances), expr(idx, instances)),
+            Self::Conditional(cond, then, otherwise) => Self::Conditional(
+                expr(cond, instances),
+                expr(then, instances),
+                expr(otherwise, instances),
+            ),
+            Self::RawAsm(t, body) => Self::RawAsm(t.substitute(param), body.clone()),
+
+            Self::Constant(_)
+            | Self::Refer(_)
+            | Self::Void
+            | Self::True
+            | Self::False
+            | Self::Character(_)
+            | Self::String(_)
+            | Self::Variable(_)
+            | Self::Flush
+            | Self::NotNull(_) => self.clone(),
+        }
+    }
+
     pub fn to_hir_expr(&self, decls: &Vec<TirDeclaration>) -> Result<HirExpression, TirError> {
         Ok(match self {
             Self::IsMovable(t) => {
@@ -1062,6 +2154,13 @@ impl TirExpression {
             Self::False => HirExpression::False,
             Self::Character(ch) => HirExpression::Character(*ch),
             Self::String(s) => HirExpression::String(s.clone()),
+            Self::CharArray(exprs) => HirExpression::CharArray({
+                let mut result = vec![];
+                for expr in exprs {
+                    result.push(expr.to_hir_expr(decls)?)
+                }
+                result
+            }),
             Self::Variable(name) => HirExpression::Variable(name.clone()),
 
             Self::Move(expr) => HirExpression::Move(Box::new(expr.to_hir_expr(decls)?)),
@@ -1100,6 +2199,36 @@ impl TirExpression {
                 Box::new(rhs.to_hir_expr(decls)?),
             ),
 
+            Self::Modulo(lhs, rhs) => HirExpression::Modulo(
+                Box::new(lhs.to_hir_expr(decls)?),
+                Box::new(rhs.to_hir_expr(decls)?),
+            ),
+
+            Self::BitAnd(lhs, rhs) => HirExpression::BitAnd(
+                Box::new(lhs.to_hir_expr(decls)?),
+                Box::new(rhs.to_hir_expr(decls)?),
+            ),
+
+            Self::BitOr(lhs, rhs) => HirExpression::BitOr(
+                Box::new(lhs.to_hir_expr(decls)?),
+                Box::new(rhs.to_hir_expr(decls)?),
+            ),
+
+            Self::BitXor(lhs, rhs) => HirExpression::BitXor(
+                Box::new(lhs.to_hir_expr(decls)?),
+                Box::new(rhs.to_hir_expr(decls)?),
+            ),
+
+            Self::ShiftLeft(lhs, rhs) => HirExpression::ShiftLeft(
+                Box::new(lhs.to_hir_expr(decls)?),
+                Box::new(rhs.to_hir_expr(decls)?),
+            ),
+
+            Self::ShiftRight(lhs, rhs) => HirExpression::ShiftRight(
+                Box::new(lhs.to_hir_expr(decls)?),
+                Box::new(rhs.to_hir_expr(decls)?),
+            ),
+
             Self::Greater(lhs, rhs) => HirExpression::Greater(
                 Box::new(lhs.to_hir_expr(decls)?),
                 Box::new(rhs.to_hir_expr(decls)?),
@@ -1138,23 +2267,86 @@ impl TirExpression {
             }
 
             Self::Alloc(expr) => HirExpression::Alloc(Box::new(expr.to_hir_expr(decls)?)),
+            Self::Memset(ptr, value, count) => HirExpression::Memset(
+                Box::new(ptr.to_hir_expr(decls)?),
+                Box::new(value.to_hir_expr(decls)?),
+                Box::new(count.to_hir_expr(decls)?),
+            ),
+            Self::Zero(ptr, count) => HirExpression::Zero(
+                Box::new(ptr.to_hir_expr(decls)?),
+                Box::new(count.to_hir_expr(decls)?),
+            ),
+            Self::Flush => HirExpression::Flush,
+            Self::NotNull(name) => HirExpression::NotNull(name.clone()),
 
             Self::Call(name, args) => HirExpression::Call(name.clone(), {
-                let mut result = vec![];
-                for arg in args {
-                    result.push(arg.to_hir_expr(decls)?)
+                // Fill in the arguments left out of the call
+                // with the default values of their parameters
+                let mut args = args.clone();
+                for decl in decls {
+                    if let TirDeclaration::Function(func) = decl {
+                        if &func.name == name {
+                            func.fill_defaults(&mut args);
+                        }
+                    }
                 }
-                result
-            }),
 
-            Self::ForeignCall(name, args) => HirExpression::ForeignCall(name.clone(), {
                 let mut result = vec![];
-                for arg in args {
+                for arg in &args {
                     result.push(arg.to_hir_expr(decls)?)
                 }
                 result
             }),
 
+            Self::KeywordCall(name, args) => {
+                // Find the parameters of the function to match the names against
+                let mut signature = None;
+                for decl in decls {
+                    match decl {
+                        TirDeclaration::Function(func) if &func.name == name => {
+                            signature = Some((&func.args, &func.defaults[..]))
+                        }
+                        TirDeclaration::ExternFunction(_, _, fn_name, params, _, _, _)
+                            if fn_name == name =>
+                        {
+                            signature = Some((params, &[][..]))
+                        }
+                        _ => {}
+                    }
+                }
+
+                match signature {
+                    Some((params, defaults)) => Self::Call(
+                        name.clone(),
+                        Self::order_args(name, params, defaults, args)?,
+                    )
+                    .to_hir_expr(decls)?,
+                    None => return Err(TirError::KeywordCallNotDefined(name.clone())),
+                }
+            }
+
+            Self::GenericCall(name, t, args) => {
+                HirExpression::Call(TirFunction::generic_name(name, t), {
+                    let mut result = vec![];
+                    for arg in args {
+                        result.push(arg.to_hir_expr(decls)?)
+                    }
+                    result
+                })
+            }
+
+            Self::ForeignCall(name, args) => HirExpression::ForeignCall(
+                name.clone(),
+                {
+                    let mut result = vec![];
+                    for arg in args {
+                        result.push(arg.to_hir_expr(decls)?)
+                    }
+                    result
+                },
+                false,
+            ),
+
             Self::Method(instance, name, args) => {
                 if name == "copy" {
                     return Err(TirError::ExplicitCopy);
@@ -1179,6 +2371,8 @@ impl TirExpression {
                 Box::new(then.to_hir_expr(decls)?),
                 Box::new(otherwise.to_hir_expr(decls)?),
             ),
+
+            Self::RawAsm(t, body) => HirExpression::RawAsm(t.to_hir_type(), body.clone()),
         })
     }
 }
@@ -0,0 +1,35 @@
+#!/usr/bin/env python3
+
+# Test that the compiler reports the path of the artifact it produced,
+# and that the artifact exists. The program is compiled once normally,
+# which should produce the executable "./main", and once with `--emit-c`,
+# which should produce the C source file "./out.c".
+# Flags:
+#     -f: the file to be tested (ex. "./examples/hello_world.ok")
+
+from os.path import exists
+from helpers import require_oak, file_argument, remove, generated_files, oak, check
+
+def reported_artifact(output):
+	prefix = "compilation successful: wrote "
+	for line in output.splitlines():
+		if line.startswith(prefix):
+			return line[len(prefix):].strip()
+	return None
+
+def main():
+	file_to_test = file_argument()
+	require_oak()
+
+	remove("./out.c", "./main", "./main.exe")
+	with generated_files({}, "./out.c", "./main", "./main.exe"):
+		executable = reported_artifact(oak(["c", file_to_test]).stdout.decode("utf-8"))
+		source = reported_artifact(oak(["--emit-c", "c", file_to_test]).stdout.decode("utf-8"))
+
+		with check(executable, source):
+			assert(executable != None and exists(executable))
+			assert(executable.startswith("main"))
+			assert(source == "out.c" and exists(source))
+
+if __name__ == "__main__":
+	main()
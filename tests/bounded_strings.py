@@ -0,0 +1,53 @@
+#!/usr/bin/env python3
+
+# Test that `strnlen` and `strncopy` stop at their bound on a string
+# without a zero terminator, and that writing the same string with
+# `putstr` under `--debug` panics instead of reading past the end of
+# memory. The generated file is written to "./bounded_strings.ok" and
+# removed afterwards.
+
+from helpers import require_oak, generated_files, compile_and_run, stdout, check
+
+PROGRAM = """#[std]
+#[memory(128)]
+
+fn main() {
+    // The last cells of memory, without a zero terminator after them
+    let s: &char = alloc(3);
+    s[0] = 'o';
+    s[1] = 'a';
+    s[2] = 'k';
+    putnumln(strnlen(s, 3));
+
+    let copy: &char = ['x', 'x', 'x', 'x', 'x'];
+    strncopy(copy, s, 3);
+    copy[3] = 0;
+    putstrln(copy);
+
+    let word: &char = ['h', 'i', 0];
+    putnumln(strnlen(word, 10));
+    strncopy(copy, word, 5);
+    putstrln(copy);
+
+    putstrln(s);
+}
+"""
+
+# The error code of a string that runs off the end of memory
+UNTERMINATED_STRING = 6
+
+def main():
+	require_oak()
+
+	with generated_files({"./bounded_strings.ok": PROGRAM}):
+		result = compile_and_run(["--debug"], "./bounded_strings.ok")
+
+	output = stdout(result)
+	with check(output):
+		assert(result != None)
+		assert(result.returncode == UNTERMINATED_STRING)
+		assert(output.startswith("3\noak\n2\nhi\noak"))
+		assert("panic: string is not terminated before the end of memory" in output)
+
+if __name__ == "__main__":
+	main()
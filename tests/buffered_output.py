@@ -0,0 +1,35 @@
+#!/usr/bin/env python3
+
+# Test that buffered output is still written when a program exits early
+# with an error code. The generated file is written to "./buffered_output.ok"
+# and removed afterwards.
+
+from helpers import require_oak, generated_files, compile_and_run, stdout, check
+
+PROGRAM = """#[std]
+
+fn main() {
+    for i in 0..1000 { putchar('x'); }
+    flush();
+    for i in 0..1000 { putchar('y'); }
+    // Freeing the memory twice makes the debug runtime panic
+    let ptr: &num = alloc(4);
+    free ptr: 4;
+    free ptr: 4;
+}
+"""
+
+def main():
+	require_oak()
+
+	with generated_files({"./buffered_output.ok": PROGRAM}):
+		result = compile_and_run(["--debug"], "./buffered_output.ok")
+
+	output = stdout(result)
+	with check():
+		assert(result != None and result.returncode == 4)
+		assert(output.startswith("x" * 1000 + "y" * 1000))
+		assert("panic: freed memory that is not allocated" in output)
+
+if __name__ == "__main__":
+	main()
@@ -0,0 +1,72 @@
+#!/usr/bin/env python3
+
+# Test that `set_byte` and `get_byte` write and read the individual bytes
+# of the cells in memory, and that a buffer of bytes written this way is a
+# C string that can be passed to a foreign function calling `strlen`. The
+# Rust backend is only checked for reading bytes back, and it is skipped
+# if rustc isn't installed. The generated files are written to
+# "./byte_memory.ok" and "./byte_memory.c", and removed afterwards.
+
+from shutil import which
+from helpers import require_oak, generated_files, compile_and_run, stdout, check
+
+PROGRAM = """#[std]
+#[if(TARGET == 'c') {
+    #[extern("byte_memory.c")]
+    extern fn byte_strlen(buf: &void) -> num;
+    fn putlenln(buf: &void) { putnumln(byte_strlen(buf)); }
+} else {
+    fn putlenln(buf: &void) {}
+}]
+
+fn main() {
+    // Two cells hold up to sixteen bytes
+    let buf: &void = alloc(2);
+    set_byte(buf, 0, 111);
+    set_byte(buf, 1, 97);
+    set_byte(buf, 2, 107);
+    set_byte(buf, 9, 255);
+    putnumln(get_byte(buf, 0));
+    putnumln(get_byte(buf, 1));
+    putnumln(get_byte(buf, 2));
+    putnumln(get_byte(buf, 3));
+    putnumln(get_byte(buf, 9));
+
+    // Overwriting a byte leaves the others in its cell alone
+    set_byte(buf, 1, 120);
+    putnumln(get_byte(buf, 0));
+    putnumln(get_byte(buf, 1));
+
+    putlenln(buf);
+    free buf: 2;
+}
+"""
+
+FOREIGN = """#include <string.h>
+
+void byte_strlen(machine *vm) {
+    int addr = machine_pop(vm);
+    machine_push(vm, strlen((char*)&vm->memory[addr]));
+}
+"""
+
+BYTES = "111\n97\n107\n0\n255\n111\n120\n"
+
+def main():
+	require_oak()
+
+	with generated_files({"./byte_memory.ok": PROGRAM, "./byte_memory.c": FOREIGN}):
+		c = compile_and_run(["--cc"], "./byte_memory.ok")
+		rs = None
+		if which("rustc") != None:
+			rs = compile_and_run(["--rs"], "./byte_memory.ok")
+
+	with check(stdout(c), stdout(rs)):
+		assert(c != None)
+		assert(stdout(c) == BYTES + "3\n")
+		if which("rustc") != None:
+			assert(rs != None)
+			assert(stdout(rs) == BYTES)
+
+if __name__ == "__main__":
+	main()
@@ -0,0 +1,43 @@
+#!/usr/bin/env python3
+
+# Test that when gcc fails to compile the generated C, its errors are
+# reported at the line of the Oak code the C was generated from. Here,
+# an `extern fn` binding doesn't match the foreign function it binds.
+# The generated files are written to "./c_diagnostics.ok" and
+# "./c_diagnostics.c", and removed afterwards.
+
+from os.path import exists
+from helpers import require_oak, generated_files, remove, oak, check
+
+FOREIGN = """void greet(void) {}
+"""
+
+PROGRAM = """#[extern("c_diagnostics.c")]
+
+// The foreign function doesn't take the virtual machine
+extern fn greet();
+
+fn main() {
+    greet();
+}
+"""
+
+# The line of the Oak binding that the bad C call is generated from
+LINE = 4
+
+def main():
+	require_oak()
+
+	remove("./main")
+	with generated_files({"./c_diagnostics.c": FOREIGN, "./c_diagnostics.ok": PROGRAM}, "./main"):
+		output = oak(["c", "./c_diagnostics.ok"]).stdout.decode("utf-8")
+		compiled = exists("./main")
+
+	with check(output):
+		assert(not compiled)
+		assert("compilation successful" not in output)
+		assert("./c_diagnostics.ok:{}: in fn greet: error:".format(LINE) in output)
+		assert("too many arguments to function" in output)
+
+if __name__ == "__main__":
+	main()
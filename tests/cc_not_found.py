@@ -0,0 +1,35 @@
+#!/usr/bin/env python3
+
+# Test that compiling with a C compiler that doesn't exist reports
+# that the compiler could not be found, both when it is given with
+# the `--c-compiler` flag and when it is given with the `CC` environment
+# variable.
+# Flags:
+#     -f: the file to be tested (ex. "./examples/hello_world.ok")
+
+import os
+from helpers import require_oak, file_argument, oak, check
+
+MISSING_COMPILER = "oak-missing-cc"
+
+def compile_with(args, env):
+	return oak(["c"] + args, env=env).stdout.decode("utf-8")
+
+def main():
+	file_to_test = file_argument()
+	require_oak()
+
+	expected = "C compiler \"" + MISSING_COMPILER + "\" not found"
+	env = dict(os.environ)
+	env.pop("CC", None)
+	flag_output = compile_with(["--c-compiler", MISSING_COMPILER, file_to_test], env)
+
+	env["CC"] = MISSING_COMPILER
+	env_output = compile_with([file_to_test], env)
+
+	with check(flag_output, env_output):
+		assert(expected in flag_output)
+		assert(expected in env_output)
+
+if __name__ == "__main__":
+	main()
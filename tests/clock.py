@@ -0,0 +1,41 @@
+#!/usr/bin/env python3
+
+# Test that `clock` never goes backwards between two calls, with some work
+# done between them, and that `time_ms` counts the same time in milliseconds.
+# The Rust backend is skipped if rustc isn't installed. The generated file is
+# written to "./clock.ok" and removed afterwards.
+
+from shutil import which
+from helpers import require_oak, generated_files, compile_and_run, stdout, check
+
+PROGRAM = """#[std]
+
+fn main() {
+    let start = clock();
+    let total = 0;
+    for (let i=0; i<100000; i+=1) {
+        total += i;
+    }
+    let end = clock();
+    let ms = time_ms();
+
+    putboolln(start >= 0);
+    putboolln(end >= start);
+    putboolln(ms >= end * 1000);
+}
+"""
+
+def main():
+	require_oak()
+
+	with generated_files({"./clock.ok": PROGRAM}):
+		results = [stdout(compile_and_run(["--cc"], "./clock.ok"))]
+		if which("rustc") != None:
+			results.append(stdout(compile_and_run(["--rs"], "./clock.ok")))
+
+	with check(*results):
+		for result in results:
+			assert(result == "true\ntrue\ntrue\n")
+
+if __name__ == "__main__":
+	main()
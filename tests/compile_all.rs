@@ -0,0 +1,52 @@
+// Test that a single call to `compile_all` generates distinct source
+// code for the C, Go, and TypeScript backends from one program, and
+// that each source contains its own target's runtime.
+
+use oakc::{compile_all, Go, Target, C, TS};
+use std::path::PathBuf;
+
+const PROGRAM: &str = "#[std]
+fn main() {
+    putnumln(TARGET as num);
+}
+";
+
+#[test]
+fn compile_all_targets() {
+    let targets: [&dyn Target; 3] = [&C::default(), &Go::default(), &TS::default()];
+    let outputs = compile_all(
+        &PathBuf::from("./"),
+        &[],
+        "compile_all.ok",
+        PROGRAM,
+        &targets,
+    );
+
+    assert_eq!(outputs.len(), 3);
+    let outputs = outputs
+        .into_iter()
+        .map(|output| output.expect("the program should compile"))
+        .collect::<Vec<_>>();
+
+    assert_ne!(outputs[0], outputs[1]);
+    assert_ne!(outputs[1], outputs[2]);
+    assert_ne!(outputs[0], outputs[2]);
+    assert!(outputs[0].contains("#include"));
+    assert!(outputs[1].contains("package main"));
+    assert!(outputs[2].contains("async function"));
+}
+
+#[test]
+fn compile_all_parse_error() {
+    let targets: [&dyn Target; 2] = [&C::default(), &Go::default()];
+    let outputs = compile_all(
+        &PathBuf::from("./"),
+        &[],
+        "compile_all.ok",
+        "fn main( {",
+        &targets,
+    );
+
+    assert_eq!(outputs.len(), 2);
+    assert!(outputs.iter().all(Result::is_err));
+}
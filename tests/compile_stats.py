@@ -0,0 +1,59 @@
+#!/usr/bin/env python3
+
+# Test that compiling a program with `--stats` prints the number of
+# functions, structures, and MIR statements in the program, the size
+# of the generated code, and the time spent in each phase of the compiler
+# to stderr, and that compiling it without the flag prints none of them.
+# The generated file is written to "./compile_stats.ok" and removed afterwards.
+
+import re
+import subprocess
+from helpers import require_oak, generated_files, oak, check
+
+PROGRAM = """#[std]
+
+struct Point {
+    let x: num,
+        y: num;
+
+    fn new(x: num, y: num) -> Point { return [x, y] }
+    fn sum(self: &Point) -> num { return self->x + self->y }
+}
+
+fn square(n: num) -> num {
+    return n * n;
+}
+
+fn main() {
+    let p = Point::new(1, 2);
+    putnumln(square(p.sum()));
+}
+"""
+
+PHASES = ["parse", "TIR to HIR", "HIR to MIR", "assemble"]
+
+def compile(flags):
+	result = oak(flags + ["c", "./compile_stats.ok"], stderr=subprocess.PIPE)
+	return result.stderr.decode("utf-8")
+
+def stat(output, name):
+	match = re.search(r"^" + name + r": (\d+)", output, re.MULTILINE)
+	return int(match.group(1)) if match else None
+
+def main():
+	require_oak()
+
+	with generated_files({"./compile_stats.ok": PROGRAM}, "./main"):
+		stats = compile(["--stats"])
+		plain = compile([])
+
+	with check(stats):
+		# The core library's functions are counted too
+		for name, least in [("functions", 3), ("structures", 1), ("MIR statements", 4), ("output size", 1)]:
+			assert(stat(stats, name) != None and stat(stats, name) >= least)
+		for phase in PHASES:
+			assert(re.search(r"^" + phase + r": \S+", stats, re.MULTILINE))
+		assert(stat(plain, "functions") == None)
+
+if __name__ == "__main__":
+	main()
@@ -0,0 +1,36 @@
+#!/usr/bin/env python3
+
+# Test that the body of an `if false` statement emits no code.
+# A program is generated whose dead branch pushes a number that
+# doesn't appear anywhere else, and the generated C code is checked
+# for that number. The generated file is written to "./dead_branch.ok"
+# and removed afterwards.
+
+from helpers import require_oak, generated_files, oak, read_output, check
+
+MARKER = "1234567"
+
+PROGRAM = """#[std]
+
+fn main() {
+    if false {
+        putnumln(""" + MARKER + """);
+    }
+    putnumln(1);
+}
+"""
+
+def main():
+	require_oak()
+
+	with generated_files({"./dead_branch.ok": PROGRAM}, "./out.c"):
+		oak(["--emit-c", "c", "./dead_branch.ok"])
+		code = read_output("./out.c")
+
+	with check():
+		assert(code != None)
+		assert("machine_push(vm, 1);" in code)
+		assert(MARKER not in code)
+
+if __name__ == "__main__":
+	main()
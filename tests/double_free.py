@@ -0,0 +1,34 @@
+#!/usr/bin/env python3
+
+# Test that freeing the same memory twice panics with the `--debug` flag,
+# and that the program runs to completion without it. The generated file
+# is written to "./double_free.ok" and removed afterwards.
+
+from helpers import require_oak, generated_files, compile_and_run, stdout, check
+
+PROGRAM = """#[std]
+
+fn main() {
+    let ptr: &num = alloc(4);
+    free ptr: 4;
+    free ptr: 4;
+    putstrln("done");
+}
+"""
+
+def main():
+	require_oak()
+
+	with generated_files({"./double_free.ok": PROGRAM}):
+		release = compile_and_run([], "./double_free.ok")
+		debug = compile_and_run(["--debug"], "./double_free.ok")
+
+	with check():
+		assert(release != None and release.returncode == 0)
+		assert(stdout(release).strip() == "done")
+		assert(debug != None and debug.returncode != 0)
+		assert("panic: " in stdout(debug))
+		assert("done" not in stdout(debug))
+
+if __name__ == "__main__":
+	main()
@@ -0,0 +1,25 @@
+#!/usr/bin/env python3
+
+# Test that the `--emit-c` flag writes the generated C code to `out.c`
+# without invoking gcc. gcc is hidden from the compiler by running it
+# with an empty PATH, so any attempt to use it would fail to compile.
+# Flags:
+#     -f: the file to be tested (ex. "./examples/hello_world.ok")
+
+from os.path import exists
+from helpers import require_oak, file_argument, remove, generated_files, oak, check
+
+def main():
+	file_to_test = file_argument()
+	require_oak()
+
+	remove("./out.c", "./main")
+	with generated_files({}, "./out.c", "./main"):
+		oak(["--emit-c", "c", file_to_test], env={"PATH": ""})
+
+		with check():
+			assert(exists("./out.c"))
+			assert(not exists("./main"))
+
+if __name__ == "__main__":
+	main()
@@ -0,0 +1,61 @@
+// Test that the cost model weighs the bodies of simple functions as
+// expected: one per instruction, one per cell a variable loads, and
+// more for calls and the instructions that begin and end an if statement.
+
+use oakc::asm::{AsmExpression, AsmFunction, AsmStatement, AsmType};
+
+fn function(args: Vec<(&str, AsmType)>, body: Vec<AsmStatement>) -> AsmFunction {
+    let args = args
+        .into_iter()
+        .map(|(name, data_type)| (String::from(name), data_type))
+        .collect();
+    AsmFunction::new(String::from("f"), args, AsmType::void(), body, false, None)
+}
+
+#[test]
+fn estimated_cost_of_arithmetic() {
+    let body = vec![AsmStatement::Expression(vec![
+        AsmExpression::Float(1.0),
+        AsmExpression::Float(2.0),
+        AsmExpression::Add,
+    ])];
+    assert_eq!(function(vec![], body).estimated_cost(), 3);
+}
+
+#[test]
+fn estimated_cost_of_variables() {
+    // let x = 5; x + 1;
+    let body = vec![
+        AsmStatement::Expression(vec![AsmExpression::Float(5.0)]),
+        AsmStatement::Define(String::from("x"), AsmType::float()),
+        AsmStatement::Assign(AsmType::float()),
+        AsmStatement::Expression(vec![
+            AsmExpression::Variable(String::from("x")),
+            AsmExpression::Float(1.0),
+            AsmExpression::Add,
+        ]),
+    ];
+    assert_eq!(function(vec![], body).estimated_cost(), 11);
+
+    // Loading a larger argument moves more cells
+    let body = vec![AsmStatement::Expression(vec![AsmExpression::Variable(
+        String::from("p"),
+    )])];
+    assert_eq!(
+        function(vec![("p", AsmType::new(3))], body).estimated_cost(),
+        6
+    );
+}
+
+#[test]
+fn estimated_cost_of_calls() {
+    // if 1 { g(); }
+    let body = vec![AsmStatement::If(
+        vec![AsmStatement::Expression(vec![AsmExpression::Float(1.0)])],
+        vec![AsmStatement::Expression(vec![AsmExpression::Call(
+            String::from("g"),
+        )])],
+        vec![],
+    )];
+    assert_eq!(function(vec![], body).estimated_cost(), 13);
+}
@@ -0,0 +1,44 @@
+#!/usr/bin/env python3
+
+# Test that the `--explicit-stack` flag lets Oak recurse deeper than the
+# native stack allows. A deeply recursive program is generated, and run
+# with an 8MB native stack after being compiled with and without the flag.
+# Without the flag, the program overflows the native stack and crashes.
+# With the flag, the recursion depth is only bounded by the VM's memory.
+# The generated file is written to "./deep_recursion.ok" and removed afterwards.
+
+import resource
+from helpers import require_oak, generated_files, compile_and_run, stdout, check
+
+PROGRAM = """#[std]
+#[memory(4000000)]
+
+fn sum(n: num) -> num {
+    let result = 0;
+    if n > 0 { result = n + sum(n - 1); }
+    return result;
+}
+
+fn main() {
+    putnumln(sum(500000));
+}
+"""
+
+def limit_native_stack():
+	size = 8 * 1024 * 1024
+	resource.setrlimit(resource.RLIMIT_STACK, (size, size))
+
+def main():
+	require_oak()
+
+	with generated_files({"./deep_recursion.ok": PROGRAM}):
+		native = compile_and_run([], "./deep_recursion.ok", preexec_fn=limit_native_stack)
+		explicit = compile_and_run(["--explicit-stack"], "./deep_recursion.ok", preexec_fn=limit_native_stack)
+
+	with check():
+		assert(native != None and native.returncode != 0)
+		assert(explicit != None and explicit.returncode == 0)
+		assert(stdout(explicit).strip() == "1.25e+11")
+
+if __name__ == "__main__":
+	main()
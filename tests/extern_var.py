@@ -0,0 +1,45 @@
+#!/usr/bin/env python3
+
+# Test that foreign global variables can be read and written through
+# `extern let` bindings. The generated files are written to
+# "./extern_var.ok" and "./extern_var.c", and removed afterwards.
+
+from helpers import require_oak, generated_files, compile_and_run, stdout, check
+
+FOREIGN = """int counter;
+
+// Increment the foreign counter without using the stack
+void increment(machine *vm) {
+    counter++;
+}
+"""
+
+PROGRAM = """#[std]
+#[extern("extern_var.c")]
+
+extern let counter: i32;
+extern fn increment();
+
+fn main() {
+    putnumln(counter() as num);
+    increment();
+    increment();
+    putnumln(counter() as num);
+    set_counter(10 as i32);
+    increment();
+    putnumln(counter() as num);
+}
+"""
+
+def main():
+	require_oak()
+
+	with generated_files({"./extern_var.ok": PROGRAM, "./extern_var.c": FOREIGN}):
+		result = compile_and_run([], "./extern_var.ok")
+
+	with check():
+		assert(result != None)
+		assert(stdout(result) == "0\n2\n11\n")
+
+if __name__ == "__main__":
+	main()
@@ -0,0 +1,70 @@
+#!/usr/bin/env python3
+
+# Test that the wrappers generated for foreign functions pass every parameter
+# on to the foreign function, so that compiling an extern binding with `--werror`
+# succeeds, and that the C code emitted for it compiles with gcc's warnings
+# enabled and treated as errors. The generated files are written to
+# "./extern_warnings.ok" and "./extern_warnings.c", and removed afterwards.
+
+import os
+from os.path import exists
+import subprocess
+from helpers import require_oak, generated_files, remove, oak, run, stdout, check
+
+PROGRAM = """#[std]
+#[extern("extern_warnings.c")]
+extern fn ext_add(a: num, b: num) -> num;
+extern fn ext_show(n: num);
+extern fn ext_ignore(a: num, b: &char);
+extern fn ext_add as add_bytes(a: u8, b: u8) -> u8;
+
+fn main() {
+    putnumln(ext_add(1, 2));
+    ext_show(4);
+    ext_ignore(5, "six");
+    putnumln(add_bytes(200 as u8, 100 as u8) as num);
+}
+"""
+
+FOREIGN = """void ext_add(machine *vm) {
+    double b = machine_pop(vm);
+    double a = machine_pop(vm);
+    machine_push(vm, a + b);
+}
+
+void ext_show(machine *vm) {
+    printf("%g\\n", machine_pop(vm));
+}
+
+void ext_ignore(machine *vm) {
+    machine_pop(vm);
+    machine_pop(vm);
+}
+"""
+
+def main():
+	require_oak()
+
+	remove("./out.c", "./main")
+	with generated_files({"./extern_warnings.ok": PROGRAM, "./extern_warnings.c": FOREIGN}, "./out.c"):
+		compiled = oak(["--werror", "c", "./extern_warnings.ok"])
+		result = run(["./main"])
+
+		oak(["--emit-c", "c", "./extern_warnings.ok"])
+		gcc = None
+		if exists("./out.c"):
+			gcc = subprocess.run(
+				["gcc", "-Wall", "-Wextra", "-Werror", "-c", "./out.c", "-o", os.devnull],
+				stdout=subprocess.PIPE,
+				stderr=subprocess.STDOUT
+			)
+
+	with check(stdout(compiled), stdout(gcc)):
+		assert(result != None)
+		assert(stdout(result) == "3\n4\n44\n")
+		assert(not "warning" in stdout(compiled))
+		assert(gcc != None)
+		assert(gcc.returncode == 0)
+
+if __name__ == "__main__":
+	main()
@@ -0,0 +1,24 @@
+#!/usr/bin/env python3
+
+# Test that `--features` lists every available target,
+# one `key=value` record per line.
+
+from helpers import require_oak, oak, check
+
+def main():
+	require_oak()
+
+	result = oak(["--features"])
+	records = [
+		dict(pair.split("=", 1) for pair in line.split())
+		for line in result.stdout.decode().splitlines()
+	]
+	languages = [record["language"] for record in records if "target" in record]
+
+	with check():
+		assert(result.returncode == 0)
+		for language in ["C", "Go", "TypeScript", "Rust", "Zig", "Ruby", "WebAssembly"]:
+			assert(language in languages)
+
+if __name__ == "__main__":
+	main()
@@ -0,0 +1,24 @@
+// Test that every target writes negative zero with its sign, since
+// `-0` is read as the integer zero by most of the output languages.
+
+use oakc::{Go, Ruby, Rust, Target, Wat, Zig, C, TS};
+
+fn targets() -> Vec<Box<dyn Target>> {
+    vec![
+        Box::new(C::new()),
+        Box::new(Go::new()),
+        Box::new(TS::new()),
+        Box::new(Rust::new()),
+        Box::new(Zig::new()),
+        Box::new(Ruby::new()),
+        Box::new(Wat::new()),
+    ]
+}
+
+#[test]
+fn negative_zero_keeps_its_sign() {
+    for target in targets() {
+        assert_eq!(target.float_literal(-0.0), "-0.0");
+        assert_ne!(target.float_literal(0.0), "-0.0");
+    }
+}
@@ -0,0 +1,64 @@
+#!/usr/bin/env python3
+
+# Test that a foreign function marked with `#[context]` is passed the
+# context pointer that the host stored in the virtual machine, and can read
+# and update the host's state through it. The generated files are written to
+# "./foreign_context.ok" and "./foreign_context.c", and removed afterwards.
+
+from helpers import require_oak, generated_files, compile_and_run, stdout, check
+
+FOREIGN = """typedef struct host {
+    double total;
+    int calls;
+} host;
+
+host state = {100, 0};
+
+// Give the foreign functions access to the host's state
+void attach_host(machine *vm) {
+    vm->context = &state;
+}
+
+// Add a number to the host's total, and push the new total
+void add_to_host(machine *vm, void *ctx) {
+    host *h = ctx;
+    h->total += machine_pop(vm);
+    h->calls++;
+    machine_push(vm, h->total);
+}
+
+// Push the number of times the host was called
+void host_calls(machine *vm, void *ctx) {
+    machine_push(vm, ((host *)ctx)->calls);
+}
+"""
+
+PROGRAM = """#[std]
+#[extern("foreign_context.c")]
+
+extern fn attach_host();
+#[context]
+extern fn add_to_host(n: num) -> num;
+#[context]
+extern fn host_calls() -> num;
+
+fn main() {
+    attach_host();
+    putnumln(add_to_host(5));
+    putnumln(add_to_host(-20));
+    putnumln(host_calls());
+}
+"""
+
+def main():
+	require_oak()
+
+	with generated_files({"./foreign_context.ok": PROGRAM, "./foreign_context.c": FOREIGN}):
+		result = compile_and_run([], "./foreign_context.ok")
+
+	with check():
+		assert(result != None)
+		assert(stdout(result) == "105\n85\n2\n")
+
+if __name__ == "__main__":
+	main()
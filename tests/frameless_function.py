@@ -0,0 +1,48 @@
+#!/usr/bin/env python3
+
+# Test that a function without arguments or local variables is assembled
+# without a stack frame, and that the values it returns are still returned.
+# The generated program is written to "./frameless_function.ok", and
+# removed afterwards.
+
+from helpers import require_oak, generated_files, remove, oak, compile_and_run, read_output, stdout, check
+
+PROGRAM = """#[std]
+
+struct Point {
+    let x: num, y: num;
+
+    fn new(x: num, y: num) -> Point { return [x, y]; }
+}
+
+fn greet() { putstrln("hello!"); }
+fn origin() -> Point { return Point::new(3, 4); }
+fn seven() -> num { greet(); return 7; }
+
+fn main() {
+    greet();
+    let p = origin();
+    putnumln(p->x);
+    putnumln(p->y);
+    putnumln(seven());
+}
+"""
+
+def main():
+	require_oak()
+
+	remove("./out.c")
+	with generated_files({"./frameless_function.ok": PROGRAM}):
+		result = compile_and_run([], "./frameless_function.ok")
+		oak(["--emit-c", "c", "./frameless_function.ok"])
+		code = read_output("./out.c") or ""
+
+	with check(stdout(result)):
+		assert(result != None)
+		assert(stdout(result) == "hello!\n3\n4\nhello!\n7\n")
+		# Only the functions with arguments or local variables have stack frames
+		assert(code.count("machine_establish_stack_frame(vm, 0, 1);") == 0)
+		assert(code.count("machine_establish_stack_frame(vm, 0, ") > 0)
+
+if __name__ == "__main__":
+	main()
@@ -0,0 +1,49 @@
+#!/usr/bin/env python3
+
+# Test that a program compiled with `--heap-stats` reports the most cells
+# allocated on the heap at once to stderr when it exits, with both the C
+# and Rust backends, and that a program compiled without it reports nothing.
+# The generated file is written to "./heap_stats.ok" and removed afterwards.
+
+import re
+import subprocess
+from helpers import require_oak, generated_files, compile_and_run, check
+
+# At most 30 cells are allocated at once, before both are freed
+PROGRAM = """#[std]
+
+fn main() {
+    let a: &num = alloc(10);
+    let b: &num = alloc(20);
+    free a: 10;
+    free b: 20;
+
+    let c: &num = alloc(5);
+    free c: 5;
+    putstrln("done");
+}
+"""
+
+def peak(result):
+	match = re.search(rb"heap high-water mark: (\d+) cells", result.stderr)
+	return int(match.group(1)) if match else None
+
+def main():
+	require_oak()
+
+	with generated_files({"./heap_stats.ok": PROGRAM}):
+		c = compile_and_run(["--heap-stats"], "./heap_stats.ok", stderr=subprocess.PIPE)
+		rust = compile_and_run(["--rs", "--heap-stats"], "./heap_stats.ok", stderr=subprocess.PIPE)
+		plain = compile_and_run([], "./heap_stats.ok", stderr=subprocess.PIPE)
+
+	with check():
+		for result in [c, rust]:
+			assert(result != None)
+			assert(result.stdout == b"done\n")
+			assert(peak(result) != None and peak(result) >= 30)
+		assert(plain != None)
+		assert(plain.stdout == b"done\n")
+		assert(peak(plain) == None)
+
+if __name__ == "__main__":
+	main()
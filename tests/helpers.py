@@ -0,0 +1,135 @@
+#!/usr/bin/env python3
+
+# Functions shared by the test scripts in this directory, which import
+# this file as `helpers`. The scripts are run from the root of the
+# repository, so every path here is relative to it.
+
+import sys, os
+from os.path import exists
+from shutil import which
+import subprocess
+from contextlib import contextmanager
+from typing import Dict, Iterator, List, Optional
+
+OAK = "./target/debug/oak"
+
+def require_oak() -> None:
+	if not exists(OAK):
+		print("Build Oak with 'cargo build' before running the test script")
+		exit(1)
+
+# The file given with the '-f' flag
+def file_argument() -> str:
+	if not "-f" in sys.argv:
+		print("Specify the file to test with the '-f' flag")
+		exit(1)
+	return sys.argv[sys.argv.index("-f")+1]
+
+# End the test without passing or failing it
+def skip(reason: str) -> None:
+	print("Skipped: " + reason)
+	exit(0)
+
+# Skip the test if any of the programs it needs aren't installed
+def skip_unless_installed(*programs: str) -> None:
+	for program in programs:
+		if which(program) == None:
+			skip(program + " is not installed")
+
+def remove(*paths: str) -> None:
+	for path in paths:
+		if exists(path):
+			os.remove(path)
+
+# Write the files that a test generates, and remove them afterwards
+# along with any of the outputs that the test left behind
+@contextmanager
+def generated_files(files: Dict[str, str], *outputs: str) -> Iterator[None]:
+	for path, contents in files.items():
+		with open(path, "w", encoding="utf-8") as f:
+			f.write(contents)
+	try:
+		yield
+	finally:
+		remove(*files.keys(), *outputs)
+
+# Run the compiler. Its stderr is captured with its stdout, unless
+# `stderr` is given.
+def oak(args: List[str], stderr: int = subprocess.STDOUT, **kwargs) -> subprocess.CompletedProcess:
+	return subprocess.run([OAK] + args, stdout=subprocess.PIPE, stderr=stderr, **kwargs)
+
+# Run a program that the compiler produced, and remove it afterwards.
+# The program is the last argument of the command, and nothing is run
+# if the compiler didn't produce it.
+def run(command: List[str], stderr: int = subprocess.STDOUT, **kwargs) -> Optional[subprocess.CompletedProcess]:
+	if not exists(command[-1]):
+		return None
+	result = subprocess.run(command, stdout=subprocess.PIPE, stderr=stderr, **kwargs)
+	os.remove(command[-1])
+	return result
+
+def compile_and_run(
+		flags: List[str],
+		file: str,
+		command: Optional[List[str]] = None,
+		stderr: int = subprocess.STDOUT,
+		**kwargs
+	) -> Optional[subprocess.CompletedProcess]:
+	command = command or ["./main"]
+	remove(command[-1])
+	oak(flags + ["c", file])
+	return run(command, stderr, **kwargs)
+
+# Read a file that the compiler produced, and remove it afterwards
+def read_output(path: str) -> Optional[str]:
+	if not exists(path):
+		return None
+	with open(path) as f:
+		contents = f.read()
+	os.remove(path)
+	return contents
+
+# The symbol of each function, from a map written with `--map`
+def read_symbols(path: str) -> Dict[str, str]:
+	symbols = {}
+	if exists(path):
+		with open(path) as f:
+			for line in f.read().splitlines():
+				symbol, name = line.split(" ", 1)
+				symbols[name] = symbol
+	return symbols
+
+# The backend flag, the compiler it needs, and the command to run the output
+BACKENDS = [
+	("--cc", "gcc", ["./main"]),
+	("--go", "go", ["./main"]),
+	("--ts", "tsc", ["node", "./main.js"]),
+	("--rs", "rustc", ["./main"]),
+]
+
+# Compile and run a file with each of the backends whose compiler is installed
+def run_backends(file: str) -> Dict[str, Optional[subprocess.CompletedProcess]]:
+	results = {}
+	for flag, compiler, command in BACKENDS:
+		if which(compiler) == None:
+			print("Skipped " + flag + ": " + compiler + " is not installed")
+			continue
+		results[flag] = compile_and_run([flag], file, command)
+	return results
+
+# The stdout of a process, or nothing if it never ran
+def stdout(result: Optional[subprocess.CompletedProcess]) -> str:
+	return result.stdout.decode("utf-8") if result != None else ""
+
+# Report whether the assertions in the block hold. The details
+# are printed to help find the cause when they don't.
+@contextmanager
+def check(*details: str) -> Iterator[None]:
+	try:
+		yield
+	except AssertionError:
+		print("Test Failed!")
+		for detail in details:
+			print(detail)
+		exit(1)
+	print("Test Passed!")
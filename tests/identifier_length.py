@@ -0,0 +1,58 @@
+#!/usr/bin/env python3
+
+# Test that functions with very long names, which only differ at their
+# ends, are emitted with C identifiers no longer than the 31 characters
+# C is sure to tell apart, and that each function still has its own
+# identifier. The generated file is written to "./identifier_length.ok"
+# and removed afterwards.
+
+from os.path import exists
+import subprocess
+from helpers import require_oak, generated_files, oak, run, read_symbols, check
+
+LONG_NAME = "a_function_with_a_very_long_name_that_goes_on_and_on_and_on"
+
+PROGRAM = """#[std]
+
+fn {0}_first() -> num {{ return 1; }}
+fn {0}_second() -> num {{ return 2; }}
+
+fn main() {{
+    putnumln({0}_first());
+    putnumln({0}_second());
+}}
+""".format(LONG_NAME)
+
+# The most characters of an identifier that C is sure to tell apart
+LIMIT = 31
+
+def main():
+	require_oak()
+
+	with generated_files({"./identifier_length.ok": PROGRAM}, "./out.c", "./out.map", "./main"):
+		oak(["--emit-c", "--map", "c", "./identifier_length.ok"])
+		code = ""
+		if exists("./out.c"):
+			with open("./out.c") as f:
+				# Leave out the comments, which name the Oak functions
+				code = "\n".join(line for line in f.read().splitlines() if not line.startswith("//"))
+			subprocess.run(["gcc", "./out.c", "-o", "./main", "-lm"])
+
+		# The symbol of each function, from the map written next to the code
+		symbols = read_symbols("./out.map")
+
+		result = run(["./main"])
+
+	with check(symbols):
+		first = symbols.get(LONG_NAME + "_first", "")
+		second = symbols.get(LONG_NAME + "_second", "")
+		assert(first != "" and second != "")
+		assert(LONG_NAME not in code)
+		assert(len(first) <= LIMIT and len(second) <= LIMIT)
+		assert(first != second)
+		assert(first + "(vm)" in code and second + "(vm)" in code)
+		assert(result != None)
+		assert(result.stdout.decode("utf-8") == "1\n2\n")
+
+if __name__ == "__main__":
+	main()
@@ -0,0 +1,59 @@
+#!/usr/bin/env python3
+
+# Test that if statements are written as native conditionals in the generated
+# C, and that they keep a small size per statement. The generated program is
+# run to check that each branch still behaves like it did when if statements
+# were lowered to loops. The generated files are written to "./if_codegen.ok"
+# and "./out.c", and removed afterwards.
+
+from helpers import require_oak, generated_files, oak, compile_and_run, read_output, stdout, check
+
+# The most generated C that a single if-else statement may take up, in bytes.
+# When if statements were lowered to a pair of loops, each took over 2000 bytes.
+MAX_BYTES_PER_IF = 600
+IF_COUNT = 100
+
+def program(if_count: int) -> str:
+	body = ""
+	for i in range(if_count):
+		body += "    if n == %d { putnum(%d); } else { putchar('.'); }\n" % (i, i)
+	return """#[std]
+
+fn main() {
+    let n = 7;
+%s    putcharln('!');
+
+    for i in 0..10 {
+        if i == 2 { continue; }
+        if i > 4 { break; } else { putnum(i); }
+    }
+    putcharln('!');
+}
+""" % body
+
+# The generated C of a program with the given number of if statements
+def emit(if_count: int) -> str:
+	with generated_files({"./if_codegen.ok": program(if_count)}, "./out.c"):
+		oak(["--emit-c", "c", "./if_codegen.ok"])
+		return read_output("./out.c") or ""
+
+def main():
+	require_oak()
+
+	empty_code = emit(0)
+	code = emit(IF_COUNT)
+	with generated_files({"./if_codegen.ok": program(IF_COUNT)}):
+		output = stdout(compile_and_run([], "./if_codegen.ok", stderr=None))
+
+	with check(output):
+		# Each if statement is a native conditional, and none of them are
+		# lowered to loops. Comparisons are also written with conditionals.
+		begin_if, begin_while = "if (machine_pop(vm)) {", "while (machine_pop(vm)) {"
+		assert(empty_code != "" and code != "")
+		assert(code.count(begin_if) - empty_code.count(begin_if) >= IF_COUNT)
+		assert(code.count(begin_while) == empty_code.count(begin_while))
+		assert((len(code) - len(empty_code)) / IF_COUNT <= MAX_BYTES_PER_IF)
+		assert(output == "......." + "7" + "." * (IF_COUNT - 8) + "!\n0134!\n")
+
+if __name__ == "__main__":
+	main()
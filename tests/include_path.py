@@ -0,0 +1,79 @@
+#!/usr/bin/env python3
+
+# Test that a module found only in a directory given with `-I` can be
+# included, along with the file it includes and its foreign file, and that
+# the directories searched are reported when the module can't be found.
+# The generated files are written to "./include_path.ok" and the
+# "./include_path_lib/" directory, and removed afterwards.
+
+import os
+from os.path import exists
+import shutil
+from helpers import require_oak, generated_files, remove, oak, run, stdout, check
+
+MODULE = """#[include("inner/twice.ok")]
+#[extern("include_path.c")]
+
+extern fn foreign_seven() -> num;
+
+fn greet() {
+    putstrln("hello from the library");
+}
+"""
+
+INNER = """fn twice(n: num) -> num {
+    return n * 2;
+}
+"""
+
+FOREIGN = """void foreign_seven(machine *vm) {
+    machine_push(vm, 7);
+}
+"""
+
+PROGRAM = """#[std]
+#[include("include_path_module.ok")]
+
+fn main() {
+    greet();
+    putnumln(twice(foreign_seven()));
+}
+"""
+
+def compile(flags):
+	return oak(flags + ["c", "./include_path.ok"])
+
+def main():
+	require_oak()
+
+	os.makedirs("./include_path_lib/inner", exist_ok=True)
+	files = {
+		"./include_path_lib/include_path_module.ok": MODULE,
+		"./include_path_lib/inner/twice.ok": INNER,
+		"./include_path_lib/include_path.c": FOREIGN,
+		"./include_path.ok": PROGRAM,
+	}
+	remove("./main")
+	try:
+		with generated_files(files, "./main"):
+			# The module isn't in the only directory that's given
+			missing = compile(["-I", "./include_path_lib/missing"])
+			missing_built = exists("./main")
+			remove("./main")
+
+			compile(["-I", "./include_path_lib/missing", "--include", "./include_path_lib"])
+			result = run(["./main"])
+	finally:
+		shutil.rmtree("./include_path_lib")
+
+	with check():
+		output = stdout(missing)
+		assert(not missing_built)
+		assert("could not find included file 'include_path_module.ok'" in output)
+		# The error names each directory that was searched
+		assert("include_path_lib/missing" in output)
+		assert(result != None)
+		assert(stdout(result) == "hello from the library\n14\n")
+
+if __name__ == "__main__":
+	main()
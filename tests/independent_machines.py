@@ -0,0 +1,91 @@
+#!/usr/bin/env python3
+
+# Test that the same compiled function can be run on two independent
+# virtual machines. The generated C code is included in a host program,
+# which runs the function on both machines with their stacks at different
+# heights, and interleaves the calls. Every address the function uses is
+# relative to its own machine, so both calls must return the same result.
+# The generated files are written to "./independent_machines.ok" and
+# "./independent_machines.c", and removed afterwards.
+
+from os.path import exists
+import re
+import subprocess
+from helpers import require_oak, generated_files, remove, oak, run, read_symbols, stdout, check
+
+PROGRAM = """#[std]
+
+fn double(n: num) -> num {
+    return n * 2;
+}
+
+// The string is stored in the global scope, the variables in the
+// function's stack frame, and the cell on the machine's heap
+fn describe(n: num) -> num {
+    let name = "oak";
+    let doubled = double(n);
+    let cell: &num = alloc(1);
+    let letter = name[1];
+    *cell = doubled + (letter as num);
+    let result = *cell;
+    free cell: 1;
+    return result;
+}
+
+fn main() {
+    putnumln(describe(1));
+}
+"""
+
+HOST = """#define main oak_main
+#include "out.c"
+#undef main
+
+int main() {{
+    machine *first = machine_new({0}, {1});
+    machine *second = machine_new({0}, {1});
+    // The first machine's stack frames begin one cell higher
+    machine_push(first, 0);
+    machine_push(first, 1);
+    machine_push(second, 10);
+    {2}(second);
+    {2}(first);
+    printf("%g %g\\n", machine_pop(first), machine_pop(second));
+    machine_push(second, 1);
+    {2}(second);
+    printf("%g %g\\n", machine_pop(first), machine_pop(second));
+    machine_drop(first);
+    machine_drop(second);
+    return 0;
+}}
+"""
+
+def main():
+	require_oak()
+
+	remove("./main")
+	outputs = ["./independent_machines.c", "./out.c", "./out.map", "./main"]
+	with generated_files({"./independent_machines.ok": PROGRAM}, *outputs):
+		oak(["--emit-c", "--map", "c", "./independent_machines.ok"])
+		code = ""
+		if exists("./out.c"):
+			with open("./out.c") as f:
+				code = f.read()
+		symbols = read_symbols("./out.map")
+
+		# Create the machines with the same sizes as the program's entry point
+		sizes = re.search(r"machine \*vm = machine_new\((\d+), (\d+)\);", code)
+		result = None
+		if sizes != None and "describe" in symbols:
+			with open("./independent_machines.c", "w") as f:
+				f.write(HOST.format(sizes.group(1), sizes.group(2), symbols["describe"]))
+			subprocess.run(["gcc", "./independent_machines.c", "-o", "./main", "-lm"])
+			result = run(["./main"])
+
+	with check(stdout(result)):
+		assert(result != None)
+		assert(result.returncode == 0)
+		assert(stdout(result) == "99 117\n0 99\n")
+
+if __name__ == "__main__":
+	main()
@@ -0,0 +1,34 @@
+#!/usr/bin/env python3
+
+# Test that a 256 character string literal is written to the generated
+# C code as a single data declaration, instead of one push per character.
+# The generated file is written to "./initialized_data.ok" and removed
+# afterwards.
+
+from helpers import require_oak, generated_files, remove, oak, read_output, check
+
+TABLE = "".join(chr(ord("a") + i % 26) for i in range(256))
+
+PROGRAM = """#[std]
+
+fn main() {
+    putstrln("%s");
+}
+""" % TABLE
+
+def main():
+	require_oak()
+
+	remove("./out.c")
+	with generated_files({"./initialized_data.ok": PROGRAM}, "./out.c"):
+		oak(["--emit-c", "c", "./initialized_data.ok"])
+		code = read_output("./out.c") or ""
+
+	declarations = [line for line in code.splitlines() if "static const double" in line]
+	with check():
+		assert(len(declarations) == 1)
+		# The characters and the zero terminator
+		assert(declarations[0].count(",") == len(TABLE))
+
+if __name__ == "__main__":
+	main()
@@ -0,0 +1,43 @@
+#!/usr/bin/env python3
+
+# Test that the `--inline-main` flag writes the body of `main` directly in
+# the C entry point instead of calling it, and that the program still runs
+# the same. The generated file is written to "./inline_main.ok" and removed afterwards.
+
+from helpers import require_oak, generated_files, remove, oak, compile_and_run, read_output, check
+
+# Without the standard library, `main` is the only function, so its name is `fn0`
+PROGRAM = """#[no_std]
+
+fn main() {
+    let x = 1 + 2;
+}
+"""
+
+def emit(flags):
+	remove("./out.c")
+	oak(flags + ["--emit-c", "c", "./inline_main.ok"])
+	return read_output("./out.c")
+
+def main():
+	require_oak()
+
+	with generated_files({"./inline_main.ok": PROGRAM}):
+		called = emit([])
+		inlined = emit(["--inline-main"])
+
+	# A program with closures in `main` still runs the same
+	expected = compile_and_run([], "./examples/closure.ok")
+	result = compile_and_run(["--inline-main"], "./examples/closure.ok")
+
+	with check():
+		assert(called != None and inlined != None)
+		assert("fn0(vm);" in called)
+		assert("fn0(vm);" not in inlined)
+		assert("void fn0(machine* vm) {" not in inlined)
+		assert("machine_add(vm);" in inlined)
+		assert(expected != None and result != None)
+		assert(result.stdout == expected.stdout)
+
+if __name__ == "__main__":
+	main()
@@ -0,0 +1,91 @@
+#!/usr/bin/env python3
+
+# Test that under `--debug`, storing through a pointer past the end of
+# memory, and loading through a pointer to freed memory, both panic
+# instead of touching memory they shouldn't. Setting a byte past the end
+# of a buffer, and getting a byte at a negative index, panic the same way.
+# The Rust backend is skipped
+# if rustc isn't installed. The generated file is written to
+# "./invalid_access.ok" and removed afterwards.
+
+from shutil import which
+import helpers
+from helpers import require_oak, generated_files, stdout, check
+
+STORE = """#[std]
+#[memory(128)]
+
+fn main() {
+    let p: &num = alloc(1);
+    let q = p + 1000;
+    putnumln(1);
+    *q = 5;
+    putnumln(2);
+}
+"""
+
+LOAD = """#[std]
+#[memory(128)]
+
+fn main() {
+    let p: &num = alloc(4);
+    p[3] = 5;
+    putnumln(p[3]);
+    free p: 4;
+    putnumln(p[3]);
+}
+"""
+
+STORE_BYTE = """#[std]
+#[memory(128)]
+
+fn main() {
+    let buf: &void = alloc(2);
+    set_byte(buf, 9, 1);
+    putnumln(get_byte(buf, 9));
+    set_byte(buf, 16, 1);
+    putnumln(2);
+}
+"""
+
+LOAD_BYTE = """#[std]
+#[memory(128)]
+
+fn main() {
+    let buf: &void = alloc(1);
+    set_byte(buf, 0, 5);
+    putnumln(get_byte(buf, 0));
+    putnumln(get_byte(buf, 0 - 1));
+}
+"""
+
+# The error code of accessing memory that isn't allocated
+INVALID_ACCESS = 7
+
+def compile_and_run(program, flags):
+	with generated_files({"./invalid_access.ok": program}):
+		return helpers.compile_and_run(["--debug"] + flags, "./invalid_access.ok")
+
+def main():
+	require_oak()
+
+	backends = [[]]
+	if which("rustc") != None:
+		backends.append(["--rs"])
+
+	results = []
+	for backend in backends:
+		# The output printed before each program panics
+		results.append(("1\n", compile_and_run(STORE, backend)))
+		results.append(("5\n", compile_and_run(LOAD, backend)))
+		results.append(("1\n", compile_and_run(STORE_BYTE, backend)))
+		results.append(("5\n", compile_and_run(LOAD_BYTE, backend)))
+
+	with check(*[stdout(result) for _, result in results]):
+		for expected, result in results:
+			assert(result != None)
+			assert(result.returncode == INVALID_ACCESS)
+			assert(stdout(result) == expected + "panic: accessed memory that is not allocated\n")
+
+if __name__ == "__main__":
+	main()
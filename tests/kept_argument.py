@@ -0,0 +1,63 @@
+#!/usr/bin/env python3
+
+# Test that the compiler warns about a non-movable variable that is passed
+# by value to a function that stores it, but not about one that is passed
+# by reference or moved. The generated file is written to "./kept_argument.ok"
+# and removed afterwards.
+
+from helpers import require_oak, generated_files, oak, check
+
+NOISY = """struct Noisy {
+    let id: num;
+
+    fn copy(self: &Noisy) -> Noisy {
+        return (self->id) as Noisy;
+    }
+
+    fn drop(self: &Noisy) {}
+}
+
+fn keep(slot: &Noisy, n: Noisy) {
+    *slot = n;
+}
+
+fn show(n: &Noisy) {}
+"""
+
+BY_VALUE = NOISY + """
+fn main() {
+    let slot: &Noisy = alloc(1);
+    let n = 1 as Noisy;
+    keep(slot, n);
+}
+"""
+
+BY_REFERENCE = NOISY + """
+fn main() {
+    let slot: &Noisy = alloc(1);
+    let n = 1 as Noisy;
+    show(&n);
+    keep(slot, move(n));
+}
+"""
+
+def compile(program: str) -> str:
+	with generated_files({"./kept_argument.ok": program}, "./out.c"):
+		return oak(["--emit-c", "c", "./kept_argument.ok"]).stdout.decode("utf-8")
+
+def main():
+	require_oak()
+
+	by_value = compile(BY_VALUE)
+	by_reference = compile(BY_REFERENCE)
+
+	with check(by_value, by_reference):
+		assert("compilation warning" in by_value)
+		assert("non-movable value 'n'" in by_value)
+		assert("parameter 'n' of 'keep'" in by_value)
+		# The warning suggests how to fix the call
+		assert("move(n)" in by_value)
+		assert("compilation warning" not in by_reference)
+
+if __name__ == "__main__":
+	main()
@@ -0,0 +1,25 @@
+#!/usr/bin/env python3
+
+# Test that the compiler warns about functions that are too large
+# for the backend's compiler. A function with thousands of statements
+# is generated, and the compiler's output is checked for the warning.
+# The generated file is written to "./long_function.ok" and removed afterwards.
+
+from helpers import require_oak, generated_files, oak, check
+
+def generate_program(statements: int) -> str:
+	body = "\n".join(["    x = x + 1;" for _ in range(statements)])
+	return "fn main() {\n    let x = 0;\n" + body + "\n}\n"
+
+def main():
+	require_oak()
+
+	with generated_files({"./long_function.ok": generate_program(5000)}, "./out.c"):
+		output = oak(["--emit-c", "c", "./long_function.ok"]).stdout.decode("utf-8")
+
+	with check(output):
+		assert("compilation warning" in output)
+		assert("function 'main'" in output)
+
+if __name__ == "__main__":
+	main()
@@ -0,0 +1,37 @@
+#!/usr/bin/env python3
+
+# Test that the generated C begins with a comment recording the version of
+# the compiler, the target, the source file, and the memory size. The generated
+# files are written to "./manifest.ok" and "./out.c", and removed afterwards.
+
+import re
+from helpers import require_oak, generated_files, oak, read_output, check
+
+PROGRAM = """#[memory(1000)]
+
+fn main() {}
+"""
+
+def main():
+	require_oak()
+
+	# The version of the compiler is the version of the package
+	with open("./Cargo.toml") as f:
+		version = re.search(r'^version\s*=\s*"([^"]+)"', f.read(), re.MULTILINE).group(1)
+
+	with generated_files({"./manifest.ok": PROGRAM}, "./out.c"):
+		oak(["--emit-c", "c", "./manifest.ok"])
+		code = read_output("./out.c") or ""
+
+	with check(code[:300]):
+		assert(code.startswith("// generated by oakc " + version + "\n"))
+		# The manifest is a block of comments before the runtime
+		assert("#include" in code)
+		manifest = code[:code.index("#include")]
+		assert(all(line.startswith("// ") for line in manifest.splitlines()))
+		assert("// target: C\n" in manifest)
+		assert("manifest.ok" in manifest)
+		assert("// memory: 1000 cells\n" in manifest)
+
+if __name__ == "__main__":
+	main()
@@ -0,0 +1,58 @@
+#!/usr/bin/env python3
+
+# Test that a variable dropped with a manual call to its `drop` method is
+# not dropped again at the end of its block, when a loop is broken out of,
+# or at the end of the function. A variable defined again after being
+# dropped is dropped once more. The generated file is written to
+# "./manual_drop.ok" and removed afterwards.
+
+from helpers import require_oak, generated_files, compile_and_run, stdout, check
+
+PROGRAM = """#[std]
+
+struct Noisy {
+    let id: num;
+
+    fn copy(self: &Noisy) -> Noisy {
+        return (self->id) as Noisy;
+    }
+
+    fn drop(self: &Noisy) {
+        putnumln(self->id);
+    }
+}
+
+fn main() {
+    let a = 1 as Noisy;
+    a.drop();
+
+    for (let i = 0; i < 3; i = i + 1) {
+        let b = 2 as Noisy;
+        b.drop();
+        break;
+    }
+
+    let n = 3;
+    if n == 3 {
+        let c = 3 as Noisy;
+        c.drop();
+    }
+
+    let d = 4 as Noisy;
+    d.drop();
+    let d = 5 as Noisy;
+}
+"""
+
+def main():
+	require_oak()
+
+	with generated_files({"./manual_drop.ok": PROGRAM}):
+		result = compile_and_run([], "./manual_drop.ok")
+
+	with check(stdout(result)):
+		assert(result != None)
+		assert(result.stdout == b"1\n2\n3\n4\n5\n")
+
+if __name__ == "__main__":
+	main()
@@ -0,0 +1,39 @@
+#!/usr/bin/env python3
+
+# Test that the `--memory` flag sets the memory for programs that don't
+# use `#[memory(n)]`, and that sizes below the minimum are rejected.
+# The generated file is written to "./memory_flag.ok" and removed afterwards.
+
+from helpers import require_oak, generated_files, remove, oak, run, stdout, check
+
+# This needs more memory than the default 512 cells
+PROGRAM = """#[std]
+
+fn main() {
+    let data: &num = alloc(2000);
+    data[1999] = 5;
+    putnumln(data[1999]);
+    free data: 2000;
+}
+"""
+
+def compile_and_run(memory):
+	remove("./main")
+	compiled = oak(["--memory", memory, "c", "./memory_flag.ok"])
+	return compiled, run(["./main"])
+
+def main():
+	require_oak()
+
+	with generated_files({"./memory_flag.ok": PROGRAM}):
+		_, enough = compile_and_run("4096")
+		too_small, not_compiled = compile_and_run("64")
+
+	with check():
+		assert(enough != None and enough.returncode == 0)
+		assert(stdout(enough) == "5\n")
+		assert(not_compiled == None)
+		assert(b"too small" in too_small.stdout)
+
+if __name__ == "__main__":
+	main()
@@ -0,0 +1,66 @@
+#!/usr/bin/env python3
+
+# Test that the member getters of two structures with the same layout,
+# which are defined exactly the same apart from their names, are merged
+# into a single function in the generated C, and that the program still
+# runs correctly. The generated file is written to "./merged_functions.ok"
+# and removed afterwards.
+
+from os.path import exists
+import subprocess
+from helpers import require_oak, generated_files, oak, run, read_symbols, stdout, check
+
+PROGRAM = """#[std]
+
+struct Point {
+    let x: num, y: num;
+
+    fn new(x: num, y: num) -> Point { return [x, y]; }
+}
+
+struct Size {
+    let width: num, height: num;
+
+    fn new(width: num, height: num) -> Size { return [width, height]; }
+}
+
+fn main() {
+    let p = Point::new(1, 2);
+    let s = Size::new(3, 4);
+    putnumln(p->x);
+    putnumln(p->y);
+    putnumln(s->width);
+    putnumln(s->height);
+}
+"""
+
+def main():
+	require_oak()
+
+	with generated_files({"./merged_functions.ok": PROGRAM}, "./out.c", "./out.map", "./main"):
+		oak(["--emit-c", "--map", "c", "./merged_functions.ok"])
+		code = ""
+		if exists("./out.c"):
+			with open("./out.c") as f:
+				code = f.read()
+			subprocess.run(["gcc", "./out.c", "-o", "./main", "-lm"])
+
+		# The symbol of each function, from the map written next to the code
+		symbols = read_symbols("./out.map")
+
+		result = run(["./main"])
+
+	with check(symbols):
+		for name in ["Point::x", "Point::y", "Size::width", "Size::height"]:
+			assert(name in symbols)
+		x = symbols["Point::x"]
+		assert(x == symbols["Size::width"])
+		assert(symbols["Point::y"] == symbols["Size::height"])
+		assert(symbols["Point::x"] != symbols["Point::y"])
+		# The merged getter is only defined once
+		assert(code.count(" " + x + "(machine* vm) {") == 1)
+		assert(result != None)
+		assert(stdout(result) == "1\n2\n3\n4\n")
+
+if __name__ == "__main__":
+	main()
@@ -0,0 +1,58 @@
+#!/usr/bin/env python3
+
+# Test that the methods of a structure are assembled in the same order every
+# time: the member methods, the explicit methods in the order they're written,
+# and then `copy` and `drop`. The order is read from the map written with `--map`.
+# The generated program is written to "./method_order.ok", and it is removed
+# along with the executable and the map afterwards.
+
+from helpers import require_oak, generated_files, remove, oak, read_output, check
+
+PROGRAM = """struct Pair {
+    let a: num,
+        b: num;
+
+    fn drop(self: &Pair) {}
+
+    fn sum(self: &Pair) -> num {
+        return self->a + self->b;
+    }
+
+    fn new(a: num, b: num) -> Pair {
+        return [a, b];
+    }
+}
+
+fn main() {
+    let p = Pair::new(1, 2);
+}
+"""
+
+def compile_map() -> str:
+	remove("./main.map")
+	oak(["--map", "c", "./method_order.ok"])
+	return read_output("./main.map") or ""
+
+def main():
+	require_oak()
+
+	with generated_files({"./method_order.ok": PROGRAM}, "./main"):
+		first = compile_map()
+		second = compile_map()
+
+	with check(first):
+		assert(first != "")
+		assert(first == second)
+		names = [line.split(" ", 1)[1] for line in first.splitlines()]
+		methods = [name for name in names if name.startswith("Pair::")]
+		assert(methods == [
+			"Pair::a",
+			"Pair::b",
+			"Pair::sum",
+			"Pair::new",
+			"Pair::copy",
+			"Pair::drop",
+		])
+
+if __name__ == "__main__":
+	main()
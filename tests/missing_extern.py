@@ -0,0 +1,34 @@
+#!/usr/bin/env python3
+
+# Test that a missing foreign file is reported before the program is type
+# checked. The program also has a type error, which must not be reported.
+# The generated program is written to "./missing_extern.ok", and removed afterwards.
+
+from helpers import require_oak, generated_files, remove, oak, stdout, check
+
+PROGRAM = """#[std]
+#[extern("missing_extern.c")]
+
+fn main() {
+    let n: num = 'a';
+    putnumln(n);
+}
+"""
+
+def main():
+	require_oak()
+
+	remove("./missing_extern.c")
+	with generated_files({"./missing_extern.ok": PROGRAM}):
+		result = oak(["c", "./missing_extern.ok"])
+
+	output = stdout(result)
+	with check(output):
+		assert(result.returncode != 0)
+		assert("could not find foreign file" in output)
+		# The error names the path that was searched
+		assert("missing_extern.c" in output)
+		assert("mismatched types" not in output)
+
+if __name__ == "__main__":
+	main()
@@ -0,0 +1,36 @@
+#!/usr/bin/env python3
+
+# Test that the compiler warns about arithmetic that mixes a 'char'
+# and a 'num', but not when one of them is cast with 'as'. The
+# generated file is written to "./mixed_char_arithmetic.ok" and
+# removed afterwards.
+
+from helpers import require_oak, generated_files, oak, stdout, check
+
+MIXED = """fn main() {
+    let a = 'A' + 5;
+}
+"""
+
+CAST = """fn main() {
+    let a = ('A' as num) + 5;
+}
+"""
+
+def compile(program: str) -> str:
+	with generated_files({"./mixed_char_arithmetic.ok": program}, "./out.c"):
+		return stdout(oak(["--emit-c", "c", "./mixed_char_arithmetic.ok"]))
+
+def main():
+	require_oak()
+
+	mixed = compile(MIXED)
+	cast = compile(CAST)
+
+	with check(mixed, cast):
+		assert("compilation warning" in mixed)
+		assert("mixes 'char' and 'num'" in mixed)
+		assert("compilation warning" not in cast)
+
+if __name__ == "__main__":
+	main()
@@ -0,0 +1,50 @@
+#!/usr/bin/env python3
+
+# Test that a program with includes and conditional compilation flags
+# prints the same output when it's compiled for C and for each other
+# backend. The generated files are written to "./multiple_targets.ok" and
+# "./multiple_targets_lib.ok", and removed afterwards. Backends whose
+# compiler isn't installed are skipped.
+
+from helpers import require_oak, generated_files, run_backends, stdout, check
+
+PROGRAM = """#[std]
+#[include("multiple_targets_lib.ok")]
+
+#[if(sizeof(num) == 1) {
+    const NUM_CELLS = 1;
+} else {
+    const NUM_CELLS = 0;
+}]
+
+fn main() {
+    putnumln(NUM_CELLS);
+    putnumln(LIB_VERSION);
+    putnumln(square(12));
+}
+"""
+
+LIBRARY = """#[if(!is_defined("LIB_VERSION")) {
+    const LIB_VERSION = 2;
+    fn square(n: num) -> num { return n * n; }
+}]
+"""
+
+EXPECTED = b"1\n2\n144\n"
+
+def main():
+	require_oak()
+
+	with generated_files({"./multiple_targets.ok": PROGRAM, "./multiple_targets_lib.ok": LIBRARY}):
+		results = run_backends("./multiple_targets.ok")
+
+	failures = [
+		"Backend " + flag + " wrote " + repr(stdout(result))
+		for flag, result in results.items()
+		if result == None or result.stdout != EXPECTED
+	]
+	with check(*failures):
+		assert(not failures)
+
+if __name__ == "__main__":
+	main()
@@ -0,0 +1,53 @@
+#!/usr/bin/env python3
+
+# Test that a leaf function returning a single `num` is compiled to a C
+# function that returns a `double` natively, that a function returning
+# multiple cells still returns them on the virtual machine's stack, and
+# that the program prints the same results. The generated file is
+# written to "./native_return.ok" and removed afterwards.
+
+from helpers import require_oak, generated_files, remove, oak, compile_and_run, read_output, check
+
+PROGRAM = """#[std]
+
+struct Pair {
+    let a: num, b: num;
+}
+
+fn square(n: num) -> num {
+    return n * n;
+}
+
+fn pair(n: num) -> Pair {
+    return [n, n + 1];
+}
+
+fn main() {
+    putnumln(square(12));
+    let p = pair(3);
+    putnumln(p->a + p->b);
+}
+"""
+
+def main():
+	require_oak()
+
+	remove("./out.c")
+	with generated_files({"./native_return.ok": PROGRAM}):
+		oak(["--emit-c", "c", "./native_return.ok"])
+		code = read_output("./out.c")
+		result = compile_and_run([], "./native_return.ok")
+
+	with check():
+		assert(code != None)
+		# `square` returns a double, and its caller pushes the result
+		assert("return machine_end_stack_frame_returning(vm, " in code)
+		assert("double fn" in code)
+		assert("machine_push(vm, fn" in code)
+		# `pair` returns two cells on the stack
+		assert("machine_end_stack_frame(vm, 2, " in code)
+		assert(result != None)
+		assert(result.stdout == b"144\n7\n")
+
+if __name__ == "__main__":
+	main()
@@ -0,0 +1,39 @@
+#!/usr/bin/env python3
+
+# Test that a program that only uses the virtual machine's primitives
+# compiles and runs without the core library, with either the `--no-core`
+# flag or the `#[no_core]` flag. The generated file is written to
+# "./no_core.ok" and removed afterwards.
+
+from helpers import require_oak, generated_files, remove, oak, run, check
+
+PROGRAM = """#[no_std]
+%s
+
+fn main() {
+    let data: &num = alloc(8);
+    data[7] = 5;
+    let x = (data[7]) * 2;
+    free data: 8;
+}
+"""
+
+def compile_and_run(flags, header):
+	remove("./main")
+	with generated_files({"./no_core.ok": PROGRAM % header}):
+		compiled = oak(flags + ["c", "./no_core.ok"])
+	return compiled, run(["./main"])
+
+def main():
+	require_oak()
+
+	flag_compiled, flag_result = compile_and_run(["--no-core"], "")
+	_, declared_result = compile_and_run([], "#[no_core]")
+
+	with check():
+		assert(b"compilation successful" in flag_compiled.stdout)
+		assert(flag_result != None and flag_result.returncode == 0)
+		assert(declared_result != None and declared_result.returncode == 0)
+
+if __name__ == "__main__":
+	main()
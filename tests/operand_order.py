@@ -0,0 +1,35 @@
+#!/usr/bin/env python3
+
+# Test that every backend subtracts and divides its operands in the same
+# order by running "./examples/comparison.ok" with each of them. Backends
+# whose compiler isn't installed are skipped.
+
+from helpers import require_oak, run_backends, stdout, check
+
+EXPECTED = """true
+false
+true
+false
+true
+false
+false
+true
+2
+2
+"""
+
+def main():
+	require_oak()
+
+	results = run_backends("./examples/comparison.ok")
+
+	failures = [
+		"Backend " + flag + " printed:\n" + stdout(result)
+		for flag, result in results.items()
+		if stdout(result) != EXPECTED
+	]
+	with check(*failures):
+		assert(not failures)
+
+if __name__ == "__main__":
+	main()
@@ -0,0 +1,44 @@
+#!/usr/bin/env python3
+
+# Test that allocating more memory than is free returns null instead of
+# crashing, with and without the `--debug` flag, and that freeing the null
+# pointer does nothing. The generated file is written to "./out_of_memory.ok"
+# and removed afterwards.
+
+from helpers import require_oak, generated_files, compile_and_run, stdout, check
+
+PROGRAM = """#[std]
+#[memory(128)]
+
+fn main() {
+    let big: ?num = alloc(1000);
+    if big? {
+        putstrln("allocated 1000");
+    } else {
+        putstrln("out of memory");
+    }
+    free big: 1000;
+
+    // The memory is still usable after an allocation fails
+    let small: ?num = alloc(4);
+    if small? {
+        putstrln("allocated 4");
+    }
+    free small: 4;
+}
+"""
+
+def main():
+	require_oak()
+
+	with generated_files({"./out_of_memory.ok": PROGRAM}):
+		release = compile_and_run([], "./out_of_memory.ok")
+		debug = compile_and_run(["--debug"], "./out_of_memory.ok")
+
+	with check():
+		for result in [release, debug]:
+			assert(result != None and result.returncode == 0)
+			assert(stdout(result) == "out of memory\nallocated 4\n")
+
+if __name__ == "__main__":
+	main()
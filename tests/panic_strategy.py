@@ -0,0 +1,55 @@
+#!/usr/bin/env python3
+
+# Test that a program which panics with `--panic return-error` exits with
+# the panic's error code from its entry point, after cleaning up the
+# virtual machine, instead of exiting from inside the runtime. This is
+# checked with the heap report, which is only printed when the machine
+# is cleaned up. The Rust backend is skipped if rustc isn't installed.
+# The generated file is written to "./panic_strategy.ok" and removed
+# afterwards.
+
+from shutil import which
+from helpers import require_oak, generated_files, compile_and_run, stdout, check
+
+PROGRAM = """#[std]
+fn main() {
+    let p: &num = alloc(4);
+    putnumln(1);
+    free p: 4;
+    free p: 4;
+    putnumln(2);
+}
+"""
+
+# The error code of freeing memory that isn't allocated
+INVALID_FREE = 4
+
+def main():
+	require_oak()
+
+	backends = [[]]
+	if which("rustc") != None:
+		backends.append(["--rs"])
+
+	results = []
+	with generated_files({"./panic_strategy.ok": PROGRAM}):
+		for backend in backends:
+			flags = ["--debug", "--heap-stats"] + backend
+			aborted = compile_and_run(flags + ["--panic", "abort"], "./panic_strategy.ok")
+			returned = compile_and_run(flags + ["--panic", "return-error"], "./panic_strategy.ok")
+			results.append((aborted, returned))
+
+	with check(*[stdout(result) for pair in results for result in pair]):
+		for aborted, returned in results:
+			assert(aborted != None and returned != None)
+			assert(aborted.returncode == INVALID_FREE)
+			assert(returned.returncode == INVALID_FREE)
+
+			for output in [stdout(aborted), stdout(returned)]:
+				assert("panic: freed memory that is not allocated" in output)
+				assert("2\n" not in output)
+			assert("heap high-water mark" not in stdout(aborted))
+			assert("heap high-water mark" in stdout(returned))
+
+if __name__ == "__main__":
+	main()
@@ -0,0 +1,82 @@
+// Test that `--emit-c --pretty` writes the nested blocks of
+// examples/pretty.ok with balanced braces, and indents each line of
+// the generated functions four spaces for every block it is inside of.
+
+use std::{fs, path::PathBuf, process::Command};
+
+// The code of a line, without its comments and the contents of
+// its string and character literals, which may hold braces
+fn code(line: &str) -> String {
+    let mut result = String::new();
+    let mut quote = None;
+    let mut chars = line.chars();
+    while let Some(ch) = chars.next() {
+        match quote {
+            Some(q) if ch == q => quote = None,
+            Some(_) if ch == '\\' => {
+                chars.next();
+            }
+            Some(_) => {}
+            None if ch == '"' || ch == '\'' => quote = Some(ch),
+            None if ch == '/' && result.ends_with('/') => {
+                result.pop();
+                break;
+            }
+            None => result.push(ch),
+        }
+    }
+    result
+}
+
+#[test]
+fn pretty_c_is_indented() {
+    let dir = std::env::temp_dir().join(format!("oak_pretty_c_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    let example = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("examples/pretty.ok");
+    let status = Command::new(env!("CARGO_BIN_EXE_oak"))
+        .args(["--emit-c", "--pretty", "c"])
+        .arg(&example)
+        .current_dir(&dir)
+        .output()
+        .unwrap()
+        .status;
+    let out = fs::read_to_string(dir.join("out.c"));
+    fs::remove_dir_all(&dir).unwrap();
+    assert!(status.success());
+    let out = out.unwrap();
+
+    let mut depth: i32 = 0;
+    let mut deepest = 0;
+    // The runtime at the top of the file is written by hand, so only
+    // the indentation of the functions generated after it is checked
+    let mut generated = false;
+    for (i, line) in out.lines().enumerate() {
+        generated = generated || line.starts_with("// from ");
+        let code = code(line);
+        let trimmed = code.trim_start();
+        if generated && !trimmed.is_empty() {
+            // A line that closes a block is written at the block's own depth
+            let expected = if trimmed.starts_with('}') {
+                depth - 1
+            } else {
+                depth
+            };
+            let indent = (code.len() - trimmed.len()) as i32;
+            assert_eq!(indent, 4 * expected, "line {}: {:?}", i + 1, line);
+        }
+
+        depth += code.matches('{').count() as i32;
+        depth -= code.matches('}').count() as i32;
+        assert!(
+            depth >= 0,
+            "line {} closes a block that was never opened",
+            i + 1
+        );
+        deepest = deepest.max(depth);
+    }
+
+    assert_eq!(depth, 0, "the braces are not balanced");
+    // The function, two loops, an if statement, and a block for its data
+    assert!(deepest >= 5);
+}
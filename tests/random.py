@@ -0,0 +1,59 @@
+#!/usr/bin/env python3
+
+# Test that `rand` returns numbers from 0 up to, but not including, 1,
+# and that seeding it with `srand` gives the same numbers every time the
+# program is seeded or run with the same seed, and different numbers with
+# a different seed. The Rust backend is skipped if rustc isn't installed.
+# The generated file is written to "./random.ok" and removed afterwards.
+
+from shutil import which
+from helpers import require_oak, generated_files, compile_and_run, stdout, check
+
+PROGRAM = """#[std]
+
+fn putrandoms(seed: num) {
+    srand(seed);
+    for (let i=0; i<5; i+=1) {
+        putnum(rand()); putchar(' ');
+    }
+    putcharln(' ');
+}
+
+fn main() {
+    putrandoms(7);
+    putrandoms(7);
+    putrandoms(8);
+}
+"""
+
+def check_randoms(first, second):
+	assert(first != "")
+	assert(first == second)
+	lines = [line.split() for line in first.splitlines()]
+	assert(len(lines) == 3)
+	seven, seven_again, eight = lines
+	assert(seven == seven_again)
+	assert(seven != eight)
+	for n in seven + eight:
+		assert(0 <= float(n) < 1)
+
+def main():
+	require_oak()
+
+	backends = [["--cc"]]
+	if which("rustc") != None:
+		backends.append(["--rs"])
+
+	results = []
+	with generated_files({"./random.ok": PROGRAM}):
+		for flags in backends:
+			first = stdout(compile_and_run(flags, "./random.ok"))
+			second = stdout(compile_and_run(flags, "./random.ok"))
+			results.append((first, second))
+
+	with check(*[first for first, _ in results]):
+		for first, second in results:
+			check_randoms(first, second)
+
+if __name__ == "__main__":
+	main()
@@ -0,0 +1,38 @@
+#!/usr/bin/env python3
+
+# Test that every backend writes characters as raw bytes, including
+# control characters such as tabs and newlines. The generated file is
+# written to "./raw_chars.ok" and removed afterwards. Backends whose
+# compiler isn't installed are skipped.
+
+from helpers import require_oak, generated_files, run_backends, stdout, check
+
+PROGRAM = """#[std]
+
+fn main() {
+    putchar('[');
+    putchar('\\t');
+    putchar(']');
+    putchar('\\n');
+    putstr("a\\tb\\n");
+}
+"""
+
+EXPECTED = b"[\t]\na\tb\n"
+
+def main():
+	require_oak()
+
+	with generated_files({"./raw_chars.ok": PROGRAM}):
+		results = run_backends("./raw_chars.ok")
+
+	failures = [
+		"Backend " + flag + " wrote " + repr(stdout(result))
+		for flag, result in results.items()
+		if result == None or result.stdout != EXPECTED
+	]
+	with check(*failures):
+		assert(not failures)
+
+if __name__ == "__main__":
+	main()
@@ -0,0 +1,44 @@
+#!/usr/bin/env python3
+
+# Test that raw string literals, written as `r"..."` or `r#"..."#`, keep
+# their backslashes instead of processing them as escapes, and that the
+# escapes in normal string and character literals are still processed.
+# The generated file is written to "./raw_strings.ok" and removed afterwards.
+
+from helpers import require_oak, generated_files, compile_and_run, stdout, check
+
+PROGRAM = """#[std]
+
+fn main() {
+    putstrln(r"C:\\path\\to");
+    putstrln(r#"has "quotes" and \\n"#);
+    putstrln(r#"ends with a quote""#);
+    putnumln(strnlen(r"\\n", 10));
+
+    putstrln("tab\\tand \\\\backslash \\"quoted\\"");
+    putnumln(strnlen("\\n", 10));
+    putcharln('\\\\');
+}
+"""
+
+EXPECTED = """C:\\path\\to
+has "quotes" and \\n
+ends with a quote"
+2
+tab\tand \\backslash "quoted"
+1
+\\
+"""
+
+def main():
+	require_oak()
+
+	with generated_files({"./raw_strings.ok": PROGRAM}):
+		result = compile_and_run([], "./raw_strings.ok")
+
+	with check(stdout(result)):
+		assert(result != None)
+		assert(stdout(result) == EXPECTED)
+
+if __name__ == "__main__":
+	main()
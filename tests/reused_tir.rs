@@ -0,0 +1,45 @@
+// Test that one parsed program can be compiled to HIR more than once,
+// such as once for each target. Compiling it again for the same target
+// gives the same HIR, and its conditional compilation flags are expanded
+// again with each target's constants.
+
+use oakc::{get_predefined_constants, parse, Go, C};
+use std::path::PathBuf;
+
+const PROGRAM: &str = "#[if(TARGET == 'c') {
+    const BACKEND = 1;
+} else {
+    const BACKEND = 2;
+}]
+
+fn backend() -> num {
+    return BACKEND;
+}
+
+fn main() {
+    let n = backend();
+}
+";
+
+#[test]
+fn compile_parsed_program_twice() {
+    let tir = parse("reused_tir.ok", PROGRAM);
+    let cwd = PathBuf::from("./");
+
+    let mut hirs = Vec::new();
+    for constants in [
+        get_predefined_constants(&C::default()),
+        get_predefined_constants(&C::default()),
+        get_predefined_constants(&Go::default()),
+    ]
+    .iter_mut()
+    {
+        let hir = tir
+            .compile(&cwd, &[], constants)
+            .expect("the program should compile");
+        hirs.push(format!("{:?}", hir));
+    }
+
+    assert_eq!(hirs[0], hirs[1]);
+    assert_ne!(hirs[0], hirs[2]);
+}
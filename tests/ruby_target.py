@@ -0,0 +1,30 @@
+#!/usr/bin/env python3
+
+# Test that the Ruby backend emits code that passes `ruby -c`,
+# and that running it with `ruby` prints the same output as the
+# C backend. The test is skipped if ruby isn't installed.
+
+from helpers import require_oak, skip_unless_installed, compile_and_run, check
+
+FILES = [
+	"./examples/fact.ok",
+	"./examples/structure.ok",
+	"./examples/closure.ok",
+	"./examples/bitwise.ok",
+	"./examples/modulo.ok",
+	"./examples/labeled_loop.ok",
+]
+
+def main():
+	require_oak()
+	skip_unless_installed("ruby")
+
+	with check():
+		for file in FILES:
+			expected = compile_and_run(["--cc"], file)
+			result = compile_and_run(["--rb"], file, ["ruby", "./main.rb"])
+			assert(expected != None and result != None)
+			assert(result.stdout == expected.stdout)
+
+if __name__ == "__main__":
+	main()
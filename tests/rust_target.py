@@ -0,0 +1,32 @@
+#!/usr/bin/env python3
+
+# Test that the Rust backend emits code that compiles with
+# `rustc --edition 2018`, and that the program prints the same
+# output as the C backend. The test is skipped if rustc isn't installed.
+
+from os.path import exists
+from helpers import require_oak, skip_unless_installed, compile_and_run, check
+
+FILES = [
+	"./examples/fact.ok",
+	"./examples/structure.ok",
+	"./examples/closure.ok",
+	"./examples/bitwise.ok",
+	"./examples/modulo.ok",
+]
+
+def main():
+	require_oak()
+	skip_unless_installed("rustc")
+
+	with check():
+		for file in FILES:
+			expected = compile_and_run(["--cc"], file)
+			result = compile_and_run(["--rs"], file)
+			assert(expected != None and result != None)
+			assert(result.stdout == expected.stdout)
+			# The generated source is removed after it's compiled
+			assert(not exists("./main.rs"))
+
+if __name__ == "__main__":
+	main()
@@ -0,0 +1,40 @@
+#!/usr/bin/env python3
+
+# Test that the compiler warns about a variable that shadows a variable
+# in an enclosing block, but not about a variable that is redefined in
+# the same block. The generated file is written to "./shadow_warning.ok"
+# and removed afterwards.
+
+from helpers import require_oak, generated_files, oak, stdout, check
+
+SHADOWED = """fn main() {
+    let x = 1;
+    while x < 3 {
+        let x = 'a';
+    }
+}
+"""
+
+REDEFINED = """fn main() {
+    let x = 1;
+    let x = 'a';
+}
+"""
+
+def compile(program: str) -> str:
+	with generated_files({"./shadow_warning.ok": program}, "./out.c"):
+		return stdout(oak(["--emit-c", "c", "./shadow_warning.ok"]))
+
+def main():
+	require_oak()
+
+	shadowed = compile(SHADOWED)
+	redefined = compile(REDEFINED)
+
+	with check(shadowed, redefined):
+		assert("compilation warning" in shadowed)
+		assert("variable 'x' in the function 'main'" in shadowed)
+		assert("compilation warning" not in redefined)
+
+if __name__ == "__main__":
+	main()
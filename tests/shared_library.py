@@ -0,0 +1,58 @@
+#!/usr/bin/env python3
+
+# Test that compiling with `--shared` builds a shared library, without an
+# entry point, whose functions marked with `#[export]` can be loaded and
+# called from another language. The generated file is written to
+# "./shared_library.ok", and it and the library are removed afterwards.
+
+import ctypes
+import os
+from os.path import exists
+import sys
+from helpers import require_oak, generated_files, remove, oak, stdout, check
+
+PROGRAM = """#[std]
+
+#[export]
+fn add(a: num, b: num) -> num {
+    return a + b;
+}
+
+fn square(n: num) -> num {
+    return n * n;
+}
+
+#[export]
+fn sum_of_squares(a: num, b: num) -> num {
+    return add(square(a), square(b));
+}
+"""
+
+def library_name():
+	if sys.platform == "win32":
+		return "./main.dll"
+	elif sys.platform == "darwin":
+		return "./libmain.dylib"
+	return "./libmain.so"
+
+def main():
+	require_oak()
+
+	library = library_name()
+	remove(library)
+	with generated_files({"./shared_library.ok": PROGRAM}, library):
+		result = oak(["--shared", "c", "./shared_library.ok"])
+
+		sums = None
+		if exists(library):
+			lib = ctypes.CDLL(os.path.abspath(library))
+			for name in ["add", "sum_of_squares"]:
+				getattr(lib, name).argtypes = [ctypes.c_double, ctypes.c_double]
+				getattr(lib, name).restype = ctypes.c_double
+			sums = [lib.add(2, 3), lib.sum_of_squares(3, 4), lib.add(-1.5, 1)]
+
+	with check(stdout(result), sums):
+		assert(sums == [5, 25, -0.5])
+
+if __name__ == "__main__":
+	main()
@@ -0,0 +1,61 @@
+#!/usr/bin/env python3
+
+# Test that the output an exported function writes is flushed when its
+# shared library is unloaded, even though it never flushes the output
+# itself, and that the machine's heap usage is reported then too. The
+# library is loaded by another process that exits without flushing its
+# output, so only the library's cleanup can write it. The generated
+# file is written to "./shared_library_flush.ok", and it and the library
+# are removed afterwards.
+
+import os
+import subprocess
+import sys
+from helpers import require_oak, generated_files, remove, oak, run, stdout, check
+
+PROGRAM = """#[std]
+
+#[export]
+fn greet(n: num) {
+    putstr("hello ");
+    putnum(n);
+}
+"""
+
+# Load the library, call its function, unload it, and exit
+# immediately, so that nothing else flushes stdout
+HOST = """import _ctypes, ctypes, os, sys
+lib = ctypes.CDLL(sys.argv[1])
+lib.greet.argtypes = [ctypes.c_double]
+lib.greet(5)
+_ctypes.dlclose(lib._handle)
+os.write(1, b" unloaded")
+os._exit(0)
+"""
+
+def library_name():
+	if sys.platform == "darwin":
+		return "./libmain.dylib"
+	return "./libmain.so"
+
+def main():
+	require_oak()
+
+	if sys.platform == "win32":
+		print("Skipped: libraries can't be unloaded with dlclose on Windows")
+		exit(0)
+
+	library = library_name()
+	remove(library)
+	with generated_files({"./shared_library_flush.ok": PROGRAM}):
+		oak(["--shared", "--heap-stats", "c", "./shared_library_flush.ok"])
+		result = run([sys.executable, "-c", HOST, os.path.abspath(library)], stderr=subprocess.PIPE)
+
+	with check(stdout(result), result.stderr.decode() if result != None else ""):
+		assert(result != None)
+		# The library's output is written before the host's
+		assert(stdout(result) == "hello 5 unloaded")
+		assert("heap high-water mark" in result.stderr.decode())
+
+if __name__ == "__main__":
+	main()
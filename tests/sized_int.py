@@ -0,0 +1,52 @@
+#!/usr/bin/env python3
+
+# Test that sized integers are truncated to their width when they're
+# passed to and returned from foreign functions. The generated files are
+# written to "./sized_int.ok" and "./sized_int.c", and removed afterwards.
+
+from helpers import require_oak, generated_files, compile_and_run, stdout, check
+
+FOREIGN = """#include <stdint.h>
+
+// Take a `uint8_t`, and push double its value without
+// truncating the result, so that Oak must truncate it
+void oak_double_u8(machine *vm) {
+    uint8_t n = machine_pop(vm);
+    machine_push(vm, n * 2);
+}
+
+// Return the argument exactly as Oak passed it
+void oak_received(machine *vm) {
+    machine_push(vm, machine_pop(vm));
+}
+"""
+
+PROGRAM = """#[std]
+#[extern("sized_int.c")]
+
+extern fn oak_double_u8 as double_u8(n: u8) -> u8;
+extern fn oak_received as received(n: u8) -> num;
+
+fn main() {
+    let n: u8 = 200 as u8;
+    putnumln(double_u8(n) as num);
+    putnumln(received(n + (100 as u8)));
+    putnumln(received(-1 as u8));
+    putnumln(double_u8(255 as u8) as num);
+}
+"""
+
+def main():
+	require_oak()
+
+	with generated_files({"./sized_int.ok": PROGRAM, "./sized_int.c": FOREIGN}):
+		result = compile_and_run([], "./sized_int.ok")
+
+	with check():
+		# 400 and 510 wrap around at 256 when they're returned,
+		# and 300 and -1 wrap around before they're passed
+		assert(result != None)
+		assert(stdout(result) == "144\n44\n255\n254\n")
+
+if __name__ == "__main__":
+	main()
@@ -0,0 +1,53 @@
+#!/usr/bin/env python3
+
+# Test that `SOURCE_DATE_EPOCH` fixes the time used for the `DATE_*`
+# constants and the manifest, without looking up the local time, and
+# that generating docs succeeds with it. The generated files are written
+# to "./source_date_epoch.ok" and "./source_date_epoch.md", and removed
+# afterwards.
+
+import os
+from os.path import exists
+import subprocess
+from helpers import require_oak, generated_files, remove, oak, run, stdout, check
+
+PROGRAM = """#[std]
+
+#[doc("Print the date the program was compiled")]
+fn main() {
+    putnumln(DATE_YEAR);
+    putnumln(DATE_MONTH);
+    putnumln(DATE_DAY);
+    putnumln(BUILD_TIMESTAMP);
+}
+"""
+
+# The second day after the Unix epoch
+EPOCH = "86400"
+
+def main():
+	require_oak()
+
+	env = dict(os.environ, SOURCE_DATE_EPOCH=EPOCH)
+	remove("./main")
+	outputs = ["./source_date_epoch.md", "./out.c", "./main"]
+	with generated_files({"./source_date_epoch.ok": PROGRAM}, *outputs):
+		docs = oak(["doc", "./source_date_epoch.ok", "-o", "./source_date_epoch.md"], env=env)
+		documented = exists("./source_date_epoch.md")
+
+		oak(["--emit-c", "c", "./source_date_epoch.ok"], env=env)
+		manifest = ""
+		if exists("./out.c"):
+			with open("./out.c") as f:
+				manifest = f.read()
+			subprocess.run(["gcc", "./out.c", "-o", "./main", "-lm"])
+		result = run(["./main"])
+
+	with check(stdout(docs), stdout(result)):
+		assert(docs.returncode == 0 and documented)
+		assert("// compiled at: 1970-01-02 00:00:00 +0000" in manifest)
+		assert(result != None)
+		assert(stdout(result) == "1970\n1\n2\n86400\n")
+
+if __name__ == "__main__":
+	main()
@@ -0,0 +1,37 @@
+#!/usr/bin/env python3
+
+# Test that the `--map` flag writes the symbol of each function next to the
+# executable. The generated program is written to "./symbol_map.ok", and it
+# is removed along with the executable and the map afterwards.
+
+from helpers import require_oak, generated_files, remove, compile_and_run, read_symbols, stdout, check
+
+PROGRAM = """#[std]
+
+fn double(n: num) -> num {
+    return n * 2;
+}
+
+fn main() {
+    putnumln(double(21));
+}
+"""
+
+def main():
+	require_oak()
+
+	remove("./main.map")
+	with generated_files({"./symbol_map.ok": PROGRAM}, "./main.map"):
+		output = stdout(compile_and_run(["--map"], "./symbol_map.ok", stderr=None))
+		symbols = read_symbols("./main.map")
+
+	with check(output, symbols):
+		assert(output == "42\n")
+		assert("main" in symbols)
+		assert("double" in symbols)
+		# Each function is assembled as its own symbol
+		assert(symbols["main"] != symbols["double"])
+		assert(symbols["double"].startswith("fn"))
+
+if __name__ == "__main__":
+	main()
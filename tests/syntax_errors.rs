@@ -0,0 +1,36 @@
+// Test that the parser continues past a syntax error, so that two
+// independent syntax errors in different functions are both reported,
+// and that a program without syntax errors reports none.
+
+use oakc::syntax_errors;
+
+const PROGRAM: &str = "#[std]
+
+fn first() -> num {
+    let x = 1 +;
+    return x;
+}
+
+fn second() {
+    putnumln(2 2);
+}
+
+fn main() {
+    putnumln(first());
+}
+";
+
+#[test]
+fn two_syntax_errors() {
+    let errors = syntax_errors("syntax_errors.ok", PROGRAM);
+
+    assert_eq!(errors.len(), 2);
+    assert!(errors[0].contains("4 |"));
+    assert!(errors[1].contains("9 |"));
+}
+
+#[test]
+fn no_syntax_errors() {
+    let errors = syntax_errors("syntax_errors.ok", "fn main() {}");
+    assert!(errors.is_empty());
+}
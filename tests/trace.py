@@ -0,0 +1,39 @@
+#!/usr/bin/env python3
+
+# Test that the `--trace` flag makes the C backend log each operation of
+# the virtual machine to stderr, and that programs compiled without it
+# log nothing. The generated file is written to "./trace.ok" and removed afterwards.
+
+import subprocess
+from helpers import require_oak, generated_files, compile_and_run, stdout, check
+
+PROGRAM = """#[std]
+
+fn main() {
+    putnumln(1 + 2);
+}
+"""
+
+def main():
+	require_oak()
+
+	with generated_files({"./trace.ok": PROGRAM}):
+		traced = compile_and_run(["--trace"], "./trace.ok", stderr=subprocess.PIPE)
+		untraced = compile_and_run([], "./trace.ok", stderr=subprocess.PIPE)
+
+	with check():
+		# Tracing doesn't change the program's output
+		assert(traced != None and stdout(traced) == "3\n")
+		assert(untraced != None and stdout(untraced) == "3\n")
+
+		# `1 + 2` is two pushes and an add, and the push
+		# of the add's result isn't logged separately
+		log = traced.stderr.decode().splitlines()
+		assert("push 1" in log)
+		i = log.index("push 1")
+		assert(log[i:i + 3] == ["push 1", "push 2", "add 1 2 -> 3"])
+		assert(log[i + 3:i + 4] != ["push 3"])
+		assert(untraced.stderr == b"")
+
+if __name__ == "__main__":
+	main()
@@ -0,0 +1,50 @@
+#!/usr/bin/env python3
+
+# Test that the TypeScript backend defines every function that the code
+# it emits calls, and that `hello_world.ok` prints the same output when
+# it's compiled with `tsc` and run with node as it does with the C
+# backend. When `tsc` isn't installed, the functions are still checked,
+# and the rest of the test is skipped.
+
+from os.path import exists
+import re
+from shutil import which
+from helpers import require_oak, remove, oak, compile_and_run, run, read_output, skip, check
+
+FILE = "./examples/hello_world.ok"
+
+# The names of the functions that are called but never defined in the code
+def undefined_functions(code):
+	code = re.sub(r"//[^\n]*", "", code)
+	defined = set(re.findall(r"function\s+(\w+)\s*\(", code))
+	called = set(re.findall(r"(?<![\w.])(\w+)\(vm\b", code))
+	return called - defined
+
+def main():
+	require_oak()
+
+	# The TypeScript code is left in OUTPUT.ts when it can't be compiled
+	remove("./OUTPUT.ts", "./main.js", "./main")
+	oak(["--ts", "--pretty", "c", FILE])
+	code = read_output("./OUTPUT.ts")
+	if code == None and exists("./main.js"):
+		with open("./main.js") as f:
+			code = f.read()
+
+	result = None
+	if which("tsc") != None:
+		result = run(["node", "./main.js"])
+	expected = compile_and_run(["--cc"], FILE)
+
+	missing = undefined_functions(code) if code != None else set()
+	details = ["Called undefined functions: " + ", ".join(sorted(missing))] if missing else []
+	with check(*details):
+		assert(code != None)
+		assert(not missing)
+		if which("tsc") == None:
+			skip("tsc is not installed, so the TypeScript output can't be run")
+		assert(expected != None and result != None)
+		assert(result.stdout == expected.stdout)
+
+if __name__ == "__main__":
+	main()
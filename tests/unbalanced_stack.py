@@ -0,0 +1,37 @@
+#!/usr/bin/env python3
+
+# Test that a program that leaves a cell on the stack panics at exit with the
+# `--debug` flag, and that the program runs to completion without it. The
+# generated file is written to "./unbalanced_stack.ok" and removed afterwards.
+
+from helpers import require_oak, generated_files, compile_and_run, stdout, check
+
+PROGRAM = """#[std]
+
+fn main() -> num {
+    // The assembly pushes a cell that nothing ever pops
+    @asm {
+        push 1;
+    }
+    putstrln("done");
+    return 0;
+}
+"""
+
+def main():
+	require_oak()
+
+	with generated_files({"./unbalanced_stack.ok": PROGRAM}):
+		release = compile_and_run([], "./unbalanced_stack.ok")
+		debug = compile_and_run(["--debug"], "./unbalanced_stack.ok")
+
+	with check():
+		assert(release != None and release.returncode == 0)
+		assert(stdout(release).strip() == "done")
+		assert(debug != None and debug.returncode != 0)
+		# The program runs to the end before the stack is checked
+		assert("done" in stdout(debug))
+		assert("panic: stack is not balanced" in stdout(debug))
+
+if __name__ == "__main__":
+	main()
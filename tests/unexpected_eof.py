@@ -0,0 +1,32 @@
+#!/usr/bin/env python3
+
+# Test that a program ending in the middle of a declaration is reported as
+# a parse error at the end of the file, instead of crashing the compiler.
+# The generated file is written to "./unexpected_eof.ok" and removed afterwards.
+
+import subprocess
+from helpers import require_oak, generated_files, oak, check
+
+PROGRAMS = [
+	"fn",
+	"fn main() {\n    let x = 1;\n",
+]
+
+def main():
+	require_oak()
+
+	results = []
+	for program in PROGRAMS:
+		with generated_files({"./unexpected_eof.ok": program}):
+			results.append(oak(["c", "./unexpected_eof.ok"], stderr=subprocess.PIPE))
+
+	with check():
+		for result in results:
+			# A parse error exits with an error code, but a panic exits with 101
+			assert(result.returncode == 1)
+			assert(b"unexpected `" in result.stderr)
+			assert(b"EOF" in result.stderr)
+			assert(b"panicked" not in result.stderr)
+
+if __name__ == "__main__":
+	main()
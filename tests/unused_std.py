@@ -0,0 +1,53 @@
+#!/usr/bin/env python3
+
+# Test that a program marked with `#[std]` that never uses a function of the
+# standard library is generated without the library's foreign functions, and
+# that a program using one of them still includes them and runs. The generated
+# files are written to "./unused_std.ok" and "./out.c", and removed afterwards.
+
+import helpers
+from helpers import require_oak, generated_files, remove, oak, read_output, check
+
+UNUSED = """#[std]
+
+fn main() {
+    let x = 1 + 2;
+}
+"""
+
+USED = """#[std]
+
+fn main() {
+    putnumln(1 + 2);
+}
+"""
+
+def emit_c(program):
+	remove("./out.c")
+	with generated_files({"./unused_std.ok": program}):
+		oak(["--emit-c", "c", "./unused_std.ok"])
+	return read_output("./out.c")
+
+def compile_and_run(program):
+	with generated_files({"./unused_std.ok": program}):
+		result = helpers.compile_and_run([], "./unused_std.ok", stderr=None)
+	return result.stdout if result != None else None
+
+def main():
+	require_oak()
+
+	unused = emit_c(UNUSED)
+	used = emit_c(USED)
+	unused_output = compile_and_run(UNUSED)
+	used_output = compile_and_run(USED)
+
+	with check():
+		assert(unused != None and used != None)
+		# The foreign functions of the standard library are left out
+		assert("void prn(machine *vm)" not in unused)
+		assert("void prn(machine *vm)" in used)
+		assert(unused_output == b"")
+		assert(used_output == b"3\n")
+
+if __name__ == "__main__":
+	main()
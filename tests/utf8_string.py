@@ -0,0 +1,34 @@
+#!/usr/bin/env python3
+
+# Test that every backend prints a string literal with multi-byte
+# UTF-8 characters as the same bytes it was written with. The generated
+# file is written to "./utf8_string.ok" and removed afterwards. Backends
+# whose compiler isn't installed are skipped.
+
+from helpers import require_oak, generated_files, run_backends, stdout, check
+
+PROGRAM = """#[std]
+
+fn main() {
+    putstrln("café");
+}
+"""
+
+EXPECTED = "café\n".encode("utf-8")
+
+def main():
+	require_oak()
+
+	with generated_files({"./utf8_string.ok": PROGRAM}):
+		results = run_backends("./utf8_string.ok")
+
+	failures = [
+		"Backend " + flag + " wrote " + repr(stdout(result))
+		for flag, result in results.items()
+		if result == None or result.stdout != EXPECTED
+	]
+	with check(*failures):
+		assert(not failures)
+
+if __name__ == "__main__":
+	main()
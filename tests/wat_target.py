@@ -0,0 +1,87 @@
+#!/usr/bin/env python3
+
+# Test that the WebAssembly text backend emits code that `wat2wasm`
+# accepts, and that running the module with node, through a host that
+# provides its I/O, prints the same output as the C backend. The host is
+# written to "./wat_host.js", and removed afterwards. The test is skipped
+# if wat2wasm or node isn't installed.
+
+from helpers import require_oak, skip_unless_installed, generated_files, compile_and_run, check
+
+FILES = [
+	"./examples/fact.ok",
+	"./examples/num.ok",
+	"./examples/infinity.ok",
+	"./examples/structure.ok",
+	"./examples/closure.ok",
+	"./examples/bitwise.ok",
+	"./examples/modulo.ok",
+	"./examples/labeled_loop.ok",
+]
+
+# The host formats numbers like C's `%g`, so that
+# the output can be compared with the C backend
+HOST = """const fs = require("fs");
+
+let output = [];
+function flush() {
+	if (output.length > 0) fs.writeSync(1, Buffer.from(output));
+	output = [];
+}
+
+function format_number(n) {
+	if (isNaN(n)) return "nan";
+	if (!isFinite(n)) return n > 0 ? "inf" : "-inf";
+	if (n == 0) return Object.is(n, -0) ? "-0" : "0";
+	let [mantissa, exponent] = n.toExponential(5).split("e");
+	exponent = parseInt(exponent);
+	const trim = (s) => s.includes(".") ? s.replace(/0+$/, "").replace(/\\.$/, "") : s;
+	if (exponent < -4 || exponent >= 6) {
+		const digits = String(Math.abs(exponent)).padStart(2, "0");
+		return trim(mantissa) + "e" + (exponent < 0 ? "-" : "+") + digits;
+	}
+	return trim(n.toFixed(5 - exponent));
+}
+
+class Exit {
+	constructor(code) { this.code = code; }
+}
+
+const imports = { oak: {
+	write_byte: (byte) => output.push(byte & 255),
+	write_number: (n) => output.push(...Buffer.from(format_number(n))),
+	read_byte: () => {
+		const buffer = Buffer.alloc(1);
+		return fs.readSync(0, buffer, 0, 1) > 0 ? buffer[0] : 0;
+	},
+	flush: flush,
+	exit: (code) => { throw new Exit(code); },
+	now: () => performance.now() / 1000,
+}};
+
+WebAssembly.instantiate(fs.readFileSync(process.argv[2]), imports).then(({ instance }) => {
+	try {
+		instance.exports._start();
+	} catch (e) {
+		flush();
+		if (e instanceof Exit) process.exit(e.code);
+		throw e;
+	}
+	flush();
+});
+"""
+
+def main():
+	require_oak()
+	skip_unless_installed("wat2wasm", "node")
+
+	with generated_files({"./wat_host.js": HOST}):
+		with check():
+			for file in FILES:
+				expected = compile_and_run(["--cc"], file)
+				result = compile_and_run(["--wat"], file, ["node", "./wat_host.js", "./main.wasm"])
+				assert(expected != None and result != None)
+				assert(result.stdout == expected.stdout)
+
+if __name__ == "__main__":
+	main()
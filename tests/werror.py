@@ -0,0 +1,39 @@
+#!/usr/bin/env python3
+
+# Test that a program with a shadowing warning fails to compile with
+# `--werror`, and reports the warning as an error, but compiles without
+# it. The generated file is written to "./werror.ok" and removed
+# afterwards.
+
+from os.path import exists
+from helpers import require_oak, generated_files, remove, oak, stdout, check
+
+PROGRAM = """fn main() {
+    let x = 1;
+    while x < 3 {
+        let x = 'a';
+    }
+}
+"""
+
+def compile(flags):
+	remove("./out.c")
+	result = oak(["--emit-c"] + flags + ["c", "./werror.ok"])
+	return result, exists("./out.c")
+
+def main():
+	require_oak()
+
+	with generated_files({"./werror.ok": PROGRAM}, "./out.c"):
+		warned, warned_output = compile([])
+		failed, failed_output = compile(["--werror"])
+
+	with check(stdout(warned), stdout(failed)):
+		assert(warned.returncode == 0 and warned_output)
+		assert("compilation warning" in stdout(warned))
+		assert(failed.returncode != 0 and not failed_output)
+		assert("compilation error" in stdout(failed))
+		assert("shadows a variable" in stdout(failed))
+
+if __name__ == "__main__":
+	main()
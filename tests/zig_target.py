@@ -0,0 +1,33 @@
+#!/usr/bin/env python3
+
+# Test that the Zig backend emits code that compiles with
+# `zig build-exe`, and that the program prints the same
+# output as the C backend. The test is skipped if zig isn't installed.
+
+from os.path import exists
+from helpers import require_oak, skip_unless_installed, compile_and_run, check
+
+FILES = [
+	"./examples/fact.ok",
+	"./examples/structure.ok",
+	"./examples/closure.ok",
+	"./examples/bitwise.ok",
+	"./examples/modulo.ok",
+	"./examples/labeled_loop.ok",
+]
+
+def main():
+	require_oak()
+	skip_unless_installed("zig")
+
+	with check():
+		for file in FILES:
+			expected = compile_and_run(["--cc"], file)
+			result = compile_and_run(["--zig"], file)
+			assert(expected != None and result != None)
+			assert(result.stdout == expected.stdout)
+			# The generated source is removed after it's compiled
+			assert(not exists("./main.zig"))
+
+if __name__ == "__main__":
+	main()